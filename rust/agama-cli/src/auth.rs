@@ -18,7 +18,10 @@
 // To contact SUSE LLC about this file by physical or electronic mail, you may
 // find current contact information at www.suse.com.
 
-use agama_lib::auth::AuthToken;
+use agama_lib::{
+    auth::{AuthToken, TokenRequest, TokenScope},
+    base_http_client::BaseHTTPClient,
+};
 use clap::Subcommand;
 
 use crate::error::CliError;
@@ -40,6 +43,26 @@ pub enum AuthCommands {
     Logout,
     /// Print the used token to the standard output.
     Show,
+    /// Manage tokens for automation tools.
+    #[command(subcommand)]
+    Token(TokenCommands),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TokenCommands {
+    /// Create a token granting a restricted set of scopes and print it.
+    ///
+    /// The available scopes are "read", "configure", "install" and "admin". Any scope grants read
+    /// access, so "read" is enough for a monitoring dashboard. Creating tokens requires the
+    /// "admin" scope.
+    Create {
+        /// Scope to grant (it can be repeated)
+        #[arg(long = "scope", required = true)]
+        scopes: Vec<TokenScope>,
+        /// Token lifetime in seconds (default: the server's token lifetime)
+        #[arg(long)]
+        ttl: Option<u64>,
+    },
 }
 
 /// Main entry point called from agama CLI main loop
@@ -48,6 +71,9 @@ pub async fn run(subcommand: AuthCommands) -> anyhow::Result<()> {
         AuthCommands::Login => login(read_password()?).await,
         AuthCommands::Logout => logout(),
         AuthCommands::Show => show(),
+        AuthCommands::Token(TokenCommands::Create { scopes, ttl }) => {
+            create_token(scopes, ttl).await
+        }
     }
 }
 
@@ -130,3 +156,16 @@ fn show() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Asks the web server for a new token with the given scopes and prints it.
+async fn create_token(scopes: Vec<TokenScope>, ttl: Option<u64>) -> anyhow::Result<()> {
+    let client = BaseHTTPClient::new()?;
+    let response: std::collections::HashMap<String, String> = client
+        .post("/auth/tokens", &TokenRequest { scopes, ttl })
+        .await?;
+    let Some(token) = response.get("token") else {
+        return Err(anyhow::anyhow!("Failed to get authentication token"));
+    };
+    println!("{}", token);
+    Ok(())
+}
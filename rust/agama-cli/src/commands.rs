@@ -21,8 +21,10 @@
 use crate::auth::AuthCommands;
 use crate::config::ConfigCommands;
 use crate::logs::LogsCommands;
+use crate::network::NetworkCommands;
 use crate::profile::ProfileCommands;
 use crate::questions::QuestionsCommands;
+use agama_lib::transfer::Checksum;
 use clap::Subcommand;
 
 #[derive(Subcommand, Debug)]
@@ -78,6 +80,13 @@ pub enum Commands {
     #[command(subcommand)]
     Logs(LogsCommands),
 
+    /// Export or import the network configuration.
+    ///
+    /// Besides the network section of Agama's profile (JSON), it supports the nmstate YAML
+    /// format. The settings which cannot be converted are reported as errors.
+    #[command(subcommand)]
+    Network(NetworkCommands),
+
     /// Authenticate with Agama's server.
     ///
     /// Unless you are executing this program as root, you need to authenticate with Agama's server
@@ -91,6 +100,9 @@ pub enum Commands {
     ///
     /// You can logout at any time by using the "auth logout" command, although this command does
     /// not affect the root user.
+    ///
+    /// Automation tools (e.g., CI bots or monitoring dashboards) can use tokens with restricted
+    /// scopes, which are created through the "auth token create" command.
     #[command(subcommand)]
     Auth(AuthCommands),
 
@@ -104,5 +116,9 @@ pub enum Commands {
     Download {
         /// URL pointing to file for download
         url: String,
+        /// Expected checksum of the file, written as "<algorithm>:<digest>" (md5, sha1, sha256
+        /// and sha512 are supported). E.g., "sha256:2cf24dba5fb0...".
+        #[arg(long)]
+        checksum: Option<Checksum>,
     },
 }
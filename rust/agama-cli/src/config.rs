@@ -25,9 +25,15 @@ use std::{
 };
 
 use crate::show_progress;
-use agama_lib::{auth::AuthToken, install_settings::InstallSettings, Store as SettingsStore};
+use agama_lib::{
+    auth::AuthToken,
+    install_settings::{InstallSettings, SettingChange, SettingsDiff},
+    profile::ProfileFormat,
+    Store as SettingsStore,
+};
 use anyhow::anyhow;
-use clap::Subcommand;
+use clap::{Subcommand, ValueEnum};
+use console::style;
 use std::io::Write;
 use tempfile::Builder;
 
@@ -41,10 +47,34 @@ pub enum ConfigCommands {
     /// are not included in the output.
     ///
     /// The output of command can be used as input for the "agama config load".
-    Show,
+    Show {
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Json)]
+        output: OutputFormat,
+    },
 
     /// Read and load a profile from the standard input.
-    Load,
+    ///
+    /// The profile can be written in JSON, YAML or TOML.
+    ///
+    /// If any section of the profile cannot be stored, the sections that were already written
+    /// are restored to their previous values.
+    Load {
+        /// Show the changes the profile would make, but do not load it
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Compare the current settings with a profile read from the standard input.
+    ///
+    /// It reports the added, removed and changed settings for each section of the profile.
+    /// Sections that are not included in the profile are not modified when loading it, so they
+    /// are not reported.
+    Diff {
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = DiffFormat::Text)]
+        format: DiffFormat,
+    },
 
     /// Edit and update installation option using an external editor.
     ///
@@ -69,18 +99,24 @@ pub async fn run(subcommand: ConfigCommands) -> anyhow::Result<()> {
     let store = SettingsStore::new(client).await?;
 
     match subcommand {
-        ConfigCommands::Show => {
+        ConfigCommands::Show { output } => {
             let model = store.load().await?;
-            let json = serde_json::to_string_pretty(&model)?;
-            println!("{}", json);
+            let content = ProfileFormat::from(output).write(&model)?;
+            println!("{}", content.trim_end());
             Ok(())
         }
-        ConfigCommands::Load => {
-            let mut stdin = io::stdin();
-            let mut contents = String::new();
-            stdin.read_to_string(&mut contents)?;
-            let result: InstallSettings = serde_json::from_str(&contents)?;
-            Ok(store.store(&result).await?)
+        ConfigCommands::Load { dry_run } => {
+            let result = read_settings()?;
+            if dry_run {
+                let diff = SettingsDiff::new(&store.load().await?, &result)?;
+                return print_diff(&diff, DiffFormat::Text);
+            }
+            Ok(store.store_transactional(&result).await?)
+        }
+        ConfigCommands::Diff { format } => {
+            let result = read_settings()?;
+            let diff = SettingsDiff::new(&store.load().await?, &result)?;
+            print_diff(&diff, format)
         }
         ConfigCommands::Edit { editor } => {
             let model = store.load().await?;
@@ -97,6 +133,79 @@ pub async fn run(subcommand: ConfigCommands) -> anyhow::Result<()> {
     }
 }
 
+/// Output format for the differences between two sets of settings.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum DiffFormat {
+    /// Human readable (and coloured) text
+    Text,
+    /// JSON document grouping the changes by section
+    Json,
+}
+
+/// Format to show the installation settings.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum OutputFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl From<OutputFormat> for ProfileFormat {
+    fn from(value: OutputFormat) -> Self {
+        match value {
+            OutputFormat::Json => ProfileFormat::Json,
+            OutputFormat::Yaml => ProfileFormat::Yaml,
+            OutputFormat::Toml => ProfileFormat::Toml,
+        }
+    }
+}
+
+/// Reads the installation settings from the standard input.
+///
+/// The format (JSON, YAML or TOML) is detected from the content.
+fn read_settings() -> anyhow::Result<InstallSettings> {
+    let mut stdin = io::stdin();
+    let mut contents = String::new();
+    stdin.read_to_string(&mut contents)?;
+    let format = ProfileFormat::detect(&contents);
+    Ok(InstallSettings::from_content(&contents, format)?)
+}
+
+/// Prints the differences between two sets of settings.
+///
+/// * `diff`: differences to print.
+/// * `format`: output format.
+pub fn print_diff(diff: &SettingsDiff, format: DiffFormat) -> anyhow::Result<()> {
+    if let DiffFormat::Json = format {
+        println!("{}", serde_json::to_string_pretty(diff)?);
+        return Ok(());
+    }
+
+    if diff.is_empty() {
+        println!("No changes");
+        return Ok(());
+    }
+
+    for (section, changes) in diff.sections() {
+        println!("{}:", style(section).bold());
+        for change in changes {
+            let line = match change {
+                SettingChange::Added { path, value } => {
+                    style(format!("  + {}: {}", path, value)).green()
+                }
+                SettingChange::Removed { path, value } => {
+                    style(format!("  - {}: {}", path, value)).red()
+                }
+                SettingChange::Changed { path, old, new } => {
+                    style(format!("  ~ {}: {} -> {}", path, old, new)).yellow()
+                }
+            };
+            println!("{}", line);
+        }
+    }
+    Ok(())
+}
+
 /// Edit the installation settings using an external editor.
 ///
 /// If the editor does not return a successful error code, it returns an error.
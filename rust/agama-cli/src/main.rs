@@ -25,6 +25,7 @@ mod commands;
 mod config;
 mod error;
 mod logs;
+mod network;
 mod profile;
 mod progress;
 mod questions;
@@ -37,6 +38,7 @@ use auth::run as run_auth_cmd;
 use commands::Commands;
 use config::run as run_config_cmd;
 use logs::run as run_logs_cmd;
+use network::run as run_network_cmd;
 use profile::run as run_profile_cmd;
 use progress::InstallerProgress;
 use questions::run as run_questions_cmd;
@@ -157,8 +159,11 @@ async fn run_command(cli: Cli) -> Result<(), ServiceError> {
         }
         Commands::Questions(subcommand) => run_questions_cmd(subcommand).await?,
         Commands::Logs(subcommand) => run_logs_cmd(subcommand).await?,
+        Commands::Network(subcommand) => run_network_cmd(subcommand).await?,
         Commands::Auth(subcommand) => run_auth_cmd(subcommand).await?,
-        Commands::Download { url } => crate::profile::download(&url, std::io::stdout())?,
+        Commands::Download { url, checksum } => {
+            crate::profile::download(&url, checksum.as_ref(), std::io::stdout()).await?
+        }
     };
 
     Ok(())
@@ -0,0 +1,201 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+use std::{
+    fs,
+    io::{self, Read},
+    path::PathBuf,
+};
+
+use agama_lib::{
+    auth::AuthToken,
+    network::{
+        diagnostics::{CheckResult, CheckStatus, DiagnosticsOptions, DiagnosticsReport},
+        NetworkClient, NetworkSettings, NetworkStore,
+    },
+};
+use anyhow::Context;
+use clap::{Subcommand, ValueEnum};
+
+#[derive(Subcommand, Debug)]
+pub enum NetworkCommands {
+    /// Print the network configuration.
+    Export {
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = NetworkFormat::Json)]
+        format: NetworkFormat,
+    },
+
+    /// Update and apply the network configuration.
+    ///
+    /// The connections which are not included in the document are kept. Using the nmstate
+    /// format, an interface can be removed by setting its state to "absent".
+    Import {
+        /// Input format
+        #[arg(short, long, value_enum, default_value_t = NetworkFormat::Json)]
+        format: NetworkFormat,
+        /// File to read the configuration from (default: standard input)
+        path: Option<PathBuf>,
+    },
+
+    /// Check the network connectivity.
+    ///
+    /// For each device, it checks the link state, the IP addresses, the default route and the
+    /// gateway reachability. Optionally, it checks the resolution of a set of hostnames and
+    /// whether a URL can be fetched. It exits with an error if any check fails.
+    Check {
+        /// Device to check (default: all of them)
+        #[arg(short, long)]
+        device: Option<String>,
+        /// Hostname to resolve (it can be repeated)
+        #[arg(long = "host")]
+        hosts: Vec<String>,
+        /// HTTP(S) URL to fetch
+        #[arg(long)]
+        url: Option<String>,
+        /// Proxy to use when fetching the URL
+        #[arg(long)]
+        proxy: Option<String>,
+        /// TCP port to check the gateway reachability (default: ICMP)
+        #[arg(long)]
+        gateway_port: Option<u16>,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// Network configuration format.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum NetworkFormat {
+    /// Network section of Agama's profile
+    Json,
+    /// nmstate YAML document
+    Nmstate,
+}
+
+pub async fn run(subcommand: NetworkCommands) -> anyhow::Result<()> {
+    let token = AuthToken::find().context("You are not logged in")?;
+    let client = agama_lib::http_client(token.as_str())?;
+
+    match subcommand {
+        NetworkCommands::Export { format } => {
+            let content = match format {
+                NetworkFormat::Json => {
+                    let settings = NetworkStore::new(client).await?.load().await?;
+                    serde_json::to_string_pretty(&settings)?
+                }
+                NetworkFormat::Nmstate => NetworkClient::new(client).await?.nmstate().await?,
+            };
+            println!("{}", content.trim_end());
+        }
+        NetworkCommands::Import { format, path } => {
+            let content = read_content(path)?;
+            match format {
+                NetworkFormat::Json => {
+                    let settings: NetworkSettings = serde_json::from_str(&content)
+                        .context("Could not parse the network settings")?;
+                    NetworkStore::new(client).await?.store(&settings).await?;
+                }
+                NetworkFormat::Nmstate => {
+                    let network = NetworkClient::new(client).await?;
+                    network.set_nmstate(&content).await?;
+                    network.apply().await?;
+                }
+            }
+        }
+        NetworkCommands::Check {
+            device,
+            hosts,
+            url,
+            proxy,
+            gateway_port,
+            json,
+        } => {
+            let options = DiagnosticsOptions {
+                device,
+                hosts: (!hosts.is_empty()).then(|| hosts.join(",")),
+                url,
+                proxy,
+                gateway_port,
+            };
+            let report = NetworkClient::new(client)
+                .await?
+                .diagnostics(&options)
+                .await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print_report(&report);
+            }
+            if !report.is_ok() {
+                return Err(anyhow::anyhow!("Some network checks failed"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints the diagnostics report in a human-readable way.
+fn print_report(report: &DiagnosticsReport) {
+    for device in &report.devices {
+        match &device.connection {
+            Some(connection) => println!("{} ({}):", device.device, connection),
+            None => println!("{}:", device.device),
+        }
+        for check in &device.checks {
+            print_check(check);
+        }
+    }
+
+    if !report.checks.is_empty() {
+        println!("Services:");
+        for check in &report.checks {
+            print_check(check);
+        }
+    }
+}
+
+fn print_check(check: &CheckResult) {
+    let mark = match check.status {
+        CheckStatus::Passed => "OK",
+        CheckStatus::Failed => "FAIL",
+        CheckStatus::Skipped => "SKIP",
+    };
+    let mut line = format!("  [{:<4}] {} {}", mark, check.kind, check.target);
+    if let Some(details) = &check.details {
+        line.push_str(&format!(": {}", details));
+    }
+    println!("{}", line);
+}
+
+/// Reads the content from the given file or from the standard input.
+fn read_content(path: Option<PathBuf>) -> anyhow::Result<String> {
+    match path {
+        Some(path) => {
+            fs::read_to_string(&path).with_context(|| format!("Could not read {}", path.display()))
+        }
+        None => {
+            let mut content = String::new();
+            io::stdin().read_to_string(&mut content)?;
+            Ok(content)
+        }
+    }
+}
@@ -18,18 +18,26 @@
 // To contact SUSE LLC about this file by physical or electronic mail, you may
 // find current contact information at www.suse.com.
 
+use crate::config::{print_diff, DiffFormat, OutputFormat};
 use agama_lib::{
     auth::AuthToken,
-    install_settings::InstallSettings,
-    profile::{AutoyastProfile, ProfileEvaluator, ProfileValidator, ValidationResult},
+    base_http_client::BaseHTTPClient,
+    error::ServiceError,
+    install_settings::{InstallSettings, SettingsDiff},
+    profile::{
+        AutoyastProfile, ProfileEvaluator, ProfileFormat, ProfileLoader, ProfilePolicy,
+        ProfileValidator, SignatureVerifier, ValidationError, ValidationResult, DEFAULT_KEYS_DIR,
+    },
+    proxy::{curlrc, ProxyStore},
+    transfer::{Checksum, HttpOptions, Transfer},
     Store as SettingsStore,
 };
 use anyhow::Context;
-use clap::Subcommand;
-use curl::easy::Easy;
+use clap::{Subcommand, ValueEnum};
+use console::style;
 use std::os::unix::process::CommandExt;
 use std::{
-    fs::File,
+    fs::{self, File},
     io::{stdout, Write},
     path::{Path, PathBuf},
     process::Command,
@@ -48,10 +56,14 @@ pub enum ProfileCommands {
 
     /// Validate a profile using JSON Schema
     ///
-    /// Schema is available at /usr/share/agama-cli/profile.schema.json
+    /// Schema is available at /usr/share/agama-cli/profile.schema.json. The profile can be
+    /// written in JSON, YAML or TOML.
     Validate {
-        /// Local path to the JSON file to validate
+        /// Local path to the profile to validate
         path: PathBuf,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = ValidationFormat::Text)]
+        format: ValidationFormat,
     },
 
     /// Evaluate a profile, injecting the hardware information from D-Bus
@@ -63,54 +75,173 @@ pub enum ProfileCommands {
         path: PathBuf,
     },
 
+    /// Print a profile after resolving its includes and variables
+    ///
+    /// The profiles listed in the "extends" and "include" keys are downloaded and merged, and
+    /// the ${env:VAR} and ${cmdline:key} references are replaced with the values from the
+    /// environment and the kernel command-line.
+    Render {
+        /// Profile's URL. Supports the same schemas than the "download" command.
+        url: String,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Json)]
+        output: OutputFormat,
+    },
+
     /// Process autoinstallation profile and loads it into agama
     ///
     /// This is top level command that do all autoinstallation processing beside starting
     /// installation. Unless there is a need to inject additional commands between processing
     /// use this command instead of set of underlying commands.
+    ///
+    /// If any section of the profile cannot be stored, the sections that were already written
+    /// are restored to their previous values.
     Import {
         /// Profile's URL. Supports the same schemas than te "download" command plus
-        /// AutoYaST specific ones. Supported files are json, jsonnet, sh for Agama profiles and ERB, XML, and rules/classes directories
+        /// AutoYaST specific ones. Supported files are json, yaml, toml, jsonnet, sh for Agama profiles and ERB, XML, and rules/classes directories
         /// for AutoYaST support.
         url: String,
         /// Specific directory where all processing happens. By default it uses a temporary directory
         dir: Option<PathBuf>,
+        /// Show the changes the profile would make, but do not load it
+        #[arg(long)]
+        dry_run: bool,
+        /// Reject the profile unless it has a valid detached signature (the profile's URL plus
+        /// ".sig") made by one of the keys in /etc/agama.d/keys. The server can enforce it too.
+        #[arg(long)]
+        require_signature: bool,
     },
 }
 
-pub fn download(url: &str, mut out_fd: impl Write) -> anyhow::Result<()> {
-    let mut handle = Easy::new();
-    handle.url(url)?;
+pub async fn download(
+    url: &str,
+    checksum: Option<&Checksum>,
+    mut out_fd: impl Write,
+) -> anyhow::Result<()> {
+    let transfer = proxy_transfer().await;
+    let content = match checksum {
+        Some(checksum) => transfer.fetch_verified(url, checksum).await?,
+        None => transfer.fetch(url).await?,
+    };
+    out_fd.write_all(&content)?;
+    Ok(())
+}
 
-    let mut transfer = handle.transfer();
-    transfer.write_function(|buf|
-        // unwrap here is ok, as we want to kill download if we failed to write content
-        Ok(out_fd.write(buf).unwrap()))?;
-    transfer.perform()?;
+/// Returns a transfer which uses the installation proxy settings.
+///
+/// The API does not expose the proxy password, so it is read from the server's curlrc file when
+/// it is available (e.g., when running on the installation system as root).
+///
+/// If the settings cannot be read (e.g., the user is not logged in), it falls back to the proxy
+/// environment variables.
+async fn proxy_transfer() -> Transfer {
+    let settings = match ProxyStore::new() {
+        Ok(store) => store.load().await,
+        Err(e) => Err(e),
+    };
 
-    Ok(())
+    match settings {
+        Ok(mut settings) => {
+            if settings.user.is_some() && settings.password.is_none() {
+                let content = fs::read_to_string(curlrc::CURLRC_PATH).unwrap_or_default();
+                if let Some((user, password)) = curlrc::proxy_credentials(&content) {
+                    if settings.user.as_ref() == Some(&user) {
+                        settings.password = password;
+                    }
+                }
+            }
+            Transfer::with_http_options(HttpOptions::default().with_proxy(&settings))
+        }
+        Err(e) => {
+            log::debug!("Could not read the proxy settings: {}", e);
+            Transfer::default()
+        }
+    }
+}
+
+/// Output format for the validation errors.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ValidationFormat {
+    /// Human readable text, including the offending lines
+    Text,
+    /// JSON array containing the errors
+    Json,
 }
 
-fn validate(path: &PathBuf) -> anyhow::Result<()> {
+fn validate(path: &PathBuf, format: ValidationFormat) -> anyhow::Result<()> {
     let validator = ProfileValidator::default_schema()?;
-    // let path = Path::new(&path);
+    let source =
+        fs::read_to_string(path).context(format!("Could not read the profile {:?}", path))?;
+    let profile_format = ProfileFormat::guess(Some(path), &source);
     let result = validator
-        .validate_file(path)
+        .validate_content(&source, profile_format)
         .context(format!("Could not validate the profile {:?}", path))?;
-    match result {
-        ValidationResult::Valid => {
-            println!("The profile is valid")
-        }
-        ValidationResult::NotValid(errors) => {
-            eprintln!("The profile is not valid. Please, check the following errors:\n");
-            for error in errors {
-                println!("* {error}")
-            }
-        }
+    let errors = match result {
+        ValidationResult::Valid => vec![],
+        ValidationResult::NotValid(errors) => errors,
+    };
+
+    if let ValidationFormat::Json = format {
+        println!("{}", serde_json::to_string_pretty(&errors)?);
+        return Ok(());
+    }
+
+    if errors.is_empty() {
+        println!("The profile is valid");
+        return Ok(());
+    }
+
+    eprintln!("The profile is not valid. Please, check the following errors:\n");
+    let lines: Vec<&str> = source.lines().collect();
+    for error in errors {
+        print_validation_error(&error, path, &lines);
     }
     Ok(())
 }
 
+/// Prints a validation error, including the offending line if it is known.
+///
+/// * `error`: error to print.
+/// * `path`: path of the profile.
+/// * `lines`: lines of the profile.
+fn print_validation_error(error: &ValidationError, path: &Path, lines: &[&str]) {
+    let pointer = if error.pointer.is_empty() {
+        "/"
+    } else {
+        &error.pointer
+    };
+    println!("* {}: {}", style(pointer).bold(), error.message);
+
+    if let (Some(line), Some(column)) = (error.line, error.column) {
+        println!("  --> {}:{}:{}", path.display(), line, column);
+        if let Some(text) = lines.get(line - 1) {
+            let number = line.to_string();
+            let padding = " ".repeat(number.len());
+            println!("  {} |", padding);
+            println!("  {} | {}", style(&number).blue(), text);
+            println!(
+                "  {} | {}{}",
+                padding,
+                " ".repeat(column - 1),
+                style("^").red().bold()
+            );
+        }
+    }
+    if let Some(expected) = &error.expected {
+        println!("  = expected: {}", expected);
+    }
+    if let Some(actual) = &error.actual {
+        println!("  = found: {}", actual);
+    }
+    if let Some(suggestion) = &error.suggestion {
+        println!(
+            "  = {}",
+            style(format!("did you mean '{}'?", suggestion)).green()
+        );
+    }
+    println!();
+}
+
 fn evaluate(path: &Path) -> anyhow::Result<()> {
     let evaluator = ProfileEvaluator {};
     evaluator
@@ -119,8 +250,35 @@ fn evaluate(path: &Path) -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn import(url_string: String, dir: Option<PathBuf>) -> anyhow::Result<()> {
+/// Returns the signature verifier to use, if the signature is required by the user or the server.
+///
+/// The server policy is only checked when the user does not require the signature. Servers
+/// that do not publish a policy do not require signatures.
+async fn signature_verifier(require_signature: bool) -> anyhow::Result<Option<SignatureVerifier>> {
+    if !require_signature && !server_requires_signature().await? {
+        return Ok(None);
+    }
+    Ok(Some(SignatureVerifier::from_dir(DEFAULT_KEYS_DIR)?))
+}
+
+/// Whether the server requires the profiles to be signed.
+async fn server_requires_signature() -> anyhow::Result<bool> {
+    let result: Result<ProfilePolicy, _> = BaseHTTPClient::new()?.get("/profile/policy").await;
+    match result {
+        Ok(policy) => Ok(policy.require_signature),
+        Err(ServiceError::BackendError(404, _)) => Ok(false),
+        Err(error) => Err(error).context("Could not read the profile policy"),
+    }
+}
+
+async fn import(
+    url_string: String,
+    dir: Option<PathBuf>,
+    dry_run: bool,
+    require_signature: bool,
+) -> anyhow::Result<()> {
     let url = Url::parse(&url_string)?;
+    let verifier = signature_verifier(require_signature).await?;
     let tmpdir = TempDir::new()?; // TODO: create it only if dir is not passed
     let path = url.path();
     let output_file = if path.ends_with(".sh") {
@@ -132,13 +290,32 @@ async fn import(url_string: String, dir: Option<PathBuf>) -> anyhow::Result<()>
     };
     let output_dir = dir.unwrap_or_else(|| tmpdir.into_path());
     let mut output_path = output_dir.join(output_file);
-    let output_fd = File::create(output_path.clone())?;
     if path.ends_with(".xml") || path.ends_with(".erb") || path.ends_with('/') {
+        if verifier.is_some() {
+            anyhow::bail!("Signatures are not supported for AutoYaST profiles");
+        }
         // autoyast specific download and convert to json
+        let output_fd = File::create(output_path.clone())?;
         AutoyastProfile::new(&url)?.read_into(output_fd)?;
-    } else {
+    } else if output_file.ends_with(".sh") || output_file.ends_with(".jsonnet") {
         // just download profile
-        download(&url_string, output_fd)?;
+        let transfer = proxy_transfer().await;
+        let content = transfer.fetch(&url_string).await?;
+        if let Some(verifier) = &verifier {
+            verifier
+                .verify_url(&transfer, &url_string, &content)
+                .await?;
+        }
+        fs::write(&output_path, content)?;
+    } else {
+        // resolve includes and variables
+        let mut loader = ProfileLoader::from_system().with_transfer(proxy_transfer().await);
+        if let Some(verifier) = verifier {
+            loader = loader.with_verifier(verifier);
+        }
+        let profile = loader.load(&url_string).await?;
+        output_path = output_dir.join("profile.json");
+        fs::write(&output_path, serde_json::to_string_pretty(&profile)?)?;
     }
 
     // exec shell scripts
@@ -159,18 +336,32 @@ async fn import(url_string: String, dir: Option<PathBuf>) -> anyhow::Result<()>
         output_path = output_dir.join("profile.json");
     }
 
-    validate(&output_path)?;
-    store_settings(&output_path).await?;
+    validate(&output_path, ValidationFormat::Text)?;
+    store_settings(&output_path, dry_run).await?;
 
     Ok(())
 }
 
-async fn store_settings<P: AsRef<Path>>(path: P) -> anyhow::Result<()> {
+async fn store_settings<P: AsRef<Path>>(path: P, dry_run: bool) -> anyhow::Result<()> {
     let token = AuthToken::find().context("You are not logged in")?;
     let client = agama_lib::http_client(token.as_str())?;
     let store = SettingsStore::new(client).await?;
     let settings = InstallSettings::from_file(&path)?;
-    store.store(&settings).await?;
+    if dry_run {
+        let diff = SettingsDiff::new(&store.load().await?, &settings)?;
+        return print_diff(&diff, DiffFormat::Text);
+    }
+    store.store_transactional(&settings).await?;
+    Ok(())
+}
+
+async fn render(url: String, output: OutputFormat) -> anyhow::Result<()> {
+    let profile = ProfileLoader::from_system()
+        .load(&url)
+        .await
+        .context(format!("Could not render the profile {}", url))?;
+    let format: ProfileFormat = output.into();
+    println!("{}", format.write(&profile)?);
     Ok(())
 }
 
@@ -184,8 +375,14 @@ fn autoyast(url_string: String) -> anyhow::Result<()> {
 pub async fn run(subcommand: ProfileCommands) -> anyhow::Result<()> {
     match subcommand {
         ProfileCommands::Autoyast { url } => autoyast(url),
-        ProfileCommands::Validate { path } => validate(&path),
+        ProfileCommands::Validate { path, format } => validate(&path, format),
         ProfileCommands::Evaluate { path } => evaluate(&path),
-        ProfileCommands::Import { url, dir } => import(url, dir).await,
+        ProfileCommands::Render { url, output } => render(url, output).await,
+        ProfileCommands::Import {
+            url,
+            dir,
+            dry_run,
+            require_signature,
+        } => import(url, dir, dry_run, require_signature).await,
     }
 }
@@ -35,6 +35,12 @@
 //! with the server. In that process, it obtains a new token that should be stored
 //! in user's home directory (`$HOME/.local/agama/token`).
 //!
+//! ## Scopes
+//!
+//! Each token grants a set of scopes ([TokenScope]). The tokens obtained by logging in and the
+//! master token grant full control (`admin`), while tokens with restricted scopes can be minted
+//! for automation purposes (e.g., a read-only token for a monitoring dashboard).
+//!
 //! ## A simplistic API
 //!
 //! The current API is rather limited and it does not support, for instance,
@@ -45,17 +51,25 @@ const USER_TOKEN_PATH: &str = ".local/agama/token";
 const AGAMA_TOKEN_FILE: &str = "/run/agama/token";
 
 use std::{
-    fmt::Display,
+    fmt::{self, Display},
     fs::{self, File},
     io::{self, BufRead, BufReader, Write},
     os::unix::fs::OpenOptionsExt,
     path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
 };
 
-use chrono::{Duration, Utc};
+use chrono::Utc;
 use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use uuid::Uuid;
+
+/// Default lifetime of the tokens (one day).
+pub const DEFAULT_TOKEN_LIFETIME: Duration = Duration::from_secs(24 * 60 * 60);
+/// Subject of the tokens issued to the root user.
+const ROOT_SUBJECT: &str = "root";
 
 #[derive(Error, Debug)]
 #[error("Invalid authentication token: {0}")]
@@ -74,12 +88,21 @@ impl AuthToken {
 
     /// Generates a new token using the given secret.
     ///
+    /// The token starts a new session and uses the default lifetime.
+    ///
     /// * `secret`: secret to encode the token.
     pub fn generate(secret: &str) -> Result<Self, AuthTokenError> {
-        let claims = TokenClaims::default();
+        Self::from_claims(&TokenClaims::default(), secret)
+    }
+
+    /// Generates a new token containing the given claims.
+    ///
+    /// * `claims`: claims to include in the token.
+    /// * `secret`: secret to encode the token.
+    pub fn from_claims(claims: &TokenClaims, secret: &str) -> Result<Self, AuthTokenError> {
         let token = jsonwebtoken::encode(
             &Header::default(),
-            &claims,
+            claims,
             &EncodingKey::from_secret(secret.as_ref()),
         )?;
         Ok(AuthToken(token))
@@ -184,33 +207,154 @@ impl Display for AuthToken {
     }
 }
 
+/// Scope granted by a token.
+///
+/// Any scope grants read access, while `admin` grants everything.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenScope {
+    /// Read the installation state and settings.
+    Read,
+    /// Change the settings (e.g., storage proposal, network, software).
+    Configure,
+    /// Run the installation and finish it, and other actions which destroy data (e.g.,
+    /// formatting DASD devices).
+    Install,
+    /// Full control, including minting new tokens.
+    Admin,
+}
+
+impl TokenScope {
+    /// Determines whether this scope grants the given one.
+    ///
+    /// * `scope`: scope to check.
+    pub fn grants(&self, scope: TokenScope) -> bool {
+        *self == TokenScope::Admin || *self == scope || scope == TokenScope::Read
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+#[error("Invalid token scope: {0}")]
+pub struct InvalidTokenScope(String);
+
+impl FromStr for TokenScope {
+    type Err = InvalidTokenScope;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(TokenScope::Read),
+            "configure" => Ok(TokenScope::Configure),
+            "install" => Ok(TokenScope::Install),
+            "admin" => Ok(TokenScope::Admin),
+            _ => Err(InvalidTokenScope(s.to_string())),
+        }
+    }
+}
+
+impl Display for TokenScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TokenScope::Read => "read",
+            TokenScope::Configure => "configure",
+            TokenScope::Install => "install",
+            TokenScope::Admin => "admin",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Request to create a new token.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenRequest {
+    /// Scopes to grant.
+    pub scopes: Vec<TokenScope>,
+    /// Token lifetime, in seconds. If it is not set, the server's default applies.
+    pub ttl: Option<u64>,
+}
+
 /// Claims that are included in the token.
 ///
 /// See <https://datatracker.ietf.org/doc/html/rfc7519> for reference.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TokenClaims {
+    /// Unique identifier of the token. It is used to revoke the token.
+    pub jti: String,
+    /// Time (as a UNIX timestamp) at which the token was issued.
+    pub iat: i64,
+    /// Time (as a UNIX timestamp) at which the token expires.
     pub exp: i64,
+    /// User the token was issued to.
+    pub sub: String,
+    /// Session identifier. It is shared by all the tokens obtained by refreshing
+    /// the one issued at login time.
+    pub sid: String,
+    /// Scopes granted by the token.
+    pub scopes: Vec<TokenScope>,
 }
 
-impl Default for TokenClaims {
-    fn default() -> Self {
-        let mut exp = Utc::now();
+impl TokenClaims {
+    /// Builds the claims for a new session.
+    ///
+    /// * `subject`: user the token is issued to.
+    /// * `scopes`: scopes granted by the token.
+    /// * `lifetime`: how long the token is valid.
+    pub fn new(subject: &str, scopes: Vec<TokenScope>, lifetime: Duration) -> Self {
+        let session = Uuid::new_v4().to_string();
+        Self::for_session(subject, &session, scopes, lifetime)
+    }
 
-        if let Some(days) = Duration::try_days(1) {
-            exp += days;
-        }
+    /// Builds the claims for a new token within the same session.
+    ///
+    /// The new claims get a new identifier and expiration time, keeping the same scopes.
+    ///
+    /// * `lifetime`: how long the new token is valid.
+    pub fn refresh(&self, lifetime: Duration) -> Self {
+        Self::for_session(&self.sub, &self.sid, self.scopes.clone(), lifetime)
+    }
+
+    /// Determines whether the token grants the given scope.
+    ///
+    /// * `scope`: scope to check.
+    pub fn allows(&self, scope: TokenScope) -> bool {
+        self.scopes.iter().any(|s| s.grants(scope))
+    }
+
+    fn for_session(
+        subject: &str,
+        session: &str,
+        scopes: Vec<TokenScope>,
+        lifetime: Duration,
+    ) -> Self {
+        let iat = Utc::now().timestamp();
+        let lifetime = i64::try_from(lifetime.as_secs()).unwrap_or(i64::MAX);
 
         Self {
-            exp: exp.timestamp(),
+            jti: Uuid::new_v4().to_string(),
+            iat,
+            exp: iat.saturating_add(lifetime),
+            sub: subject.to_string(),
+            sid: session.to_string(),
+            scopes,
         }
     }
 }
 
+impl Default for TokenClaims {
+    fn default() -> Self {
+        Self::new(
+            ROOT_SUBJECT,
+            vec![TokenScope::Admin],
+            DEFAULT_TOKEN_LIFETIME,
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
     use tempfile::tempdir;
 
-    use super::AuthToken;
+    use super::{AuthToken, TokenClaims, TokenScope};
 
     #[test]
     fn test_generate_token() {
@@ -236,4 +380,51 @@ mod tests {
         let decoded = read_token.claims("nots3cr3t");
         assert!(decoded.is_ok());
     }
+
+    #[test]
+    fn test_token_claims() {
+        let claims = TokenClaims::new("root", vec![TokenScope::Admin], Duration::from_secs(60));
+        assert_eq!(claims.exp - claims.iat, 60);
+        assert_eq!(claims.sub, "root");
+
+        let token = AuthToken::from_claims(&claims, "nots3cr3t").unwrap();
+        let decoded = token.claims("nots3cr3t").unwrap();
+        assert_eq!(decoded.jti, claims.jti);
+        assert_eq!(decoded.sid, claims.sid);
+    }
+
+    #[test]
+    fn test_refresh_token_claims() {
+        let claims = TokenClaims::new("root", vec![TokenScope::Read], Duration::from_secs(60));
+        let refreshed = claims.refresh(Duration::from_secs(120));
+        assert_ne!(refreshed.jti, claims.jti);
+        assert_eq!(refreshed.sid, claims.sid);
+        assert_eq!(refreshed.sub, claims.sub);
+        assert_eq!(refreshed.exp - refreshed.iat, 120);
+        assert_eq!(refreshed.scopes, vec![TokenScope::Read]);
+    }
+
+    #[test]
+    fn test_token_scopes() {
+        let claims = TokenClaims::new("root", vec![TokenScope::Read], Duration::from_secs(60));
+        assert!(claims.allows(TokenScope::Read));
+        assert!(!claims.allows(TokenScope::Configure));
+
+        let claims = TokenClaims::new("root", vec![TokenScope::Install], Duration::from_secs(60));
+        assert!(claims.allows(TokenScope::Read));
+        assert!(claims.allows(TokenScope::Install));
+        assert!(!claims.allows(TokenScope::Configure));
+        assert!(!claims.allows(TokenScope::Admin));
+
+        let claims = TokenClaims::default();
+        assert!(claims.allows(TokenScope::Install));
+        assert!(claims.allows(TokenScope::Admin));
+    }
+
+    #[test]
+    fn test_parse_token_scope() {
+        assert_eq!("configure".parse(), Ok(TokenScope::Configure));
+        assert!("root".parse::<TokenScope>().is_err());
+        assert_eq!(TokenScope::Install.to_string(), "install");
+    }
 }
@@ -69,11 +69,24 @@ impl BaseHTTPClient {
         })
     }
 
+    /// Uses `localhost`, authenticates with the given token.
+    ///
+    /// * `token`: authentication token.
+    pub fn new_with_token(token: &AuthToken) -> Result<Self, ServiceError> {
+        Ok(Self {
+            client: Self::client_for(token)?,
+            ..Default::default()
+        })
+    }
+
     fn authenticated_client() -> Result<reqwest::Client, ServiceError> {
         // TODO: this error is subtly misleading, leading me to believe the SERVER said it,
         // but in fact it is the CLIENT not finding an auth token
         let token = AuthToken::find().ok_or(ServiceError::NotAuthenticated)?;
+        Self::client_for(&token)
+    }
 
+    fn client_for(token: &AuthToken) -> Result<reqwest::Client, ServiceError> {
         let mut headers = header::HeaderMap::new();
         // just use generic anyhow error here as Bearer format is constructed by us, so failures can come only from token
         let value = header::HeaderValue::from_str(format!("Bearer {}", token).as_str())
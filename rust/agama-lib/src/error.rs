@@ -18,7 +18,7 @@
 // To contact SUSE LLC about this file by physical or electronic mail, you may
 // find current contact information at www.suse.com.
 
-use curl;
+use crate::{profile::JsonnetError, transfer::TransferError, SettingsSection};
 use serde_json;
 use std::io;
 use thiserror::Error;
@@ -61,23 +61,62 @@ pub enum ServiceError {
     QuestionNotExist(u32),
     #[error("Backend call failed with status {0} and text '{1}'")]
     BackendError(u16, String),
+    #[error("Invalid proxy settings: {0}")]
+    InvalidProxy(#[from] crate::proxy::ProxyError),
     #[error("You are not logged in. Please use: agama auth login")]
     NotAuthenticated,
+    #[error("Could not store the {section} settings: {source}. Rolled back sections: {}. Sections that could not be rolled back: {}", sections_list(.rolled_back), sections_list(.not_rolled_back))]
+    SettingsRolledBack {
+        section: SettingsSection,
+        source: Box<ServiceError>,
+        rolled_back: Vec<SettingsSection>,
+        not_rolled_back: Vec<SettingsSection>,
+    },
     // Specific error when something does not work as expected, but it is not user fault
     #[error("Internal error. Please report a bug and attach logs. Details: {0}")]
     InternalError(String),
 }
 
+/// Formats a list of sections for the error messages (e.g., "network, proxy").
+fn sections_list(sections: &[SettingsSection]) -> String {
+    if sections.is_empty() {
+        return "none".to_string();
+    }
+    sections
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 #[derive(Error, Debug)]
 pub enum ProfileError {
-    #[error("Could not read the profile")]
-    Unreachable(#[from] curl::Error),
     #[error("Jsonnet evaluation failed:\n{0}")]
-    EvaluationError(String),
+    EvaluationError(#[from] JsonnetError),
     #[error("I/O error")]
     InputOutputError(#[from] io::Error),
     #[error("The profile is not a valid JSON file")]
     FormatError(#[from] serde_json::Error),
+    #[error("The profile is not a valid YAML file: {0}")]
+    YamlFormatError(#[from] serde_yaml::Error),
+    #[error("The profile is not a valid TOML file: {0}")]
+    TomlFormatError(#[from] toml::de::Error),
+    #[error("Could not write the profile as TOML: {0}")]
+    TomlWriteError(#[from] toml::ser::Error),
+    #[error("The profile '{0}' includes itself")]
+    CircularInclude(String),
+    #[error("Could not include the profile '{0}': {1}")]
+    InvalidInclude(String, String),
+    #[error("Undefined variable '${{{0}}}'")]
+    UndefinedVariable(String),
+    #[error("The profile {0} is not signed")]
+    MissingSignature(String),
+    #[error("The signature of the profile {0} is not valid")]
+    InvalidSignature(String),
+    #[error("Could not read the trusted keys: {0}")]
+    TrustedKeysError(String),
+    #[error(transparent)]
+    Transfer(#[from] TransferError),
     #[error("Error: {0}")]
     Anyhow(#[from] anyhow::Error),
 }
@@ -0,0 +1,496 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! Hardware inventory.
+//!
+//! This module builds a summary of the system's hardware (disks, network interfaces, CPU,
+//! memory, firmware and virtualization) by reading sysfs and /proc. It is injected into the
+//! profiles (as `hw.libsonnet`) and offered through the HTTP API.
+//!
+//! All paths are read relative to a root directory, so the inventory can be built from a fake
+//! sysfs tree.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Size of the sectors reported by /sys/block/*/size.
+const SECTOR_SIZE: u64 = 512;
+
+/// Block devices that do not correspond to a real disk.
+const VIRTUAL_DISK_PREFIXES: [&str; 5] = ["loop", "ram", "zram", "dm-", "md"];
+
+/// Hardware inventory.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HardwareInfo {
+    /// Firmware type.
+    pub firmware: Firmware,
+    /// Hypervisor (e.g., "kvm", "vmware") or `None` if running on bare metal.
+    pub virtualization: Option<String>,
+    /// CPU information.
+    pub cpu: Cpu,
+    /// Memory information.
+    pub memory: Memory,
+    /// Disks, sorted by name.
+    pub disks: Vec<Disk>,
+    /// Physical network interfaces, sorted by name.
+    pub network_interfaces: Vec<NetworkInterface>,
+}
+
+/// Firmware type.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Firmware {
+    #[default]
+    Bios,
+    Uefi,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Cpu {
+    /// Architecture (e.g., "x86_64").
+    pub architecture: String,
+    /// Model name (e.g., "Intel(R) Core(TM) i7-8550U CPU @ 1.80GHz").
+    pub model: Option<String>,
+    /// Vendor (e.g., "GenuineIntel").
+    pub vendor: Option<String>,
+    /// Number of logical processors.
+    pub count: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Memory {
+    /// Total memory in bytes.
+    pub total: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Disk {
+    /// Kernel name (e.g., "sda").
+    pub name: String,
+    /// Device path (e.g., "/dev/sda").
+    pub device: String,
+    /// Size in bytes.
+    pub size: u64,
+    /// Model name.
+    pub model: Option<String>,
+    /// Vendor name.
+    pub vendor: Option<String>,
+    /// Transport (e.g., "sata", "nvme", "usb", "virtio").
+    pub transport: Option<String>,
+    /// Whether it is a removable device.
+    pub removable: bool,
+    /// Whether it is a rotational device.
+    pub rotational: bool,
+    /// Persistent paths from /dev/disk/by-id, sorted.
+    pub by_id: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkInterface {
+    /// Interface name (e.g., "eth0").
+    pub name: String,
+    /// MAC address.
+    pub mac: Option<String>,
+    /// Kernel driver (e.g., "e1000e").
+    pub driver: Option<String>,
+}
+
+/// Reads the hardware inventory from sysfs and /proc.
+///
+/// ```no_run
+/// use agama_lib::hardware::HardwareReader;
+///
+/// let hardware = HardwareReader::default().read();
+/// println!("{} disks found", hardware.disks.len());
+/// ```
+pub struct HardwareReader {
+    root: PathBuf,
+}
+
+impl Default for HardwareReader {
+    fn default() -> Self {
+        Self::new("/")
+    }
+}
+
+impl HardwareReader {
+    /// Builds a reader for the given root directory.
+    ///
+    /// * `root`: directory containing the `sys`, `proc` and `dev` directories.
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Builds the hardware inventory.
+    ///
+    /// Missing information is not considered an error, so the corresponding values are just
+    /// left empty.
+    pub fn read(&self) -> HardwareInfo {
+        HardwareInfo {
+            firmware: self.firmware(),
+            virtualization: self.virtualization(),
+            cpu: self.cpu(),
+            memory: self.memory(),
+            disks: self.disks(),
+            network_interfaces: self.network_interfaces(),
+        }
+    }
+
+    fn path(&self, path: &str) -> PathBuf {
+        self.root.join(path.trim_start_matches('/'))
+    }
+
+    fn firmware(&self) -> Firmware {
+        if self.path("/sys/firmware/efi").exists() {
+            Firmware::Uefi
+        } else {
+            Firmware::Bios
+        }
+    }
+
+    fn virtualization(&self) -> Option<String> {
+        if let Some(hypervisor) = read_value(&self.path("/sys/hypervisor/type")) {
+            return Some(hypervisor);
+        }
+
+        let dmi = ["sys_vendor", "product_name", "bios_vendor"]
+            .iter()
+            .filter_map(|f| read_value(&self.path("/sys/class/dmi/id").join(f)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let known = [
+            ("KVM", "kvm"),
+            ("QEMU", "qemu"),
+            ("VMware", "vmware"),
+            ("VirtualBox", "oracle"),
+            ("Microsoft Corporation", "microsoft"),
+            ("Xen", "xen"),
+            ("Amazon EC2", "amazon"),
+            ("Google Compute Engine", "google"),
+        ];
+        if let Some((_, name)) = known.iter().find(|(text, _)| dmi.contains(text)) {
+            return Some(name.to_string());
+        }
+
+        let cpuinfo = fs::read_to_string(self.path("/proc/cpuinfo")).unwrap_or_default();
+        let is_virtual = cpuinfo_values(&cpuinfo, "flags")
+            .any(|flags| flags.split_whitespace().any(|f| f == "hypervisor"));
+        is_virtual.then(|| "unknown".to_string())
+    }
+
+    fn cpu(&self) -> Cpu {
+        let cpuinfo = fs::read_to_string(self.path("/proc/cpuinfo")).unwrap_or_default();
+        let model = cpuinfo_values(&cpuinfo, "model name").next();
+        let vendor = cpuinfo_values(&cpuinfo, "vendor_id").next();
+        let count = cpuinfo_values(&cpuinfo, "processor").count();
+        Cpu {
+            architecture: std::env::consts::ARCH.to_string(),
+            model,
+            vendor,
+            count,
+        }
+    }
+
+    fn memory(&self) -> Memory {
+        let meminfo = fs::read_to_string(self.path("/proc/meminfo")).unwrap_or_default();
+        let total = meminfo
+            .lines()
+            .find_map(|l| l.strip_prefix("MemTotal:"))
+            .and_then(|v| v.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+            .unwrap_or_default();
+        Memory {
+            total: total * 1024,
+        }
+    }
+
+    fn disks(&self) -> Vec<Disk> {
+        let mut disks: Vec<_> = dir_names(&self.path("/sys/block"))
+            .into_iter()
+            .filter(|n| !VIRTUAL_DISK_PREFIXES.iter().any(|p| n.starts_with(p)))
+            .filter_map(|n| self.disk(&n))
+            .collect();
+        disks.sort_by(|a, b| a.name.cmp(&b.name));
+        disks
+    }
+
+    fn disk(&self, name: &str) -> Option<Disk> {
+        let path = self.path("/sys/block").join(name);
+        let device_path = path.join("device");
+        if !device_path.exists() {
+            return None;
+        }
+
+        let size = read_value(&path.join("size"))
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or_default();
+        Some(Disk {
+            name: name.to_string(),
+            device: format!("/dev/{}", name),
+            size: size * SECTOR_SIZE,
+            model: read_value(&device_path.join("model")),
+            vendor: read_value(&device_path.join("vendor")),
+            transport: self.transport(name, &path),
+            removable: read_value(&path.join("removable")).as_deref() == Some("1"),
+            rotational: read_value(&path.join("queue/rotational")).as_deref() == Some("1"),
+            by_id: self.disk_ids(name),
+        })
+    }
+
+    fn transport(&self, name: &str, path: &Path) -> Option<String> {
+        let by_name = [
+            ("nvme", "nvme"),
+            ("vd", "virtio"),
+            ("mmcblk", "mmc"),
+            ("xvd", "xen"),
+        ];
+        if let Some((_, transport)) = by_name.iter().find(|(p, _)| name.starts_with(p)) {
+            return Some(transport.to_string());
+        }
+
+        // /sys/block/<name> links to the device in the /sys/devices tree, which includes the bus
+        let target = fs::read_link(path).ok()?;
+        let target = target.to_string_lossy();
+        let transport = if target.contains("/usb") {
+            "usb"
+        } else if target.contains("/ata") {
+            "sata"
+        } else if target.contains("/virtio") {
+            "virtio"
+        } else if target.contains("/host") {
+            "scsi"
+        } else {
+            return None;
+        };
+        Some(transport.to_string())
+    }
+
+    fn disk_ids(&self, name: &str) -> Vec<String> {
+        let dir = self.path("/dev/disk/by-id");
+        let mut ids: Vec<_> = dir_names(&dir)
+            .into_iter()
+            .filter(|id| {
+                fs::read_link(dir.join(id))
+                    .map(|t| t.file_name().is_some_and(|f| f == name))
+                    .unwrap_or(false)
+            })
+            .map(|id| format!("/dev/disk/by-id/{}", id))
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    fn network_interfaces(&self) -> Vec<NetworkInterface> {
+        let dir = self.path("/sys/class/net");
+        let mut interfaces: Vec<_> = dir_names(&dir)
+            .into_iter()
+            // virtual interfaces (loopback, bridges, etc.) have no device
+            .filter(|n| dir.join(n).join("device").exists())
+            .map(|name| {
+                let path = dir.join(&name);
+                let driver = fs::read_link(path.join("device/driver"))
+                    .ok()
+                    .and_then(|t| t.file_name().map(|f| f.to_string_lossy().to_string()));
+                NetworkInterface {
+                    mac: read_value(&path.join("address")),
+                    driver,
+                    name,
+                }
+            })
+            .collect();
+        interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+        interfaces
+    }
+}
+
+/// Reads a sysfs value, returning `None` if it does not exist or it is empty.
+fn read_value(path: &Path) -> Option<String> {
+    let value = fs::read_to_string(path).ok()?;
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Returns the names of the entries of a directory, or an empty list if it does not exist.
+fn dir_names(path: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(path) else {
+        return vec![];
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect()
+}
+
+/// Returns the values of the given key from /proc/cpuinfo (one per processor).
+fn cpuinfo_values<'a>(cpuinfo: &'a str, key: &'a str) -> impl Iterator<Item = String> + 'a {
+    cpuinfo.lines().filter_map(move |line| {
+        let (k, v) = line.split_once(':')?;
+        (k.trim() == key).then(|| v.trim().to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn link(root: &Path, path: &str, target: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        symlink(target, path).unwrap();
+    }
+
+    fn fake_root() -> TempDir {
+        let dir = TempDir::with_prefix("hardware").unwrap();
+        let root = dir.path();
+
+        fs::create_dir_all(root.join("sys/firmware/efi")).unwrap();
+        write(
+            root,
+            "proc/cpuinfo",
+            "processor\t: 0\nvendor_id\t: GenuineIntel\nmodel name\t: Intel(R) Xeon(R)\nflags\t\t: fpu hypervisor\n\n\
+             processor\t: 1\nvendor_id\t: GenuineIntel\nmodel name\t: Intel(R) Xeon(R)\nflags\t\t: fpu hypervisor\n",
+        );
+        write(
+            root,
+            "proc/meminfo",
+            "MemTotal:        8045000 kB\nMemFree:  1000 kB\n",
+        );
+        write(root, "sys/class/dmi/id/sys_vendor", "QEMU\n");
+
+        // a SATA disk
+        fs::create_dir_all(root.join("sys/devices/pci0000:00/ata1/host0/block/sda")).unwrap();
+        link(
+            root,
+            "sys/block/sda",
+            "../devices/pci0000:00/ata1/host0/block/sda",
+        );
+        let sda = "sys/devices/pci0000:00/ata1/host0/block/sda";
+        write(root, &format!("{sda}/size"), "41943040\n");
+        write(root, &format!("{sda}/removable"), "0\n");
+        write(root, &format!("{sda}/queue/rotational"), "1\n");
+        write(root, &format!("{sda}/device/model"), "QEMU HARDDISK   \n");
+        write(root, &format!("{sda}/device/vendor"), "ATA\n");
+        link(root, "dev/disk/by-id/ata-QEMU_HARDDISK_QM1", "../../sda");
+        link(
+            root,
+            "dev/disk/by-id/ata-QEMU_HARDDISK_QM1-part1",
+            "../../sda1",
+        );
+
+        // an NVMe disk
+        write(root, "sys/block/nvme0n1/size", "2048\n");
+        write(root, "sys/block/nvme0n1/device/model", "Samsung SSD\n");
+        link(root, "dev/disk/by-id/nvme-Samsung_SSD_1", "../../nvme0n1");
+
+        // a loop device
+        write(root, "sys/block/loop0/size", "1024\n");
+
+        // network interfaces
+        write(root, "sys/class/net/eth0/address", "52:54:00:12:34:56\n");
+        fs::create_dir_all(root.join("sys/bus/pci/drivers/e1000e")).unwrap();
+        fs::create_dir_all(root.join("sys/class/net/eth0/device")).unwrap();
+        link(
+            root,
+            "sys/class/net/eth0/device/driver",
+            "../../../../bus/pci/drivers/e1000e",
+        );
+        write(root, "sys/class/net/lo/address", "00:00:00:00:00:00\n");
+
+        dir
+    }
+
+    #[test]
+    fn test_read_hardware() {
+        let root = fake_root();
+        let hardware = HardwareReader::new(root.path()).read();
+
+        assert_eq!(hardware.firmware, Firmware::Uefi);
+        assert_eq!(hardware.virtualization, Some("qemu".to_string()));
+        assert_eq!(hardware.cpu.count, 2);
+        assert_eq!(hardware.cpu.model, Some("Intel(R) Xeon(R)".to_string()));
+        assert_eq!(hardware.cpu.vendor, Some("GenuineIntel".to_string()));
+        assert_eq!(hardware.memory.total, 8045000 * 1024);
+
+        assert_eq!(
+            hardware.disks,
+            vec![
+                Disk {
+                    name: "nvme0n1".to_string(),
+                    device: "/dev/nvme0n1".to_string(),
+                    size: 1048576,
+                    model: Some("Samsung SSD".to_string()),
+                    transport: Some("nvme".to_string()),
+                    by_id: vec!["/dev/disk/by-id/nvme-Samsung_SSD_1".to_string()],
+                    ..Default::default()
+                },
+                Disk {
+                    name: "sda".to_string(),
+                    device: "/dev/sda".to_string(),
+                    size: 21474836480,
+                    model: Some("QEMU HARDDISK".to_string()),
+                    vendor: Some("ATA".to_string()),
+                    transport: Some("sata".to_string()),
+                    removable: false,
+                    rotational: true,
+                    by_id: vec!["/dev/disk/by-id/ata-QEMU_HARDDISK_QM1".to_string()],
+                }
+            ]
+        );
+
+        assert_eq!(
+            hardware.network_interfaces,
+            vec![NetworkInterface {
+                name: "eth0".to_string(),
+                mac: Some("52:54:00:12:34:56".to_string()),
+                driver: Some("e1000e".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_read_empty_root() {
+        let root = TempDir::with_prefix("hardware").unwrap();
+        let hardware = HardwareReader::new(root.path()).read();
+
+        assert_eq!(hardware.firmware, Firmware::Bios);
+        assert_eq!(hardware.virtualization, None);
+        assert_eq!(hardware.cpu.count, 0);
+        assert_eq!(hardware.memory.total, 0);
+        assert!(hardware.disks.is_empty());
+        assert!(hardware.network_interfaces.is_empty());
+    }
+}
@@ -22,16 +22,20 @@
 //!
 //! This module implements the mechanisms to load and store the installation settings.
 use crate::{
-    localization::LocalizationSettings, network::NetworkSettings, product::ProductSettings,
+    error::ProfileError, localization::LocalizationSettings, network::NetworkSettings,
+    product::ProductSettings, profile::ProfileFormat, proxy::ProxySettings,
     software::SoftwareSettings, users::UserSettings,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::value::RawValue;
 use std::default::Default;
-use std::fs::File;
-use std::io::BufReader;
+use std::fs;
+use std::io;
 use std::path::Path;
 
+pub mod diff;
+pub use diff::{SettingChange, SettingsDiff};
+
 /// Installation settings
 ///
 /// This struct represents installation settings. It serves as an entry point and it is composed of
@@ -54,14 +58,30 @@ pub struct InstallSettings {
     #[serde(default)]
     pub network: Option<NetworkSettings>,
     #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<ProxySettings>,
+    #[serde(default)]
     pub localization: Option<LocalizationSettings>,
 }
 
 impl InstallSettings {
+    /// Reads the settings from a file.
+    ///
+    /// The format (JSON, YAML or TOML) is determined by the extension or the content.
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, std::io::Error> {
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
-        let data = serde_json::from_reader(reader)?;
-        Ok(data)
+        let content = fs::read_to_string(&path)?;
+        let format = ProfileFormat::guess(Some(path.as_ref()), &content);
+        Self::from_content(&content, format)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Reads the settings from a string in the given format.
+    ///
+    /// * `content`: settings content.
+    /// * `format`: settings format.
+    pub fn from_content(content: &str, format: ProfileFormat) -> Result<Self, ProfileError> {
+        // the settings contain raw JSON values, so they must be read from JSON
+        let json = format.to_json(content)?;
+        Ok(serde_json::from_str(&json)?)
     }
 }
@@ -0,0 +1,225 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! Compares two sets of installation settings.
+//!
+//! The comparison works on the JSON representation of the settings and it is done per section
+//! (top-level key). Only the sections included in the candidate settings are compared, as the
+//! rest of them are not modified when the settings are stored.
+//!
+//! Within a section:
+//!
+//! * A `null` value in the candidate settings means "not set", so it is not reported.
+//! * A key that is missing in the candidate settings is reported as removed.
+//! * Arrays are compared as a whole.
+
+use super::InstallSettings;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Represents a change in a setting.
+///
+/// The `path` is relative to the section and uses dots to separate the keys (e.g.,
+/// `firstUser.userName`).
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum SettingChange {
+    Added {
+        path: String,
+        value: Value,
+    },
+    Removed {
+        path: String,
+        value: Value,
+    },
+    Changed {
+        path: String,
+        old: Value,
+        new: Value,
+    },
+}
+
+/// Differences between two sets of installation settings, grouped by section.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct SettingsDiff(BTreeMap<String, Vec<SettingChange>>);
+
+impl SettingsDiff {
+    /// Compares the current settings with the candidate ones.
+    ///
+    /// * `current`: current installation settings.
+    /// * `candidate`: settings to compare with.
+    pub fn new(
+        current: &InstallSettings,
+        candidate: &InstallSettings,
+    ) -> Result<Self, serde_json::Error> {
+        let current = serde_json::to_value(current)?;
+        let candidate = serde_json::to_value(candidate)?;
+        Ok(Self::from_values(&current, &candidate))
+    }
+
+    /// Compares the JSON representation of two sets of settings.
+    ///
+    /// * `current`: current installation settings.
+    /// * `candidate`: settings to compare with.
+    pub fn from_values(current: &Value, candidate: &Value) -> Self {
+        let mut sections = BTreeMap::new();
+        let Some(candidate) = candidate.as_object() else {
+            return Self(sections);
+        };
+
+        for (section, value) in candidate {
+            if value.is_null() {
+                continue;
+            }
+            let mut changes = vec![];
+            compare("", current.get(section), Some(value), &mut changes);
+            if !changes.is_empty() {
+                sections.insert(section.to_string(), changes);
+            }
+        }
+        Self(sections)
+    }
+
+    /// Whether there are no differences.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns an iterator over the changed sections and their changes.
+    pub fn sections(&self) -> impl Iterator<Item = (&String, &Vec<SettingChange>)> {
+        self.0.iter()
+    }
+}
+
+/// Compares two values, adding the differences to the list of changes.
+///
+/// * `path`: path of the values.
+/// * `old`: current value, if any.
+/// * `new`: candidate value, if any.
+/// * `changes`: list of changes.
+fn compare(path: &str, old: Option<&Value>, new: Option<&Value>, changes: &mut Vec<SettingChange>) {
+    let old = old.filter(|v| !v.is_null());
+    match (old, new) {
+        (_, Some(Value::Null)) | (None, None) => {}
+        (Some(old), None) => changes.push(SettingChange::Removed {
+            path: path.to_string(),
+            value: old.clone(),
+        }),
+        (old, Some(Value::Object(new))) if matches!(old, None | Some(Value::Object(_))) => {
+            let old = old.and_then(Value::as_object);
+            let mut keys: Vec<&String> = new.keys().collect();
+            if let Some(old) = old {
+                keys.extend(old.keys().filter(|k| !new.contains_key(*k)));
+            }
+            for key in keys {
+                let path = if path.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                compare(&path, old.and_then(|o| o.get(key)), new.get(key), changes);
+            }
+        }
+        (None, Some(new)) => changes.push(SettingChange::Added {
+            path: path.to_string(),
+            value: new.clone(),
+        }),
+        (Some(old), Some(new)) => {
+            if old != new {
+                changes.push(SettingChange::Changed {
+                    path: path.to_string(),
+                    old: old.clone(),
+                    new: new.clone(),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SettingChange, SettingsDiff};
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_sections() {
+        let current = json!({
+            "product": { "id": "Tumbleweed", "registrationCode": null },
+            "software": { "patterns": ["gnome"] },
+            "storage": { "drives": [], "boot": { "configure": true } }
+        });
+        let candidate = json!({
+            "product": { "id": "MicroOS", "registrationCode": "123" },
+            "software": null,
+            "storage": { "drives": [] },
+            "localization": { "language": "es_ES.UTF-8", "keyboard": null }
+        });
+
+        let diff = SettingsDiff::from_values(&current, &candidate);
+        let sections: Vec<_> = diff.sections().collect();
+        assert_eq!(sections.len(), 3);
+
+        let (name, changes) = sections[0];
+        assert_eq!(name, "localization");
+        assert_eq!(
+            changes,
+            &vec![SettingChange::Added {
+                path: "language".to_string(),
+                value: json!("es_ES.UTF-8")
+            }]
+        );
+
+        let (name, changes) = sections[1];
+        assert_eq!(name, "product");
+        assert_eq!(
+            changes,
+            &vec![
+                SettingChange::Changed {
+                    path: "id".to_string(),
+                    old: json!("Tumbleweed"),
+                    new: json!("MicroOS")
+                },
+                SettingChange::Added {
+                    path: "registrationCode".to_string(),
+                    value: json!("123")
+                }
+            ]
+        );
+
+        let (name, changes) = sections[2];
+        assert_eq!(name, "storage");
+        assert_eq!(
+            changes,
+            &vec![SettingChange::Removed {
+                path: "boot".to_string(),
+                value: json!({ "configure": true })
+            }]
+        );
+    }
+
+    #[test]
+    fn test_diff_no_changes() {
+        let current = json!({ "software": { "patterns": ["gnome"] } });
+        let diff = SettingsDiff::from_values(&current, &current);
+        assert!(diff.is_empty());
+    }
+}
@@ -46,6 +46,7 @@
 pub mod auth;
 pub mod base_http_client;
 pub mod error;
+pub mod hardware;
 pub mod install_settings;
 pub mod jobs;
 pub mod localization;
@@ -53,15 +54,17 @@ pub mod manager;
 pub mod network;
 pub mod product;
 pub mod profile;
+pub mod proxy;
 pub mod software;
 pub mod storage;
+pub mod transfer;
 pub mod users;
 // TODO: maybe expose only clients when we have it?
 pub mod dbus;
 pub mod progress;
 pub mod proxies;
 mod store;
-pub use store::Store;
+pub use store::{SectionResult, SectionStatus, SettingsSection, Store};
 pub mod questions;
 use crate::error::ServiceError;
 use reqwest::{header, Client};
@@ -21,6 +21,7 @@
 //! Implements support for handling the network settings
 
 mod client;
+pub mod diagnostics;
 mod proxies;
 pub mod settings;
 mod store;
@@ -18,10 +18,15 @@
 // To contact SUSE LLC about this file by physical or electronic mail, you may
 // find current contact information at www.suse.com.
 
-use super::{settings::NetworkConnection, types::Device};
+use super::{
+    diagnostics::{DiagnosticsOptions, DiagnosticsReport},
+    settings::{GlobalSettings, NetworkConnection},
+    types::Device,
+};
 use crate::error::ServiceError;
-use reqwest::{Client, Response};
+use reqwest::{header::CONTENT_TYPE, Client, Response};
 use serde_json;
+use url::form_urlencoded;
 
 const API_URL: &str = "http://localhost/api/network";
 
@@ -82,7 +87,7 @@ impl NetworkClient {
 
     /// Returns an array of network connections
     pub async fn connection(&self, id: &str) -> Result<NetworkConnection, ServiceError> {
-        let text = self.get(format!("/connections/{id}").as_str()).await?;
+        let text = self.get(&connection_path(id)).await?;
         let json: NetworkConnection = serde_json::from_str(&text)
             .map_err(|e| ServiceError::NetworkClientError(e.to_string()))?;
 
@@ -98,7 +103,7 @@ impl NetworkClient {
         let response = self.connection(id.as_str()).await;
 
         if response.is_ok() {
-            let path = format!("{API_URL}/connections/{id}");
+            let path = format!("{API_URL}{}", connection_path(&id));
             self.client
                 .put(path)
                 .json(&connection)
@@ -117,14 +122,132 @@ impl NetworkClient {
         Ok(())
     }
 
+    /// Removes a network connection
+    ///
+    /// * `id`: connection ID.
+    pub async fn remove_connection(&self, id: &str) -> Result<(), ServiceError> {
+        let response = self
+            .client
+            .delete(format!("{API_URL}{}", connection_path(id)))
+            .send()
+            .await
+            .map_err(|e| ServiceError::NetworkClientError(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(ServiceError::NetworkClientError(format!(
+                "Could not remove the connection '{id}'"
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the global network settings (hostname, DNS, etc.)
+    pub async fn global(&self) -> Result<GlobalSettings, ServiceError> {
+        let text = self.get("/global").await?;
+        let json: GlobalSettings = serde_json::from_str(&text)
+            .map_err(|e| ServiceError::NetworkClientError(e.to_string()))?;
+
+        Ok(json)
+    }
+
+    /// Updates the global network settings
+    ///
+    /// * `settings`: global network settings.
+    pub async fn set_global(&self, settings: &GlobalSettings) -> Result<(), ServiceError> {
+        let response = self
+            .client
+            .put(format!("{API_URL}/global"))
+            .json(settings)
+            .send()
+            .await
+            .map_err(|e| ServiceError::NetworkClientError(e.to_string()))?;
+        self.text_for(response).await?;
+
+        Ok(())
+    }
+
     /// Returns an array of network connections
     pub async fn apply(&self) -> Result<(), ServiceError> {
         self.client
-            .put(format!("{API_URL}/system/apply"))
+            .post(format!("{API_URL}/system/apply"))
             .send()
             .await
             .map_err(|e| ServiceError::NetworkClientError(e.to_string()))?;
 
         Ok(())
     }
+
+    /// Returns the network configuration as an nmstate YAML document
+    pub async fn nmstate(&self) -> Result<String, ServiceError> {
+        self.get("/nmstate").await
+    }
+
+    /// Updates the network configuration from an nmstate YAML document
+    ///
+    /// * `content`: nmstate YAML document.
+    pub async fn set_nmstate(&self, content: &str) -> Result<(), ServiceError> {
+        let response = self
+            .client
+            .put(format!("{API_URL}/nmstate"))
+            .header(CONTENT_TYPE, "application/yaml")
+            .body(content.to_string())
+            .send()
+            .await
+            .map_err(|e| ServiceError::NetworkClientError(e.to_string()))?;
+
+        if !response.status().is_success() {
+            let text = response
+                .text()
+                .await
+                .map_err(|e| ServiceError::NetworkClientError(e.to_string()))?;
+            return Err(ServiceError::NetworkClientError(text));
+        }
+
+        Ok(())
+    }
+
+    /// Runs the network connectivity diagnostics
+    ///
+    /// * `options`: diagnostics options.
+    pub async fn diagnostics(
+        &self,
+        options: &DiagnosticsOptions,
+    ) -> Result<DiagnosticsReport, ServiceError> {
+        let response = self
+            .client
+            .post(format!("{API_URL}/diagnostics"))
+            .json(options)
+            .send()
+            .await
+            .map_err(|e| ServiceError::NetworkClientError(e.to_string()))?;
+        let text = self.text_for(response).await?;
+        let json: DiagnosticsReport = serde_json::from_str(&text)
+            .map_err(|e| ServiceError::NetworkClientError(e.to_string()))?;
+
+        Ok(json)
+    }
+}
+
+/// Returns the path of the given connection, percent-encoding its ID.
+///
+/// * `id`: connection ID.
+fn connection_path(id: &str) -> String {
+    // byte_serialize encodes "+" too, so any remaining "+" stands for a space
+    let encoded: String = form_urlencoded::byte_serialize(id.as_bytes()).collect();
+    format!("/connections/{}", encoded.replace('+', "%20"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::connection_path;
+
+    #[test]
+    fn test_connection_path() {
+        assert_eq!(connection_path("eth0"), "/connections/eth0");
+        assert_eq!(
+            connection_path("Wired 1/a+b?#"),
+            "/connections/Wired%201%2Fa%2Bb%3F%23"
+        );
+    }
 }
@@ -0,0 +1,165 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! Types to represent the network connectivity diagnostics.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Options to run the network diagnostics.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsOptions {
+    /// Device to check (all of them, except the loopback, if not set).
+    pub device: Option<String>,
+    /// Comma-separated list of hostnames to resolve.
+    pub hosts: Option<String>,
+    /// HTTP(S) URL to fetch. Its hostname is resolved too.
+    pub url: Option<String>,
+    /// Proxy to use when fetching the URL.
+    pub proxy: Option<String>,
+    /// TCP port to check the gateways reachability (ICMP is used if not set).
+    pub gateway_port: Option<u16>,
+}
+
+impl DiagnosticsOptions {
+    /// Returns the hostnames to resolve.
+    pub fn hostnames(&self) -> Vec<&str> {
+        self.hosts
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|h| !h.is_empty())
+            .collect()
+    }
+}
+
+/// Kind of check.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckKind {
+    /// The device is connected.
+    Link,
+    /// The device has an IP address.
+    Address,
+    /// The device has a default route.
+    DefaultRoute,
+    /// The gateway is reachable.
+    Gateway,
+    /// The hostname can be resolved.
+    Dns,
+    /// The URL can be fetched.
+    Http,
+}
+
+impl fmt::Display for CheckKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CheckKind::Link => "link",
+            CheckKind::Address => "address",
+            CheckKind::DefaultRoute => "default route",
+            CheckKind::Gateway => "gateway",
+            CheckKind::Dns => "dns",
+            CheckKind::Http => "http",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Result of a check.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    /// The check could not run (e.g., there is no gateway to reach).
+    Skipped,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CheckStatus::Passed => "passed",
+            CheckStatus::Failed => "failed",
+            CheckStatus::Skipped => "skipped",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckResult {
+    pub kind: CheckKind,
+    pub status: CheckStatus,
+    /// Checked element (a device, an IP address, a hostname or a URL).
+    pub target: String,
+    /// Additional information (e.g., the resolved addresses or the error).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+}
+
+impl CheckResult {
+    pub fn new(kind: CheckKind, status: CheckStatus, target: &str) -> Self {
+        Self {
+            kind,
+            status,
+            target: target.to_string(),
+            details: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: &str) -> Self {
+        self.details = Some(details.to_string());
+        self
+    }
+}
+
+/// Checks of a single device.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceDiagnostics {
+    pub device: String,
+    /// ID of the connection which is active on the device.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connection: Option<String>,
+    pub checks: Vec<CheckResult>,
+}
+
+/// Network diagnostics report.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsReport {
+    pub devices: Vec<DeviceDiagnostics>,
+    /// Checks which do not depend on a device (DNS resolution and HTTP reachability).
+    pub checks: Vec<CheckResult>,
+}
+
+impl DiagnosticsReport {
+    /// Whether all the checks passed (or were skipped).
+    pub fn is_ok(&self) -> bool {
+        self.devices
+            .iter()
+            .flat_map(|d| d.checks.iter())
+            .chain(self.checks.iter())
+            .all(|c| c.status != CheckStatus::Failed)
+    }
+}
@@ -31,7 +31,35 @@ use std::net::IpAddr;
 #[serde(rename_all = "camelCase")]
 pub struct NetworkSettings {
     /// Connections to use in the installation
+    #[serde(default)]
     pub connections: Vec<NetworkConnection>,
+    /// Settings which are not bound to any connection (hostname, DNS, etc.)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub global: Option<GlobalSettings>,
+}
+
+/// Global network settings
+///
+/// They are not bound to any connection, so they apply to the whole system.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GlobalSettings {
+    /// System hostname
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    /// Whether the hostname is "static" (persistent) or "transient" (only for the running
+    /// system)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname_mode: Option<String>,
+    /// Name servers to use regardless of the connections
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub nameservers: Vec<IpAddr>,
+    /// Search domains to use regardless of the connections
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub dns_searchlist: Vec<String>,
+    /// Resolver options (e.g., "rotate" or "timeout:2")
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub dns_options: Vec<String>,
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -113,6 +141,213 @@ impl Default for BondSettings {
     }
 }
 
+/// Bridge configuration
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgeSettings {
+    /// Whether the Spanning Tree Protocol is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stp: Option<bool>,
+    /// Bridge priority (lower values have a higher priority)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u32>,
+    /// Spanning tree forwarding delay, in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forward_delay: Option<u32>,
+    /// Spanning tree hello time, in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hello_time: Option<u32>,
+    /// Spanning tree maximum message age, in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<u32>,
+    /// Ethernet MAC address aging time, in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ageing_time: Option<u32>,
+    /// Interfaces or connections to add to the bridge
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub ports: Vec<String>,
+}
+
+/// Settings of a connection which is a port of a bridge
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgePortSettings {
+    /// Spanning tree priority of the port
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u32>,
+    /// Spanning tree cost of the port
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path_cost: Option<u32>,
+}
+
+/// VLAN configuration
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VlanSettings {
+    /// VLAN identifier
+    pub id: u32,
+    /// Interface or connection of the parent device
+    pub parent: String,
+    /// VLAN protocol ("802.1Q" or "802.1ad")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<String>,
+}
+
+/// InfiniBand configuration
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InfinibandSettings {
+    /// Partition key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub p_key: Option<i32>,
+    /// Parent interface (only for partitions)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// Transport mode ("datagram" or "connected")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transport_mode: Option<String>,
+}
+
+/// TUN/TAP configuration
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TunSettings {
+    /// Device mode ("tun" or "tap")
+    pub mode: String,
+    /// Group ID of the device owner
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// User ID of the device owner
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+}
+
+/// WireGuard configuration
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WireguardSettings {
+    /// Base64-encoded private key of the interface
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub private_key: Option<String>,
+    /// UDP port to listen on (random if not set)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub listen_port: Option<u32>,
+    /// Firewall mark for the outgoing packets
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fwmark: Option<u32>,
+    /// Peers of the tunnel
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub peers: Vec<WireguardPeerSettings>,
+}
+
+/// WireGuard peer
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WireguardPeerSettings {
+    /// Base64-encoded public key of the peer
+    pub public_key: String,
+    /// Base64-encoded pre-shared key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preshared_key: Option<String>,
+    /// Endpoint of the peer ("host:port")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// Networks which are routed through the peer (e.g., "10.0.0.0/24")
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub allowed_ips: Vec<IpInet>,
+    /// Interval, in seconds, to send keep-alive packets
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub persistent_keepalive: Option<u32>,
+}
+
+/// VRF configuration
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VrfSettings {
+    /// Routing table associated to the VRF
+    pub table: u32,
+    /// Interfaces or connections to enslave to the VRF
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub ports: Vec<String>,
+}
+
+/// Open vSwitch bridge configuration
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OvsBridgeSettings {
+    /// Whether the Spanning Tree Protocol is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stp: Option<bool>,
+    /// Whether the Rapid Spanning Tree Protocol is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rstp: Option<bool>,
+    /// Whether the multicast snooping is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mcast_snooping: Option<bool>,
+    /// Fail mode ("secure" or "standalone")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fail_mode: Option<String>,
+    /// OVS ports of the bridge
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub ports: Vec<String>,
+}
+
+/// Open vSwitch port configuration
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OvsPortSettings {
+    /// VLAN tag of the port
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<u32>,
+    /// Interfaces or connections to add to the port
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub ports: Vec<String>,
+}
+
+/// Open vSwitch interface configuration
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OvsInterfaceSettings {
+    /// Interface type ("internal" or "system")
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub interface_type: Option<String>,
+}
+
+/// DHCP client settings
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DhcpSettings {
+    /// Client identifier (DHCPv4) or DUID (DHCPv6)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    /// Whether to send the hostname to the DHCP server
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub send_hostname: Option<bool>,
+    /// Hostname to send instead of the system one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    /// Fully qualified domain name to send (DHCPv4 only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fqdn: Option<String>,
+    /// Timeout (in seconds) to get a lease
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u32>,
+}
+
+/// Static route
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IpRouteSettings {
+    /// Destination network (e.g., "10.0.0.0/8")
+    pub destination: IpInet,
+    /// Next hop address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_hop: Option<IpAddr>,
+    /// Route metric
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metric: Option<u32>,
+}
+
 /// IEEE 802.1x (EAP) settings
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -186,6 +421,28 @@ pub struct NetworkConnection {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ignore_auto_dns: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub routes4: Option<Vec<IpRouteSettings>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub routes6: Option<Vec<IpRouteSettings>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub route_metric4: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub route_metric6: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dhcp4: Option<DhcpSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dhcp6: Option<DhcpSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub may_fail4: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub may_fail6: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub addr_gen_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip6_privacy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub firewall_zone: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub wireless: Option<WirelessSettings>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub interface: Option<String>,
@@ -195,6 +452,30 @@ pub struct NetworkConnection {
     pub parent: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bond: Option<BondSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bridge: Option<BridgeSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bridge_port: Option<BridgePortSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vlan: Option<VlanSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub infiniband: Option<InfinibandSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tun: Option<TunSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wireguard: Option<WireguardSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vrf: Option<VrfSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ovs_bridge: Option<OvsBridgeSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ovs_port: Option<OvsPortSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ovs_interface: Option<OvsInterfaceSettings>,
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub dummy: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub loopback: bool,
     #[serde(rename = "mac-address", skip_serializing_if = "Option::is_none")]
     pub mac_address: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -219,8 +500,71 @@ impl NetworkConnection {
             DeviceType::Wireless
         } else if self.bond.is_some() {
             DeviceType::Bond
+        } else if self.bridge.is_some() {
+            DeviceType::Bridge
+        } else if self.vlan.is_some() {
+            DeviceType::Vlan
+        } else if self.wireguard.is_some() {
+            DeviceType::Wireguard
+        } else if self.vrf.is_some() {
+            DeviceType::Vrf
+        } else if self.ovs_bridge.is_some() {
+            DeviceType::OvsBridge
+        } else if self.ovs_port.is_some() {
+            DeviceType::OvsPort
+        } else if self.ovs_interface.is_some() {
+            DeviceType::OvsInterface
+        } else if self.dummy {
+            DeviceType::Dummy
+        } else if self.loopback {
+            DeviceType::Loopback
         } else {
             DeviceType::Ethernet
         }
     }
+
+    /// Names of the connections or interfaces which this connection depends on.
+    ///
+    /// They include the ports of a controller (e.g., a bond or a bridge) and the parent of a VLAN
+    /// or an InfiniBand partition.
+    pub fn dependencies(&self) -> Vec<&str> {
+        let mut dependencies: Vec<&str> = vec![];
+        if let Some(ports) = self.ports() {
+            dependencies.extend(ports.iter().map(String::as_str));
+        }
+        if let Some(vlan) = &self.vlan {
+            dependencies.push(vlan.parent.as_str());
+        }
+        if let Some(parent) = self.infiniband.as_ref().and_then(|i| i.parent.as_ref()) {
+            dependencies.push(parent.as_str());
+        }
+        dependencies
+    }
+
+    /// Ports of the connection, if it is a controller (a bond, a bridge, a VRF or an OVS bridge
+    /// or port).
+    pub fn ports(&self) -> Option<&Vec<String>> {
+        self.bond
+            .as_ref()
+            .map(|b| &b.ports)
+            .or(self.bridge.as_ref().map(|b| &b.ports))
+            .or(self.vrf.as_ref().map(|v| &v.ports))
+            .or(self.ovs_bridge.as_ref().map(|b| &b.ports))
+            .or(self.ovs_port.as_ref().map(|p| &p.ports))
+    }
+
+    /// Mutable reference to the ports of the connection, if it is a controller.
+    pub fn ports_mut(&mut self) -> Option<&mut Vec<String>> {
+        if let Some(bond) = self.bond.as_mut() {
+            Some(&mut bond.ports)
+        } else if let Some(bridge) = self.bridge.as_mut() {
+            Some(&mut bridge.ports)
+        } else if let Some(vrf) = self.vrf.as_mut() {
+            Some(&mut vrf.ports)
+        } else if let Some(ovs_bridge) = self.ovs_bridge.as_mut() {
+            Some(&mut ovs_bridge.ports)
+        } else {
+            self.ovs_port.as_mut().map(|p| &mut p.ports)
+        }
+    }
 }
@@ -37,8 +37,12 @@ impl NetworkStore {
     // TODO: read the settings from the service
     pub async fn load(&self) -> Result<NetworkSettings, ServiceError> {
         let connections = self.network_client.connections().await?;
+        let global = self.network_client.global().await?;
 
-        Ok(NetworkSettings { connections })
+        Ok(NetworkSettings {
+            connections,
+            global: Some(global),
+        })
     }
 
     pub async fn store(&self, settings: &NetworkSettings) -> Result<(), ServiceError> {
@@ -50,10 +54,29 @@ impl NetworkStore {
                 .add_or_update_connection(conn.clone())
                 .await?;
         }
+        if let Some(global) = &settings.global {
+            self.network_client.set_global(global).await?;
+        }
         self.network_client.apply().await?;
 
         Ok(())
     }
+
+    /// Restores the network settings from a previous [NetworkStore::load] call
+    ///
+    /// Unlike [NetworkStore::store], it removes the connections that are not included in the
+    /// given settings.
+    ///
+    /// * `settings`: settings to restore.
+    pub async fn restore(&self, settings: &NetworkSettings) -> Result<(), ServiceError> {
+        let current = self.network_client.connections().await?;
+        for conn in current {
+            if !settings.connections.iter().any(|c| c.id == conn.id) {
+                self.network_client.remove_connection(&conn.id).await?;
+            }
+        }
+        self.store(settings).await
+    }
 }
 
 /// Returns the list of connections in the order they should be written to the D-Bus service.
@@ -69,6 +92,9 @@ fn ordered_connections(conns: &Vec<NetworkConnection>) -> Vec<String> {
 
 /// Adds a connections and its dependencies to the list.
 ///
+/// The ports of a bond or a bridge which are not defined are added too, so a default
+/// connection is created for them.
+///
 /// * `conn`: connection to add.
 /// * `conns`: existing connections.
 /// * `ordered`: ordered list of connections.
@@ -77,13 +103,14 @@ fn add_ordered_connection(
     conns: &Vec<NetworkConnection>,
     ordered: &mut Vec<String>,
 ) {
-    if let Some(bond) = &conn.bond {
-        for port in &bond.ports {
-            if let Some(conn) = find_connection(port, conns) {
-                add_ordered_connection(conn, conns, ordered);
-            } else if !ordered.contains(&conn.id) {
-                ordered.push(port.clone());
-            }
+    let ports = conn.ports();
+    for dependency in conn.dependencies() {
+        if let Some(conn) = find_connection(dependency, conns) {
+            add_ordered_connection(conn, conns, ordered);
+        } else if ports.is_some_and(|p| p.iter().any(|p| p == dependency))
+            && !ordered.iter().any(|o| o == dependency)
+        {
+            ordered.push(dependency.to_string());
         }
     }
 
@@ -112,7 +139,7 @@ fn default_connection(id: &str) -> NetworkConnection {
 #[cfg(test)]
 mod tests {
     use super::ordered_connections;
-    use crate::network::settings::{BondSettings, NetworkConnection};
+    use crate::network::settings::{BondSettings, BridgeSettings, NetworkConnection, VlanSettings};
 
     #[test]
     fn test_ordered_connections() {
@@ -152,4 +179,36 @@ mod tests {
             ]
         )
     }
+
+    #[test]
+    fn test_ordered_connections_with_bridges_and_vlans() {
+        let vlan = NetworkConnection {
+            id: "bond0.10".to_string(),
+            vlan: Some(VlanSettings {
+                id: 10,
+                parent: "bond0".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let bridge = NetworkConnection {
+            id: "br0".to_string(),
+            bridge: Some(BridgeSettings {
+                ports: vec!["bond0.10".to_string()],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let bond = NetworkConnection {
+            id: "bond0".to_string(),
+            bond: Some(BondSettings {
+                ports: vec!["eth0".to_string()],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let conns = vec![bridge, vlan, bond];
+        let ordered = ordered_connections(&conns);
+        assert_eq!(ordered, vec!["eth0", "bond0", "bond0.10", "br0"]);
+    }
 }
@@ -76,6 +76,11 @@ pub enum DeviceType {
     Bond = 4,
     Vlan = 5,
     Bridge = 6,
+    Wireguard = 7,
+    Vrf = 8,
+    OvsBridge = 9,
+    OvsPort = 10,
+    OvsInterface = 11,
 }
 
 // For now this mirrors NetworkManager, because it was less mental work than coming up with
@@ -283,6 +288,11 @@ impl TryFrom<u8> for DeviceType {
             4 => Ok(DeviceType::Bond),
             5 => Ok(DeviceType::Vlan),
             6 => Ok(DeviceType::Bridge),
+            7 => Ok(DeviceType::Wireguard),
+            8 => Ok(DeviceType::Vrf),
+            9 => Ok(DeviceType::OvsBridge),
+            10 => Ok(DeviceType::OvsPort),
+            11 => Ok(DeviceType::OvsInterface),
             _ => Err(InvalidDeviceType(value)),
         }
     }
@@ -316,6 +326,9 @@ mod tests {
         let dtype = DeviceType::try_from(0);
         assert_eq!(dtype, Ok(DeviceType::Loopback));
 
+        let dtype = DeviceType::try_from(9);
+        assert_eq!(dtype, Ok(DeviceType::OvsBridge));
+
         let dtype = DeviceType::try_from(128);
         assert_eq!(dtype, Err(InvalidDeviceType(128)));
     }
@@ -37,6 +37,16 @@ impl ProductStore {
         })
     }
 
+    pub fn new_with_client(
+        product_client: ProductHTTPClient,
+        manager_client: ManagerHTTPClient,
+    ) -> Result<ProductStore, ServiceError> {
+        Ok(Self {
+            product_client,
+            manager_client,
+        })
+    }
+
     fn non_empty_string(s: String) -> Option<String> {
         if s.is_empty() {
             None
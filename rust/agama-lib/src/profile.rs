@@ -18,15 +18,29 @@
 // To contact SUSE LLC about this file by physical or electronic mail, you may
 // find current contact information at www.suse.com.
 
-use crate::error::ProfileError;
+mod format;
+mod jsonnet;
+mod loader;
+mod signature;
+mod validation;
+pub use format::ProfileFormat;
+pub use jsonnet::{JsonnetError, SourceLocation};
+pub use loader::{merge, parse_cmdline, ProfileLoader};
+pub use signature::{ProfilePolicy, SignatureVerifier, DEFAULT_KEYS_DIR};
+pub use validation::ValidationError;
+
+use crate::{error::ProfileError, hardware::HardwareReader};
 use anyhow::Context;
 use jsonschema::JSONSchema;
 use log::info;
 use serde_json;
-use std::{fs, io::Write, path::Path, process::Command};
-use tempfile::{tempdir, TempDir};
+use std::{collections::HashMap, fs, io::Write, path::Path, process::Command};
+use tempfile::TempDir;
 use url::Url;
 
+/// Name of the file containing the hardware information which can be imported from a profile.
+const HWINFO_FILE: &str = "hw.libsonnet";
+
 /// Downloads and converts autoyast profile.
 pub struct AutoyastProfile {
     url: Url,
@@ -66,7 +80,7 @@ impl AutoyastProfile {
 #[derive(Debug)]
 pub enum ValidationResult {
     Valid,
-    NotValid(Vec<String>),
+    NotValid(Vec<ValidationError>),
 }
 
 /// Checks whether an autoinstallation profile is valid
@@ -91,6 +105,8 @@ pub enum ValidationResult {
 /// ```
 pub struct ProfileValidator {
     schema: JSONSchema,
+    // raw schema, used to find the allowed property names
+    raw_schema: serde_json::Value,
 }
 
 impl ProfileValidator {
@@ -108,77 +124,309 @@ impl ProfileValidator {
     pub fn new(schema_path: &Path) -> Result<Self, ProfileError> {
         let contents = fs::read_to_string(schema_path)
             .context(format!("Failed to read schema at {:?}", schema_path))?;
-        let schema = serde_json::from_str(&contents)?;
-        let schema = JSONSchema::compile(&schema).expect("A valid schema");
-        Ok(Self { schema })
+        let raw_schema = serde_json::from_str(&contents)?;
+        let schema = JSONSchema::compile(&raw_schema).expect("A valid schema");
+        Ok(Self { schema, raw_schema })
     }
 
+    /// Validates a profile file.
+    ///
+    /// The format (JSON, YAML or TOML) is determined by the extension or the content.
+    ///
+    /// * `profile_path`: path to the profile.
     pub fn validate_file(&self, profile_path: &Path) -> Result<ValidationResult, ProfileError> {
         let contents = fs::read_to_string(profile_path)?;
-        self.validate_str(&contents)
+        let format = ProfileFormat::guess(Some(profile_path), &contents);
+        self.validate_content(&contents, format)
     }
 
+    /// Validates a profile, detecting its format (JSON, YAML or TOML) from the content.
+    ///
+    /// * `profile`: profile source code.
     pub fn validate_str(&self, profile: &str) -> Result<ValidationResult, ProfileError> {
-        let contents = serde_json::from_str(profile)?;
-        let result = self.schema.validate(&contents);
+        self.validate_content(profile, ProfileFormat::detect(profile))
+    }
+
+    /// Validates a profile in the given format.
+    ///
+    /// For JSON profiles, the errors include the line and column of the offending element.
+    ///
+    /// * `profile`: profile source code.
+    /// * `format`: profile format.
+    pub fn validate_content(
+        &self,
+        profile: &str,
+        format: ProfileFormat,
+    ) -> Result<ValidationResult, ProfileError> {
+        let contents = format.parse(profile)?;
+        let mut result = self.validate(&contents);
+        if format != ProfileFormat::Json {
+            return Ok(result);
+        }
+
+        if let ValidationResult::NotValid(errors) = &mut result {
+            let positions = validation::json_positions(profile);
+            for error in errors.iter_mut() {
+                if let Some((line, column)) = positions.get(&error.pointer) {
+                    error.line = Some(*line);
+                    error.column = Some(*column);
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Validates an already parsed profile.
+    ///
+    /// As the source is not available, the errors do not include the line and column.
+    ///
+    /// * `profile`: JSON representation of the profile.
+    pub fn validate(&self, profile: &serde_json::Value) -> ValidationResult {
+        let result = self.schema.validate(profile);
         if let Err(errors) = result {
-            let messages: Vec<String> = errors.map(|e| format!("{e}. {e:?}")).collect();
-            return Ok(ValidationResult::NotValid(messages));
+            let errors = errors
+                .flat_map(|e| ValidationError::from_schema_error(e, &self.raw_schema))
+                .collect();
+            return ValidationResult::NotValid(errors);
         }
-        Ok(ValidationResult::Valid)
+        ValidationResult::Valid
     }
 }
 
 /// Evaluates a profile
 ///
 /// Evaluating a profile means injecting the hardware information (coming from D-Bus)
-/// and running the jsonnet code to generate a plain JSON file. For this struct to
-/// work, the `/usr/bin/jsonnet` command must be available.
+/// and running the jsonnet code to generate a plain JSON file. The evaluation happens
+/// in-process, so no external `jsonnet` command is needed.
 pub struct ProfileEvaluator {}
 
 impl ProfileEvaluator {
     pub fn evaluate(&self, profile_path: &Path, mut out_fd: impl Write) -> anyhow::Result<()> {
-        let dir = tempdir()?;
-
-        let working_path = dir.path().join("profile.jsonnet");
-        fs::copy(profile_path, working_path)?;
-
-        let hwinfo_path = dir.path().join("hw.libsonnet");
-        self.write_hwinfo(&hwinfo_path)
+        let source = fs::read(profile_path)
+            .context(format!("Failed to read the profile at {:?}", profile_path))?;
+        let hwinfo = self
+            .read_hwinfo()
             .context("Failed to read system's hardware information")?;
-
-        let result = Command::new("/usr/bin/jsonnet")
-            .arg("profile.jsonnet")
-            .current_dir(&dir)
-            .output()
-            .context("Failed to run jsonnet")?;
-        if !result.status.success() {
-            let message =
-                String::from_utf8(result.stderr).context("Invalid UTF-8 sequence from jsonnet")?;
-            return Err(ProfileError::EvaluationError(message).into());
-        }
-        out_fd.write_all(&result.stdout)?;
+        let result = self.evaluate_source(profile_path, &source, hwinfo)?;
+        out_fd.write_all(result.as_bytes())?;
         Ok(())
     }
 
-    // Write the hardware information in JSON format to a given path and also helpers to help with it
-    //
-    // TODO: we need a better way to generate this information, as lshw and hwinfo are not usable
-    // out of the box.
-    fn write_hwinfo(&self, path: &Path) -> anyhow::Result<()> {
-        let result = Command::new("/usr/sbin/lshw")
-            .args(["-json"])
-            .output()
-            .context("Failed to run lshw")?;
+    /// Evaluates the jsonnet code of a profile and returns the resulting JSON.
+    ///
+    /// * `profile_path`: path of the profile, used to resolve imports and report errors.
+    /// * `source`: jsonnet code of the profile.
+    /// * `hwinfo`: content of the `hw.libsonnet` file that the profile can import.
+    pub fn evaluate_source(
+        &self,
+        profile_path: &Path,
+        source: &[u8],
+        hwinfo: String,
+    ) -> Result<String, ProfileError> {
+        let files = HashMap::from([(HWINFO_FILE.to_string(), hwinfo.into_bytes())]);
+        Ok(jsonnet::evaluate(profile_path, source, files)?)
+    }
+
+    // Returns the hardware inventory in JSON format and also helpers to help with it
+    fn read_hwinfo(&self) -> anyhow::Result<String> {
+        let hardware = HardwareReader::default().read();
         let helpers = fs::read_to_string("agama.libsonnet")
             .or_else(|_| fs::read_to_string("/usr/share/agama-cli/agama.libsonnet"))
             .context("Failed to read agama.libsonnet")?;
-        let mut file = fs::File::create(path)?;
-        file.write_all(b"{\n")?;
-        file.write_all(helpers.as_bytes())?;
-        file.write_all(b"\n\"lshw\":\n")?;
-        file.write_all(&result.stdout)?;
-        file.write_all(b"\n}")?;
-        Ok(())
+        let hardware = serde_json::to_string_pretty(&hardware)?;
+        Ok(format!("{{\n{}\n\"hardware\":\n{}\n}}", helpers, hardware))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const HWINFO: &str = r#"{
+  nvmeDisks(hardware):: std.filter(function(d) d.transport == 'nvme', hardware.disks),
+  hardware: { disks: [{ name: 'nvme0n1', transport: 'nvme' }, { name: 'sda', transport: 'sata' }] },
+}"#;
+
+    fn evaluate(source: &str) -> Result<serde_json::Value, ProfileError> {
+        let evaluator = ProfileEvaluator {};
+        let result = evaluator.evaluate_source(
+            Path::new("profile.jsonnet"),
+            source.as_bytes(),
+            HWINFO.to_string(),
+        )?;
+        Ok(serde_json::from_str(&result)?)
+    }
+
+    #[test]
+    fn test_validate_str() {
+        let validator = ProfileValidator::new(Path::new("share/profile.schema.json")).unwrap();
+        let profile = r#"{
+  "user": {
+    "fullName": "Jane Doe",
+    "username": "jane.doe"
+  },
+  "localization": { "language": 1 }
+}"#;
+        let ValidationResult::NotValid(errors) = validator.validate_str(profile).unwrap() else {
+            panic!("The profile should not be valid");
+        };
+
+        let unknown = errors
+            .iter()
+            .find(|e| e.pointer == "/user/username")
+            .unwrap();
+        assert_eq!(unknown.keyword, "additionalProperties");
+        assert_eq!(unknown.suggestion, Some("userName".to_string()));
+        assert_eq!((unknown.line, unknown.column), (Some(4), Some(5)));
+
+        let wrong_type = errors
+            .iter()
+            .find(|e| e.pointer == "/localization/language")
+            .unwrap();
+        assert_eq!(wrong_type.keyword, "type");
+        assert_eq!(wrong_type.expected, Some(serde_json::json!("string")));
+        assert_eq!(wrong_type.actual, Some(serde_json::json!(1)));
+        assert_eq!((wrong_type.line, wrong_type.column), (Some(6), Some(21)));
+    }
+
+    #[test]
+    fn test_validate_str_with_references() {
+        let validator = ProfileValidator::new(Path::new("share/profile.schema.json")).unwrap();
+        let profile = r#"{
+  "storage": {
+    "boot": { "configure": true, "devise": "/dev/vda" }
+  }
+}"#;
+        let ValidationResult::NotValid(errors) = validator.validate_str(profile).unwrap() else {
+            panic!("The profile should not be valid");
+        };
+
+        let unknown = errors
+            .iter()
+            .find(|e| e.pointer == "/storage/boot/devise")
+            .unwrap();
+        assert_eq!(unknown.keyword, "additionalProperties");
+        assert_eq!(unknown.suggestion, Some("device".to_string()));
+    }
+
+    #[test]
+    fn test_evaluate_with_hwinfo() {
+        let source = r#"
+local agama = import 'hw.libsonnet';
+{
+  product: { id: 'Tumbleweed' },
+  disks: std.length(agama.nvmeDisks(agama.hardware)),
+}
+"#;
+        let result = evaluate(source).unwrap();
+        assert_eq!(
+            result,
+            serde_json::json!({ "product": { "id": "Tumbleweed" }, "disks": 1 })
+        );
+    }
+
+    // hw.libsonnet using the shipped helpers
+    fn helpers_hwinfo(hardware: serde_json::Value) -> String {
+        let helpers = fs::read_to_string("../share/agama.libsonnet").unwrap();
+        format!("{{\n{}\n\"hardware\":\n{}\n}}", helpers, hardware)
+    }
+
+    #[test]
+    fn test_evaluate_with_lshw_helpers() {
+        let hardware = serde_json::json!({
+            "cpu": { "architecture": "x86_64", "model": "QEMU", "vendor": null, "count": 2 },
+            "memory": { "total": 4294967296u64 },
+            "disks": [
+                { "name": "vda", "device": "/dev/vda", "size": 1000, "model": null, "vendor": null },
+                { "name": "vdb", "device": "/dev/vdb", "size": 2000, "model": null, "vendor": null }
+            ],
+            "networkInterfaces": [{ "name": "eth0", "mac": "52:54:00:12:34:56", "driver": null }]
+        });
+        let source = r#"
+local agama = import 'hw.libsonnet';
+{
+  memory: agama.findByID(agama.lshw, 'memory').size,
+  disks: [d.logicalname for d in agama.selectByClass(agama.lshw, 'disk')],
+  missing: agama.findByID(agama.lshw, 'display'),
+}
+"#;
+        let evaluator = ProfileEvaluator {};
+        let result = evaluator
+            .evaluate_source(
+                Path::new("profile.jsonnet"),
+                source.as_bytes(),
+                helpers_hwinfo(hardware),
+            )
+            .unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&result).unwrap(),
+            serde_json::json!({
+                "memory": 4294967296u64,
+                "disks": ["/dev/vda", "/dev/vdb"],
+                "missing": null
+            })
+        );
+    }
+
+    #[test]
+    fn test_evaluate_example_without_disks() {
+        let hardware = serde_json::json!({
+            "cpu": { "architecture": "x86_64", "model": null, "vendor": null, "count": 1 },
+            "memory": { "total": 4294967296u64 },
+            "disks": [],
+            "networkInterfaces": []
+        });
+        let path = Path::new("share/examples/profile.jsonnet");
+        let source = fs::read(path).unwrap();
+        let evaluator = ProfileEvaluator {};
+        let result = evaluator
+            .evaluate_source(path, &source, helpers_hwinfo(hardware))
+            .unwrap();
+        let profile: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(profile["product"]["id"], "MicroOS");
+        assert!(profile.get("storage").is_none());
+    }
+
+    #[test]
+    fn test_evaluate_syntax_error() {
+        let source = "{\n  product: { id: 'Tumbleweed' ,\n}\n";
+        let Err(ProfileError::EvaluationError(error)) = evaluate(source) else {
+            panic!("The evaluation should fail");
+        };
+        let location = error.location.unwrap();
+        assert_eq!(location.file, "profile.jsonnet");
+        assert_eq!((location.line, location.column), (4, 1));
+    }
+
+    #[test]
+    fn test_evaluate_runtime_error() {
+        let source = "{\n  product: {\n    id: error 'missing product',\n  },\n}\n";
+        let Err(ProfileError::EvaluationError(error)) = evaluate(source) else {
+            panic!("The evaluation should fail");
+        };
+        assert_eq!(error.message, "missing product");
+        assert_eq!(
+            error.location,
+            Some(SourceLocation {
+                file: "profile.jsonnet".to_string(),
+                line: 3,
+                column: 9
+            })
+        );
+    }
+
+    #[test]
+    fn test_evaluate_missing_import() {
+        let evaluator = ProfileEvaluator {};
+        let path = PathBuf::from("share/examples/missing.jsonnet");
+        let source = "{\n  user: import 'user.libsonnet',\n}\n";
+        let Err(ProfileError::EvaluationError(error)) =
+            evaluator.evaluate_source(&path, source.as_bytes(), HWINFO.to_string())
+        else {
+            panic!("The evaluation should fail");
+        };
+        assert!(error.message.contains("share/examples/user.libsonnet"));
+        assert_eq!(error.location.unwrap().line, 2);
     }
 }
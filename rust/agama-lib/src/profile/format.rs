@@ -0,0 +1,196 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! Profile formats.
+//!
+//! Profiles can be written in JSON, YAML or TOML. Internally, they are always converted to
+//! JSON, which is the format used by the JSON schema and the [crate::install_settings] module.
+
+use crate::error::ProfileError;
+use serde::Serialize;
+use serde_json::Value;
+use std::{fmt, path::Path};
+
+/// Format of a profile.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProfileFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl fmt::Display for ProfileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Json => "json",
+            Self::Yaml => "yaml",
+            Self::Toml => "toml",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl ProfileFormat {
+    /// Determines the format from the file extension.
+    ///
+    /// * `path`: file path.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "toml" => Some(Self::Toml),
+            _ => None,
+        }
+    }
+
+    /// Determines the format by looking at the content.
+    ///
+    /// YAML is a superset of JSON and it accepts almost anything, so it is used as a fallback
+    /// when the content is neither JSON nor TOML.
+    ///
+    /// * `content`: profile content.
+    pub fn detect(content: &str) -> Self {
+        let trimmed = content.trim_start();
+        if trimmed.starts_with('{') && serde_json::from_str::<Value>(content).is_ok() {
+            return Self::Json;
+        }
+        if toml::from_str::<toml::Table>(content).is_ok() {
+            return Self::Toml;
+        }
+        Self::Yaml
+    }
+
+    /// Determines the format using the file extension, if known, or the content.
+    ///
+    /// * `path`: file path.
+    /// * `content`: profile content.
+    pub fn guess(path: Option<&Path>, content: &str) -> Self {
+        path.and_then(Self::from_path)
+            .unwrap_or_else(|| Self::detect(content))
+    }
+
+    /// Parses a profile in this format.
+    ///
+    /// * `content`: profile content.
+    pub fn parse(&self, content: &str) -> Result<Value, ProfileError> {
+        let value = match self {
+            Self::Json => serde_json::from_str(content)?,
+            Self::Yaml => serde_yaml::from_str(content)?,
+            Self::Toml => toml::from_str(content)?,
+        };
+        Ok(value)
+    }
+
+    /// Converts a profile in this format to JSON.
+    ///
+    /// * `content`: profile content.
+    pub fn to_json(&self, content: &str) -> Result<String, ProfileError> {
+        if *self == Self::Json {
+            return Ok(content.to_string());
+        }
+        Ok(serde_json::to_string(&self.parse(content)?)?)
+    }
+
+    /// Writes a value in this format.
+    ///
+    /// As TOML does not support null values, they are omitted.
+    ///
+    /// * `value`: value to write (e.g., the installation settings).
+    pub fn write<T: Serialize>(&self, value: &T) -> Result<String, ProfileError> {
+        // going through serde_json::Value is needed to handle raw JSON values
+        let mut value = serde_json::to_value(value)?;
+        let content = match self {
+            Self::Json => serde_json::to_string_pretty(&value)?,
+            Self::Yaml => serde_yaml::to_string(&value)?,
+            Self::Toml => {
+                remove_nulls(&mut value);
+                toml::to_string_pretty(&value)?
+            }
+        };
+        Ok(content)
+    }
+}
+
+fn remove_nulls(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|_, v| !v.is_null());
+            map.values_mut().for_each(remove_nulls);
+        }
+        Value::Array(items) => items.iter_mut().for_each(remove_nulls),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::install_settings::InstallSettings;
+    use serde_json::json;
+
+    #[test]
+    fn test_guess_format() {
+        assert_eq!(
+            ProfileFormat::guess(Some(Path::new("profile.yml")), "{}"),
+            ProfileFormat::Yaml
+        );
+        assert_eq!(
+            ProfileFormat::guess(None, "{ \"user\": {} }"),
+            ProfileFormat::Json
+        );
+        assert_eq!(
+            ProfileFormat::guess(None, "[user]\nuserName = \"jane\"\n"),
+            ProfileFormat::Toml
+        );
+        assert_eq!(
+            ProfileFormat::guess(None, "user:\n  userName: jane\n"),
+            ProfileFormat::Yaml
+        );
+    }
+
+    #[test]
+    fn test_parse_and_write() {
+        let expected = json!({ "user": { "userName": "jane", "fullName": "Jane Doe" } });
+        let yaml = "user:\n  userName: jane\n  fullName: Jane Doe\n";
+        let toml = "[user]\nuserName = \"jane\"\nfullName = \"Jane Doe\"\n";
+
+        assert_eq!(ProfileFormat::Yaml.parse(yaml).unwrap(), expected);
+        assert_eq!(ProfileFormat::Toml.parse(toml).unwrap(), expected);
+
+        let with_null = json!({ "user": { "userName": "jane", "password": null } });
+        let written = ProfileFormat::Toml.write(&with_null).unwrap();
+        assert_eq!(written, "[user]\nuserName = \"jane\"\n");
+    }
+
+    #[test]
+    fn test_read_install_settings() {
+        let yaml = "user:\n  userName: jane\nstorage:\n  drives:\n    - search: /dev/vda\n";
+        let settings = InstallSettings::from_content(yaml, ProfileFormat::Yaml).unwrap();
+        assert_eq!(
+            settings.user.unwrap().first_user.unwrap().user_name,
+            Some("jane".to_string())
+        );
+        assert_eq!(
+            settings.storage.unwrap().get(),
+            r#"{"drives":[{"search":"/dev/vda"}]}"#
+        );
+    }
+}
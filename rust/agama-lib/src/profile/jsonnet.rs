@@ -0,0 +1,540 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! In-process Jsonnet evaluation.
+//!
+//! It relies on [rsjsonnet_lang], so there is no need to call an external `jsonnet` binary.
+
+use rsjsonnet_lang::{
+    arena::Arena,
+    interner::InternedStr,
+    lexer::LexError,
+    parser::{ActualToken, ParseError},
+    program::{
+        AnalyzeError, Callbacks, EvalError, EvalErrorKind, EvalStackTraceItem, ImportError,
+        LoadError, NativeError, Program, Thunk, Value,
+    },
+    span::{SpanContextId, SpanId},
+};
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+/// Position of an error in a Jsonnet file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceLocation {
+    /// File name.
+    pub file: String,
+    /// Line number (starting at 1).
+    pub line: usize,
+    /// Column number (starting at 1).
+    pub column: usize,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+/// Error found while loading or evaluating Jsonnet code.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonnetError {
+    /// Error description.
+    pub message: String,
+    /// Where the error happened, if known.
+    pub location: Option<SourceLocation>,
+}
+
+impl fmt::Display for JsonnetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{}: {}", location, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for JsonnetError {}
+
+/// Evaluates Jsonnet code and returns the resulting JSON document.
+///
+/// * `path`: path of the file containing the code. It is used to resolve relative
+///   imports and to report errors.
+/// * `source`: Jsonnet code.
+/// * `virtual_files`: files that can be imported by name although they do not exist
+///   in the file system (e.g., `hw.libsonnet`).
+pub fn evaluate(
+    path: &Path,
+    source: &[u8],
+    virtual_files: HashMap<String, Vec<u8>>,
+) -> Result<String, JsonnetError> {
+    let arena = Arena::new();
+    let mut program = Program::new(&arena);
+    let base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let mut importer = Importer::new(base_dir, virtual_files);
+
+    let name = path.to_string_lossy().to_string();
+    let thunk = importer.load(&mut program, name, source.to_vec())?;
+    let value = program
+        .eval_value(&thunk, &mut importer)
+        .map_err(|e| importer.eval_error(&program, e))?;
+    let mut json = program
+        .manifest_json(&value, true)
+        .map_err(|e| importer.eval_error(&program, e))?;
+    json.push('\n');
+    Ok(json)
+}
+
+/// Jsonnet file which has been loaded into the program.
+struct Source {
+    context: SpanContextId,
+    name: String,
+    data: Vec<u8>,
+}
+
+/// Resolves the imports and keeps track of the loaded files.
+struct Importer<'p> {
+    base_dir: PathBuf,
+    virtual_files: HashMap<String, Vec<u8>>,
+    sources: Vec<Source>,
+    cache: HashMap<String, Thunk<'p>>,
+    import_error: Option<JsonnetError>,
+}
+
+impl<'p> Importer<'p> {
+    fn new(base_dir: PathBuf, virtual_files: HashMap<String, Vec<u8>>) -> Self {
+        Self {
+            base_dir,
+            virtual_files,
+            sources: vec![],
+            cache: HashMap::new(),
+            import_error: None,
+        }
+    }
+
+    /// Loads Jsonnet code into the program.
+    fn load(
+        &mut self,
+        program: &mut Program<'p>,
+        name: String,
+        data: Vec<u8>,
+    ) -> Result<Thunk<'p>, JsonnetError> {
+        let (context, _) = program.span_manager_mut().insert_source_context(data.len());
+        let result = program.load_source(context, &data, true, &name);
+        self.sources.push(Source {
+            context,
+            name,
+            data,
+        });
+        result.map_err(|e| self.load_error(program, e))
+    }
+
+    /// Reads a file, looking first into the virtual files.
+    ///
+    /// Relative paths are resolved against the directory of the importing file.
+    fn read(
+        &self,
+        program: &Program<'p>,
+        from: SpanId,
+        path: &str,
+    ) -> Result<(String, Vec<u8>), JsonnetError> {
+        if let Some(data) = self.virtual_files.get(path) {
+            return Ok((path.to_string(), data.clone()));
+        }
+
+        let (context, _, _) = program.span_manager().get_span(from);
+        let dir = self
+            .sources
+            .iter()
+            .find(|s| s.context == context)
+            .and_then(|s| Path::new(&s.name).parent())
+            .filter(|d| !d.as_os_str().is_empty())
+            .unwrap_or(&self.base_dir);
+        let full_path = dir.join(path);
+        let name = full_path.to_string_lossy().to_string();
+        match fs::read(&full_path) {
+            Ok(data) => Ok((name, data)),
+            Err(e) => Err(JsonnetError {
+                message: format!("could not import '{}': {}", name, e),
+                location: self.location(program, from),
+            }),
+        }
+    }
+
+    /// Converts a span into a location (file, line and column).
+    fn location(&self, program: &Program<'p>, span: SpanId) -> Option<SourceLocation> {
+        let (context, start, _) = program.span_manager().get_span(span);
+        let source = self.sources.iter().find(|s| s.context == context)?;
+        let before = source.data.get(..start)?;
+        let line_start = before
+            .iter()
+            .rposition(|c| *c == b'\n')
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        let line = before.iter().filter(|c| **c == b'\n').count() + 1;
+        let column = String::from_utf8_lossy(&before[line_start..])
+            .chars()
+            .count()
+            + 1;
+        Some(SourceLocation {
+            file: source.name.clone(),
+            line,
+            column,
+        })
+    }
+
+    fn load_error(&self, program: &Program<'p>, error: LoadError) -> JsonnetError {
+        let (message, span) = load_error_details(&error);
+        JsonnetError {
+            message,
+            location: self.location(program, span),
+        }
+    }
+
+    fn eval_error(&mut self, program: &Program<'p>, error: EvalError) -> JsonnetError {
+        // errors while loading an imported file are more useful than a plain "import failed"
+        if matches!(error.kind, EvalErrorKind::ImportFailed { .. }) {
+            if let Some(import_error) = self.import_error.take() {
+                return import_error;
+            }
+        }
+
+        // if the error itself does not point to a location, use the innermost one from the
+        // stack trace
+        let span = eval_error_span(&error.kind).or_else(|| {
+            error
+                .stack_trace
+                .iter()
+                .rev()
+                .find_map(stack_trace_item_span)
+        });
+        JsonnetError {
+            message: eval_error_message(&error.kind),
+            location: span.and_then(|s| self.location(program, s)),
+        }
+    }
+
+    fn import_file(
+        &mut self,
+        program: &mut Program<'p>,
+        from: SpanId,
+        path: &str,
+    ) -> Result<Vec<u8>, ImportError> {
+        match self.read(program, from, path) {
+            Ok((_, data)) => Ok(data),
+            Err(e) => {
+                self.import_error = Some(e);
+                Err(ImportError)
+            }
+        }
+    }
+}
+
+impl<'p> Callbacks<'p> for Importer<'p> {
+    fn import(
+        &mut self,
+        program: &mut Program<'p>,
+        from: SpanId,
+        path: &str,
+    ) -> Result<Thunk<'p>, ImportError> {
+        let result =
+            self.read(program, from, path)
+                .and_then(|(name, data)| match self.cache.get(&name) {
+                    Some(thunk) => Ok(thunk.clone()),
+                    None => {
+                        let thunk = self.load(program, name.clone(), data)?;
+                        self.cache.insert(name, thunk.clone());
+                        Ok(thunk)
+                    }
+                });
+        result.map_err(|e| {
+            self.import_error = Some(e);
+            ImportError
+        })
+    }
+
+    fn import_str(
+        &mut self,
+        program: &mut Program<'p>,
+        from: SpanId,
+        path: &str,
+    ) -> Result<String, ImportError> {
+        let data = self.import_file(program, from, path)?;
+        String::from_utf8(data).map_err(|_| {
+            self.import_error = Some(JsonnetError {
+                message: format!("'{}' is not a valid UTF-8 file", path),
+                location: self.location(program, from),
+            });
+            ImportError
+        })
+    }
+
+    fn import_bin(
+        &mut self,
+        program: &mut Program<'p>,
+        from: SpanId,
+        path: &str,
+    ) -> Result<Vec<u8>, ImportError> {
+        self.import_file(program, from, path)
+    }
+
+    fn trace(&mut self, _program: &mut Program<'p>, message: &str, _stack: &[EvalStackTraceItem]) {
+        log::info!("Jsonnet trace: {}", message);
+    }
+
+    fn native_call(
+        &mut self,
+        _program: &mut Program<'p>,
+        _name: InternedStr<'p>,
+        _args: &[Value<'p>],
+    ) -> Result<Value<'p>, NativeError> {
+        Err(NativeError)
+    }
+}
+
+fn load_error_details(error: &LoadError) -> (String, SpanId) {
+    match error {
+        LoadError::Lex(error) => lex_error_details(error),
+        LoadError::Parse(ParseError::Expected { span, instead, .. }) => {
+            let found = match instead {
+                ActualToken::EndOfFile => "end of file".to_string(),
+                ActualToken::OtherOp(op) => format!("operator '{}'", op),
+                ActualToken::Ident(name) => format!("identifier '{}'", name),
+                ActualToken::Number => "number".to_string(),
+                ActualToken::String => "string".to_string(),
+                ActualToken::TextBlock => "text block".to_string(),
+                ActualToken::Simple(_) => "token".to_string(),
+            };
+            (format!("syntax error: unexpected {}", found), *span)
+        }
+        LoadError::Analyze(error) => analyze_error_details(error),
+    }
+}
+
+fn lex_error_details(error: &LexError) -> (String, SpanId) {
+    match error {
+        LexError::InvalidChar { span, chr } => (format!("invalid character '{}'", chr), *span),
+        LexError::InvalidUtf8 { span, .. } => ("invalid UTF-8 sequence".to_string(), *span),
+        LexError::UnfinishedMultilineComment { span } => {
+            ("unfinished multi-line comment".to_string(), *span)
+        }
+        LexError::LeadingZeroInNumber { span }
+        | LexError::MissingFracDigits { span }
+        | LexError::MissingExpDigits { span }
+        | LexError::ExpOverflow { span } => ("invalid number".to_string(), *span),
+        LexError::InvalidEscapeInString { span, chr } => {
+            (format!("invalid escape sequence '\\{}'", chr), *span)
+        }
+        LexError::IncompleteUnicodeEscape { span }
+        | LexError::InvalidUtf16EscapeSequence { span, .. } => {
+            ("invalid unicode escape sequence".to_string(), *span)
+        }
+        LexError::UnfinishedString { span } => ("unfinished string".to_string(), *span),
+        LexError::MissingLineBreakAfterTextBlockStart { span }
+        | LexError::MissingWhitespaceTextBlockStart { span }
+        | LexError::InvalidTextBlockTermination { span } => {
+            ("invalid text block".to_string(), *span)
+        }
+    }
+}
+
+fn analyze_error_details(error: &AnalyzeError) -> (String, SpanId) {
+    match error {
+        AnalyzeError::UnknownVariable { span, name } => {
+            (format!("unknown variable '{}'", name), *span)
+        }
+        AnalyzeError::SelfOutsideObject { self_span } => {
+            ("'self' used outside an object".to_string(), *self_span)
+        }
+        AnalyzeError::SuperOutsideObject { super_span } => {
+            ("'super' used outside an object".to_string(), *super_span)
+        }
+        AnalyzeError::DollarOutsideObject { dollar_span } => {
+            ("'$' used outside an object".to_string(), *dollar_span)
+        }
+        AnalyzeError::RepeatedLocalName {
+            repeated_span,
+            name,
+            ..
+        } => (format!("repeated local '{}'", name), *repeated_span),
+        AnalyzeError::RepeatedFieldName {
+            repeated_span,
+            name,
+            ..
+        } => (format!("repeated field '{}'", name), *repeated_span),
+        AnalyzeError::RepeatedParamName {
+            repeated_span,
+            name,
+            ..
+        } => (format!("repeated parameter '{}'", name), *repeated_span),
+        AnalyzeError::PositionalArgAfterNamed { arg_span } => (
+            "positional argument after a named one".to_string(),
+            *arg_span,
+        ),
+        AnalyzeError::TextBlockAsImportPath { span }
+        | AnalyzeError::ComputedImportPath { span } => {
+            ("import path must be a string literal".to_string(), *span)
+        }
+    }
+}
+
+fn eval_error_span(kind: &EvalErrorKind) -> Option<SpanId> {
+    use EvalErrorKind as K;
+
+    match *kind {
+        K::InvalidIndexedType { span, .. }
+        | K::InvalidSlicedType { span, .. }
+        | K::SliceIndexOrStepIsNotNumber { span, .. }
+        | K::StringIndexIsNotNumber { span, .. }
+        | K::ArrayIndexIsNotNumber { span, .. }
+        | K::NumericIndexIsNotValid { span, .. }
+        | K::NumericIndexOutOfRange { span, .. }
+        | K::ObjectIndexIsNotString { span, .. }
+        | K::RepeatedFieldName { span, .. }
+        | K::FieldNameIsNotString { span, .. }
+        | K::UnknownObjectField { span, .. }
+        | K::FieldOfNonObject { span }
+        | K::SuperWithoutSuperObject { span }
+        | K::ForSpecValueIsNotArray { span, .. }
+        | K::CondIsNotBool { span, .. }
+        | K::InvalidUnaryOpType { span, .. }
+        | K::AssertFailed { span, .. }
+        | K::ExplicitError { span, .. }
+        | K::ImportFailed { span, .. } => Some(span),
+        K::CalleeIsNotFunction { span, .. }
+        | K::TooManyCallArgs { span, .. }
+        | K::UnknownCallParam { span, .. }
+        | K::RepeatedCallParam { span, .. }
+        | K::CallParamNotBound { span, .. }
+        | K::InvalidBinaryOpTypes { span, .. }
+        | K::NumberNotBitwiseSafe { span }
+        | K::NumberOverflow { span }
+        | K::NumberNan { span }
+        | K::DivByZero { span }
+        | K::ShiftByNegative { span }
+        | K::Other { span, .. } => span,
+        _ => None,
+    }
+}
+
+fn stack_trace_item_span(item: &EvalStackTraceItem) -> Option<SpanId> {
+    match *item {
+        EvalStackTraceItem::Expr { span }
+        | EvalStackTraceItem::Variable { span, .. }
+        | EvalStackTraceItem::Import { span } => Some(span),
+        EvalStackTraceItem::Call { span, .. }
+        | EvalStackTraceItem::ArrayItem { span, .. }
+        | EvalStackTraceItem::ObjectField { span, .. } => span,
+        _ => None,
+    }
+}
+
+fn eval_error_message(kind: &EvalErrorKind) -> String {
+    use EvalErrorKind as K;
+
+    match kind {
+        K::StackOverflow => "stack overflow".to_string(),
+        K::InfiniteRecursion => "infinite recursion".to_string(),
+        K::InvalidIndexedType { got_type, .. } => {
+            format!("cannot index a value of type {:?}", got_type)
+        }
+        K::InvalidSlicedType { got_type, .. } => {
+            format!("cannot slice a value of type {:?}", got_type)
+        }
+        K::SliceIndexOrStepIsNotNumber { got_type, .. }
+        | K::StringIndexIsNotNumber { got_type, .. }
+        | K::ArrayIndexIsNotNumber { got_type, .. } => {
+            format!("index must be a number, got {:?}", got_type)
+        }
+        K::NumericIndexIsNotValid { index, .. } => format!("invalid index {}", index),
+        K::NumericIndexOutOfRange { index, length, .. } => {
+            format!("index {} out of range (length {})", index, length)
+        }
+        K::ObjectIndexIsNotString { got_type, .. } | K::FieldNameIsNotString { got_type, .. } => {
+            format!("field name must be a string, got {:?}", got_type)
+        }
+        K::RepeatedFieldName { name, .. } => format!("repeated field '{}'", name),
+        K::UnknownObjectField { field_name, .. } => format!("field '{}' not found", field_name),
+        K::FieldOfNonObject { .. } => "field access on a non-object value".to_string(),
+        K::SuperWithoutSuperObject { .. } => "'super' used without a parent object".to_string(),
+        K::ForSpecValueIsNotArray { got_type, .. } => {
+            format!("'for' expects an array, got {:?}", got_type)
+        }
+        K::CondIsNotBool { got_type, .. } => {
+            format!("condition must be a boolean, got {:?}", got_type)
+        }
+        K::CalleeIsNotFunction { got_type, .. } => {
+            format!("cannot call a value of type {:?}", got_type)
+        }
+        K::TooManyCallArgs { num_params, .. } => {
+            format!("too many arguments (the function takes {})", num_params)
+        }
+        K::UnknownCallParam { param_name, .. } => format!("unknown parameter '{}'", param_name),
+        K::RepeatedCallParam { param_name, .. } => {
+            format!("parameter '{}' passed more than once", param_name)
+        }
+        K::CallParamNotBound { param_name, .. } => {
+            format!("missing argument for parameter '{}'", param_name)
+        }
+        K::InvalidUnaryOpType { op, rhs_type, .. } => {
+            format!("invalid operand of type {:?} for {:?}", rhs_type, op)
+        }
+        K::InvalidBinaryOpTypes {
+            op,
+            lhs_type,
+            rhs_type,
+            ..
+        } => format!(
+            "invalid operands of types {:?} and {:?} for {:?}",
+            lhs_type, rhs_type, op
+        ),
+        K::NumberNotBitwiseSafe { .. } => "number is not bitwise safe".to_string(),
+        K::NumberOverflow { .. } => "number overflow".to_string(),
+        K::NumberNan { .. } => "result is not a number".to_string(),
+        K::DivByZero { .. } => "division by zero".to_string(),
+        K::ShiftByNegative { .. } => "shift by a negative number".to_string(),
+        K::InvalidStdFuncArgType {
+            func_name,
+            arg_index,
+            got_type,
+            ..
+        } => format!(
+            "invalid type {:?} for argument {} of std.{}",
+            got_type,
+            arg_index + 1,
+            func_name
+        ),
+        K::AssertEqualFailed { lhs, rhs } => {
+            format!("assertion failed: {} != {}", lhs, rhs)
+        }
+        K::AssertFailed { message, .. } => match message {
+            Some(message) => format!("assertion failed: {}", message),
+            None => "assertion failed".to_string(),
+        },
+        K::ExplicitError { message, .. } => message.to_string(),
+        K::ImportFailed { path, .. } => format!("could not import '{}'", path),
+        K::UnknownExtVar { name } => format!("unknown external variable '{}'", name),
+        K::Other { message, .. } => message.to_string(),
+        other => format!("{:?}", other),
+    }
+}
@@ -0,0 +1,535 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! Profile composition.
+//!
+//! A profile can be built from several ones using two top-level keys, which contain a list of
+//! URLs (relative URLs are resolved against the URL of the profile that contains them):
+//!
+//! * `extends`: base profiles. They are merged in order and the profile itself is merged on top
+//!   of them, so it can override their values.
+//! * `include`: overlays. They are merged, in order, on top of the profile, so they override
+//!   its values.
+//!
+//! The included profiles can include other profiles too. They are downloaded using
+//! [crate::transfer], so any of its schemes is supported (including `relurl://`), and they can be
+//! written in JSON, YAML or TOML.
+//!
+//! The profiles are deep-merged according to these rules:
+//!
+//! * Objects are merged key by key.
+//! * A `null` value removes the key.
+//! * Arrays of objects in which every object has an `id` (e.g., the network connections) are
+//!   merged by `id`: objects with the same `id` are deep-merged and new ones are appended.
+//! * Any other array, as well as any scalar value, replaces the previous one.
+//!
+//! Once the profiles are merged, the `${env:VAR}` and `${cmdline:key}` references in the string
+//! values are replaced with the value of the `VAR` environment variable and the `key` kernel
+//! command-line argument. Any other `${...}` is kept as it is, and `$${` can be used to write a
+//! literal `${` (e.g., `$${env:HOME}`).
+//!
+//! When a [SignatureVerifier] is set, the signature of every downloaded profile, including the
+//! included ones, is verified.
+
+use super::{ProfileFormat, SignatureVerifier};
+use crate::{error::ProfileError, transfer::Transfer};
+use futures_util::future::BoxFuture;
+use serde_json::{Map, Value};
+use std::{collections::HashMap, fs};
+use url::Url;
+
+const EXTENDS_KEY: &str = "extends";
+const INCLUDE_KEY: &str = "include";
+const MAX_DEPTH: usize = 16;
+
+/// Loads a profile, resolving the included profiles and the variables.
+///
+/// ```no_run
+/// use agama_lib::profile::ProfileLoader;
+///
+/// # async fn load() {
+/// let loader = ProfileLoader::from_system();
+/// let profile = loader.load("http://example.net/machine.yaml").await.unwrap();
+/// println!("{}", serde_json::to_string_pretty(&profile).unwrap());
+/// # }
+/// ```
+pub struct ProfileLoader {
+    env: HashMap<String, String>,
+    cmdline: HashMap<String, String>,
+    verifier: Option<SignatureVerifier>,
+    transfer: Transfer,
+}
+
+impl ProfileLoader {
+    /// Builds a loader using the given variables.
+    ///
+    /// * `env`: environment variables.
+    /// * `cmdline`: kernel command-line arguments.
+    pub fn new(env: HashMap<String, String>, cmdline: HashMap<String, String>) -> Self {
+        Self {
+            env,
+            cmdline,
+            verifier: None,
+            transfer: Transfer::default(),
+        }
+    }
+
+    /// Builds a loader using the process environment and `/proc/cmdline`.
+    pub fn from_system() -> Self {
+        let cmdline = fs::read_to_string("/proc/cmdline").unwrap_or_default();
+        Self::new(std::env::vars().collect(), parse_cmdline(&cmdline))
+    }
+
+    /// Requires the downloaded profiles to be signed by a trusted key.
+    ///
+    /// * `verifier`: signature verifier.
+    pub fn with_verifier(mut self, verifier: SignatureVerifier) -> Self {
+        self.verifier = Some(verifier);
+        self
+    }
+
+    /// Sets the object to download the profiles (e.g., to use a proxy).
+    ///
+    /// * `transfer`: transfer to use.
+    pub fn with_transfer(mut self, transfer: Transfer) -> Self {
+        self.transfer = transfer;
+        self
+    }
+
+    /// Loads and resolves the profile at the given URL.
+    ///
+    /// * `url`: profile URL.
+    pub async fn load(&self, url: &str) -> Result<Value, ProfileError> {
+        let url = parse_url(url, None)?;
+        let mut profile = self.resolve(&url, &mut vec![]).await?;
+        self.substitute(&mut profile)?;
+        Ok(profile)
+    }
+
+    /// Resolves an already downloaded profile.
+    ///
+    /// The signature of the content is not verified, but the ones of the included profiles are.
+    ///
+    /// * `content`: profile content.
+    /// * `format`: profile format.
+    /// * `base`: URL used to resolve relative includes.
+    pub async fn load_content(
+        &self,
+        content: &str,
+        format: ProfileFormat,
+        base: &str,
+    ) -> Result<Value, ProfileError> {
+        let base = parse_url(base, None)?;
+        let profile = format.parse(content)?;
+        let mut profile = self
+            .compose(profile, &base, &mut vec![base.clone()])
+            .await?;
+        self.substitute(&mut profile)?;
+        Ok(profile)
+    }
+
+    // boxed, as it is called recursively through compose
+    fn resolve<'a>(
+        &'a self,
+        url: &'a Url,
+        stack: &'a mut Vec<Url>,
+    ) -> BoxFuture<'a, Result<Value, ProfileError>> {
+        Box::pin(self.resolve_unboxed(url, stack))
+    }
+
+    async fn resolve_unboxed(
+        &self,
+        url: &Url,
+        stack: &mut Vec<Url>,
+    ) -> Result<Value, ProfileError> {
+        if stack.contains(url) {
+            return Err(ProfileError::CircularInclude(url.to_string()));
+        }
+        if stack.len() >= MAX_DEPTH {
+            return Err(ProfileError::InvalidInclude(
+                url.to_string(),
+                "too many nested includes".to_string(),
+            ));
+        }
+
+        let content = self.transfer.fetch(url.as_str()).await?;
+        if let Some(verifier) = &self.verifier {
+            verifier
+                .verify_url(&self.transfer, url.as_str(), &content)
+                .await?;
+        }
+        let content = String::from_utf8(content)
+            .map_err(|e| ProfileError::InvalidInclude(url.to_string(), e.to_string()))?;
+        let format = ProfileFormat::from_path(url.path().as_ref())
+            .unwrap_or_else(|| ProfileFormat::detect(&content));
+        let profile = format.parse(&content)?;
+
+        stack.push(url.clone());
+        let result = self.compose(profile, url, stack).await;
+        stack.pop();
+        result
+    }
+
+    /// Merges a profile with the ones it extends and includes.
+    async fn compose(
+        &self,
+        mut profile: Value,
+        url: &Url,
+        stack: &mut Vec<Url>,
+    ) -> Result<Value, ProfileError> {
+        let Some(object) = profile.as_object_mut() else {
+            return Ok(profile);
+        };
+        let extends = take_urls(object, EXTENDS_KEY, url)?;
+        let includes = take_urls(object, INCLUDE_KEY, url)?;
+
+        let mut result = Value::Object(Map::new());
+        for base in extends {
+            merge(&mut result, self.resolve(&base, stack).await?);
+        }
+        merge(&mut result, profile);
+        for overlay in includes {
+            merge(&mut result, self.resolve(&overlay, stack).await?);
+        }
+        Ok(result)
+    }
+
+    /// Replaces the variables in all the string values.
+    fn substitute(&self, value: &mut Value) -> Result<(), ProfileError> {
+        match value {
+            Value::String(text) => *text = self.substitute_str(text)?,
+            Value::Array(items) => {
+                for item in items {
+                    self.substitute(item)?;
+                }
+            }
+            Value::Object(map) => {
+                for item in map.values_mut() {
+                    self.substitute(item)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn substitute_str(&self, text: &str) -> Result<String, ProfileError> {
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find("${") {
+            // "$${" is an escaped "${"
+            if rest[..start].ends_with('$') {
+                result.push_str(&rest[..start - 1]);
+                result.push_str("${");
+                rest = &rest[start + 2..];
+                continue;
+            }
+
+            result.push_str(&rest[..start]);
+            let reference = &rest[start + 2..];
+            match reference.find('}') {
+                Some(end) if is_variable(&reference[..end]) => {
+                    result.push_str(&self.lookup(&reference[..end])?);
+                    rest = &reference[end + 1..];
+                }
+                // anything else is kept as it is (e.g., "${HOME}" in a script)
+                _ => {
+                    result.push_str("${");
+                    rest = reference;
+                }
+            }
+        }
+        result.push_str(rest);
+        Ok(result)
+    }
+
+    fn lookup(&self, reference: &str) -> Result<String, ProfileError> {
+        let value = match reference.split_once(':') {
+            Some(("env", name)) => self.env.get(name),
+            Some(("cmdline", name)) => self.cmdline.get(name),
+            _ => None,
+        };
+        value
+            .cloned()
+            .ok_or_else(|| ProfileError::UndefinedVariable(reference.to_string()))
+    }
+}
+
+/// Whether a `${...}` reference is a variable that the loader replaces.
+fn is_variable(reference: &str) -> bool {
+    reference.starts_with("env:") || reference.starts_with("cmdline:")
+}
+
+/// Deep-merges two values, as described in the module documentation.
+///
+/// * `base`: value to merge into.
+/// * `overlay`: value to merge on top of `base`.
+pub fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                if value.is_null() {
+                    base.remove(&key);
+                    continue;
+                }
+                match base.get_mut(&key) {
+                    Some(current) => merge(current, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(base), Value::Array(overlay))
+            if has_ids(base.as_slice()) && has_ids(&overlay) =>
+        {
+            for item in overlay {
+                let position = base.iter().position(|i| i.get("id") == item.get("id"));
+                match position {
+                    Some(position) => merge(&mut base[position], item),
+                    None => base.push(item),
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn has_ids(items: &[Value]) -> bool {
+    !items.is_empty() && items.iter().all(|i| i.get("id").is_some())
+}
+
+/// Removes a list of URLs from the profile and resolves them against the profile URL.
+fn take_urls(
+    object: &mut Map<String, Value>,
+    key: &str,
+    base: &Url,
+) -> Result<Vec<Url>, ProfileError> {
+    let urls = match object.remove(key) {
+        None | Some(Value::Null) => vec![],
+        Some(Value::Array(items)) => items
+            .into_iter()
+            .map(|i| match i {
+                Value::String(url) => Ok(url),
+                other => Err(ProfileError::InvalidInclude(
+                    other.to_string(),
+                    "it is not a string".to_string(),
+                )),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(other) => {
+            return Err(ProfileError::InvalidInclude(
+                other.to_string(),
+                format!("'{}' must be a list of URLs", key),
+            ))
+        }
+    };
+    urls.iter().map(|u| parse_url(u, Some(base))).collect()
+}
+
+fn parse_url(url: &str, base: Option<&Url>) -> Result<Url, ProfileError> {
+    let error = |e: String| ProfileError::InvalidInclude(url.to_string(), e);
+    let Some(base) = base else {
+        return Url::parse(url).map_err(|e| error(e.to_string()));
+    };
+
+    // "relurl://" URLs are resolved against the profile that contains them
+    let transfer = Transfer::empty().with_base(base.clone());
+    match transfer.resolve(url) {
+        Ok(url) => Ok(url),
+        Err(_) => base.join(url).map_err(|e| error(e.to_string())),
+    }
+}
+
+/// Parses the kernel command-line, returning the value of each argument.
+///
+/// Arguments without a value (e.g., "quiet") get an empty string.
+///
+/// * `cmdline`: content of /proc/cmdline.
+pub fn parse_cmdline(cmdline: &str) -> HashMap<String, String> {
+    let mut args = HashMap::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in cmdline.chars().chain(std::iter::once(' ')) {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    let (key, value) = current.split_once('=').unwrap_or((&current, ""));
+                    args.insert(key.to_string(), value.to_string());
+                    current.clear();
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_merge() {
+        let mut base = json!({
+            "product": { "id": "Tumbleweed" },
+            "software": { "patterns": ["gnome"] },
+            "user": { "userName": "jane", "fullName": "Jane" },
+            "network": {
+                "connections": [
+                    { "id": "eth0", "method4": "auto" },
+                    { "id": "eth1", "method4": "auto" }
+                ]
+            }
+        });
+        let overlay = json!({
+            "software": { "patterns": ["kde"] },
+            "user": { "fullName": null, "password": "nots3cr3t" },
+            "network": {
+                "connections": [
+                    { "id": "eth1", "method4": "manual" },
+                    { "id": "wlan0" }
+                ]
+            }
+        });
+        merge(&mut base, overlay);
+
+        assert_eq!(
+            base,
+            json!({
+                "product": { "id": "Tumbleweed" },
+                "software": { "patterns": ["kde"] },
+                "user": { "userName": "jane", "password": "nots3cr3t" },
+                "network": {
+                    "connections": [
+                        { "id": "eth0", "method4": "auto" },
+                        { "id": "eth1", "method4": "manual" },
+                        { "id": "wlan0" }
+                    ]
+                }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_with_includes() {
+        let dir = TempDir::with_prefix("profile").unwrap();
+        fs::write(
+            dir.path().join("base.json"),
+            r#"{ "product": { "id": "Tumbleweed" }, "user": { "userName": "${env:AGAMA_USER}" } }"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("site.yaml"),
+            "extends: [base.json]\nlocalization:\n  language: ${cmdline:lang}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("overlay.toml"),
+            "[product]\nid = \"MicroOS\"\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("machine.json"),
+            r#"{ "extends": ["site.yaml"], "include": ["relurl://overlay.toml"], "product": { "id": "SLES" },
+                 "root": { "password": "$${literal}" } }"#,
+        )
+        .unwrap();
+
+        let loader = ProfileLoader::new(
+            HashMap::from([("AGAMA_USER".to_string(), "jane".to_string())]),
+            parse_cmdline("quiet lang=es_ES"),
+        );
+        let url = Url::from_file_path(dir.path().join("machine.json")).unwrap();
+        let profile = loader.load(url.as_str()).await.unwrap();
+
+        assert_eq!(
+            profile,
+            json!({
+                "product": { "id": "MicroOS" },
+                "user": { "userName": "jane" },
+                "localization": { "language": "es_ES" },
+                "root": { "password": "${literal}" }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_circular_include() {
+        let dir = TempDir::with_prefix("profile").unwrap();
+        fs::write(dir.path().join("a.json"), r#"{ "include": ["b.json"] }"#).unwrap();
+        fs::write(dir.path().join("b.json"), r#"{ "extends": ["a.json"] }"#).unwrap();
+
+        let loader = ProfileLoader::new(HashMap::new(), HashMap::new());
+        let url = Url::from_file_path(dir.path().join("a.json")).unwrap();
+        let result = loader.load(url.as_str()).await;
+        assert!(matches!(result, Err(ProfileError::CircularInclude(_))));
+    }
+
+    #[tokio::test]
+    async fn test_undefined_variable() {
+        let loader = ProfileLoader::new(HashMap::new(), HashMap::new());
+        let result = loader.load_content(
+            r#"{ "user": { "userName": "${env:MISSING}" } }"#,
+            ProfileFormat::Json,
+            "file:///tmp/profile.json",
+        );
+        assert!(matches!(
+            result.await,
+            Err(ProfileError::UndefinedVariable(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_load_literal_references() {
+        let loader = ProfileLoader::new(
+            HashMap::from([("AGAMA_USER".to_string(), "jane".to_string())]),
+            HashMap::new(),
+        );
+        let profile = loader
+            .load_content(
+                r#"{ "scripts": { "post": [{ "name": "home", "body": "echo ${HOME} ${x" }] },
+                     "user": { "userName": "${env:AGAMA_USER}", "fullName": "$${env:AGAMA_USER}" } }"#,
+                ProfileFormat::Json,
+                "file:///tmp/profile.json",
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            profile,
+            json!({
+                "scripts": { "post": [{ "name": "home", "body": "echo ${HOME} ${x" }] },
+                "user": { "userName": "jane", "fullName": "${env:AGAMA_USER}" }
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_cmdline() {
+        let args = parse_cmdline("BOOT_IMAGE=/vmlinuz quiet agama.auto=\"http://a b\"\n");
+        assert_eq!(args.get("BOOT_IMAGE"), Some(&"/vmlinuz".to_string()));
+        assert_eq!(args.get("quiet"), Some(&"".to_string()));
+        assert_eq!(args.get("agama.auto"), Some(&"http://a b".to_string()));
+    }
+}
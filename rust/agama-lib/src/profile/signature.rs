@@ -0,0 +1,287 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! Profile signatures.
+//!
+//! A profile can be signed using a detached signature, which lives next to the profile with the
+//! `.sig` suffix (e.g., `profile.json.sig`). The signature is checked against the PEM-encoded
+//! public keys in the `/etc/agama.d/keys` directory.
+//!
+//! RSA and EC signatures use SHA-256 and Ed25519 signatures are done over the raw content. The
+//! signature can be binary or base64-encoded. For instance:
+//!
+//! ```text
+//! openssl dgst -sha256 -sign private.pem -out profile.json.sig profile.json
+//! ```
+
+use crate::{error::ProfileError, transfer::Transfer};
+use openssl::{
+    base64,
+    hash::MessageDigest,
+    pkey::{Id, PKey, Public},
+    sign::Verifier,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+/// Directory containing the trusted keys.
+pub const DEFAULT_KEYS_DIR: &str = "/etc/agama.d/keys";
+
+/// Suffix of the detached signatures.
+const SIGNATURE_SUFFIX: &str = ".sig";
+
+/// Server-side policy for importing profiles.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfilePolicy {
+    /// Whether the profiles must be signed with a trusted key.
+    pub require_signature: bool,
+}
+
+/// Verifies the detached signatures of the profiles.
+pub struct SignatureVerifier {
+    keys: Vec<PKey<Public>>,
+}
+
+impl SignatureVerifier {
+    /// Builds a verifier using the given keys.
+    ///
+    /// * `keys`: trusted public keys.
+    pub fn new(keys: Vec<PKey<Public>>) -> Self {
+        Self { keys }
+    }
+
+    /// Builds a verifier using the keys from the given directory.
+    ///
+    /// Every file with a `.pem` or `.pub` extension is considered a public key.
+    ///
+    /// * `dir`: directory containing the keys.
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Result<Self, ProfileError> {
+        let dir = dir.as_ref();
+        let entries = fs::read_dir(dir).map_err(|e| {
+            ProfileError::TrustedKeysError(format!("could not read {}: {}", dir.display(), e))
+        })?;
+
+        let mut keys = vec![];
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_key = path.extension().is_some_and(|e| e == "pem" || e == "pub");
+            if !is_key {
+                continue;
+            }
+            let key = fs::read(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|pem| PKey::public_key_from_pem(&pem).map_err(|e| e.to_string()))
+                .map_err(|e| {
+                    ProfileError::TrustedKeysError(format!("invalid key {}: {}", path.display(), e))
+                })?;
+            keys.push(key);
+        }
+
+        if keys.is_empty() {
+            return Err(ProfileError::TrustedKeysError(format!(
+                "no keys found in {}",
+                dir.display()
+            )));
+        }
+        Ok(Self::new(keys))
+    }
+
+    /// Downloads the signature of a profile and verifies it.
+    ///
+    /// * `transfer`: object to download the signature.
+    /// * `url`: profile URL. The signature is downloaded from the same URL plus `.sig`.
+    /// * `content`: profile content.
+    pub async fn verify_url(
+        &self,
+        transfer: &Transfer,
+        url: &str,
+        content: &[u8],
+    ) -> Result<(), ProfileError> {
+        let signature = transfer
+            .fetch(&format!("{}{}", url, SIGNATURE_SUFFIX))
+            .await
+            .map_err(|_| ProfileError::MissingSignature(url.to_string()))?;
+        if signature.is_empty() {
+            return Err(ProfileError::MissingSignature(url.to_string()));
+        }
+        if !self.verify(content, &signature) {
+            return Err(ProfileError::InvalidSignature(url.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Verifies that any of the trusted keys produced the signature.
+    ///
+    /// * `content`: signed content.
+    /// * `signature`: binary or base64-encoded signature.
+    pub fn verify(&self, content: &[u8], signature: &[u8]) -> bool {
+        let signature = decode_signature(signature);
+        self.keys
+            .iter()
+            .any(|k| verify_with_key(k, content, &signature).unwrap_or(false))
+    }
+}
+
+fn verify_with_key(
+    key: &PKey<Public>,
+    content: &[u8],
+    signature: &[u8],
+) -> Result<bool, openssl::error::ErrorStack> {
+    match key.id() {
+        Id::ED25519 | Id::ED448 => {
+            let mut verifier = Verifier::new_without_digest(key)?;
+            verifier.verify_oneshot(signature, content)
+        }
+        _ => {
+            let mut verifier = Verifier::new(MessageDigest::sha256(), key)?;
+            verifier.update(content)?;
+            verifier.verify(signature)
+        }
+    }
+}
+
+/// Decodes a base64-encoded signature, returning the original one if it is binary.
+fn decode_signature(signature: &[u8]) -> Vec<u8> {
+    let Ok(text) = std::str::from_utf8(signature) else {
+        return signature.to_vec();
+    };
+    let text: String = text.split_whitespace().collect();
+    base64::decode_block(&text).unwrap_or_else(|_| signature.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::ProfileLoader;
+    use openssl::{
+        ec::{EcGroup, EcKey},
+        nid::Nid,
+        pkey::Private,
+        sign::Signer,
+    };
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+    use url::Url;
+
+    fn ec_key() -> PKey<Private> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+    }
+
+    fn write_public_key(dir: &Path, name: &str, key: &PKey<Private>) {
+        fs::write(dir.join(name), key.public_key_to_pem().unwrap()).unwrap();
+    }
+
+    fn sign(key: &PKey<Private>, content: &[u8]) -> Vec<u8> {
+        if key.id() == Id::ED25519 {
+            let mut signer = Signer::new_without_digest(key).unwrap();
+            return signer.sign_oneshot_to_vec(content).unwrap();
+        }
+        let mut signer = Signer::new(MessageDigest::sha256(), key).unwrap();
+        signer.update(content).unwrap();
+        signer.sign_to_vec().unwrap()
+    }
+
+    #[test]
+    fn test_verify() {
+        let dir = TempDir::with_prefix("keys").unwrap();
+        let ec = ec_key();
+        let ed25519 = PKey::generate_ed25519().unwrap();
+        write_public_key(dir.path(), "ec.pem", &ec);
+        write_public_key(dir.path(), "ed25519.pub", &ed25519);
+        fs::write(dir.path().join("README"), "not a key").unwrap();
+
+        let verifier = SignatureVerifier::from_dir(dir.path()).unwrap();
+        let content = br#"{ "user": { "userName": "jane" } }"#;
+
+        let signature = sign(&ec, content);
+        assert!(verifier.verify(content, &signature));
+        let signature = base64::encode_block(&sign(&ed25519, content));
+        assert!(verifier.verify(content, signature.as_bytes()));
+
+        let tampered = br#"{ "user": { "userName": "mallory" } }"#;
+        assert!(!verifier.verify(tampered, signature.as_bytes()));
+        let untrusted = sign(&ec_key(), content);
+        assert!(!verifier.verify(content, &untrusted));
+    }
+
+    #[tokio::test]
+    async fn test_verify_url() {
+        let transfer = Transfer::default();
+        let keys = TempDir::with_prefix("keys").unwrap();
+        let key = ec_key();
+        write_public_key(keys.path(), "profile.pem", &key);
+        let verifier = SignatureVerifier::from_dir(keys.path()).unwrap();
+
+        let dir = TempDir::with_prefix("profile").unwrap();
+        let content = br#"{ "product": { "id": "Tumbleweed" } }"#;
+        let path = dir.path().join("profile.json");
+        fs::write(&path, content).unwrap();
+        let url = Url::from_file_path(&path).unwrap();
+
+        let result = verifier.verify_url(&transfer, url.as_str(), content).await;
+        assert!(matches!(result, Err(ProfileError::MissingSignature(_))));
+
+        fs::write(dir.path().join("profile.json.sig"), sign(&key, content)).unwrap();
+        assert!(verifier
+            .verify_url(&transfer, url.as_str(), content)
+            .await
+            .is_ok());
+
+        let result = verifier.verify_url(&transfer, url.as_str(), b"{}").await;
+        assert!(matches!(result, Err(ProfileError::InvalidSignature(_))));
+    }
+
+    #[tokio::test]
+    async fn test_load_signed_profiles() {
+        let keys = TempDir::with_prefix("keys").unwrap();
+        let key = ec_key();
+        write_public_key(keys.path(), "profile.pem", &key);
+        let verifier = SignatureVerifier::from_dir(keys.path()).unwrap();
+        let loader = ProfileLoader::new(HashMap::new(), HashMap::new()).with_verifier(verifier);
+
+        let dir = TempDir::with_prefix("profile").unwrap();
+        let profile = br#"{ "include": ["user.json"] }"#;
+        fs::write(dir.path().join("profile.json"), profile).unwrap();
+        fs::write(dir.path().join("profile.json.sig"), sign(&key, profile)).unwrap();
+        fs::write(dir.path().join("user.json"), r#"{ "user": {} }"#).unwrap();
+        let url = Url::from_file_path(dir.path().join("profile.json")).unwrap();
+
+        let result = loader.load(url.as_str()).await;
+        assert!(
+            matches!(result, Err(ProfileError::MissingSignature(u)) if u.ends_with("user.json"))
+        );
+
+        fs::write(
+            dir.path().join("user.json.sig"),
+            sign(&key, br#"{ "user": {} }"#),
+        )
+        .unwrap();
+        assert!(loader.load(url.as_str()).await.is_ok());
+    }
+
+    #[test]
+    fn test_no_keys() {
+        let dir = TempDir::with_prefix("keys").unwrap();
+        let result = SignatureVerifier::from_dir(dir.path());
+        assert!(matches!(result, Err(ProfileError::TrustedKeysError(_))));
+    }
+}
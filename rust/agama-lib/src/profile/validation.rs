@@ -0,0 +1,447 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! Structured errors for the profile validation.
+//!
+//! It converts the errors reported by [jsonschema] into [ValidationError] structs, which
+//! include the JSON pointer to the offending element, the failing keyword, the expected and
+//! actual values and, when possible, a suggestion and the position in the source file.
+
+use jsonschema::{
+    error::{TypeKind, ValidationErrorKind},
+    paths::PathChunk,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{collections::HashMap, fmt};
+
+/// Error found when validating a profile against the JSON schema.
+#[derive(Clone, Debug, PartialEq, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationError {
+    /// JSON pointer to the offending element (e.g., "/user/userName"). It is empty when the
+    /// error affects the whole profile.
+    pub pointer: String,
+    /// JSON schema keyword that failed (e.g., "type", "required" or "additionalProperties").
+    pub keyword: String,
+    /// Error description.
+    pub message: String,
+    /// Expected value (e.g., the allowed types or values), if it applies.
+    #[schema(value_type = Object)]
+    pub expected: Option<Value>,
+    /// Actual value, if it applies.
+    #[schema(value_type = Object)]
+    pub actual: Option<Value>,
+    /// Suggested property name or value when the actual one looks misspelled.
+    pub suggestion: Option<String>,
+    /// Line of the offending element in the source (starting at 1), if known.
+    pub line: Option<usize>,
+    /// Column of the offending element in the source (starting at 1), if known.
+    pub column: Option<usize>,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, "{}:{}: ", line, column)?;
+        }
+        let pointer = if self.pointer.is_empty() {
+            "/"
+        } else {
+            &self.pointer
+        };
+        write!(f, "{}: {}", pointer, self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean '{}'?)", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+impl ValidationError {
+    /// Converts an error from the JSON schema validator.
+    ///
+    /// An error about additional properties is split into one error per unexpected property.
+    ///
+    /// * `error`: error reported by the validator.
+    /// * `schema`: JSON schema, used to find the names of the allowed properties.
+    pub(super) fn from_schema_error(
+        error: jsonschema::ValidationError,
+        schema: &Value,
+    ) -> Vec<ValidationError> {
+        let pointer = error.instance_path.to_string();
+        let keyword = match error.schema_path.last() {
+            Some(PathChunk::Keyword(keyword)) => keyword.to_string(),
+            Some(PathChunk::Property(property)) => property.to_string(),
+            Some(PathChunk::Index(index)) => index.to_string(),
+            None => String::new(),
+        };
+        let actual = error.instance.clone().into_owned();
+        let base = ValidationError {
+            pointer,
+            keyword,
+            message: error.to_string(),
+            expected: None,
+            actual: Some(actual.clone()),
+            suggestion: None,
+            line: None,
+            column: None,
+        };
+
+        match error.kind {
+            ValidationErrorKind::AdditionalProperties { unexpected } => {
+                let allowed = allowed_properties(schema, &error.schema_path.to_string());
+                unexpected
+                    .into_iter()
+                    .map(|name| ValidationError {
+                        pointer: format!("{}/{}", base.pointer, escape_pointer_token(&name)),
+                        message: format!("unknown property '{}'", name),
+                        expected: (!allowed.is_empty()).then(|| json!(allowed)),
+                        suggestion: suggest(&name, &allowed),
+                        actual: Some(Value::String(name)),
+                        ..base.clone()
+                    })
+                    .collect()
+            }
+            ValidationErrorKind::Required { property } => {
+                let message = format!("missing property {}", property);
+                vec![ValidationError {
+                    message,
+                    expected: Some(property),
+                    actual: None,
+                    ..base
+                }]
+            }
+            ValidationErrorKind::Enum { options } => {
+                let names: Vec<String> = options
+                    .as_array()
+                    .map(|o| {
+                        o.iter()
+                            .filter_map(|v| v.as_str().map(String::from))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let suggestion = actual.as_str().and_then(|a| suggest(a, &names));
+                vec![ValidationError {
+                    expected: Some(options),
+                    suggestion,
+                    ..base
+                }]
+            }
+            kind => vec![ValidationError {
+                expected: expected_value(kind),
+                ..base
+            }],
+        }
+    }
+}
+
+/// Returns the expected value for a given kind of error.
+fn expected_value(kind: ValidationErrorKind) -> Option<Value> {
+    let value = match kind {
+        ValidationErrorKind::Type { kind } => match kind {
+            TypeKind::Single(primitive) => json!(primitive.to_string()),
+            TypeKind::Multiple(types) => {
+                let types: Vec<String> = types.into_iter().map(|t| t.to_string()).collect();
+                json!(types)
+            }
+        },
+        ValidationErrorKind::Constant { expected_value } => expected_value,
+        ValidationErrorKind::Minimum { limit }
+        | ValidationErrorKind::Maximum { limit }
+        | ValidationErrorKind::ExclusiveMinimum { limit }
+        | ValidationErrorKind::ExclusiveMaximum { limit } => limit,
+        ValidationErrorKind::MinLength { limit }
+        | ValidationErrorKind::MaxLength { limit }
+        | ValidationErrorKind::MinItems { limit }
+        | ValidationErrorKind::MaxItems { limit }
+        | ValidationErrorKind::MinProperties { limit }
+        | ValidationErrorKind::MaxProperties { limit } => json!(limit),
+        ValidationErrorKind::Pattern { pattern } => json!(pattern),
+        ValidationErrorKind::Format { format } => json!(format),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Returns the names of the properties allowed by the schema at the given path.
+///
+/// * `schema`: JSON schema.
+/// * `schema_path`: path to the failing "additionalProperties" keyword.
+fn allowed_properties(schema: &Value, schema_path: &str) -> Vec<String> {
+    let parent = schema_path
+        .rsplit_once('/')
+        .map(|(parent, _)| parent)
+        .unwrap_or_default();
+    let Some(properties) = resolve_schema_path(schema, parent)
+        .and_then(|s| s.get("properties"))
+        .and_then(Value::as_object)
+    else {
+        return vec![];
+    };
+    properties.keys().cloned().collect()
+}
+
+/// Returns the subschema at the given path, following the references ("$ref") in the way.
+///
+/// The validator does not include the references in the path, so they are followed whenever
+/// they are found. Only local references (e.g., "#/$defs/boot") are supported.
+///
+/// * `schema`: JSON schema.
+/// * `schema_path`: path to the subschema, as reported by the validator.
+fn resolve_schema_path<'a>(schema: &'a Value, schema_path: &str) -> Option<&'a Value> {
+    let mut current = follow_references(schema, schema)?;
+    for token in schema_path.split('/').skip(1) {
+        if token == "$ref" {
+            continue;
+        }
+        let token = token.replace("~1", "/").replace("~0", "~");
+        let next = match current {
+            Value::Object(object) => object.get(&token)?,
+            Value::Array(array) => array.get(token.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+        current = follow_references(schema, next)?;
+    }
+    Some(current)
+}
+
+/// Follows the references ("$ref") of a subschema until reaching one without a reference.
+///
+/// * `schema`: JSON schema.
+/// * `subschema`: subschema to resolve.
+fn follow_references<'a>(schema: &'a Value, subschema: &'a Value) -> Option<&'a Value> {
+    const MAX_DEPTH: usize = 32;
+
+    let mut current = subschema;
+    for _ in 0..MAX_DEPTH {
+        let Some(reference) = current.get("$ref").and_then(Value::as_str) else {
+            return Some(current);
+        };
+        current = schema.pointer(reference.strip_prefix('#')?)?;
+    }
+    None
+}
+
+/// Returns the candidate which is closest to the given value, if it is close enough.
+fn suggest(value: &str, candidates: &[String]) -> Option<String> {
+    let max_distance = (value.chars().count() / 3).max(2);
+    candidates
+        .iter()
+        .map(|c| (levenshtein(&value.to_lowercase(), &c.to_lowercase()), c))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, c)| c.to_string())
+}
+
+/// Computes the Levenshtein distance between two strings.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                previous
+            } else {
+                1 + previous.min(row[j]).min(current)
+            };
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
+
+fn escape_pointer_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// Finds the position (line and column) of each element of a JSON document.
+///
+/// The elements are identified by their JSON pointer. For object members, the position
+/// points to the key.
+///
+/// * `source`: JSON document. It is expected to be valid JSON.
+pub(super) fn json_positions(source: &str) -> HashMap<String, (usize, usize)> {
+    let mut scanner = PositionScanner {
+        bytes: source.as_bytes(),
+        pos: 0,
+        line: 1,
+        column: 1,
+        positions: HashMap::new(),
+    };
+    scanner.value(String::new());
+    scanner.positions
+}
+
+struct PositionScanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    line: usize,
+    column: usize,
+    positions: HashMap<String, (usize, usize)>,
+}
+
+impl<'a> PositionScanner<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) {
+        let Some(byte) = self.peek() else {
+            return;
+        };
+        self.pos += 1;
+        if byte == b'\n' {
+            self.line += 1;
+            self.column = 1;
+        } else if byte & 0xC0 != 0x80 {
+            // UTF-8 continuation bytes do not start a new character
+            self.column += 1;
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.bump();
+        }
+    }
+
+    fn value(&mut self, pointer: String) {
+        self.skip_whitespace();
+        self.positions
+            .entry(pointer.clone())
+            .or_insert((self.line, self.column));
+
+        match self.peek() {
+            Some(b'{') => self.object(&pointer),
+            Some(b'[') => self.array(&pointer),
+            Some(b'"') => {
+                self.string();
+            }
+            Some(_) => {
+                while matches!(self.peek(), Some(c) if !b",}] \t\r\n".contains(&c)) {
+                    self.bump();
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn object(&mut self, pointer: &str) {
+        self.bump();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.bump(),
+                Some(b'"') => {
+                    let position = (self.line, self.column);
+                    let key = self.string();
+                    let child = format!("{}/{}", pointer, escape_pointer_token(&key));
+                    self.positions.insert(child.clone(), position);
+                    self.skip_whitespace();
+                    if self.peek() == Some(b':') {
+                        self.bump();
+                    }
+                    self.value(child);
+                }
+                Some(b'}') => {
+                    self.bump();
+                    return;
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn array(&mut self, pointer: &str) {
+        self.bump();
+        let mut index = 0;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => {
+                    self.bump();
+                    index += 1;
+                }
+                Some(b']') => {
+                    self.bump();
+                    return;
+                }
+                Some(_) => {
+                    let start = self.pos;
+                    self.value(format!("{}/{}", pointer, index));
+                    if self.pos == start {
+                        return;
+                    }
+                }
+                None => return,
+            }
+        }
+    }
+
+    /// Consumes a string and returns its (unescaped) content.
+    fn string(&mut self) -> String {
+        let start = self.pos;
+        self.bump();
+        while let Some(byte) = self.peek() {
+            self.bump();
+            match byte {
+                b'\\' => self.bump(),
+                b'"' => break,
+                _ => {}
+            }
+        }
+        let raw = String::from_utf8_lossy(&self.bytes[start..self.pos]);
+        serde_json::from_str(&raw).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggest() {
+        let candidates = vec!["userName".to_string(), "fullName".to_string()];
+        assert_eq!(
+            suggest("username", &candidates),
+            Some("userName".to_string())
+        );
+        assert_eq!(
+            suggest("fulName", &candidates),
+            Some("fullName".to_string())
+        );
+        assert_eq!(suggest("password", &candidates), None);
+    }
+
+    #[test]
+    fn test_json_positions() {
+        let source = "{\n  \"user\": {\n    \"userName\": \"jane\",\n    \"groups\": [\"a\", \"ñ/b\"]\n  }\n}\n";
+        let positions = json_positions(source);
+        assert_eq!(positions.get(""), Some(&(1, 1)));
+        assert_eq!(positions.get("/user"), Some(&(2, 3)));
+        assert_eq!(positions.get("/user/userName"), Some(&(3, 5)));
+        assert_eq!(positions.get("/user/groups/0"), Some(&(4, 16)));
+        assert_eq!(positions.get("/user/groups/1"), Some(&(4, 21)));
+    }
+}
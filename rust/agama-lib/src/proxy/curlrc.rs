@@ -0,0 +1,123 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! Reads the proxy credentials from a curlrc file.
+//!
+//! The server writes the credentials to `/root/.curlrc` (see the `--proxy-user` option of
+//! curl), as they are not exposed through the HTTP API.
+
+/// Path of the curlrc file containing the proxy credentials.
+pub const CURLRC_PATH: &str = "/root/.curlrc";
+/// Option holding the credentials.
+pub const CURL_PROXY_USER: &str = "--proxy-user";
+
+/// Returns the value of a `--proxy-user` line, if it is one.
+///
+/// * `line`: curlrc line.
+pub fn parse_proxy_user(line: &str) -> Option<String> {
+    let rest = line.trim().strip_prefix(CURL_PROXY_USER)?;
+    let value = rest.trim_start_matches(|c: char| c == '=' || c.is_whitespace());
+    match value.strip_prefix('"') {
+        Some(quoted) => Some(unescape(quoted.strip_suffix('"').unwrap_or(quoted))),
+        None => Some(value.to_string()),
+    }
+}
+
+/// Returns the `--proxy-user` line for the given credentials.
+///
+/// The value is quoted and escaped, so it cannot add other options to the file.
+///
+/// * `credentials`: "user:password" or "user".
+pub fn format_proxy_user(credentials: &str) -> String {
+    let mut escaped = String::with_capacity(credentials.len());
+    for c in credentials.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    format!("{} \"{}\"", CURL_PROXY_USER, escaped)
+}
+
+/// Unescapes a quoted curlrc value.
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('t') => unescaped.push('\t'),
+            Some(c) => unescaped.push(c),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+/// Returns the proxy user and password (if any) from a curlrc file content.
+///
+/// * `content`: curlrc file content.
+pub fn proxy_credentials(content: &str) -> Option<(String, Option<String>)> {
+    let credentials = content.lines().find_map(parse_proxy_user)?;
+    match credentials.split_once(':') {
+        Some((user, password)) => Some((user.to_string(), Some(password.to_string()))),
+        None => Some((credentials, None)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proxy_credentials() {
+        let content = "insecure\n--proxy-user=\"john:secret\"\n";
+        assert_eq!(
+            proxy_credentials(content),
+            Some(("john".to_string(), Some("secret".to_string())))
+        );
+        assert_eq!(
+            proxy_credentials("--proxy-user jane\n"),
+            Some(("jane".to_string(), None))
+        );
+        assert_eq!(proxy_credentials("insecure\n"), None);
+    }
+
+    #[test]
+    fn test_format_proxy_user() {
+        let line = format_proxy_user("john:se\"c\\r\net");
+        assert_eq!(line, r#"--proxy-user "john:se\"c\\r\net""#);
+        assert_eq!(line.lines().count(), 1);
+        assert_eq!(
+            parse_proxy_user(&line),
+            Some("john:se\"c\\r\net".to_string())
+        );
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! Implements the store for the proxy settings.
+
+use super::{ProxyHTTPClient, ProxySettings};
+use crate::error::ServiceError;
+
+/// Loads and stores the proxy settings from/to the HTTP API.
+pub struct ProxyStore {
+    proxy_client: ProxyHTTPClient,
+}
+
+impl ProxyStore {
+    pub fn new() -> Result<ProxyStore, ServiceError> {
+        Ok(Self {
+            proxy_client: ProxyHTTPClient::new()?,
+        })
+    }
+
+    pub fn new_with_client(client: ProxyHTTPClient) -> Result<ProxyStore, ServiceError> {
+        Ok(Self {
+            proxy_client: client,
+        })
+    }
+
+    pub async fn load(&self) -> Result<ProxySettings, ServiceError> {
+        self.proxy_client.get_config().await
+    }
+
+    /// Stores the proxy settings, replacing the current ones.
+    ///
+    /// The settings are validated before sending them to the server.
+    pub async fn store(&self, settings: &ProxySettings) -> Result<(), ServiceError> {
+        settings.validate()?;
+        self.proxy_client.set_config(settings).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::base_http_client::BaseHTTPClient;
+    use httpmock::prelude::*;
+    use std::error::Error;
+    use tokio::test; // without this, "error: async functions cannot be used for tests"
+
+    fn proxy_store(mock_server_url: String) -> Result<ProxyStore, ServiceError> {
+        let mut bhc = BaseHTTPClient::default();
+        bhc.base_url = mock_server_url;
+        let client = ProxyHTTPClient::new_with_base(bhc)?;
+        ProxyStore::new_with_client(client)
+    }
+
+    #[test]
+    async fn test_getting_proxy() -> Result<(), Box<dyn Error>> {
+        let server = MockServer::start();
+        let proxy_mock = server.mock(|when, then| {
+            when.method(GET).path("/api/network/proxy");
+            then.status(200)
+                .header("content-type", "application/json")
+                .body(
+                    r#"{
+                    "http": "http://proxy.example.net:3128",
+                    "noProxy": ["localhost"]
+                }"#,
+                );
+        });
+        let url = server.url("/api");
+
+        let store = proxy_store(url)?;
+        let settings = store.load().await?;
+
+        let expected = ProxySettings {
+            http: Some("http://proxy.example.net:3128".to_string()),
+            no_proxy: Some(vec!["localhost".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(settings, expected);
+
+        proxy_mock.assert();
+        Ok(())
+    }
+
+    #[test]
+    async fn test_setting_proxy() -> Result<(), Box<dyn Error>> {
+        let server = MockServer::start();
+        let proxy_mock = server.mock(|when, then| {
+            when.method(PUT)
+                .path("/api/network/proxy")
+                .header("content-type", "application/json")
+                .body(r#"{"https":"http://proxy.example.net:3128","user":"john"}"#);
+            then.status(204);
+        });
+        let url = server.url("/api");
+
+        let store = proxy_store(url)?;
+        let settings = ProxySettings {
+            https: Some("http://proxy.example.net:3128".to_string()),
+            user: Some("john".to_string()),
+            ..Default::default()
+        };
+        store.store(&settings).await?;
+        proxy_mock.assert();
+
+        // invalid settings are not sent to the server
+        let settings = ProxySettings {
+            https: Some("proxy.example.net".to_string()),
+            ..Default::default()
+        };
+        assert!(store.store(&settings).await.is_err());
+        Ok(())
+    }
+}
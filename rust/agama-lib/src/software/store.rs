@@ -37,6 +37,12 @@ impl SoftwareStore {
         })
     }
 
+    pub fn new_with_client(client: SoftwareHTTPClient) -> Result<SoftwareStore, ServiceError> {
+        Ok(Self {
+            software_client: client,
+        })
+    }
+
     pub async fn load(&self) -> Result<SoftwareSettings, ServiceError> {
         let patterns = self.software_client.user_selected_patterns().await?;
         Ok(SoftwareSettings { patterns })
@@ -52,6 +58,28 @@ impl SoftwareStore {
 
         Ok(())
     }
+
+    /// Restores the software settings from a previous [SoftwareStore::load] call
+    ///
+    /// Unlike [SoftwareStore::store], it deselects the patterns that are not included in the
+    /// given settings.
+    ///
+    /// * `settings`: settings to restore.
+    pub async fn restore(&self, settings: &SoftwareSettings) -> Result<(), ServiceError> {
+        let mut patterns: HashMap<String, bool> = self
+            .software_client
+            .user_selected_patterns()
+            .await?
+            .into_iter()
+            .map(|name| (name, false))
+            .collect();
+        for name in &settings.patterns {
+            patterns.insert(name.to_owned(), true);
+        }
+        self.software_client.select_patterns(patterns).await?;
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -155,4 +183,41 @@ mod test {
         software_mock.assert();
         Ok(())
     }
+
+    #[test]
+    async fn test_restoring_software() -> Result<(), Box<dyn Error>> {
+        let server = MockServer::start();
+        let get_mock = server.mock(|when, then| {
+            when.method(GET).path("/api/software/config");
+            then.status(200)
+                .header("content-type", "application/json")
+                .body(r#"{"patterns": {"xfce":true, "gnome":true}, "product": "Tumbleweed"}"#);
+        });
+        let put_mock = server.mock(|when, then| {
+            when.method(PUT)
+                .path("/api/software/config")
+                .header("content-type", "application/json")
+                .json_body(serde_json::json!({
+                    "patterns": {"xfce": true, "gnome": false},
+                    "product": null
+                }));
+            then.status(200);
+        });
+        let url = server.url("/api");
+
+        let store = software_store(url);
+        let settings = SoftwareSettings {
+            patterns: vec!["xfce".to_owned()],
+        };
+
+        let result = store.restore(&settings).await;
+
+        // main assertion
+        result?;
+
+        // Ensure the specified mocks were called exactly one time (or fail with a detailed error description).
+        get_mock.assert();
+        put_mock.assert();
+        Ok(())
+    }
 }
@@ -36,6 +36,12 @@ impl StorageStore {
         })
     }
 
+    pub fn new_with_client(client: StorageHTTPClient) -> Result<StorageStore, ServiceError> {
+        Ok(Self {
+            storage_client: client,
+        })
+    }
+
     pub async fn load(&self) -> Result<StorageSettings, ServiceError> {
         self.storage_client.get_config().await
     }
@@ -24,9 +24,116 @@
 use crate::error::ServiceError;
 use crate::install_settings::InstallSettings;
 use crate::{
-    localization::LocalizationStore, network::NetworkStore, product::ProductStore,
-    software::SoftwareStore, storage::StorageStore, users::UsersStore,
+    auth::AuthToken,
+    base_http_client::BaseHTTPClient,
+    localization::{LocalizationHTTPClient, LocalizationStore},
+    manager::ManagerHTTPClient,
+    network::NetworkStore,
+    product::{ProductHTTPClient, ProductSettings, ProductStore},
+    proxy::{ProxyHTTPClient, ProxyStore},
+    software::{SoftwareHTTPClient, SoftwareStore},
+    storage::{http_client::StorageHTTPClient, StorageStore},
+    users::{UsersHTTPClient, UsersStore},
 };
+use serde::{Deserialize, Serialize};
+
+/// Sections of the installation settings.
+///
+/// The sections are listed in the order they must be written to the services (see
+/// [SettingsSection::ORDERED]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SettingsSection {
+    Network,
+    Proxy,
+    Product,
+    Localization,
+    Software,
+    User,
+    Storage,
+}
+
+impl SettingsSection {
+    /// Sections in the order they should be stored.
+    ///
+    /// Network (and the proxy) goes first as it can be critical for connecting to the
+    /// registration server, and selecting the product is important for the rest. Localization
+    /// goes after the product because some products may miss some locales.
+    pub const ORDERED: [SettingsSection; 7] = [
+        SettingsSection::Network,
+        SettingsSection::Proxy,
+        SettingsSection::Product,
+        SettingsSection::Localization,
+        SettingsSection::Software,
+        SettingsSection::User,
+        SettingsSection::Storage,
+    ];
+
+    /// Whether the given settings include this section.
+    ///
+    /// * `settings`: installation settings to check.
+    pub fn is_present(&self, settings: &InstallSettings) -> bool {
+        match self {
+            Self::Network => settings.network.is_some(),
+            Self::Proxy => settings.proxy.is_some(),
+            Self::Product => settings.product.is_some(),
+            Self::Localization => settings.localization.is_some(),
+            Self::Software => settings.software.is_some(),
+            Self::User => settings.user.is_some(),
+            Self::Storage => settings.storage.is_some() || settings.storage_autoyast.is_some(),
+        }
+    }
+}
+
+impl std::fmt::Display for SettingsSection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Network => "network",
+            Self::Proxy => "proxy",
+            Self::Product => "product",
+            Self::Localization => "localization",
+            Self::Software => "software",
+            Self::User => "user",
+            Self::Storage => "storage",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Outcome of storing a section of the installation settings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SectionStatus {
+    /// The section was written to the service.
+    Applied,
+    /// The section is not included in the settings.
+    NotPresent,
+    /// Writing the section failed.
+    Failed,
+    /// The section was not written because a previous one failed.
+    Skipped,
+}
+
+/// Result of storing a section of the installation settings.
+#[derive(Clone, Debug, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SectionResult {
+    pub section: SettingsSection,
+    pub status: SectionStatus,
+    /// Error message when the section could not be stored.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl SectionResult {
+    fn new(section: SettingsSection, status: SectionStatus) -> Self {
+        Self {
+            section,
+            status,
+            error: None,
+        }
+    }
+}
 
 /// Struct that loads/stores the settings from/to the D-Bus services.
 ///
@@ -37,6 +144,7 @@ use crate::{
 pub struct Store {
     users: UsersStore,
     network: NetworkStore,
+    proxy: ProxyStore,
     product: ProductStore,
     software: SoftwareStore,
     storage: StorageStore,
@@ -49,16 +157,44 @@ impl Store {
             localization: LocalizationStore::new()?,
             users: UsersStore::new()?,
             network: NetworkStore::new(http_client).await?,
+            proxy: ProxyStore::new()?,
             product: ProductStore::new()?,
             software: SoftwareStore::new()?,
             storage: StorageStore::new()?,
         })
     }
 
+    /// Builds a store which authenticates all the requests with the given token.
+    ///
+    /// Unlike [Store::new], it does not read the token of the current user, so it can act on
+    /// behalf of other users (e.g., the clients of the web server).
+    ///
+    /// * `token`: authentication token.
+    pub async fn new_with_token(token: &AuthToken) -> Result<Store, ServiceError> {
+        let base = BaseHTTPClient::new_with_token(token)?;
+        Ok(Self {
+            localization: LocalizationStore::new_with_client(
+                LocalizationHTTPClient::new_with_base(base.clone())?,
+            )?,
+            users: UsersStore::new_with_client(UsersHTTPClient::new_with_base(base.clone())?)?,
+            network: NetworkStore::new(crate::http_client(token.as_str())?).await?,
+            proxy: ProxyStore::new_with_client(ProxyHTTPClient::new_with_base(base.clone())?)?,
+            product: ProductStore::new_with_client(
+                ProductHTTPClient::new_with_base(base.clone()),
+                ManagerHTTPClient::new_with_base(base.clone()),
+            )?,
+            software: SoftwareStore::new_with_client(SoftwareHTTPClient::new_with_base(
+                base.clone(),
+            ))?,
+            storage: StorageStore::new_with_client(StorageHTTPClient::new_with_base(base))?,
+        })
+    }
+
     /// Loads the installation settings from the HTTP interface.
     pub async fn load(&self) -> Result<InstallSettings, ServiceError> {
         let mut settings = InstallSettings {
             network: Some(self.network.load().await?),
+            proxy: Some(self.proxy.load().await?),
             software: Some(self.software.load().await?),
             user: Some(self.users.load().await?),
             product: Some(self.product.load().await?),
@@ -75,28 +211,260 @@ impl Store {
     }
 
     /// Stores the given installation settings in the D-Bus service
+    ///
+    /// It stops at the first section that fails.
     pub async fn store(&self, settings: &InstallSettings) -> Result<(), ServiceError> {
-        if let Some(network) = &settings.network {
-            self.network.store(network).await?;
+        for section in SettingsSection::ORDERED {
+            self.store_section(section, settings).await?;
         }
-        // order is important here as network can be critical for connection
-        // to registration server and selecting product is important for rest
-        if let Some(product) = &settings.product {
-            self.product.store(product).await?;
+        Ok(())
+    }
+
+    /// Stores the given installation settings and reports the result of each section
+    ///
+    /// Unlike [Store::store], it does not return an error. Instead, it reports which sections
+    /// were applied, which one failed and which ones were skipped because of that failure.
+    pub async fn store_sections(&self, settings: &InstallSettings) -> Vec<SectionResult> {
+        let mut results = Vec::with_capacity(SettingsSection::ORDERED.len());
+        let mut failed = false;
+
+        for section in SettingsSection::ORDERED {
+            if !section.is_present(settings) {
+                results.push(SectionResult::new(section, SectionStatus::NotPresent));
+                continue;
+            }
+
+            if failed {
+                results.push(SectionResult::new(section, SectionStatus::Skipped));
+                continue;
+            }
+
+            let result = match self.store_section(section, settings).await {
+                Ok(()) => SectionResult::new(section, SectionStatus::Applied),
+                Err(error) => {
+                    failed = true;
+                    SectionResult {
+                        error: Some(error.to_string()),
+                        ..SectionResult::new(section, SectionStatus::Failed)
+                    }
+                }
+            };
+            results.push(result);
         }
-        // ordering: localization after product as some product may miss some locales
-        if let Some(localization) = &settings.localization {
-            self.localization.store(localization).await?;
+        results
+    }
+
+    /// Stores the given installation settings, rolling back on failure
+    ///
+    /// Before writing a section, it takes a snapshot of the current values using the `load`
+    /// method of the corresponding store. If a section fails, that section and the ones that
+    /// were already written are restored (in reverse order) and the returned error tells which
+    /// ones were rolled back.
+    pub async fn store_transactional(
+        &self,
+        settings: &InstallSettings,
+    ) -> Result<(), ServiceError> {
+        let mut snapshot = InstallSettings::default();
+        let mut applied: Vec<SettingsSection> = vec![];
+
+        for section in SettingsSection::ORDERED {
+            if !section.is_present(settings) {
+                continue;
+            }
+
+            let result = match self.load_section(section, &mut snapshot).await {
+                Ok(()) => {
+                    // the failing section might be partially written, so restore it too
+                    let result = self.store_section(section, settings).await;
+                    if result.is_err() {
+                        applied.push(section);
+                    }
+                    result
+                }
+                Err(error) => Err(error),
+            };
+
+            if let Err(error) = result {
+                let mut rolled_back = vec![];
+                let mut not_rolled_back = vec![];
+                for applied_section in applied.into_iter().rev() {
+                    match self.restore_section(applied_section, &snapshot).await {
+                        Ok(()) => rolled_back.push(applied_section),
+                        Err(rollback_error) => {
+                            log::error!(
+                                "Could not roll back the {} settings: {}",
+                                applied_section,
+                                rollback_error
+                            );
+                            not_rolled_back.push(applied_section);
+                        }
+                    }
+                }
+                return Err(ServiceError::SettingsRolledBack {
+                    section,
+                    source: Box::new(error),
+                    rolled_back,
+                    not_rolled_back,
+                });
+            }
+            applied.push(section);
         }
-        if let Some(software) = &settings.software {
-            self.software.store(software).await?;
+        Ok(())
+    }
+
+    /// Loads a single section of the installation settings
+    ///
+    /// * `section`: section to load.
+    /// * `settings`: installation settings to write the section to.
+    async fn load_section(
+        &self,
+        section: SettingsSection,
+        settings: &mut InstallSettings,
+    ) -> Result<(), ServiceError> {
+        match section {
+            SettingsSection::Network => settings.network = Some(self.network.load().await?),
+            SettingsSection::Proxy => settings.proxy = Some(self.proxy.load().await?),
+            SettingsSection::Product => settings.product = Some(self.product.load().await?),
+            SettingsSection::Localization => {
+                settings.localization = Some(self.localization.load().await?)
+            }
+            SettingsSection::Software => settings.software = Some(self.software.load().await?),
+            SettingsSection::User => settings.user = Some(self.users.load().await?),
+            SettingsSection::Storage => {
+                let storage_settings = self.storage.load().await?;
+                settings.storage = storage_settings.storage;
+                settings.storage_autoyast = storage_settings.storage_autoyast;
+            }
         }
-        if let Some(user) = &settings.user {
-            self.users.store(user).await?;
+        Ok(())
+    }
+
+    /// Restores a single section from a snapshot taken with [Store::load_section]
+    ///
+    /// * `section`: section to restore.
+    /// * `snapshot`: installation settings containing the section.
+    async fn restore_section(
+        &self,
+        section: SettingsSection,
+        snapshot: &InstallSettings,
+    ) -> Result<(), ServiceError> {
+        match section {
+            SettingsSection::Network => {
+                if let Some(network) = &snapshot.network {
+                    self.network.restore(network).await?;
+                }
+            }
+            SettingsSection::Product => {
+                // registration cannot be undone, so just select the previous product (if any)
+                let id = snapshot.product.as_ref().and_then(|p| p.id.clone());
+                if let Some(id) = id.filter(|id| !id.is_empty()) {
+                    let product = ProductSettings {
+                        id: Some(id),
+                        ..Default::default()
+                    };
+                    self.product.store(&product).await?;
+                }
+            }
+            SettingsSection::Software => {
+                if let Some(software) = &snapshot.software {
+                    self.software.restore(software).await?;
+                }
+            }
+            SettingsSection::User => {
+                if let Some(user) = &snapshot.user {
+                    self.users.restore(user).await?;
+                }
+            }
+            _ => self.store_section(section, snapshot).await?,
         }
-        if settings.storage.is_some() || settings.storage_autoyast.is_some() {
-            self.storage.store(&settings.into()).await?
+        Ok(())
+    }
+
+    /// Stores a single section of the installation settings
+    ///
+    /// It does nothing if the section is not present in the settings.
+    ///
+    /// * `section`: section to store.
+    /// * `settings`: installation settings.
+    async fn store_section(
+        &self,
+        section: SettingsSection,
+        settings: &InstallSettings,
+    ) -> Result<(), ServiceError> {
+        match section {
+            SettingsSection::Network => {
+                if let Some(network) = &settings.network {
+                    self.network.store(network).await?;
+                }
+            }
+            SettingsSection::Proxy => {
+                if let Some(proxy) = &settings.proxy {
+                    self.proxy.store(proxy).await?;
+                }
+            }
+            SettingsSection::Product => {
+                if let Some(product) = &settings.product {
+                    self.product.store(product).await?;
+                }
+            }
+            SettingsSection::Localization => {
+                if let Some(localization) = &settings.localization {
+                    self.localization.store(localization).await?;
+                }
+            }
+            SettingsSection::Software => {
+                if let Some(software) = &settings.software {
+                    self.software.store(software).await?;
+                }
+            }
+            SettingsSection::User => {
+                if let Some(user) = &settings.user {
+                    self.users.store(user).await?;
+                }
+            }
+            SettingsSection::Storage => {
+                if section.is_present(settings) {
+                    self.storage.store(&settings.into()).await?;
+                }
+            }
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::software::SoftwareSettings;
+    use serde_json::value::RawValue;
+
+    #[test]
+    fn test_section_is_present() {
+        let settings = InstallSettings {
+            software: Some(SoftwareSettings::default()),
+            storage_autoyast: Some(RawValue::from_string("{}".to_string()).unwrap()),
+            ..Default::default()
+        };
+
+        let present: Vec<SettingsSection> = SettingsSection::ORDERED
+            .into_iter()
+            .filter(|s| s.is_present(&settings))
+            .collect();
+        assert_eq!(
+            present,
+            vec![SettingsSection::Software, SettingsSection::Storage]
+        );
+    }
+    #[test]
+    fn test_rolled_back_error_message() {
+        let error = ServiceError::SettingsRolledBack {
+            section: SettingsSection::Software,
+            source: Box::new(ServiceError::NotAuthenticated),
+            rolled_back: vec![SettingsSection::Software, SettingsSection::Proxy],
+            not_rolled_back: vec![],
+        };
+        assert!(error.to_string().ends_with(
+            "Rolled back sections: software, proxy. Sections that could not be rolled back: none"
+        ));
+    }
+}
@@ -0,0 +1,492 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! Downloads files from the URLs supported by AutoYaST.
+//!
+//! [Transfer] dispatches each URL to the [SchemeHandler] registered for its scheme. By default,
+//! these schemes are supported:
+//!
+//! * `http` and `https`: retries on failure and supports proxies and custom TLS settings (see
+//!   [HttpOptions]).
+//! * `ftp` and `tftp`.
+//! * `file`.
+//! * `device`, `usb` and `label`: the file is read from a block device which is mounted
+//!   read-only (e.g., `device://sda1/profile.json`, `usb:///profile.json` or
+//!   `label://OEMDRV/profile.json`). When the device is omitted, all the devices (or all the USB
+//!   ones) are searched.
+//! * `relurl`: the URL is relative to the base URL (usually, the URL of the profile), e.g.,
+//!   `relurl://scripts/pre.sh`.
+//!
+//! Additionally, the content can be verified using a [Checksum].
+//!
+//! ```no_run
+//! use agama_lib::transfer::{Checksum, Transfer};
+//!
+//! # async fn fetch() -> Result<(), Box<dyn std::error::Error>> {
+//! let transfer = Transfer::default();
+//! let checksum: Checksum = "sha256:2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae"
+//!     .parse()?;
+//! let content = transfer
+//!     .fetch_verified("http://example.net/profile.json", &checksum)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod curl;
+mod device;
+mod file;
+mod http;
+
+pub use curl::CurlHandler;
+pub use device::{DeviceHandler, Mounter, SystemMounter};
+pub use file::FileHandler;
+pub use http::{HttpHandler, HttpOptions};
+
+use async_trait::async_trait;
+use openssl::hash::{hash, MessageDigest};
+use std::{fmt, str::FromStr, sync::Arc};
+use thiserror::Error;
+use url::Url;
+
+const RELURL_SCHEME: &str = "relurl";
+
+#[derive(Error, Debug)]
+pub enum TransferError {
+    #[error("Invalid URL '{0}': {1}")]
+    InvalidUrl(String, String),
+    #[error("Unsupported scheme '{0}'")]
+    UnsupportedScheme(String),
+    #[error("Could not resolve '{0}' without a base URL")]
+    MissingBaseUrl(String),
+    #[error("Could not download '{0}': {1}")]
+    Download(String, String),
+    #[error("Could not find '{0}' in any device")]
+    NotFound(String),
+    #[error("I/O error: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("Invalid checksum '{0}'")]
+    InvalidChecksum(String),
+    #[error("Checksum mismatch for '{url}': expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        url: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+/// Downloads the content of the URLs with a given scheme.
+#[async_trait]
+pub trait SchemeHandler: Send + Sync {
+    /// Schemes supported by this handler (e.g., "http" and "https").
+    fn schemes(&self) -> &[&str];
+
+    /// Returns the content of the given URL.
+    ///
+    /// * `url`: URL to download.
+    async fn fetch(&self, url: &Url) -> Result<Vec<u8>, TransferError>;
+}
+
+/// Downloads files using the registered scheme handlers.
+#[derive(Clone)]
+pub struct Transfer {
+    handlers: Vec<Arc<dyn SchemeHandler>>,
+    base: Option<Url>,
+}
+
+impl Default for Transfer {
+    /// Returns a transfer supporting all the schemes described in the [module](self) documentation.
+    fn default() -> Self {
+        Self::with_http_options(HttpOptions::default())
+    }
+}
+
+impl Transfer {
+    /// Returns a transfer without any handler.
+    pub fn empty() -> Self {
+        Self {
+            handlers: vec![],
+            base: None,
+        }
+    }
+
+    /// Returns a transfer supporting the default schemes, using the given HTTP options.
+    ///
+    /// * `options`: HTTP options (retries, proxy, TLS, etc.).
+    pub fn with_http_options(options: HttpOptions) -> Self {
+        Self::empty()
+            .with_handler(HttpHandler::new(options))
+            .with_handler(CurlHandler::default())
+            .with_handler(FileHandler)
+            .with_handler(DeviceHandler::default())
+    }
+
+    /// Registers a handler, replacing the previous handlers of the same schemes.
+    ///
+    /// * `handler`: scheme handler.
+    pub fn with_handler<T: SchemeHandler + 'static>(mut self, handler: T) -> Self {
+        self.handlers.insert(0, Arc::new(handler));
+        self
+    }
+
+    /// Sets the URL to resolve `relurl://` URLs against.
+    ///
+    /// * `base`: base URL.
+    pub fn with_base(mut self, base: Url) -> Self {
+        self.base = Some(base);
+        self
+    }
+
+    /// Returns the content of the given URL.
+    ///
+    /// * `url`: URL to download.
+    pub async fn fetch(&self, url: &str) -> Result<Vec<u8>, TransferError> {
+        let url = self.resolve(url)?;
+        let handler = self
+            .handlers
+            .iter()
+            .find(|h| h.schemes().contains(&url.scheme()))
+            .ok_or(TransferError::UnsupportedScheme(url.scheme().to_string()))?;
+        handler.fetch(&url).await
+    }
+
+    /// Returns the content of the given URL, checking that it matches the checksum.
+    ///
+    /// * `url`: URL to download.
+    /// * `checksum`: expected checksum.
+    pub async fn fetch_verified(
+        &self,
+        url: &str,
+        checksum: &Checksum,
+    ) -> Result<Vec<u8>, TransferError> {
+        let content = self.fetch(url).await?;
+        checksum.verify(url, &content)?;
+        Ok(content)
+    }
+
+    /// Parses the URL, resolving `relurl://` URLs against the base one.
+    ///
+    /// * `url`: URL to resolve.
+    pub fn resolve(&self, url: &str) -> Result<Url, TransferError> {
+        let parsed = Url::parse(url)
+            .map_err(|e| TransferError::InvalidUrl(url.to_string(), e.to_string()))?;
+        if parsed.scheme() != RELURL_SCHEME {
+            return Ok(parsed);
+        }
+
+        let base = self
+            .base
+            .as_ref()
+            .ok_or(TransferError::MissingBaseUrl(url.to_string()))?;
+        let relative = &url[RELURL_SCHEME.len() + 3..];
+        base.join(relative)
+            .map_err(|e| TransferError::InvalidUrl(url.to_string(), e.to_string()))
+    }
+}
+
+/// Hash algorithm of a [Checksum].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChecksumAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl ChecksumAlgorithm {
+    fn digest(&self) -> MessageDigest {
+        match self {
+            Self::Md5 => MessageDigest::md5(),
+            Self::Sha1 => MessageDigest::sha1(),
+            Self::Sha256 => MessageDigest::sha256(),
+            Self::Sha512 => MessageDigest::sha512(),
+        }
+    }
+}
+
+impl fmt::Display for ChecksumAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Md5 => "md5",
+            Self::Sha1 => "sha1",
+            Self::Sha256 => "sha256",
+            Self::Sha512 => "sha512",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Expected checksum of a file, written as `<algorithm>:<hex digest>` (e.g., "sha256:2c26...").
+#[derive(Clone, Debug, PartialEq)]
+pub struct Checksum {
+    pub algorithm: ChecksumAlgorithm,
+    pub digest: String,
+}
+
+impl Checksum {
+    /// Computes the checksum of the given content.
+    ///
+    /// * `algorithm`: hash algorithm.
+    /// * `content`: content to hash.
+    pub fn compute(algorithm: ChecksumAlgorithm, content: &[u8]) -> Result<Self, TransferError> {
+        let digest = hash(algorithm.digest(), content)
+            .map_err(|e| TransferError::InvalidChecksum(e.to_string()))?;
+        let digest = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        Ok(Self { algorithm, digest })
+    }
+
+    /// Checks whether the content matches the checksum.
+    ///
+    /// * `url`: URL of the content, used in the error message.
+    /// * `content`: content to check.
+    pub fn verify(&self, url: &str, content: &[u8]) -> Result<(), TransferError> {
+        let actual = Self::compute(self.algorithm, content)?;
+        if actual != *self {
+            return Err(TransferError::ChecksumMismatch {
+                url: url.to_string(),
+                expected: self.to_string(),
+                actual: actual.to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Checksum {
+    type Err = TransferError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || TransferError::InvalidChecksum(s.to_string());
+        let (algorithm, digest) = s.split_once(':').ok_or_else(invalid)?;
+        let algorithm = match algorithm.to_lowercase().as_str() {
+            "md5" => ChecksumAlgorithm::Md5,
+            "sha1" => ChecksumAlgorithm::Sha1,
+            "sha256" => ChecksumAlgorithm::Sha256,
+            "sha512" => ChecksumAlgorithm::Sha512,
+            _ => return Err(invalid()),
+        };
+        if digest.is_empty() || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        Ok(Self {
+            algorithm,
+            digest: digest.to_lowercase(),
+        })
+    }
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.algorithm, self.digest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::ProxySettings;
+    use httpmock::prelude::*;
+    use std::{fs, time::Duration};
+    use tempfile::TempDir;
+
+    const HELLO_SHA256: &str =
+        "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    fn transfer() -> Transfer {
+        Transfer::with_http_options(HttpOptions {
+            retries: 2,
+            retry_delay: Duration::from_millis(10),
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_fetch_http() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(GET).path("/profile.json");
+            then.status(200).body("hello");
+        });
+
+        let content = transfer()
+            .fetch(&server.url("/profile.json"))
+            .await
+            .unwrap();
+        assert_eq!(content, b"hello");
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_fetch_http_proxy() {
+        let proxy = MockServer::start();
+        let mock = proxy.mock(|when, then| {
+            when.method(GET).path("/profile.json");
+            then.status(200).body("hello");
+        });
+
+        let settings = ProxySettings {
+            http: Some(proxy.base_url()),
+            no_proxy: Some(vec!["localhost".to_string()]),
+            ..Default::default()
+        };
+        let transfer = Transfer::with_http_options(HttpOptions::default().with_proxy(&settings));
+        let content = transfer
+            .fetch("http://example.net/profile.json")
+            .await
+            .unwrap();
+        assert_eq!(content, b"hello");
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_fetch_http_and_https_proxies() {
+        let http_proxy = MockServer::start();
+        let http_mock = http_proxy.mock(|when, then| {
+            when.path("/profile.json");
+            then.status(200).body("hello");
+        });
+        let https_proxy = MockServer::start();
+        let https_mock = https_proxy.mock(|when, then| {
+            when.method("CONNECT");
+            then.status(502);
+        });
+
+        let settings = ProxySettings {
+            http: Some(http_proxy.base_url()),
+            https: Some(https_proxy.base_url()),
+            ..Default::default()
+        };
+        let transfer = Transfer::with_http_options(HttpOptions {
+            retries: 0,
+            ..HttpOptions::default().with_proxy(&settings)
+        });
+
+        let content = transfer
+            .fetch("http://example.net/profile.json")
+            .await
+            .unwrap();
+        assert_eq!(content, b"hello");
+        http_mock.assert();
+        https_mock.assert_hits(0);
+
+        let result = transfer.fetch("https://example.net/profile.json").await;
+        assert!(matches!(result, Err(TransferError::Download(_, _))));
+        http_mock.assert_hits(1);
+        https_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_fetch_http_retries() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(GET).path("/profile.json");
+            then.status(503);
+        });
+
+        let result = transfer().fetch(&server.url("/profile.json")).await;
+        assert!(matches!(result, Err(TransferError::Download(_, _))));
+        mock.assert_hits(3);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_http_not_found() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(GET).path("/missing.json");
+            then.status(404);
+        });
+
+        let result = transfer().fetch(&server.url("/missing.json")).await;
+        assert!(matches!(result, Err(TransferError::Download(_, _))));
+        // client errors are not retried
+        mock.assert_hits(1);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_relurl() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(GET).path("/profiles/scripts/pre.sh");
+            then.status(200).body("hello");
+        });
+
+        let base = Url::parse(&server.url("/profiles/profile.json")).unwrap();
+        let content = transfer()
+            .with_base(base)
+            .fetch("relurl://scripts/pre.sh")
+            .await
+            .unwrap();
+        assert_eq!(content, b"hello");
+        mock.assert();
+
+        let result = transfer().fetch("relurl://scripts/pre.sh").await;
+        assert!(matches!(result, Err(TransferError::MissingBaseUrl(_))));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_file() {
+        let dir = TempDir::with_prefix("transfer").unwrap();
+        let path = dir.path().join("profile.json");
+        fs::write(&path, "hello").unwrap();
+        let url = Url::from_file_path(&path).unwrap();
+
+        let content = transfer().fetch(url.as_str()).await.unwrap();
+        assert_eq!(content, b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_verified() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/profile.json");
+            then.status(200).body("hello");
+        });
+        let url = server.url("/profile.json");
+
+        let checksum: Checksum = HELLO_SHA256.parse().unwrap();
+        let content = transfer().fetch_verified(&url, &checksum).await.unwrap();
+        assert_eq!(content, b"hello");
+
+        let checksum: Checksum = "md5:00000000000000000000000000000000".parse().unwrap();
+        let result = transfer().fetch_verified(&url, &checksum).await;
+        assert!(matches!(
+            result,
+            Err(TransferError::ChecksumMismatch { actual, .. })
+                if actual == "md5:5d41402abc4b2a76b9719d911017c592"
+        ));
+    }
+
+    #[test]
+    fn test_parse_checksum() {
+        let checksum: Checksum = "SHA256:ABCDEF".parse().unwrap();
+        assert_eq!(checksum.algorithm, ChecksumAlgorithm::Sha256);
+        assert_eq!(checksum.to_string(), "sha256:abcdef");
+
+        assert!("crc32:abcdef".parse::<Checksum>().is_err());
+        assert!("sha256:xyz".parse::<Checksum>().is_err());
+        assert!("abcdef".parse::<Checksum>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_scheme() {
+        let result = transfer().fetch("gopher://example.net/profile.json").await;
+        assert!(matches!(result, Err(TransferError::UnsupportedScheme(_))));
+    }
+}
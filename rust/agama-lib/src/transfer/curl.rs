@@ -0,0 +1,73 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! Implements the `ftp` and `tftp` schemes using curl.
+
+use super::{SchemeHandler, TransferError};
+use async_trait::async_trait;
+use curl::easy::Easy;
+use url::Url;
+
+/// Downloads files using curl, which runs in a blocking task.
+pub struct CurlHandler {
+    schemes: Vec<&'static str>,
+}
+
+impl Default for CurlHandler {
+    fn default() -> Self {
+        Self {
+            schemes: vec!["ftp", "tftp"],
+        }
+    }
+}
+
+fn download(url: &str) -> Result<Vec<u8>, curl::Error> {
+    let mut content = vec![];
+    let mut handle = Easy::new();
+    handle.url(url)?;
+    // do not take an error page as the content
+    handle.fail_on_error(true)?;
+
+    let mut transfer = handle.transfer();
+    transfer.write_function(|buf| {
+        content.extend_from_slice(buf);
+        Ok(buf.len())
+    })?;
+    transfer.perform()?;
+    drop(transfer);
+
+    Ok(content)
+}
+
+#[async_trait]
+impl SchemeHandler for CurlHandler {
+    fn schemes(&self) -> &[&str] {
+        &self.schemes
+    }
+
+    async fn fetch(&self, url: &Url) -> Result<Vec<u8>, TransferError> {
+        let error = |e: String| TransferError::Download(url.to_string(), e);
+        let owned = url.to_string();
+        tokio::task::spawn_blocking(move || download(&owned))
+            .await
+            .map_err(|e| error(e.to_string()))?
+            .map_err(|e| error(e.to_string()))
+    }
+}
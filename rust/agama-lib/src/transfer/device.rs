@@ -0,0 +1,336 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! Implements the `device`, `usb` and `label` schemes.
+//!
+//! * `device://sda1/path/file`: reads the file from the given device.
+//! * `device:///path/file`: searches the file in all the devices.
+//! * `usb:///path/file`: searches the file in the USB devices.
+//! * `label://OEMDRV/path/file`: reads the file from the file system with the given label.
+//!
+//! The label and the path are percent-decoded (e.g., `label://MY%20DISK/my%20profile.json`).
+//!
+//! The devices are mounted read-only in a temporary directory and unmounted right after reading
+//! the file.
+
+use super::{SchemeHandler, TransferError};
+use async_trait::async_trait;
+use percent_encoding::percent_decode_str;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Arc,
+};
+use tempfile::TempDir;
+use url::Url;
+
+/// Mounts and unmounts file systems.
+pub trait Mounter: Send + Sync {
+    /// Mounts the device read-only.
+    ///
+    /// * `device`: device to mount.
+    /// * `target`: mount point.
+    fn mount(&self, device: &Path, target: &Path) -> io::Result<()>;
+
+    /// Unmounts the file system.
+    ///
+    /// * `target`: mount point.
+    fn umount(&self, target: &Path) -> io::Result<()>;
+}
+
+/// Mounts file systems using the `mount` and `umount` commands.
+pub struct SystemMounter;
+
+fn run(command: &mut Command) -> io::Result<()> {
+    let output = command.output()?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(io::Error::other(message));
+    }
+    Ok(())
+}
+
+impl Mounter for SystemMounter {
+    fn mount(&self, device: &Path, target: &Path) -> io::Result<()> {
+        run(Command::new("mount")
+            .args(["-o", "ro"])
+            .arg(device)
+            .arg(target))
+    }
+
+    fn umount(&self, target: &Path) -> io::Result<()> {
+        run(Command::new("umount").arg(target))
+    }
+}
+
+/// Reads files from block devices.
+pub struct DeviceHandler {
+    dev_dir: PathBuf,
+    mounter: Arc<dyn Mounter>,
+}
+
+impl Default for DeviceHandler {
+    fn default() -> Self {
+        Self::new("/dev", SystemMounter)
+    }
+}
+
+impl DeviceHandler {
+    /// Builds a handler.
+    ///
+    /// * `dev_dir`: directory containing the device nodes (usually `/dev`).
+    /// * `mounter`: object to mount the devices.
+    pub fn new<P: AsRef<Path>, M: Mounter + 'static>(dev_dir: P, mounter: M) -> Self {
+        Self {
+            dev_dir: dev_dir.as_ref().to_path_buf(),
+            mounter: Arc::new(mounter),
+        }
+    }
+}
+
+#[async_trait]
+impl SchemeHandler for DeviceHandler {
+    fn schemes(&self) -> &[&str] {
+        &["device", "usb", "label"]
+    }
+
+    async fn fetch(&self, url: &Url) -> Result<Vec<u8>, TransferError> {
+        let lookup = DeviceLookup {
+            dev_dir: self.dev_dir.clone(),
+            mounter: Arc::clone(&self.mounter),
+        };
+        let url = url.clone();
+        tokio::task::spawn_blocking(move || lookup.read(&url))
+            .await
+            .map_err(|e| TransferError::IO(io::Error::other(e)))?
+    }
+}
+
+struct DeviceLookup {
+    dev_dir: PathBuf,
+    mounter: Arc<dyn Mounter>,
+}
+
+impl DeviceLookup {
+    fn read(&self, url: &Url) -> Result<Vec<u8>, TransferError> {
+        let decode = |value: &'_ str| {
+            percent_decode_str(value)
+                .decode_utf8()
+                .map(|v| v.into_owned())
+                .map_err(|e| TransferError::InvalidUrl(url.to_string(), e.to_string()))
+        };
+        let host = decode(url.host_str().unwrap_or_default())?;
+        let path = decode(url.path())?;
+        let path = path.trim_start_matches('/');
+        if path.is_empty() {
+            return Err(TransferError::InvalidUrl(
+                url.to_string(),
+                "missing file path".to_string(),
+            ));
+        }
+
+        let devices = match (url.scheme(), host.as_str()) {
+            ("label", "") => {
+                return Err(TransferError::InvalidUrl(
+                    url.to_string(),
+                    "missing label".to_string(),
+                ))
+            }
+            ("label", label) => vec![self.dev_dir.join("disk/by-label").join(encode_label(label))],
+            ("usb", "") => self.find_devices(Some("usb-")),
+            ("device", "") => self.find_devices(None),
+            (_, device) => vec![self.dev_dir.join(device)],
+        };
+
+        for device in devices {
+            match self.read_from(&device, path) {
+                Ok(content) => return Ok(content),
+                Err(error) => log::debug!("{} not found in {:?}: {}", path, device, error),
+            }
+        }
+        Err(TransferError::NotFound(url.to_string()))
+    }
+
+    /// Returns the block devices, optionally filtering by the prefix of their id.
+    fn find_devices(&self, prefix: Option<&str>) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(self.dev_dir.join("disk/by-id")) else {
+            return vec![];
+        };
+
+        let mut devices: Vec<PathBuf> = entries
+            .flatten()
+            .filter(|e| match prefix {
+                Some(prefix) => e.file_name().to_string_lossy().starts_with(prefix),
+                None => true,
+            })
+            .filter_map(|e| fs::canonicalize(e.path()).ok())
+            .collect();
+        devices.sort();
+        devices.dedup();
+        devices
+    }
+
+    fn read_from(&self, device: &Path, path: &str) -> io::Result<Vec<u8>> {
+        let mount_point = TempDir::with_prefix("agama-device")?;
+        self.mounter.mount(device, mount_point.path())?;
+        let content = fs::read(mount_point.path().join(path));
+        if let Err(error) = self.mounter.umount(mount_point.path()) {
+            log::warn!("Could not unmount {:?}: {}", device, error);
+        }
+        content
+    }
+}
+
+/// Encodes a file system label as udev does for the /dev/disk/by-label links.
+///
+/// The characters which are not allowed (e.g., spaces or slashes) are written as "\\xNN".
+///
+/// * `label`: file system label.
+fn encode_label(label: &str) -> String {
+    let mut encoded = String::with_capacity(label.len());
+    for c in label.chars() {
+        if c.is_ascii_alphanumeric() || "#+-.:=@_".contains(c) || !c.is_ascii() {
+            encoded.push(c);
+        } else {
+            encoded.push_str(&format!("\\x{:02x}", c as u32));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::HashMap, os::unix::fs::symlink};
+
+    /// Fake mounter which links the content of a directory into the mount point.
+    struct FakeMounter {
+        contents: HashMap<PathBuf, PathBuf>,
+    }
+
+    impl Mounter for FakeMounter {
+        fn mount(&self, device: &Path, target: &Path) -> io::Result<()> {
+            let source = self
+                .contents
+                .get(&fs::canonicalize(device)?)
+                .ok_or(io::Error::other("not a file system"))?;
+            for entry in fs::read_dir(source)? {
+                let entry = entry?;
+                symlink(entry.path(), target.join(entry.file_name()))?;
+            }
+            Ok(())
+        }
+
+        fn umount(&self, target: &Path) -> io::Result<()> {
+            for entry in fs::read_dir(target)? {
+                fs::remove_file(entry?.path())?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Builds a fake /dev with two devices (sda1 and a USB stick, sdb1).
+    fn setup() -> (TempDir, DeviceHandler) {
+        let root = TempDir::with_prefix("devices").unwrap();
+        let dev = root.path().join("dev");
+        let by_id = dev.join("disk/by-id");
+        let by_label = dev.join("disk/by-label");
+        fs::create_dir_all(&by_id).unwrap();
+        fs::create_dir_all(&by_label).unwrap();
+
+        let mut contents = HashMap::new();
+        for (name, id, label, file) in [
+            ("sda1", "ata-QEMU_HARDDISK-part1", "ROOT", "root.json"),
+            ("sdb1", "usb-SanDisk_Cruzer-0:0-part1", "OEMDRV", "usb.json"),
+        ] {
+            let device = dev.join(name);
+            fs::write(&device, "").unwrap();
+            symlink(&device, by_id.join(id)).unwrap();
+            symlink(&device, by_label.join(label)).unwrap();
+
+            let fs_dir = root.path().join(format!("fs-{}", name));
+            fs::create_dir(&fs_dir).unwrap();
+            fs::write(fs_dir.join(file), name).unwrap();
+            contents.insert(fs::canonicalize(&device).unwrap(), fs_dir);
+        }
+        let handler = DeviceHandler::new(dev, FakeMounter { contents });
+        (root, handler)
+    }
+
+    async fn fetch(handler: &DeviceHandler, url: &str) -> Result<Vec<u8>, TransferError> {
+        handler.fetch(&Url::parse(url).unwrap()).await
+    }
+
+    #[tokio::test]
+    async fn test_fetch_from_device() {
+        let (_root, handler) = setup();
+        assert_eq!(
+            fetch(&handler, "device://sda1/root.json").await.unwrap(),
+            b"sda1"
+        );
+        assert_eq!(
+            fetch(&handler, "device:///usb.json").await.unwrap(),
+            b"sdb1"
+        );
+        assert!(matches!(
+            fetch(&handler, "device://sda1/usb.json").await,
+            Err(TransferError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_from_usb() {
+        let (_root, handler) = setup();
+        assert_eq!(fetch(&handler, "usb:///usb.json").await.unwrap(), b"sdb1");
+        assert!(matches!(
+            fetch(&handler, "usb:///root.json").await,
+            Err(TransferError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_from_label() {
+        let (_root, handler) = setup();
+        assert_eq!(
+            fetch(&handler, "label://OEMDRV/usb.json").await.unwrap(),
+            b"sdb1"
+        );
+        assert!(matches!(
+            fetch(&handler, "label:///usb.json").await,
+            Err(TransferError::InvalidUrl(_, _))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_from_label_with_spaces() {
+        let (root, handler) = setup();
+        let by_label = root.path().join("dev/disk/by-label");
+        symlink(root.path().join("dev/sdb1"), by_label.join("MY\\x20DISK")).unwrap();
+        fs::write(root.path().join("fs-sdb1/my profile.json"), "spaces").unwrap();
+
+        assert_eq!(
+            fetch(&handler, "label://MY%20DISK/my%20profile.json")
+                .await
+                .unwrap(),
+            b"spaces"
+        );
+    }
+}
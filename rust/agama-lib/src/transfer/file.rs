@@ -0,0 +1,44 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! Implements the `file` scheme.
+
+use super::{SchemeHandler, TransferError};
+use async_trait::async_trait;
+use url::Url;
+
+/// Reads local files.
+pub struct FileHandler;
+
+#[async_trait]
+impl SchemeHandler for FileHandler {
+    fn schemes(&self) -> &[&str] {
+        &["file"]
+    }
+
+    async fn fetch(&self, url: &Url) -> Result<Vec<u8>, TransferError> {
+        let path = url
+            .to_file_path()
+            .map_err(|_| TransferError::InvalidUrl(url.to_string(), "not a file path".into()))?;
+        tokio::fs::read(&path)
+            .await
+            .map_err(|e| TransferError::Download(url.to_string(), e.to_string()))
+    }
+}
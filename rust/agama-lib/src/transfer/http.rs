@@ -0,0 +1,162 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! Implements the `http` and `https` schemes.
+
+use super::{SchemeHandler, TransferError};
+use crate::proxy::ProxySettings;
+use async_trait::async_trait;
+use reqwest::{Certificate, Client, NoProxy, Proxy};
+use std::{fs, path::PathBuf, time::Duration};
+use url::Url;
+
+/// Options for HTTP transfers.
+#[derive(Clone, Debug)]
+pub struct HttpOptions {
+    /// Number of retries after a failed request (server errors and connection problems).
+    pub retries: u32,
+    /// Time to wait before retrying. It is doubled after each retry.
+    pub retry_delay: Duration,
+    /// Timeout for each request.
+    pub timeout: Duration,
+    /// Proxy URL for HTTP requests (e.g., "http://proxy.example.net:3128"). If no proxy is
+    /// set, reqwest uses the proxy environment variables (`http_proxy`, `https_proxy`, etc.).
+    pub http_proxy: Option<String>,
+    /// Proxy URL for HTTPS requests.
+    pub https_proxy: Option<String>,
+    /// Comma-separated list of hosts to reach without the proxy.
+    pub no_proxy: Option<String>,
+    /// Additional CA certificate (PEM) to trust.
+    pub ca_cert: Option<PathBuf>,
+    /// Whether to accept invalid TLS certificates.
+    pub insecure: bool,
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            retry_delay: Duration::from_secs(1),
+            timeout: Duration::from_secs(60),
+            http_proxy: None,
+            https_proxy: None,
+            no_proxy: None,
+            ca_cert: None,
+            insecure: false,
+        }
+    }
+}
+
+impl HttpOptions {
+    /// Uses the given proxy settings.
+    ///
+    /// If no proxy is set, the options are not modified.
+    ///
+    /// * `settings`: proxy settings.
+    pub fn with_proxy(mut self, settings: &ProxySettings) -> Self {
+        let http_proxy = settings.http_url();
+        let https_proxy = settings.https_url();
+        if http_proxy.is_some() || https_proxy.is_some() {
+            self.http_proxy = http_proxy;
+            self.https_proxy = https_proxy;
+            self.no_proxy = settings.no_proxy_list();
+        }
+        self
+    }
+}
+
+/// Downloads files using HTTP(S).
+pub struct HttpHandler {
+    options: HttpOptions,
+}
+
+impl HttpHandler {
+    pub fn new(options: HttpOptions) -> Self {
+        Self { options }
+    }
+
+    fn client(&self) -> Result<Client, String> {
+        let mut builder = Client::builder()
+            .timeout(self.options.timeout)
+            .danger_accept_invalid_certs(self.options.insecure);
+        if let Some(url) = &self.options.http_proxy {
+            builder = builder.proxy(self.proxy(Proxy::http(url))?);
+        }
+        if let Some(url) = &self.options.https_proxy {
+            builder = builder.proxy(self.proxy(Proxy::https(url))?);
+        }
+        if let Some(path) = &self.options.ca_cert {
+            let pem = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            builder = builder.add_root_certificate(
+                Certificate::from_pem(&pem).map_err(|e| format!("{}: {}", path.display(), e))?,
+            );
+        }
+        builder.build().map_err(|e| e.to_string())
+    }
+
+    fn proxy(&self, proxy: reqwest::Result<Proxy>) -> Result<Proxy, String> {
+        let proxy = proxy.map_err(|e| e.to_string())?;
+        match &self.options.no_proxy {
+            Some(no_proxy) => Ok(proxy.no_proxy(NoProxy::from_string(no_proxy))),
+            None => Ok(proxy),
+        }
+    }
+}
+
+#[async_trait]
+impl SchemeHandler for HttpHandler {
+    fn schemes(&self) -> &[&str] {
+        &["http", "https"]
+    }
+
+    async fn fetch(&self, url: &Url) -> Result<Vec<u8>, TransferError> {
+        let error = |e: String| TransferError::Download(url.to_string(), e);
+        let client = self.client().map_err(error)?;
+
+        let mut delay = self.options.retry_delay;
+        let mut attempt = 0;
+        loop {
+            let result = client.get(url.clone()).send().await;
+            let retry = match result {
+                Ok(response) if response.status().is_success() => {
+                    let body = response.bytes().await.map_err(|e| error(e.to_string()))?;
+                    return Ok(body.to_vec());
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    if !status.is_server_error() || attempt >= self.options.retries {
+                        return Err(error(status.to_string()));
+                    }
+                    status.to_string()
+                }
+                Err(e) => {
+                    if attempt >= self.options.retries {
+                        return Err(error(e.to_string()));
+                    }
+                    e.to_string()
+                }
+            };
+            log::warn!("Could not download {} ({}), retrying", url, retry);
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+            attempt += 1;
+        }
+    }
+}
@@ -52,6 +52,11 @@ impl UsersHTTPClient {
         result
     }
 
+    /// Removes the configuration of the first user
+    pub async fn remove_first_user(&self) -> Result<(), ServiceError> {
+        self.client.delete_void("/users/first").await
+    }
+
     async fn root_config(&self) -> Result<RootConfig, ServiceError> {
         self.client.get("/users/root").await
     }
@@ -70,6 +70,32 @@ impl UsersStore {
         Ok(())
     }
 
+    /// Restores the users settings from a previous [UsersStore::load] call
+    ///
+    /// Unlike [UsersStore::store], it removes the first user and the root SSH key when they
+    /// are not set in the given settings. The root password cannot be restored because it is
+    /// never read from the service.
+    ///
+    /// * `settings`: settings to restore.
+    pub async fn restore(&self, settings: &UserSettings) -> Result<(), ServiceError> {
+        let first_user = settings
+            .first_user
+            .as_ref()
+            .filter(|u| u.user_name.as_ref().is_some_and(|n| !n.is_empty()));
+        match first_user {
+            Some(first_user) => self.store_first_user(first_user).await?,
+            None => self.users_client.remove_first_user().await?,
+        }
+
+        let ssh_public_key = settings
+            .root
+            .as_ref()
+            .and_then(|r| r.ssh_public_key.clone())
+            .unwrap_or_default();
+        self.users_client.set_root_sshkey(&ssh_public_key).await?;
+        Ok(())
+    }
+
     async fn store_first_user(&self, settings: &FirstUserSettings) -> Result<(), ServiceError> {
         let first_user = FirstUser {
             user_name: settings.user_name.clone().unwrap_or_default(),
@@ -228,4 +254,41 @@ mod test {
         root_mock2.assert();
         Ok(())
     }
+
+    #[test]
+    async fn test_restoring_users() -> Result<(), Box<dyn Error>> {
+        let server = MockServer::start();
+        let user_mock = server.mock(|when, then| {
+            when.method(DELETE).path("/api/users/first");
+            then.status(200);
+        });
+        let root_mock = server.mock(|when, then| {
+            when.method(PATCH)
+                .path("/api/users/root")
+                .header("content-type", "application/json")
+                .body(r#"{"sshkey":"","password":null,"passwordEncrypted":null}"#);
+            then.status(200).body("0");
+        });
+        let url = server.url("/api");
+
+        let store = users_store(url)?;
+        // settings as returned by the load method when there is no first user nor SSH key
+        let settings = UserSettings {
+            first_user: Some(FirstUserSettings {
+                user_name: Some("".to_owned()),
+                ..Default::default()
+            }),
+            root: Some(RootUserSettings::default()),
+        };
+
+        let result = store.restore(&settings).await;
+
+        // main assertion
+        result?;
+
+        // Ensure the specified mocks were called exactly one time (or fail with a detailed error description).
+        user_mock.assert();
+        root_mock.assert();
+        Ok(())
+    }
 }
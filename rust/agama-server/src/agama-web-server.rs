@@ -29,11 +29,12 @@ use agama_server::{
     cert::Certificate,
     l10n::helpers,
     logs::init_logging,
-    web::{self, run_monitor},
+    network::NetworkBackend,
+    web::{self, run_monitor, ClientCertificate},
 };
 use anyhow::Context;
 use axum::{
-    extract::Request as AxumRequest,
+    extract::{ConnectInfo, Request as AxumRequest},
     http::{Request, Response},
     Router,
 };
@@ -42,7 +43,8 @@ use futures_util::pin_mut;
 use hyper::body::Incoming;
 use hyper_util::rt::{TokioExecutor, TokioIo};
 use hyper_util::server::conn::auto::Builder;
-use openssl::ssl::{Ssl, SslAcceptor, SslMethod};
+use openssl::ssl::{Ssl, SslAcceptor, SslMethod, SslVerifyMode};
+use openssl::x509::{store::X509StoreBuilder, X509VerifyResult, X509};
 use tokio::sync::broadcast::channel;
 use tokio_openssl::SslStream;
 use tower::Service;
@@ -113,6 +115,10 @@ struct ServeArgs {
     // Directory containing the web UI code
     #[arg(long)]
     web_ui_dir: Option<PathBuf>,
+
+    /// Network backend (it overrides the value from the configuration file)
+    #[arg(long, value_enum)]
+    network_backend: Option<NetworkBackend>,
 }
 
 impl ServeArgs {
@@ -149,7 +155,10 @@ impl ServeArgs {
 }
 
 /// Builds an SSL acceptor using a provided SSL certificate or generates a self-signed one
-fn ssl_acceptor(certificate: &Certificate) -> Result<SslAcceptor, openssl::error::ErrorStack> {
+fn ssl_acceptor(
+    certificate: &Certificate,
+    client_ca: Option<&Path>,
+) -> anyhow::Result<SslAcceptor> {
     let mut tls_builder = SslAcceptor::mozilla_modern_v5(SslMethod::tls_server())?;
 
     tls_builder.set_private_key(&certificate.key)?;
@@ -158,6 +167,27 @@ fn ssl_acceptor(certificate: &Certificate) -> Result<SslAcceptor, openssl::error
     // check that the key belongs to the certificate
     tls_builder.check_private_key()?;
 
+    if let Some(path) = client_ca {
+        let pem = std::fs::read(path)
+            .with_context(|| format!("could not read the client CA from {}", path.display()))?;
+        let certs = X509::stack_from_pem(&pem)?;
+        if certs.is_empty() {
+            return Err(anyhow::anyhow!(
+                "no CA certificates found in {}",
+                path.display()
+            ));
+        }
+
+        let mut store = X509StoreBuilder::new()?;
+        for cert in certs {
+            tls_builder.add_client_ca(&cert)?;
+            store.add_cert(cert)?;
+        }
+        tls_builder.set_verify_cert_store(store.build())?;
+        // the client certificate is optional, so the users can still log in with the password
+        tls_builder.set_verify(SslVerifyMode::PEER);
+    }
+
     Ok(tls_builder.build())
 }
 
@@ -241,8 +271,22 @@ async fn handle_https_stream(
     if let Err(err) = SslStream::accept(Pin::new(&mut tls_stream)).await {
         tracing::error!("Error during TSL handshake from {}: {}", addr, err);
     } else {
+        let ssl = tls_stream.ssl();
+        let client_cert = ssl
+            .peer_certificate()
+            .filter(|_| ssl.verify_result() == X509VerifyResult::OK)
+            .map(|cert| ClientCertificate::from_x509(&cert));
+        if let Some(cert) = &client_cert {
+            tracing::info!("Client certificate from {}: {}", addr, cert.subject);
+        }
+
         let stream = TokioIo::new(tls_stream);
-        let hyper_service = hyper::service::service_fn(move |request: Request<Incoming>| {
+        let hyper_service = hyper::service::service_fn(move |mut request: Request<Incoming>| {
+            // make the client address available to the handlers (e.g., for rate limiting)
+            request.extensions_mut().insert(ConnectInfo(addr));
+            if let Some(cert) = &client_cert {
+                request.extensions_mut().insert(cert.clone());
+            }
             service.clone().call(request)
         });
 
@@ -264,7 +308,8 @@ async fn handle_http_stream(
     redirector_service: axum::Router,
 ) {
     let stream = TokioIo::new(tcp_stream);
-    let hyper_service = hyper::service::service_fn(move |request: Request<Incoming>| {
+    let hyper_service = hyper::service::service_fn(move |mut request: Request<Incoming>| {
+        request.extensions_mut().insert(ConnectInfo(addr));
         // check if it is local connection or external
         // the to_canonical() converts IPv4-mapped IPv6 addresses
         // to plain IPv4, then is_loopback() works correctly for the IPv4 connections
@@ -336,20 +381,21 @@ async fn serve_command(args: ServeArgs) -> anyhow::Result<()> {
     let (tx, _) = channel(16);
     run_monitor(tx.clone()).await?;
 
-    let config = web::ServiceConfig::load()?;
+    let mut config = web::ServiceConfig::load()?;
+    if let Some(network_backend) = args.network_backend {
+        config.network_backend = network_backend;
+    }
 
     write_token(TOKEN_FILE, &config.jwt_secret).context("could not create the token file")?;
+    let client_ca = config.client_ca.clone();
 
     let dbus = connection_to(&args.dbus_address).await?;
     let web_ui_dir = args.web_ui_dir.clone().unwrap_or(find_web_ui_dir());
     let service = web::service(config, tx, dbus, web_ui_dir).await?;
     // TODO: Move elsewhere? Use a singleton? (It would be nice to use the same
     // generated self-signed certificate on both ports.)
-    let ssl_acceptor = if let Ok(ssl_acceptor) = ssl_acceptor(&args.to_certificate()?) {
-        ssl_acceptor
-    } else {
-        return Err(anyhow::anyhow!("SSL initialization failed"));
-    };
+    let ssl_acceptor = ssl_acceptor(&args.to_certificate()?, client_ca.as_deref())
+        .context("SSL initialization failed")?;
 
     let mut addresses = vec![args.address];
 
@@ -0,0 +1,56 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! This module implements the web API for the hardware inventory.
+//!
+//! The module offers one public function:
+//!
+//! * `hardware_service` which returns the Axum service.
+
+use crate::error::Error;
+use agama_lib::{
+    error::ServiceError,
+    hardware::{HardwareInfo, HardwareReader},
+};
+use axum::{routing::get, Json, Router};
+
+/// Sets up and returns the axum service for the hardware module.
+pub async fn hardware_service() -> Result<Router, ServiceError> {
+    let router = Router::new().route("/", get(hardware));
+    Ok(router)
+}
+
+/// Returns the hardware inventory.
+///
+/// The inventory is read on each request, so it reflects hot-plugged devices.
+#[utoipa::path(
+    get,
+    path = "/",
+    context_path = "/api/hardware",
+    responses(
+        (status = 200, description = "Hardware inventory", body = HardwareInfo)
+    )
+)]
+async fn hardware() -> Result<Json<HardwareInfo>, Error> {
+    let hardware = tokio::task::spawn_blocking(|| HardwareReader::default().read())
+        .await
+        .map_err(anyhow::Error::from)?;
+    Ok(Json(hardware))
+}
@@ -21,10 +21,12 @@
 pub mod cert;
 pub mod dbus;
 pub mod error;
+pub mod hardware;
 pub mod l10n;
 pub mod logs;
 pub mod manager;
 pub mod network;
+pub mod profile;
 pub mod questions;
 pub mod software;
 pub mod storage;
@@ -26,6 +26,7 @@
 //! * `manager_stream` which offers an stream that emits the manager events coming from D-Bus.
 
 use agama_lib::{
+    auth::TokenScope,
     error::ServiceError,
     manager::{InstallationPhase, ManagerClient},
     proxies::Manager1Proxy,
@@ -33,6 +34,7 @@ use agama_lib::{
 use axum::{
     extract::{Request, State},
     http::StatusCode,
+    middleware,
     response::IntoResponse,
     routing::{get, post},
     Json, Router,
@@ -47,7 +49,7 @@ use crate::{
     error::Error,
     web::{
         common::{progress_router, service_status_router},
-        Event,
+        require_scope, Event,
     },
 };
 
@@ -109,11 +111,15 @@ pub async fn manager_service(dbus: zbus::Connection) -> Result<Router, ServiceEr
     let progress_router = progress_router(&dbus, DBUS_SERVICE, DBUS_PATH).await?;
     let manager = ManagerClient::new(dbus.clone()).await?;
     let state = ManagerState { manager, dbus };
+    let install_scope = middleware::from_fn_with_state(TokenScope::Install, require_scope);
     Ok(Router::new()
         .route("/probe", post(probe_action))
         .route("/probe_sync", post(probe_sync_action))
-        .route("/install", post(install_action))
-        .route("/finish", post(finish_action))
+        .route(
+            "/install",
+            post(install_action).route_layer(install_scope.clone()),
+        )
+        .route("/finish", post(finish_action).route_layer(install_scope))
         .route("/installer", get(installer_status))
         .route("/logs", get(download_logs))
         .merge(status_router)
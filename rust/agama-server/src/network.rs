@@ -60,14 +60,20 @@
 
 mod action;
 mod adapter;
+pub mod cmdline;
+pub mod diagnostics;
 pub mod error;
 pub mod model;
 mod nm;
+pub mod nmstate;
+pub mod proxy;
+mod simulated;
 pub mod system;
 pub mod web;
 
 pub use action::Action;
-pub use adapter::{Adapter, NetworkAdapterError};
+pub use adapter::{Adapter, NetworkAdapterError, NetworkBackend};
 pub use model::NetworkState;
-pub use nm::NetworkManagerAdapter;
+pub use nm::{KeyfileAdapter, NetworkManagerAdapter};
+pub use simulated::{SimulatedAdapter, SimulatedEvent, SimulatedScript};
 pub use system::NetworkSystem;
@@ -20,10 +20,15 @@
 
 use crate::network::model::{AccessPoint, Connection, Device};
 use agama_lib::network::types::DeviceType;
+use std::time::Duration;
 use tokio::sync::oneshot;
 use uuid::Uuid;
 
-use super::{error::NetworkStateError, model::GeneralState, NetworkAdapterError};
+use super::{
+    error::NetworkStateError,
+    model::{GeneralState, GlobalConfig},
+    NetworkAdapterError,
+};
 
 pub type Responder<T> = oneshot::Sender<T>;
 pub type ControllerConnection = (Connection, Vec<String>);
@@ -71,12 +76,23 @@ pub enum Action {
     ),
     /// Updates a connection (replacing the old one).
     UpdateConnection(Box<Connection>, Responder<Result<(), NetworkStateError>>),
+    /// Adds or updates several connections. Nothing changes if any of them fails.
+    UpdateConnections(Vec<Connection>, Responder<Result<(), NetworkStateError>>),
     /// Updates the general network configuration
     UpdateGeneralState(GeneralState),
+    /// Gets the global configuration (hostname, DNS, etc.)
+    GetGlobalConfig(Responder<GlobalConfig>),
+    /// Updates the global configuration
+    UpdateGlobalConfig(Box<GlobalConfig>),
     /// Forces a wireless networks scan refresh
     RefreshScan(Responder<Result<(), NetworkAdapterError>>),
     /// Remove the connection with the given Uuid.
     RemoveConnection(String, Responder<Result<(), NetworkStateError>>),
     /// Apply the current configuration.
     Apply(Responder<Result<(), NetworkAdapterError>>),
+    /// Apply the current configuration, rolling it back unless it is confirmed before the
+    /// given timeout.
+    ApplyWithConfirmation(Duration, Responder<Result<(), NetworkAdapterError>>),
+    /// Keep the configuration applied with [Action::ApplyWithConfirmation].
+    Confirm(Responder<Result<(), NetworkAdapterError>>),
 }
@@ -21,6 +21,8 @@
 use crate::network::{model::StateConfig, Action, NetworkState};
 use agama_lib::error::ServiceError;
 use async_trait::async_trait;
+use serde::Deserialize;
+use std::time::Duration;
 use thiserror::Error;
 use tokio::sync::mpsc::UnboundedSender;
 
@@ -32,15 +34,57 @@ pub enum NetworkAdapterError {
     Write(ServiceError),
     #[error("Checkpoint handling error: {0}")]
     Checkpoint(ServiceError), // only relevant for adapters that implement a checkpoint mechanism
+    #[error("The network backend does not support confirming changes")]
+    CheckpointNotSupported,
+    #[error("There are no network changes waiting for confirmation")]
+    NoPendingChanges,
+    #[error("The previous network changes are still waiting for confirmation")]
+    PendingChanges,
+    #[error("Invalid confirmation timeout: {0:?}")]
+    InvalidTimeout(Duration),
     #[error("The network watcher cannot run: {0}")]
     Watcher(ServiceError),
 }
 
+/// Network backends which the network service can use.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum NetworkBackend {
+    /// NetworkManager running on the system (see [crate::network::NetworkManagerAdapter]).
+    #[default]
+    NetworkManager,
+    /// In-memory network state with simulated events (see [crate::network::SimulatedAdapter]).
+    Simulated,
+}
+
 /// A trait for the ability to read/write from/to a network service.
 #[async_trait]
 pub trait Adapter {
     async fn read(&self, config: StateConfig) -> Result<NetworkState, NetworkAdapterError>;
     async fn write(&self, network: &NetworkState) -> Result<(), NetworkAdapterError>;
+    /// Writes the configuration keeping the possibility to undo the changes.
+    ///
+    /// The changes must be kept with [Adapter::confirm] or undone with [Adapter::rollback]. The
+    /// backend might undo them by itself if neither happens after `timeout` (e.g., because the
+    /// service died).
+    ///
+    /// * `network`: network model.
+    /// * `timeout`: time to wait for a confirmation.
+    async fn write_with_checkpoint(
+        &self,
+        _network: &NetworkState,
+        _timeout: Duration,
+    ) -> Result<(), NetworkAdapterError> {
+        Err(NetworkAdapterError::CheckpointNotSupported)
+    }
+    /// Keeps the changes written by [Adapter::write_with_checkpoint].
+    async fn confirm(&self) -> Result<(), NetworkAdapterError> {
+        Err(NetworkAdapterError::CheckpointNotSupported)
+    }
+    /// Undoes the changes written by [Adapter::write_with_checkpoint].
+    async fn rollback(&self) -> Result<(), NetworkAdapterError> {
+        Err(NetworkAdapterError::CheckpointNotSupported)
+    }
     /// Returns the watcher, which is responsible for listening for network changes.
     fn watcher(&self) -> Option<Box<dyn Watcher + Send>> {
         None
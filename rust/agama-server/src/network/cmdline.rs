@@ -0,0 +1,1110 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! Network options from the kernel command line.
+//!
+//! This module turns the network options of the kernel command line into connections. It
+//! supports the dracut syntax (`ip=`, `nameserver=`, `rd.route=`, `vlan=`, `bond=` and
+//! `bridge=`) and the linuxrc one (`ifcfg=`, `hostip=`, `netmask=`, `gateway=` and
+//! `netdevice=`). Unrelated options are ignored, while unsupported network options (e.g.,
+//! `team=` or `ip=ibft`) are reported as errors. An invalid option does not prevent the rest
+//! of them from being applied.
+//!
+//! The host name included in the `ip=` option is ignored.
+
+use crate::network::model::{
+    BondConfig, BondOptions, BridgeConfig, Connection, ConnectionConfig, IpRoute, Ipv4Method,
+    Ipv6Method, MacAddress, NetworkState, VlanConfig,
+};
+use agama_lib::network::types::{BondMode, DeviceType};
+use cidr::IpInet;
+use std::{
+    collections::HashMap,
+    fs,
+    net::{IpAddr, Ipv4Addr},
+    str::FromStr,
+};
+use thiserror::Error;
+
+/// Path to the kernel command line.
+pub const KERNEL_CMDLINE_PATH: &str = "/proc/cmdline";
+
+/// ID of the connection for the options which do not specify an interface.
+const DEFAULT_CONNECTION_ID: &str = "default";
+
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum CmdlineError {
+    #[error("Invalid value for the '{0}' option: '{1}'")]
+    InvalidValue(String, String),
+    #[error("Unsupported '{0}' option: '{1}'")]
+    Unsupported(String, String),
+    #[error("No connection to apply the '{0}' option to: '{1}'")]
+    NoConnection(String, String),
+    #[error("Unterminated quote in the kernel command line")]
+    UnterminatedQuote,
+}
+
+/// Reads the kernel command line of the running system.
+///
+/// It returns `None` (logging the problem) if the command line cannot be read.
+pub fn read_kernel_cmdline() -> Option<String> {
+    match fs::read_to_string(KERNEL_CMDLINE_PATH) {
+        Ok(cmdline) => Some(cmdline),
+        Err(e) => {
+            log::warn!("Could not read {}: {}", KERNEL_CMDLINE_PATH, e);
+            None
+        }
+    }
+}
+
+/// Parses the network options of a kernel command line.
+///
+/// It returns the connections built from the valid options and the errors found in the rest
+/// of them, which are skipped.
+///
+/// * `cmdline`: kernel command line.
+pub fn parse(cmdline: &str) -> (Vec<Connection>, Vec<CmdlineError>) {
+    let mut parser = CmdlineParser::default();
+    for arg in split_args(cmdline, &mut parser.errors) {
+        let (key, value) = match arg.split_once('=') {
+            Some((key, value)) => (key, Some(value)),
+            None => (arg.as_str(), None),
+        };
+        // an invalid option might leave the parser half-updated
+        let backup = parser.clone();
+        if let Err(e) = parser.parse_option(key, value) {
+            parser = backup;
+            parser.errors.push(e);
+        }
+    }
+    parser.finish()
+}
+
+/// Adds the connections from the kernel command line to the network state.
+///
+/// The connections whose interface (or ID) is already known are skipped, as they were
+/// probably set up by NetworkManager's initrd generator. References to the skipped
+/// controllers are updated to point to the existing connections.
+///
+/// It returns the number of connections that were added.
+///
+/// * `state`: network state to update.
+/// * `connections`: connections from the kernel command line.
+pub fn merge(state: &mut NetworkState, connections: Vec<Connection>) -> usize {
+    let mut replaced = HashMap::new();
+    let mut pending = vec![];
+    for conn in connections {
+        let existing = conn
+            .interface
+            .as_ref()
+            .and_then(|i| state.get_connection_by_interface(i))
+            .or_else(|| state.get_connection(&conn.id));
+        match existing {
+            Some(existing) => {
+                log::info!(
+                    "Skipping connection '{}' from the kernel command line: it already exists",
+                    &conn.id
+                );
+                replaced.insert(conn.uuid, existing.uuid);
+            }
+            None => pending.push(conn),
+        }
+    }
+
+    let mut added = 0;
+    for mut conn in pending {
+        if let Some(controller) = conn.controller.and_then(|c| replaced.get(&c)) {
+            conn.controller = Some(*controller);
+        }
+        match state.add_connection(conn) {
+            Ok(()) => added += 1,
+            Err(e) => log::warn!(
+                "Could not add a connection from the kernel command line: {}",
+                e
+            ),
+        }
+    }
+    added
+}
+
+/// Splits the command line into arguments, honoring the double quotes.
+///
+/// An argument with an unterminated quote is dropped.
+///
+/// * `cmdline`: kernel command line.
+/// * `errors`: list to add the errors to.
+fn split_args(cmdline: &str, errors: &mut Vec<CmdlineError>) -> Vec<String> {
+    let mut args = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut in_arg = false;
+
+    for c in cmdline.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_arg = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+
+    if quoted {
+        errors.push(CmdlineError::UnterminatedQuote);
+    } else if in_arg {
+        args.push(current);
+    }
+    args
+}
+
+/// Splits a dracut value using colons as separator, except for the ones enclosed in brackets
+/// (IPv6 addresses).
+fn split_fields(value: &str) -> Vec<&str> {
+    let mut fields = vec![];
+    let mut start = 0;
+    let mut in_brackets = false;
+
+    for (i, c) in value.char_indices() {
+        match c {
+            '[' => in_brackets = true,
+            ']' => in_brackets = false,
+            ':' if !in_brackets => {
+                fields.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    fields.push(&value[start..]);
+    fields
+}
+
+/// Removes the brackets which enclose an IPv6 address.
+fn unbracket(value: &str) -> String {
+    value.replace(['[', ']'], "")
+}
+
+fn parse_ip_addr(value: &str) -> Option<IpAddr> {
+    IpAddr::from_str(&unbracket(value)).ok()
+}
+
+/// Converts a netmask (e.g., "255.255.255.0" or "24") into a prefix length.
+fn parse_prefix(value: &str) -> Option<u8> {
+    if let Ok(prefix) = value.parse::<u8>() {
+        return (prefix <= 128).then_some(prefix);
+    }
+
+    let mask = u32::from(Ipv4Addr::from_str(value).ok()?);
+    let prefix = mask.leading_ones();
+    (mask.checked_shl(prefix).unwrap_or(0) == 0).then_some(prefix as u8)
+}
+
+/// Builds an IP address with its prefix.
+///
+/// * `address`: IP address, optionally including the prefix (e.g., "192.168.1.10/24").
+/// * `netmask`: netmask or prefix length, used when `address` does not include the prefix.
+fn parse_inet(address: &str, netmask: Option<&str>) -> Option<IpInet> {
+    let address = unbracket(address);
+    if address.contains('/') {
+        return IpInet::from_str(&address).ok();
+    }
+
+    let prefix = parse_prefix(netmask?)?;
+    IpInet::new(IpAddr::from_str(&address).ok()?, prefix).ok()
+}
+
+fn invalid(key: &str, value: &str) -> CmdlineError {
+    CmdlineError::InvalidValue(key.to_string(), value.to_string())
+}
+
+fn unsupported(key: &str, value: &str) -> CmdlineError {
+    CmdlineError::Unsupported(key.to_string(), value.to_string())
+}
+
+/// Linuxrc legacy options (`hostip=`, `netmask=`, `gateway=` and `netdevice=`).
+#[derive(Clone, Default)]
+struct LinuxrcOptions {
+    hostip: Option<String>,
+    netmask: Option<String>,
+    gateway: Option<String>,
+    netdevice: Option<String>,
+}
+
+#[derive(Clone, Default)]
+struct CmdlineParser {
+    connections: Vec<Connection>,
+    nameservers: Vec<IpAddr>,
+    routes: Vec<(String, IpRoute)>,
+    ports: Vec<(String, Vec<String>)>,
+    linuxrc: LinuxrcOptions,
+    errors: Vec<CmdlineError>,
+}
+
+impl CmdlineParser {
+    fn parse_option(&mut self, key: &str, value: Option<&str>) -> Result<(), CmdlineError> {
+        match (key, value) {
+            ("ip", Some(value)) => self.parse_ip(value),
+            ("nameserver", Some(value)) => self.parse_nameserver(value),
+            ("rd.route", Some(value)) => self.parse_route(value),
+            ("vlan", Some(value)) => self.parse_vlan(value),
+            ("bond", value) => self.parse_bond(value.unwrap_or_default()),
+            ("bridge", value) => self.parse_bridge(value.unwrap_or_default()),
+            ("ifcfg", Some(value)) => self.parse_ifcfg(value),
+            ("hostip", Some(value)) => {
+                self.linuxrc.hostip = Some(value.to_string());
+                Ok(())
+            }
+            ("netmask", Some(value)) => {
+                self.linuxrc.netmask = Some(value.to_string());
+                Ok(())
+            }
+            ("gateway", Some(value)) => {
+                self.linuxrc.gateway = Some(value.to_string());
+                Ok(())
+            }
+            ("netdevice", Some(value)) => {
+                self.linuxrc.netdevice = Some(value.to_string());
+                Ok(())
+            }
+            ("team", value) => Err(unsupported(key, value.unwrap_or_default())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the connection for the given interface, creating it if it does not exist.
+    ///
+    /// * `interface`: interface name. If it is empty, it returns the default connection.
+    fn connection(&mut self, interface: &str) -> &mut Connection {
+        let id = if interface.is_empty() {
+            DEFAULT_CONNECTION_ID
+        } else {
+            interface
+        };
+
+        let index = match self.connections.iter().position(|c| c.id == id) {
+            Some(index) => index,
+            None => {
+                let mut conn = Connection::new(id.to_string(), DeviceType::Ethernet);
+                if !interface.is_empty() {
+                    conn.interface = Some(interface.to_string());
+                }
+                self.connections.push(conn);
+                self.connections.len() - 1
+            }
+        };
+        &mut self.connections[index]
+    }
+
+    /// Parses the dracut `ip=` option.
+    ///
+    /// It supports these forms:
+    ///
+    /// * `ip=<autoconf>`
+    /// * `ip=<interface>:<autoconf>[:[<mtu>][:<macaddr>]]`
+    /// * `ip=<client-IP>:[<peer>]:<gateway-IP>:<netmask>:<hostname>:<interface>:<autoconf>[:[<mtu>][:<macaddr>]]`
+    /// * `ip=<client-IP>:[<peer>]:<gateway-IP>:<netmask>:<hostname>:<interface>:<autoconf>[:[<dns1>][:<dns2>]]`
+    fn parse_ip(&mut self, value: &str) -> Result<(), CmdlineError> {
+        let fields = split_fields(value);
+
+        if fields.len() == 1 {
+            let conn = self.connection("");
+            return apply_autoconf(conn, fields[0], value);
+        }
+
+        if parse_inet(fields[0], Some("0")).is_none() {
+            let conn = self.connection(fields[0]);
+            apply_autoconf(conn, fields[1], value)?;
+            return apply_mtu_and_mac(conn, &fields[2..]).map_err(|_| invalid("ip", value));
+        }
+
+        if fields.len() < 7 {
+            return Err(invalid("ip", value));
+        }
+
+        let netmask = (!fields[3].is_empty()).then_some(fields[3]);
+        let address = parse_inet(fields[0], netmask).ok_or_else(|| invalid("ip", value))?;
+        let gateway = if fields[2].is_empty() {
+            None
+        } else {
+            Some(parse_ip_addr(fields[2]).ok_or_else(|| invalid("ip", value))?)
+        };
+
+        let extra = &fields[7..];
+        let dns: Option<Vec<IpAddr>> = if !extra.is_empty() && extra.len() <= 2 {
+            extra
+                .iter()
+                .filter(|f| !f.is_empty())
+                .map(|f| parse_ip_addr(f))
+                .collect()
+        } else {
+            None
+        };
+
+        let conn = self.connection(fields[5]);
+        add_address(conn, address, gateway);
+        if !matches!(fields[6], "none" | "off" | "") {
+            apply_autoconf(conn, fields[6], value)?;
+        }
+
+        match dns {
+            Some(dns) if !dns.is_empty() => conn.ip_config.nameservers.extend(dns),
+            _ => apply_mtu_and_mac(conn, extra).map_err(|_| invalid("ip", value))?,
+        }
+        Ok(())
+    }
+
+    /// Parses the `nameserver=` option (a comma-separated list is accepted too).
+    fn parse_nameserver(&mut self, value: &str) -> Result<(), CmdlineError> {
+        for server in value.split(',').filter(|s| !s.is_empty()) {
+            let address = parse_ip_addr(server).ok_or_else(|| invalid("nameserver", value))?;
+            self.nameservers.push(address);
+        }
+        Ok(())
+    }
+
+    /// Parses the `rd.route=<net>/<netmask>:<gateway>[:<interface>]` option.
+    fn parse_route(&mut self, value: &str) -> Result<(), CmdlineError> {
+        let fields = split_fields(value);
+        if fields.len() < 2 || fields.len() > 3 {
+            return Err(invalid("rd.route", value));
+        }
+
+        let destination =
+            IpInet::from_str(&unbracket(fields[0])).map_err(|_| invalid("rd.route", value))?;
+        let next_hop = if fields[1].is_empty() {
+            None
+        } else {
+            Some(parse_ip_addr(fields[1]).ok_or_else(|| invalid("rd.route", value))?)
+        };
+        let interface = fields.get(2).copied().unwrap_or_default();
+
+        let route = IpRoute {
+            destination,
+            next_hop,
+            metric: None,
+        };
+        self.routes.push((interface.to_string(), route));
+        Ok(())
+    }
+
+    /// Parses the `vlan=<vlanname>:<phys_device>` option.
+    ///
+    /// The VLAN ID is taken from the name, which can be `<device>.<id>` or `vlan<id>`.
+    fn parse_vlan(&mut self, value: &str) -> Result<(), CmdlineError> {
+        let Some((name, parent)) = value.split_once(':') else {
+            return Err(invalid("vlan", value));
+        };
+
+        let id = match name.rsplit_once('.') {
+            Some((_, id)) => id,
+            None => name.strip_prefix("vlan").unwrap_or_default(),
+        };
+        let id: u32 = id.parse().map_err(|_| invalid("vlan", value))?;
+        if parent.is_empty() || id > 4094 {
+            return Err(invalid("vlan", value));
+        }
+
+        self.connection(parent);
+        let conn = self.connection(name);
+        conn.config = ConnectionConfig::Vlan(VlanConfig {
+            parent: parent.to_string(),
+            id,
+            ..Default::default()
+        });
+        Ok(())
+    }
+
+    /// Parses the `bond=<bondname>[:<ports>[:<options>[:<mtu>]]]` option.
+    ///
+    /// The ports and the options are comma-separated lists. When no value is given, it
+    /// defines a `bond0` device using `eth0` and `eth1`.
+    fn parse_bond(&mut self, value: &str) -> Result<(), CmdlineError> {
+        let fields: Vec<&str> = value.split(':').collect();
+        if fields.len() > 4 {
+            return Err(invalid("bond", value));
+        }
+
+        let name = fields.first().filter(|n| !n.is_empty()).unwrap_or(&"bond0");
+        let ports: Vec<String> = match fields.get(1) {
+            Some(ports) => ports.split(',').map(str::to_string).collect(),
+            None => vec!["eth0".to_string(), "eth1".to_string()],
+        };
+
+        let mut config = BondConfig::default();
+        if let Some(options) = fields.get(2) {
+            let mut bond_options = HashMap::new();
+            for option in options.split(',').filter(|o| !o.is_empty()) {
+                let (key, option_value) = option
+                    .split_once('=')
+                    .ok_or_else(|| invalid("bond", value))?;
+                if key == "mode" {
+                    config.mode =
+                        BondMode::try_from(option_value).map_err(|_| invalid("bond", value))?;
+                } else {
+                    bond_options.insert(key.to_string(), option_value.to_string());
+                }
+            }
+            config.options = BondOptions(bond_options);
+        }
+
+        let conn = self.connection(name);
+        conn.config = ConnectionConfig::Bond(config);
+        apply_mtu_and_mac(conn, fields.get(3..).unwrap_or_default())
+            .map_err(|_| invalid("bond", value))?;
+        self.ports.push((name.to_string(), ports));
+        Ok(())
+    }
+
+    /// Parses the `bridge=<bridgename>:<ports>` option.
+    ///
+    /// The ports are a comma-separated list. When no value is given, it defines a `br0`
+    /// device using `eth0`.
+    fn parse_bridge(&mut self, value: &str) -> Result<(), CmdlineError> {
+        let (name, ports) = match value.split_once(':') {
+            Some((name, ports)) => (name, ports.split(',').map(str::to_string).collect()),
+            None if value.is_empty() => ("br0", vec!["eth0".to_string()]),
+            None => return Err(invalid("bridge", value)),
+        };
+
+        let conn = self.connection(name);
+        conn.config = ConnectionConfig::Bridge(BridgeConfig::default());
+        self.ports.push((name.to_string(), ports));
+        Ok(())
+    }
+
+    /// Parses the linuxrc `ifcfg=<interface>=<options>` option.
+    ///
+    /// The interface can be a name, a glob (e.g., `eth*`) or `*`. The options can be
+    /// `dhcp`, `dhcp4`, `dhcp6`, `auto` or `<IP>[,<gateway>[,<nameservers>[,<domains>]]]`,
+    /// where the name servers and the domains are space-separated lists.
+    fn parse_ifcfg(&mut self, value: &str) -> Result<(), CmdlineError> {
+        let Some((spec, options)) = value.split_once('=') else {
+            return Err(invalid("ifcfg", value));
+        };
+
+        if options.contains('=')
+            || matches!(MacAddress::from_str(spec), Ok(MacAddress::MacAddress(_)))
+        {
+            return Err(unsupported("ifcfg", value));
+        }
+
+        let conn = if spec == "*" {
+            self.connection("")
+        } else if spec.contains(['*', '?', '[']) {
+            let conn = self.connection(spec);
+            conn.interface = None;
+            conn.match_config.interface = vec![spec.to_string()];
+            conn
+        } else {
+            self.connection(spec)
+        };
+
+        match options.to_lowercase().as_str() {
+            "dhcp" | "dhcp4" => conn.ip_config.method4 = Ipv4Method::Auto,
+            "dhcp6" => conn.ip_config.method6 = Ipv6Method::Dhcp,
+            "auto" => {
+                conn.ip_config.method4 = Ipv4Method::Auto;
+                conn.ip_config.method6 = Ipv6Method::Auto;
+            }
+            _ => {
+                let mut fields = options.split(',');
+                let address = fields
+                    .next()
+                    .and_then(|a| parse_inet(a, None))
+                    .ok_or_else(|| invalid("ifcfg", value))?;
+                let gateway = match fields.next().filter(|g| !g.is_empty()) {
+                    Some(gateway) => {
+                        Some(parse_ip_addr(gateway).ok_or_else(|| invalid("ifcfg", value))?)
+                    }
+                    None => None,
+                };
+                add_address(conn, address, gateway);
+
+                if let Some(nameservers) = fields.next() {
+                    for server in nameservers.split_whitespace() {
+                        let server =
+                            parse_ip_addr(server).ok_or_else(|| invalid("ifcfg", value))?;
+                        conn.ip_config.nameservers.push(server);
+                    }
+                }
+                if let Some(domains) = fields.next() {
+                    conn.ip_config
+                        .dns_searchlist
+                        .extend(domains.split_whitespace().map(str::to_string));
+                }
+                if fields.next().is_some() {
+                    return Err(invalid("ifcfg", value));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the linuxrc legacy options (`hostip=`, `netmask=`, `gateway=` and
+    /// `netdevice=`).
+    fn apply_linuxrc(&mut self) -> Result<(), CmdlineError> {
+        let linuxrc = std::mem::take(&mut self.linuxrc);
+        let Some(hostip) = linuxrc.hostip else {
+            return Ok(());
+        };
+
+        let address = parse_inet(&hostip, linuxrc.netmask.as_deref())
+            .ok_or_else(|| invalid("hostip", &hostip))?;
+        let gateway = match linuxrc.gateway {
+            Some(gateway) => {
+                Some(parse_ip_addr(&gateway).ok_or_else(|| invalid("gateway", &gateway))?)
+            }
+            None => None,
+        };
+
+        let conn = self.connection(linuxrc.netdevice.as_deref().unwrap_or_default());
+        add_address(conn, address, gateway);
+        Ok(())
+    }
+
+    /// Sets the ports, the routes and the name servers and returns the connections and the
+    /// errors.
+    fn finish(mut self) -> (Vec<Connection>, Vec<CmdlineError>) {
+        if let Err(e) = self.apply_linuxrc() {
+            self.errors.push(e);
+        }
+
+        for (controller, ports) in std::mem::take(&mut self.ports) {
+            let uuid = self.connection(&controller).uuid;
+            for port in ports.iter().filter(|p| !p.is_empty()) {
+                self.connection(port).controller = Some(uuid);
+            }
+        }
+
+        for (interface, route) in std::mem::take(&mut self.routes) {
+            let is_ipv4 = route.destination.is_ipv4();
+            let mut connections: Vec<&mut Connection> = if interface.is_empty() {
+                self.connections
+                    .iter_mut()
+                    .filter(|c| c.controller.is_none() && has_ip_config(c, is_ipv4))
+                    .collect()
+            } else {
+                vec![self.connection(&interface)]
+            };
+
+            if connections.is_empty() {
+                self.errors.push(CmdlineError::NoConnection(
+                    "rd.route".to_string(),
+                    route.destination.to_string(),
+                ));
+                continue;
+            }
+
+            for conn in connections.iter_mut() {
+                let routes = if is_ipv4 {
+                    &mut conn.ip_config.routes4
+                } else {
+                    &mut conn.ip_config.routes6
+                };
+                routes.get_or_insert_with(Vec::new).push(route.clone());
+            }
+        }
+
+        if !self.nameservers.is_empty() {
+            let mut connections = self
+                .connections
+                .iter_mut()
+                .filter(|c| c.controller.is_none())
+                .peekable();
+            if connections.peek().is_none() {
+                self.errors.push(CmdlineError::NoConnection(
+                    "nameserver".to_string(),
+                    self.nameservers[0].to_string(),
+                ));
+            }
+            for conn in connections {
+                for server in &self.nameservers {
+                    if !conn.ip_config.nameservers.contains(server) {
+                        conn.ip_config.nameservers.push(*server);
+                    }
+                }
+            }
+        }
+
+        (self.connections, self.errors)
+    }
+}
+
+/// Applies a dracut autoconfiguration method (e.g., "dhcp" or "dhcp,auto6").
+///
+/// * `conn`: connection to update.
+/// * `autoconf`: autoconfiguration methods.
+/// * `value`: value of the `ip=` option (for error reporting).
+fn apply_autoconf(conn: &mut Connection, autoconf: &str, value: &str) -> Result<(), CmdlineError> {
+    for method in autoconf.split(',') {
+        match method {
+            "dhcp" | "on" | "any" => conn.ip_config.method4 = Ipv4Method::Auto,
+            "dhcp6" => conn.ip_config.method6 = Ipv6Method::Dhcp,
+            "auto6" => conn.ip_config.method6 = Ipv6Method::Auto,
+            "link6" => conn.ip_config.method6 = Ipv6Method::LinkLocal,
+            "link-local" => conn.ip_config.method4 = Ipv4Method::LinkLocal,
+            "none" | "off" => {}
+            "ibft" | "either6" => return Err(unsupported("ip", value)),
+            _ => return Err(invalid("ip", value)),
+        }
+    }
+    Ok(())
+}
+
+/// Applies the optional `[<mtu>][:<macaddr>]` fields.
+///
+/// As the MAC address contains colons, it spans the rest of the fields.
+fn apply_mtu_and_mac(conn: &mut Connection, fields: &[&str]) -> Result<(), ()> {
+    let Some((mtu, mac)) = fields.split_first() else {
+        return Ok(());
+    };
+
+    if !mtu.is_empty() {
+        conn.mtu = mtu.parse().map_err(|_| ())?;
+    }
+
+    if !mac.is_empty() {
+        let mac = MacAddress::from_str(&mac.join(":")).map_err(|_| ())?;
+        if !matches!(mac, MacAddress::MacAddress(_)) {
+            return Err(());
+        }
+        conn.mac_address = mac;
+    }
+    Ok(())
+}
+
+/// Adds a static address (and its gateway) to the connection.
+fn add_address(conn: &mut Connection, address: IpInet, gateway: Option<IpAddr>) {
+    if address.is_ipv4() {
+        conn.ip_config.method4 = Ipv4Method::Manual;
+    } else {
+        conn.ip_config.method6 = Ipv6Method::Manual;
+    }
+    conn.ip_config.addresses.push(address);
+
+    match gateway {
+        Some(gateway @ IpAddr::V4(_)) => conn.ip_config.gateway4 = Some(gateway),
+        Some(gateway @ IpAddr::V6(_)) => conn.ip_config.gateway6 = Some(gateway),
+        None => {}
+    }
+}
+
+/// Determines whether the connection has an IPv4 or IPv6 configuration.
+fn has_ip_config(conn: &Connection, ipv4: bool) -> bool {
+    if ipv4 {
+        conn.ip_config.method4 != Ipv4Method::Disabled
+    } else {
+        conn.ip_config.method6 != Ipv6Method::Disabled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::model::{BridgeConfig, VlanProtocol};
+
+    fn inet(value: &str) -> IpInet {
+        IpInet::from_str(value).unwrap()
+    }
+
+    fn addr(value: &str) -> IpAddr {
+        IpAddr::from_str(value).unwrap()
+    }
+
+    // parses a command line which is expected to be valid
+    fn parse_valid(cmdline: &str) -> Vec<Connection> {
+        let (connections, errors) = parse(cmdline);
+        assert_eq!(errors, vec![], "{}", cmdline);
+        connections
+    }
+
+    fn find<'a>(connections: &'a [Connection], id: &str) -> &'a Connection {
+        connections
+            .iter()
+            .find(|c| c.id == id)
+            .unwrap_or_else(|| panic!("Connection '{}' not found", id))
+    }
+
+    #[test]
+    fn test_parse_ip() {
+        #[derive(Clone)]
+        struct Case {
+            cmdline: &'static str,
+            id: &'static str,
+            method4: Ipv4Method,
+            method6: Ipv6Method,
+            addresses: Vec<IpInet>,
+            gateway4: Option<IpAddr>,
+            gateway6: Option<IpAddr>,
+            nameservers: Vec<IpAddr>,
+            mtu: u32,
+        }
+
+        let default = Case {
+            cmdline: "",
+            id: "eth0",
+            method4: Ipv4Method::Disabled,
+            method6: Ipv6Method::Disabled,
+            addresses: vec![],
+            gateway4: None,
+            gateway6: None,
+            nameservers: vec![],
+            mtu: 0,
+        };
+
+        let cases = vec![
+            Case {
+                cmdline: "ip=dhcp",
+                id: DEFAULT_CONNECTION_ID,
+                method4: Ipv4Method::Auto,
+                ..default.clone()
+            },
+            Case {
+                cmdline: "ip=eth0:dhcp",
+                method4: Ipv4Method::Auto,
+                ..default.clone()
+            },
+            Case {
+                cmdline: "ip=eth0:dhcp,auto6:9000",
+                method4: Ipv4Method::Auto,
+                method6: Ipv6Method::Auto,
+                mtu: 9000,
+                ..default.clone()
+            },
+            Case {
+                cmdline: "ip=eth0:dhcp ip=eth0:dhcp6",
+                method4: Ipv4Method::Auto,
+                method6: Ipv6Method::Dhcp,
+                ..default.clone()
+            },
+            Case {
+                cmdline: "ip=192.168.1.10::192.168.1.1:255.255.255.0:host:eth0:none",
+                method4: Ipv4Method::Manual,
+                addresses: vec![inet("192.168.1.10/24")],
+                gateway4: Some(addr("192.168.1.1")),
+                ..default.clone()
+            },
+            Case {
+                cmdline: "ip=192.168.1.10/24:::::eth0:off:192.168.1.2:192.168.1.3",
+                method4: Ipv4Method::Manual,
+                addresses: vec![inet("192.168.1.10/24")],
+                nameservers: vec![addr("192.168.1.2"), addr("192.168.1.3")],
+                ..default.clone()
+            },
+            Case {
+                cmdline: "ip=[2001:db8::2]::[2001:db8::1]:64::eth0:none:1500",
+                method6: Ipv6Method::Manual,
+                addresses: vec![inet("2001:db8::2/64")],
+                gateway6: Some(addr("2001:db8::1")),
+                mtu: 1500,
+                ..default.clone()
+            },
+            Case {
+                cmdline: "ip=10.0.0.5::10.0.0.1:8::eth0:none nameserver=10.0.0.2",
+                method4: Ipv4Method::Manual,
+                addresses: vec![inet("10.0.0.5/8")],
+                gateway4: Some(addr("10.0.0.1")),
+                nameservers: vec![addr("10.0.0.2")],
+                ..default.clone()
+            },
+            Case {
+                cmdline: "ifcfg=eth0=dhcp",
+                method4: Ipv4Method::Auto,
+                ..default.clone()
+            },
+            Case {
+                cmdline: "ifcfg=*=auto",
+                id: DEFAULT_CONNECTION_ID,
+                method4: Ipv4Method::Auto,
+                method6: Ipv6Method::Auto,
+                ..default.clone()
+            },
+            Case {
+                cmdline: r#"ifcfg="eth0=10.0.0.5/24,10.0.0.1,10.0.0.2 10.0.0.3,example.net""#,
+                method4: Ipv4Method::Manual,
+                addresses: vec![inet("10.0.0.5/24")],
+                gateway4: Some(addr("10.0.0.1")),
+                nameservers: vec![addr("10.0.0.2"), addr("10.0.0.3")],
+                ..default.clone()
+            },
+            Case {
+                cmdline: "hostip=10.0.0.5 netmask=255.255.255.0 gateway=10.0.0.1 netdevice=eth0",
+                method4: Ipv4Method::Manual,
+                addresses: vec![inet("10.0.0.5/24")],
+                gateway4: Some(addr("10.0.0.1")),
+                ..default.clone()
+            },
+        ];
+
+        for case in cases {
+            let connections = parse_valid(case.cmdline);
+            assert_eq!(connections.len(), 1, "{}", case.cmdline);
+            let conn = find(&connections, case.id);
+            let ip_config = &conn.ip_config;
+            assert_eq!(ip_config.method4, case.method4, "{}", case.cmdline);
+            assert_eq!(ip_config.method6, case.method6, "{}", case.cmdline);
+            assert_eq!(ip_config.addresses, case.addresses, "{}", case.cmdline);
+            assert_eq!(ip_config.gateway4, case.gateway4, "{}", case.cmdline);
+            assert_eq!(ip_config.gateway6, case.gateway6, "{}", case.cmdline);
+            assert_eq!(ip_config.nameservers, case.nameservers, "{}", case.cmdline);
+            assert_eq!(conn.mtu, case.mtu, "{}", case.cmdline);
+        }
+    }
+
+    #[test]
+    fn test_parse_mac_address() {
+        let connections = parse_valid("ip=eth0:dhcp::52:54:00:12:34:56");
+        let conn = find(&connections, "eth0");
+        assert_eq!(
+            conn.mac_address,
+            MacAddress::from_str("52:54:00:12:34:56").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_routes() {
+        let connections = parse_valid(
+            "ip=eth0:dhcp ip=eth1:auto6 rd.route=10.0.0.0/8:192.168.1.1 \
+             rd.route=[2001:db8:3::]/48:[2001:db8:2::1]:eth1 rd.route=172.16.0.0/12::eth1",
+        );
+
+        let eth0 = find(&connections, "eth0");
+        let routes4 = eth0.ip_config.routes4.as_ref().unwrap();
+        assert_eq!(routes4.len(), 1);
+        assert_eq!(routes4[0].destination, inet("10.0.0.0/8"));
+        assert_eq!(routes4[0].next_hop, Some(addr("192.168.1.1")));
+        assert_eq!(eth0.ip_config.routes6, None);
+
+        let eth1 = find(&connections, "eth1");
+        let routes6 = eth1.ip_config.routes6.as_ref().unwrap();
+        assert_eq!(routes6[0].destination, inet("2001:db8:3::/48"));
+        assert_eq!(routes6[0].next_hop, Some(addr("2001:db8:2::1")));
+        let routes4 = eth1.ip_config.routes4.as_ref().unwrap();
+        assert_eq!(routes4[0].destination, inet("172.16.0.0/12"));
+        assert_eq!(routes4[0].next_hop, None);
+    }
+
+    #[test]
+    fn test_parse_virtual_devices() {
+        struct Case {
+            cmdline: &'static str,
+            id: &'static str,
+            config: ConnectionConfig,
+            ports: Vec<&'static str>,
+        }
+
+        let bond_options = BondOptions(HashMap::from([("miimon".to_string(), "100".to_string())]));
+
+        let cases = vec![
+            Case {
+                cmdline: "bond",
+                id: "bond0",
+                config: ConnectionConfig::Bond(BondConfig::default()),
+                ports: vec!["eth0", "eth1"],
+            },
+            Case {
+                cmdline: "bond=bond1:eth2,eth3:mode=active-backup,miimon=100 ip=bond1:dhcp",
+                id: "bond1",
+                config: ConnectionConfig::Bond(BondConfig {
+                    mode: BondMode::ActiveBackup,
+                    options: bond_options,
+                }),
+                ports: vec!["eth2", "eth3"],
+            },
+            Case {
+                cmdline: "bridge",
+                id: "br0",
+                config: ConnectionConfig::Bridge(BridgeConfig::default()),
+                ports: vec!["eth0"],
+            },
+            Case {
+                cmdline: "bridge=br1:eth0,eth1",
+                id: "br1",
+                config: ConnectionConfig::Bridge(BridgeConfig::default()),
+                ports: vec!["eth0", "eth1"],
+            },
+            Case {
+                cmdline: "vlan=eth0.10:eth0",
+                id: "eth0.10",
+                config: ConnectionConfig::Vlan(VlanConfig {
+                    parent: "eth0".to_string(),
+                    id: 10,
+                    protocol: VlanProtocol::IEEE802_1Q,
+                }),
+                ports: vec![],
+            },
+            Case {
+                cmdline: "vlan=vlan0020:eth1",
+                id: "vlan0020",
+                config: ConnectionConfig::Vlan(VlanConfig {
+                    parent: "eth1".to_string(),
+                    id: 20,
+                    protocol: VlanProtocol::IEEE802_1Q,
+                }),
+                ports: vec![],
+            },
+        ];
+
+        for case in cases {
+            let connections = parse_valid(case.cmdline);
+            let conn = find(&connections, case.id);
+            assert_eq!(conn.config, case.config, "{}", case.cmdline);
+
+            let mut ports: Vec<&str> = connections
+                .iter()
+                .filter(|c| c.controller == Some(conn.uuid))
+                .map(|c| c.id.as_str())
+                .collect();
+            ports.sort();
+            assert_eq!(ports, case.ports, "{}", case.cmdline);
+        }
+    }
+
+    #[test]
+    fn test_parse_vlan_on_bond() {
+        let connections = parse_valid(
+            "bond=bond0:eth0,eth1 vlan=bond0.10:bond0 ip=bond0.10:dhcp nameserver=10.0.0.2",
+        );
+
+        let bond0 = find(&connections, "bond0");
+        assert!(matches!(bond0.config, ConnectionConfig::Bond(_)));
+        let vlan = find(&connections, "bond0.10");
+        assert_eq!(vlan.ip_config.method4, Ipv4Method::Auto);
+        assert_eq!(vlan.ip_config.nameservers, vec![addr("10.0.0.2")]);
+        let eth0 = find(&connections, "eth0");
+        assert_eq!(eth0.controller, Some(bond0.uuid));
+        assert!(eth0.ip_config.nameservers.is_empty());
+    }
+
+    #[test]
+    fn test_ignore_unrelated_options() {
+        let connections = parse("quiet splash=silent console=ttyS0 BOOTIF=01-52-54-00-12-34-56");
+        assert_eq!(connections, (vec![], vec![]));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let cases = vec![
+            ("ip=eth0:foo", invalid("ip", "eth0:foo")),
+            ("ip=eth0:ibft", unsupported("ip", "eth0:ibft")),
+            ("ip=either6", unsupported("ip", "either6")),
+            (
+                "ip=192.168.1.10::192.168.1.1",
+                invalid("ip", "192.168.1.10::192.168.1.1"),
+            ),
+            (
+                "ip=192.168.1.10::192.168.1.1:255.0.255.0::eth0:none",
+                invalid("ip", "192.168.1.10::192.168.1.1:255.0.255.0::eth0:none"),
+            ),
+            ("nameserver=foo", invalid("nameserver", "foo")),
+            ("rd.route=10.0.0.0", invalid("rd.route", "10.0.0.0")),
+            (
+                "rd.route=10.0.0.0/8:10.0.0.1",
+                CmdlineError::NoConnection("rd.route".to_string(), "10.0.0.0/8".to_string()),
+            ),
+            (
+                "nameserver=10.0.0.2",
+                CmdlineError::NoConnection("nameserver".to_string(), "10.0.0.2".to_string()),
+            ),
+            ("vlan=eth0", invalid("vlan", "eth0")),
+            ("vlan=foo:eth0", invalid("vlan", "foo:eth0")),
+            (
+                "bond=bond0:eth0:mode=foo",
+                invalid("bond", "bond0:eth0:mode=foo"),
+            ),
+            ("bridge=br0", invalid("bridge", "br0")),
+            (
+                "team=team0:eth0,eth1",
+                unsupported("team", "team0:eth0,eth1"),
+            ),
+            (
+                "ifcfg=eth0=BOOTPROTO=dhcp",
+                unsupported("ifcfg", "eth0=BOOTPROTO=dhcp"),
+            ),
+            (
+                "ifcfg=52:54:00:12:34:56=dhcp",
+                unsupported("ifcfg", "52:54:00:12:34:56=dhcp"),
+            ),
+            ("ifcfg=eth0=foo", invalid("ifcfg", "eth0=foo")),
+            ("hostip=10.0.0.5", invalid("hostip", "10.0.0.5")),
+            (r#"ifcfg="eth0=dhcp"#, CmdlineError::UnterminatedQuote),
+        ];
+
+        for (cmdline, error) in cases {
+            assert_eq!(parse(cmdline), (vec![], vec![error]), "{}", cmdline);
+        }
+    }
+
+    #[test]
+    fn test_parse_skip_invalid_options() {
+        let (connections, errors) = parse(
+            "ip=eth0:dhcp team=team0:eth1 ip=eth1:foo bond=bond0:eth2:mode=foo nameserver=10.0.0.2",
+        );
+
+        assert_eq!(
+            errors,
+            vec![
+                unsupported("team", "team0:eth1"),
+                invalid("ip", "eth1:foo"),
+                invalid("bond", "bond0:eth2:mode=foo"),
+            ]
+        );
+        let ids: Vec<_> = connections.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["eth0"]);
+        let eth0 = find(&connections, "eth0");
+        assert_eq!(eth0.ip_config.nameservers, vec![addr("10.0.0.2")]);
+    }
+
+    #[test]
+    fn test_parse_ifcfg_glob() {
+        let connections = parse_valid("ifcfg=eth*=dhcp");
+        let conn = find(&connections, "eth*");
+        assert_eq!(conn.interface, None);
+        assert_eq!(conn.match_config.interface, vec!["eth*".to_string()]);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut eth0 = Connection::new("Wired connection 1".to_string(), DeviceType::Ethernet);
+        eth0.interface = Some("eth0".to_string());
+        let mut bond0 = Connection::new("bond0".to_string(), DeviceType::Bond);
+        bond0.interface = Some("bond0".to_string());
+        let bond0_uuid = bond0.uuid;
+        let mut state = NetworkState::new(Default::default(), vec![], vec![], vec![eth0, bond0]);
+
+        let connections = parse_valid("bond=bond0:eth0,eth1 ip=bond0:dhcp");
+        assert_eq!(merge(&mut state, connections), 1);
+
+        assert_eq!(state.connections.len(), 3);
+        let eth1 = state.get_connection("eth1").unwrap();
+        assert_eq!(eth1.controller, Some(bond0_uuid));
+        let bond0 = state.get_connection("bond0").unwrap();
+        assert_eq!(bond0.ip_config.method4, Ipv4Method::Disabled);
+    }
+}
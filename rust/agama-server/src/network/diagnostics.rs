@@ -0,0 +1,574 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! Implements the network connectivity diagnostics.
+//!
+//! [Diagnostics] checks, for each device, the link state, the IP addresses, the default route and
+//! the gateway reachability. Additionally, it can check the resolution of a set of hostnames and
+//! whether a URL can be fetched (optionally, through a proxy).
+//!
+//! The checks that need to talk to the network rely on a [Probe], so they can be tested without
+//! a real network. The service uses the [SystemProbe].
+
+use crate::network::{error::NetworkStateError, model::Device};
+use agama_lib::network::{
+    diagnostics::{
+        CheckKind, CheckResult, CheckStatus, DeviceDiagnostics, DiagnosticsOptions,
+        DiagnosticsReport,
+    },
+    types::{DeviceState, DeviceType},
+};
+use async_trait::async_trait;
+use std::{io, net::IpAddr, time::Duration};
+use thiserror::Error;
+use tokio::{net::TcpStream, process::Command, time::timeout};
+
+#[derive(Error, Debug)]
+pub enum ProbeError {
+    #[error("Could not run '{0}': {1}")]
+    Command(String, #[source] io::Error),
+    #[error("'{0}' is not reachable")]
+    Unreachable(String),
+    #[error("Could not resolve '{0}': {1}")]
+    Resolve(String, #[source] io::Error),
+    #[error("'{0}' did not answer in time")]
+    Timeout(String),
+    #[error("Invalid URL '{0}'")]
+    InvalidUrl(String),
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] reqwest::Error),
+}
+
+/// How to check whether an address is reachable.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReachMethod {
+    /// Sends an ICMP echo request.
+    Icmp,
+    /// Opens a TCP connection to the given port.
+    Tcp(u16),
+}
+
+/// A trait for the checks which need to talk to the network.
+#[async_trait]
+pub trait Probe {
+    /// Checks whether the given address is reachable.
+    ///
+    /// * `address`: address to reach.
+    /// * `method`: how to reach the address.
+    async fn reach(&self, address: IpAddr, method: ReachMethod) -> Result<(), ProbeError>;
+    /// Resolves the given hostname.
+    ///
+    /// * `host`: hostname to resolve.
+    async fn resolve(&self, host: &str) -> Result<Vec<IpAddr>, ProbeError>;
+    /// Fetches the given URL and returns the HTTP status code.
+    ///
+    /// * `url`: URL to fetch.
+    /// * `proxy`: proxy to use (the system one if it is not set).
+    async fn fetch(&self, url: &str, proxy: Option<&str>) -> Result<u16, ProbeError>;
+}
+
+/// Probe which talks to the real network.
+pub struct SystemProbe {
+    timeout: Duration,
+}
+
+impl SystemProbe {
+    /// Builds a probe which gives up after the given `timeout`.
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+}
+
+impl Default for SystemProbe {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(5))
+    }
+}
+
+#[async_trait]
+impl Probe for SystemProbe {
+    async fn reach(&self, address: IpAddr, method: ReachMethod) -> Result<(), ProbeError> {
+        match method {
+            ReachMethod::Icmp => {
+                let output = Command::new("ping")
+                    .args(["-c", "1", "-W"])
+                    .arg(self.timeout.as_secs().max(1).to_string())
+                    .arg(address.to_string())
+                    .output()
+                    .await
+                    .map_err(|e| ProbeError::Command("ping".to_string(), e))?;
+                if !output.status.success() {
+                    return Err(ProbeError::Unreachable(address.to_string()));
+                }
+            }
+            ReachMethod::Tcp(port) => {
+                let target = format!("{}:{}", address, port);
+                match timeout(self.timeout, TcpStream::connect((address, port))).await {
+                    Err(_) => return Err(ProbeError::Timeout(target)),
+                    // a refused connection means that the host answered
+                    Ok(Err(e)) if e.kind() != io::ErrorKind::ConnectionRefused => {
+                        return Err(ProbeError::Unreachable(target))
+                    }
+                    Ok(_) => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn resolve(&self, host: &str) -> Result<Vec<IpAddr>, ProbeError> {
+        let addresses = timeout(self.timeout, tokio::net::lookup_host((host, 0)))
+            .await
+            .map_err(|_| ProbeError::Timeout(host.to_string()))?
+            .map_err(|e| ProbeError::Resolve(host.to_string(), e))?;
+
+        let mut ips: Vec<IpAddr> = vec![];
+        for address in addresses {
+            if !ips.contains(&address.ip()) {
+                ips.push(address.ip());
+            }
+        }
+        Ok(ips)
+    }
+
+    async fn fetch(&self, url: &str, proxy: Option<&str>) -> Result<u16, ProbeError> {
+        let mut builder = reqwest::Client::builder().timeout(self.timeout);
+        if let Some(proxy) = proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+        }
+        let response = builder.build()?.get(url).send().await?;
+        Ok(response.status().as_u16())
+    }
+}
+
+/// Runs the network diagnostics using the given [Probe].
+pub struct Diagnostics<P: Probe> {
+    probe: P,
+}
+
+impl<P: Probe + Send + Sync> Diagnostics<P> {
+    pub fn new(probe: P) -> Self {
+        Self { probe }
+    }
+
+    /// Runs the diagnostics.
+    ///
+    /// * `devices`: devices to check.
+    /// * `options`: diagnostics options.
+    pub async fn run(
+        &self,
+        devices: &[Device],
+        options: &DiagnosticsOptions,
+    ) -> Result<DiagnosticsReport, NetworkStateError> {
+        let devices: Vec<&Device> = match &options.device {
+            Some(name) => {
+                let device = devices
+                    .iter()
+                    .find(|d| &d.name == name)
+                    .ok_or_else(|| NetworkStateError::UnknownDevice(name.to_string()))?;
+                vec![device]
+            }
+            None => devices
+                .iter()
+                .filter(|d| d.type_ != DeviceType::Loopback)
+                .collect(),
+        };
+
+        let mut report = DiagnosticsReport::default();
+        for device in devices {
+            report
+                .devices
+                .push(self.check_device(device, options).await);
+        }
+
+        let mut hosts: Vec<String> = options
+            .hostnames()
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        // the URL hostname is checked too, so a failed HTTP check can be told apart from a
+        // DNS problem
+        let url_host = options
+            .url
+            .as_deref()
+            .and_then(|u| url::Url::parse(u).ok())
+            .and_then(|u| match u.host() {
+                Some(url::Host::Domain(domain)) => Some(domain.to_string()),
+                _ => None,
+            });
+        if let Some(host) = url_host {
+            if !hosts.contains(&host) {
+                hosts.push(host);
+            }
+        }
+
+        for host in hosts {
+            report.checks.push(self.check_dns(&host).await);
+        }
+
+        if let Some(url) = &options.url {
+            report
+                .checks
+                .push(self.check_http(url, options.proxy.as_deref()).await);
+        }
+
+        Ok(report)
+    }
+
+    async fn check_device(
+        &self,
+        device: &Device,
+        options: &DiagnosticsOptions,
+    ) -> DeviceDiagnostics {
+        let mut checks = vec![];
+        let name = device.name.as_str();
+
+        let link = if device.state == DeviceState::Activated {
+            CheckResult::new(CheckKind::Link, CheckStatus::Passed, name)
+        } else {
+            CheckResult::new(CheckKind::Link, CheckStatus::Failed, name)
+                .with_details(&format!("device state: {:?}", device.state))
+        };
+        checks.push(link);
+
+        let ip_config = device.ip_config.as_ref();
+        let addresses: Vec<String> = ip_config
+            .map(|c| c.addresses.iter().map(|a| a.to_string()).collect())
+            .unwrap_or_default();
+        let address = if addresses.is_empty() {
+            CheckResult::new(CheckKind::Address, CheckStatus::Failed, name)
+                .with_details("no IP address")
+        } else {
+            CheckResult::new(CheckKind::Address, CheckStatus::Passed, name)
+                .with_details(&addresses.join(", "))
+        };
+        checks.push(address);
+
+        let gateways = default_gateways(device);
+        let default_route = if gateways.is_empty() {
+            CheckResult::new(CheckKind::DefaultRoute, CheckStatus::Failed, name)
+                .with_details("no default route")
+        } else {
+            let gateways: Vec<String> = gateways.iter().map(|g| g.to_string()).collect();
+            CheckResult::new(CheckKind::DefaultRoute, CheckStatus::Passed, name)
+                .with_details(&format!("via {}", gateways.join(", ")))
+        };
+        checks.push(default_route);
+
+        if gateways.is_empty() {
+            checks.push(
+                CheckResult::new(CheckKind::Gateway, CheckStatus::Skipped, name)
+                    .with_details("no gateway"),
+            );
+        }
+
+        let method = match options.gateway_port {
+            Some(port) => ReachMethod::Tcp(port),
+            None => ReachMethod::Icmp,
+        };
+        for gateway in gateways {
+            let target = gateway.to_string();
+            let result = match self.probe.reach(gateway, method).await {
+                Ok(()) => CheckResult::new(CheckKind::Gateway, CheckStatus::Passed, &target),
+                Err(e) => CheckResult::new(CheckKind::Gateway, CheckStatus::Failed, &target)
+                    .with_details(&e.to_string()),
+            };
+            checks.push(result);
+        }
+
+        DeviceDiagnostics {
+            device: device.name.clone(),
+            connection: device.connection.clone(),
+            checks,
+        }
+    }
+
+    async fn check_dns(&self, host: &str) -> CheckResult {
+        match self.probe.resolve(host).await {
+            Ok(ips) if ips.is_empty() => {
+                CheckResult::new(CheckKind::Dns, CheckStatus::Failed, host)
+                    .with_details("no addresses")
+            }
+            Ok(ips) => {
+                let ips: Vec<String> = ips.iter().map(|ip| ip.to_string()).collect();
+                CheckResult::new(CheckKind::Dns, CheckStatus::Passed, host)
+                    .with_details(&ips.join(", "))
+            }
+            Err(e) => CheckResult::new(CheckKind::Dns, CheckStatus::Failed, host)
+                .with_details(&e.to_string()),
+        }
+    }
+
+    async fn check_http(&self, url: &str, proxy: Option<&str>) -> CheckResult {
+        let result = match url::Url::parse(url) {
+            Ok(parsed) if ["http", "https"].contains(&parsed.scheme()) => {
+                self.probe.fetch(url, proxy).await
+            }
+            _ => Err(ProbeError::InvalidUrl(url.to_string())),
+        };
+
+        match result {
+            Ok(code) if code < 400 => CheckResult::new(CheckKind::Http, CheckStatus::Passed, url)
+                .with_details(&format!("HTTP {}", code)),
+            Ok(code) => CheckResult::new(CheckKind::Http, CheckStatus::Failed, url)
+                .with_details(&format!("HTTP {}", code)),
+            Err(e) => CheckResult::new(CheckKind::Http, CheckStatus::Failed, url)
+                .with_details(&e.to_string()),
+        }
+    }
+}
+
+/// Returns the gateways of the default routes of the given device.
+fn default_gateways(device: &Device) -> Vec<IpAddr> {
+    let Some(ip_config) = &device.ip_config else {
+        return vec![];
+    };
+
+    let mut gateways: Vec<IpAddr> = ip_config
+        .gateway4
+        .iter()
+        .chain(ip_config.gateway6.iter())
+        .cloned()
+        .collect();
+    let routes = ip_config
+        .routes4
+        .iter()
+        .chain(ip_config.routes6.iter())
+        .flatten();
+    for route in routes {
+        if route.destination.network_length() != 0 {
+            continue;
+        }
+        if let Some(next_hop) = route.next_hop {
+            if !gateways.contains(&next_hop) {
+                gateways.push(next_hop);
+            }
+        }
+    }
+    gateways
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::model::{IpConfig, IpRoute};
+    use cidr::IpInet;
+    use std::{collections::HashMap, str::FromStr};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    /// Probe which answers using a predefined set of reachable addresses, hostnames and URLs.
+    #[derive(Default)]
+    struct FakeProbe {
+        reachable: Vec<IpAddr>,
+        hosts: HashMap<String, Vec<IpAddr>>,
+        urls: HashMap<String, u16>,
+    }
+
+    #[async_trait]
+    impl Probe for FakeProbe {
+        async fn reach(&self, address: IpAddr, _method: ReachMethod) -> Result<(), ProbeError> {
+            if self.reachable.contains(&address) {
+                Ok(())
+            } else {
+                Err(ProbeError::Unreachable(address.to_string()))
+            }
+        }
+
+        async fn resolve(&self, host: &str) -> Result<Vec<IpAddr>, ProbeError> {
+            self.hosts.get(host).cloned().ok_or_else(|| {
+                ProbeError::Resolve(host.to_string(), io::ErrorKind::NotFound.into())
+            })
+        }
+
+        async fn fetch(&self, url: &str, _proxy: Option<&str>) -> Result<u16, ProbeError> {
+            self.urls
+                .get(url)
+                .cloned()
+                .ok_or_else(|| ProbeError::Unreachable(url.to_string()))
+        }
+    }
+
+    fn build_devices() -> Vec<Device> {
+        let eth0 = Device {
+            name: "eth0".to_string(),
+            state: DeviceState::Activated,
+            connection: Some("Wired connection 1".to_string()),
+            ip_config: Some(IpConfig {
+                addresses: vec![IpInet::from_str("192.168.1.10/24").unwrap()],
+                gateway4: Some(IpAddr::from_str("192.168.1.1").unwrap()),
+                routes6: Some(vec![IpRoute {
+                    destination: IpInet::from_str("::/0").unwrap(),
+                    next_hop: Some(IpAddr::from_str("fd00::1").unwrap()),
+                    metric: None,
+                }]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let eth1 = Device {
+            name: "eth1".to_string(),
+            state: DeviceState::Disconnected,
+            ..Default::default()
+        };
+        let lo = Device {
+            name: "lo".to_string(),
+            type_: DeviceType::Loopback,
+            ..Default::default()
+        };
+        vec![eth0, eth1, lo]
+    }
+
+    fn statuses(checks: &[CheckResult]) -> Vec<(CheckKind, CheckStatus)> {
+        checks.iter().map(|c| (c.kind, c.status)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_device_checks() {
+        let probe = FakeProbe {
+            reachable: vec![IpAddr::from_str("192.168.1.1").unwrap()],
+            ..Default::default()
+        };
+        let diagnostics = Diagnostics::new(probe);
+        let report = diagnostics
+            .run(&build_devices(), &DiagnosticsOptions::default())
+            .await
+            .unwrap();
+
+        let names: Vec<_> = report.devices.iter().map(|d| d.device.as_str()).collect();
+        assert_eq!(names, vec!["eth0", "eth1"]);
+        assert!(report.checks.is_empty());
+        assert!(!report.is_ok());
+
+        let eth0 = &report.devices[0];
+        assert_eq!(eth0.connection, Some("Wired connection 1".to_string()));
+        assert_eq!(
+            statuses(&eth0.checks),
+            vec![
+                (CheckKind::Link, CheckStatus::Passed),
+                (CheckKind::Address, CheckStatus::Passed),
+                (CheckKind::DefaultRoute, CheckStatus::Passed),
+                (CheckKind::Gateway, CheckStatus::Passed),
+                (CheckKind::Gateway, CheckStatus::Failed),
+            ]
+        );
+        assert_eq!(eth0.checks[3].target, "192.168.1.1");
+        assert_eq!(eth0.checks[4].target, "fd00::1");
+
+        let eth1 = &report.devices[1];
+        assert_eq!(
+            statuses(&eth1.checks),
+            vec![
+                (CheckKind::Link, CheckStatus::Failed),
+                (CheckKind::Address, CheckStatus::Failed),
+                (CheckKind::DefaultRoute, CheckStatus::Failed),
+                (CheckKind::Gateway, CheckStatus::Skipped),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dns_and_http_checks() {
+        let mut probe = FakeProbe::default();
+        probe.hosts.insert(
+            "download.opensuse.org".to_string(),
+            vec![IpAddr::from_str("195.135.223.226").unwrap()],
+        );
+        probe
+            .urls
+            .insert("https://download.opensuse.org/".to_string(), 200);
+        let diagnostics = Diagnostics::new(probe);
+
+        let options = DiagnosticsOptions {
+            device: Some("eth0".to_string()),
+            hosts: Some("scc.suse.com, download.opensuse.org".to_string()),
+            url: Some("https://download.opensuse.org/".to_string()),
+            ..Default::default()
+        };
+        let report = diagnostics.run(&build_devices(), &options).await.unwrap();
+        assert_eq!(report.devices.len(), 1);
+
+        let checks: Vec<_> = report
+            .checks
+            .iter()
+            .map(|c| (c.kind, c.status, c.target.as_str()))
+            .collect();
+        assert_eq!(
+            checks,
+            vec![
+                (CheckKind::Dns, CheckStatus::Failed, "scc.suse.com"),
+                (CheckKind::Dns, CheckStatus::Passed, "download.opensuse.org"),
+                (
+                    CheckKind::Http,
+                    CheckStatus::Passed,
+                    "https://download.opensuse.org/"
+                ),
+            ]
+        );
+        assert_eq!(
+            report.checks[1].details,
+            Some("195.135.223.226".to_string())
+        );
+
+        let options = DiagnosticsOptions {
+            device: Some("wlan0".to_string()),
+            ..Default::default()
+        };
+        let error = diagnostics.run(&build_devices(), &options).await;
+        assert!(matches!(error, Err(NetworkStateError::UnknownDevice(_))));
+    }
+
+    #[tokio::test]
+    async fn test_system_probe() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buffer = [0; 1024];
+                _ = stream.read(&mut buffer).await;
+                _ = stream
+                    .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+                    .await;
+            }
+        });
+
+        let probe = SystemProbe::new(Duration::from_secs(2));
+        probe
+            .reach(address.ip(), ReachMethod::Tcp(address.port()))
+            .await
+            .unwrap();
+
+        let url = format!("http://{}/", address);
+        assert_eq!(probe.fetch(&url, None).await.unwrap(), 204);
+
+        // using the stand-in as a proxy
+        let proxy = format!("http://{}", address);
+        let code = probe
+            .fetch("http://example.net/", Some(&proxy))
+            .await
+            .unwrap();
+        assert_eq!(code, 204);
+
+        let ips = probe.resolve("localhost").await.unwrap();
+        assert!(ips.iter().all(|ip| ip.is_loopback()));
+    }
+}
@@ -70,6 +70,30 @@ pub enum NetworkStateError {
     InvalidWirelessBand(String),
     #[error("Invalid bssid: '{0}'")]
     InvalidBssid(String),
+    #[error("Invalid MAC address: '{0}'")]
+    InvalidMacAddress(String),
+    #[error("Invalid VLAN protocol: '{0}'")]
+    InvalidVlanProtocol(String),
+    #[error("Invalid InfiniBand transport mode: '{0}'")]
+    InvalidInfinibandTransportMode(String),
+    #[error("Invalid TUN mode: '{0}'")]
+    InvalidTunMode(String),
+    #[error("Invalid WireGuard key")]
+    InvalidWireguardKey,
+    #[error("Invalid OVS fail mode: '{0}'")]
+    InvalidOvsFailMode(String),
+    #[error("Invalid OVS interface type: '{0}'")]
+    InvalidOvsInterfaceType(String),
+    #[error("Invalid hostname: '{0}'")]
+    InvalidHostname(String),
+    #[error("Invalid hostname mode: '{0}'")]
+    InvalidHostnameMode(String),
+    #[error("Invalid DHCP options: {0}")]
+    InvalidDhcpOptions(String),
+    #[error("Invalid IPv6 address generation mode: '{0}'")]
+    InvalidAddrGenMode(String),
+    #[error("Invalid IPv6 privacy extensions mode: '{0}'")]
+    InvalidIpv6Privacy(String),
 }
 
 impl From<NetworkStateError> for zbus::fdo::Error {
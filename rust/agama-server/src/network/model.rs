@@ -24,7 +24,10 @@
 //! agnostic from the real network service (e.g., NetworkManager).
 use crate::network::error::NetworkStateError;
 use agama_lib::network::settings::{
-    BondSettings, IEEE8021XSettings, NetworkConnection, WirelessSettings,
+    BondSettings, BridgePortSettings, BridgeSettings, DhcpSettings, GlobalSettings,
+    IEEE8021XSettings, InfinibandSettings, IpRouteSettings, MatchSettings, NetworkConnection,
+    OvsBridgeSettings, OvsInterfaceSettings, OvsPortSettings, TunSettings, VlanSettings,
+    VrfSettings, WireguardPeerSettings, WireguardSettings, WirelessSettings,
 };
 use agama_lib::network::types::{BondMode, DeviceState, DeviceType, Status, SSID};
 use cidr::IpInet;
@@ -47,6 +50,7 @@ pub struct StateConfig {
     pub devices: bool,
     pub connections: bool,
     pub general_state: bool,
+    pub global: bool,
 }
 
 impl Default for StateConfig {
@@ -56,6 +60,7 @@ impl Default for StateConfig {
             devices: true,
             connections: true,
             general_state: true,
+            global: true,
         }
     }
 }
@@ -63,6 +68,7 @@ impl Default for StateConfig {
 #[derive(Default, Clone, Debug)]
 pub struct NetworkState {
     pub general_state: GeneralState,
+    pub global: GlobalConfig,
     pub access_points: Vec<AccessPoint>,
     pub devices: Vec<Device>,
     pub connections: Vec<Connection>,
@@ -86,6 +92,7 @@ impl NetworkState {
             access_points,
             devices,
             connections,
+            ..Default::default()
         }
     }
 
@@ -175,6 +182,31 @@ impl NetworkState {
         Ok(())
     }
 
+    /// Adds or updates several connections at once.
+    ///
+    /// The connections are matched by UUID. If any of them cannot be added or updated, the
+    /// state is not modified at all.
+    ///
+    /// * `connections`: connections to add or update (they might be marked as removed).
+    pub fn update_connections(
+        &mut self,
+        connections: Vec<Connection>,
+    ) -> Result<(), NetworkStateError> {
+        let backup = self.connections.clone();
+        for conn in connections {
+            let result = if self.get_connection_by_uuid(conn.uuid).is_some() {
+                self.update_connection(conn)
+            } else {
+                self.add_connection(conn)
+            };
+            if let Err(e) = result {
+                self.connections = backup;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
     /// Removes a connection from the state.
     ///
     /// Additionally, it registers the connection to be removed when the changes are applied.
@@ -221,7 +253,7 @@ impl NetworkState {
         controller: &Connection,
         ports: Vec<String>,
     ) -> Result<(), NetworkStateError> {
-        if let ConnectionConfig::Bond(_) = &controller.config {
+        if controller.is_controller() {
             let mut controlled = vec![];
             for port in ports {
                 let connection = self
@@ -357,6 +389,30 @@ mod tests {
         assert!(matches!(error, NetworkStateError::UnknownConnection(_)));
     }
 
+    #[test]
+    fn test_update_connections() {
+        let mut state = NetworkState::default();
+        let conn0 = Connection::new("eth0".to_string(), DeviceType::Ethernet);
+        state.add_connection(conn0.clone()).unwrap();
+
+        let mut updated = conn0.clone();
+        updated.mtu = 9000;
+        let conn1 = Connection::new("eth1".to_string(), DeviceType::Ethernet);
+        state
+            .update_connections(vec![updated.clone(), conn1])
+            .unwrap();
+        assert_eq!(state.get_connection("eth0").unwrap().mtu, 9000);
+        assert!(state.get_connection("eth1").is_some());
+
+        // a new connection reusing an existing ID
+        updated.mtu = 1500;
+        let conn2 = Connection::new("eth1".to_string(), DeviceType::Ethernet);
+        let error = state.update_connections(vec![updated, conn2]).unwrap_err();
+        assert!(matches!(error, NetworkStateError::ConnectionExists(_)));
+        assert_eq!(state.get_connection("eth0").unwrap().mtu, 9000);
+        assert_eq!(state.connections.len(), 2);
+    }
+
     #[test]
     fn test_remove_connection() {
         let mut state = NetworkState::default();
@@ -449,6 +505,473 @@ mod tests {
             NetworkStateError::NotControllerConnection(_),
         ));
     }
+
+    #[test]
+    fn test_set_bridge_ports() {
+        let mut state = NetworkState::default();
+        let bond0 = Connection {
+            id: "bond0".to_string(),
+            interface: Some("bond0".to_string()),
+            config: ConnectionConfig::Bond(Default::default()),
+            ..Default::default()
+        };
+        let br0 = Connection {
+            id: "br0".to_string(),
+            interface: Some("br0".to_string()),
+            config: ConnectionConfig::Bridge(Default::default()),
+            ..Default::default()
+        };
+        state.add_connection(bond0).unwrap();
+        state.add_connection(br0.clone()).unwrap();
+
+        state.set_ports(&br0, vec!["bond0".to_string()]).unwrap();
+
+        let bond0_found = state.get_connection("bond0").unwrap();
+        assert_eq!(bond0_found.controller, Some(br0.uuid));
+    }
+
+    #[test]
+    fn test_set_ovs_ports() {
+        let mut state = NetworkState::default();
+        let ovs_bridge = Connection::new("ovs-br0".to_string(), DeviceType::OvsBridge);
+        let ovs_port = Connection::new("ovs-port0".to_string(), DeviceType::OvsPort);
+        let ovs_interface = Connection::new("ovs-iface0".to_string(), DeviceType::OvsInterface);
+        state.add_connection(ovs_bridge.clone()).unwrap();
+        state.add_connection(ovs_port.clone()).unwrap();
+        state.add_connection(ovs_interface.clone()).unwrap();
+
+        state
+            .set_ports(&ovs_bridge, vec!["ovs-port0".to_string()])
+            .unwrap();
+        state
+            .set_ports(&ovs_port, vec!["ovs-iface0".to_string()])
+            .unwrap();
+        let error = state
+            .set_ports(&ovs_interface, vec!["eth0".to_string()])
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            NetworkStateError::NotControllerConnection(_)
+        ));
+
+        let port_found = state.get_connection("ovs-port0").unwrap();
+        assert_eq!(port_found.controller, Some(ovs_bridge.uuid));
+        let interface_found = state.get_connection("ovs-iface0").unwrap();
+        assert_eq!(interface_found.controller, Some(ovs_port.uuid));
+    }
+
+    #[test]
+    fn test_connection_from_settings() {
+        let settings: NetworkConnection = serde_json::from_str(
+            r#"{
+                "id": "bond0.10",
+                "interface": "bond0.10",
+                "mac-address": "00:11:22:33:44:55",
+                "firewallZone": "public",
+                "routes4": [
+                    { "destination": "10.0.0.0/8", "nextHop": "192.168.1.1", "metric": 100 }
+                ],
+                "vlan": { "id": 10, "parent": "bond0", "protocol": "802.1ad" },
+                "bridgePort": { "priority": 32, "pathCost": 100 }
+            }"#,
+        )
+        .unwrap();
+
+        let conn = Connection::try_from(settings).unwrap();
+        assert_eq!(
+            conn.config,
+            ConnectionConfig::Vlan(VlanConfig {
+                parent: "bond0".to_string(),
+                id: 10,
+                protocol: VlanProtocol::IEEE802_1ad,
+            })
+        );
+        assert_eq!(
+            conn.port_config,
+            PortConfig::Bridge(BridgePortConfig {
+                priority: Some(32),
+                path_cost: Some(100),
+            })
+        );
+        assert_eq!(conn.firewall_zone, Some("public".to_string()));
+        assert_eq!(
+            conn.mac_address,
+            MacAddress::MacAddress(macaddr::MacAddr6::new(0x00, 0x11, 0x22, 0x33, 0x44, 0x55))
+        );
+        let routes = conn.ip_config.routes4.clone().unwrap();
+        assert_eq!(routes[0].destination.to_string(), "10.0.0.0/8");
+        assert_eq!(routes[0].metric, Some(100));
+
+        let settings = NetworkConnection::try_from(conn).unwrap();
+        let vlan = settings.vlan.unwrap();
+        assert_eq!(vlan.parent, "bond0");
+        assert_eq!(vlan.protocol, Some("802.1ad".to_string()));
+        assert_eq!(settings.bridge_port.unwrap().path_cost, Some(100));
+        assert_eq!(settings.routes4.unwrap().len(), 1);
+        assert_eq!(settings.firewall_zone, Some("public".to_string()));
+    }
+
+    #[test]
+    fn test_connection_config_from_settings() {
+        let bridge = NetworkConnection {
+            id: "br0".to_string(),
+            bridge: Some(BridgeSettings {
+                stp: Some(true),
+                priority: Some(4096),
+                ports: vec!["eth0".to_string()],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let conn = Connection::try_from(bridge).unwrap();
+        let ConnectionConfig::Bridge(config) = &conn.config else {
+            panic!("Unexpected config: {:?}", conn.config);
+        };
+        assert!(config.stp);
+        assert_eq!(config.priority, Some(4096));
+
+        let tun = NetworkConnection {
+            id: "tap0".to_string(),
+            tun: Some(TunSettings {
+                mode: "tap".to_string(),
+                owner: Some("1000".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let conn = Connection::try_from(tun).unwrap();
+        let settings = NetworkConnection::try_from(conn).unwrap();
+        assert_eq!(settings.tun.unwrap().mode, "tap");
+
+        let infiniband = NetworkConnection {
+            id: "ib0".to_string(),
+            infiniband: Some(InfinibandSettings {
+                p_key: Some(0x8001),
+                transport_mode: Some("connected".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let conn = Connection::try_from(infiniband).unwrap();
+        let ConnectionConfig::Infiniband(config) = &conn.config else {
+            panic!("Unexpected config: {:?}", conn.config);
+        };
+        assert_eq!(config.transport_mode, InfinibandTransportMode::Connected);
+
+        let dummy = NetworkConnection {
+            id: "dummy0".to_string(),
+            dummy: true,
+            ..Default::default()
+        };
+        let conn = Connection::try_from(dummy).unwrap();
+        assert_eq!(conn.config, ConnectionConfig::Dummy);
+        assert!(NetworkConnection::try_from(conn).unwrap().dummy);
+    }
+
+    #[test]
+    fn test_wireguard_config_from_settings() {
+        let settings: NetworkConnection = serde_json::from_str(
+            r#"{
+                "id": "wg0",
+                "wireguard": {
+                    "privateKey": "yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=",
+                    "listenPort": 51820,
+                    "peers": [
+                        {
+                            "publicKey": "xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=",
+                            "endpoint": "vpn.example.net:51820",
+                            "allowedIps": ["10.0.0.0/24", "fd00::/64"],
+                            "persistentKeepalive": 25
+                        }
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(settings.device_type(), DeviceType::Wireguard);
+
+        let conn = Connection::try_from(settings).unwrap();
+        let ConnectionConfig::Wireguard(config) = &conn.config else {
+            panic!("Unexpected config: {:?}", conn.config);
+        };
+        assert_eq!(config.listen_port, Some(51820));
+        assert_eq!(config.peers[0].allowed_ips.len(), 2);
+
+        let settings = NetworkConnection::try_from(conn).unwrap();
+        let wireguard = settings.wireguard.unwrap();
+        assert_eq!(
+            wireguard.private_key,
+            Some("yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=".to_string())
+        );
+        let peer = &wireguard.peers[0];
+        assert_eq!(peer.endpoint, Some("vpn.example.net:51820".to_string()));
+        assert_eq!(peer.persistent_keepalive, Some(25));
+        assert_eq!(peer.allowed_ips[1].to_string(), "fd00::/64");
+    }
+
+    #[test]
+    fn test_vrf_and_ovs_config_from_settings() {
+        let vrf = NetworkConnection {
+            id: "vrf0".to_string(),
+            vrf: Some(VrfSettings {
+                table: 100,
+                ports: vec!["eth0".to_string()],
+            }),
+            ..Default::default()
+        };
+        assert_eq!(vrf.ports(), Some(&vec!["eth0".to_string()]));
+        let conn = Connection::try_from(vrf).unwrap();
+        assert_eq!(conn.config, ConnectionConfig::Vrf(VrfConfig { table: 100 }));
+        assert_eq!(
+            NetworkConnection::try_from(conn)
+                .unwrap()
+                .vrf
+                .unwrap()
+                .table,
+            100
+        );
+
+        let ovs_bridge = NetworkConnection {
+            id: "ovs-br0".to_string(),
+            ovs_bridge: Some(OvsBridgeSettings {
+                rstp: Some(true),
+                fail_mode: Some("standalone".to_string()),
+                ports: vec!["ovs-port0".to_string()],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let conn = Connection::try_from(ovs_bridge).unwrap();
+        let ConnectionConfig::OvsBridge(config) = &conn.config else {
+            panic!("Unexpected config: {:?}", conn.config);
+        };
+        assert!(config.rstp);
+        assert_eq!(config.fail_mode, Some(OvsFailMode::Standalone));
+        let settings = NetworkConnection::try_from(conn)
+            .unwrap()
+            .ovs_bridge
+            .unwrap();
+        assert_eq!(settings.fail_mode, Some("standalone".to_string()));
+
+        let ovs_port = NetworkConnection {
+            id: "ovs-port0".to_string(),
+            ovs_port: Some(OvsPortSettings {
+                tag: Some(10),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let conn = Connection::try_from(ovs_port).unwrap();
+        assert_eq!(
+            conn.config,
+            ConnectionConfig::OvsPort(OvsPortConfig { tag: Some(10) })
+        );
+        assert_eq!(
+            NetworkConnection::try_from(conn)
+                .unwrap()
+                .ovs_port
+                .unwrap()
+                .tag,
+            Some(10)
+        );
+
+        let ovs_interface = NetworkConnection {
+            id: "ovs-iface0".to_string(),
+            ovs_interface: Some(Default::default()),
+            ..Default::default()
+        };
+        let conn = Connection::try_from(ovs_interface).unwrap();
+        assert_eq!(
+            conn.config,
+            ConnectionConfig::OvsInterface(OvsInterfaceConfig {
+                interface_type: OvsInterfaceType::Internal
+            })
+        );
+        let settings = NetworkConnection::try_from(conn).unwrap();
+        assert_eq!(
+            settings.ovs_interface.unwrap().interface_type,
+            Some("internal".to_string())
+        );
+    }
+
+    #[test]
+    fn test_connection_from_invalid_settings() {
+        let tun = NetworkConnection {
+            id: "tun0".to_string(),
+            tun: Some(TunSettings {
+                mode: "unknown".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let error = Connection::try_from(tun).unwrap_err();
+        assert!(matches!(error, NetworkStateError::InvalidTunMode(_)));
+
+        let vlan = NetworkConnection {
+            id: "eth0.10".to_string(),
+            vlan: Some(VlanSettings {
+                id: 10,
+                parent: "eth0".to_string(),
+                protocol: Some("802.1X".to_string()),
+            }),
+            ..Default::default()
+        };
+        let error = Connection::try_from(vlan).unwrap_err();
+        assert!(matches!(error, NetworkStateError::InvalidVlanProtocol(_)));
+
+        let wireguard = NetworkConnection {
+            id: "wg0".to_string(),
+            wireguard: Some(WireguardSettings {
+                peers: vec![WireguardPeerSettings {
+                    public_key: "not-a-key".to_string(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let error = Connection::try_from(wireguard).unwrap_err();
+        assert!(matches!(error, NetworkStateError::InvalidWireguardKey));
+
+        let ovs_bridge = NetworkConnection {
+            id: "ovs-br0".to_string(),
+            ovs_bridge: Some(OvsBridgeSettings {
+                fail_mode: Some("unknown".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let error = Connection::try_from(ovs_bridge).unwrap_err();
+        assert!(matches!(error, NetworkStateError::InvalidOvsFailMode(_)));
+    }
+
+    #[test]
+    fn test_ip_options_from_settings() {
+        let settings = NetworkConnection {
+            id: "eth0".to_string(),
+            route_metric4: Some(100),
+            dhcp4: Some(DhcpSettings {
+                client_id: Some("mac".to_string()),
+                fqdn: Some("host.example.net".to_string()),
+                timeout: Some(60),
+                ..Default::default()
+            }),
+            dhcp6: Some(DhcpSettings {
+                send_hostname: Some(false),
+                ..Default::default()
+            }),
+            may_fail6: Some(false),
+            addr_gen_mode: Some("eui64".to_string()),
+            ip6_privacy: Some("prefer-temporary".to_string()),
+            ..Default::default()
+        };
+
+        let conn = Connection::try_from(settings).unwrap();
+        let ip_config = &conn.ip_config;
+        assert_eq!(ip_config.route_metric4, Some(100));
+        assert_eq!(ip_config.route_metric6, None);
+        assert_eq!(ip_config.dhcp4.client_id, Some("mac".to_string()));
+        assert_eq!(ip_config.dhcp4.fqdn, Some("host.example.net".to_string()));
+        assert_eq!(ip_config.dhcp4.timeout, Some(60));
+        assert_eq!(ip_config.dhcp6.send_hostname, Some(false));
+        assert_eq!(ip_config.may_fail4, None);
+        assert_eq!(ip_config.may_fail6, Some(false));
+        assert_eq!(ip_config.addr_gen_mode, Some(Ipv6AddrGenMode::Eui64));
+        assert_eq!(ip_config.ip6_privacy, Some(Ipv6Privacy::PreferTemporary));
+
+        let settings = NetworkConnection::try_from(conn).unwrap();
+        assert_eq!(settings.dhcp4.unwrap().timeout, Some(60));
+        assert_eq!(settings.addr_gen_mode, Some("eui64".to_string()));
+        assert_eq!(settings.ip6_privacy, Some("prefer-temporary".to_string()));
+        assert!(settings.dhcp6.is_some());
+    }
+
+    #[test]
+    fn test_ip_options_from_invalid_settings() {
+        let settings = NetworkConnection {
+            id: "eth0".to_string(),
+            dhcp6: Some(DhcpSettings {
+                fqdn: Some("host.example.net".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let error = Connection::try_from(settings).unwrap_err();
+        assert!(matches!(error, NetworkStateError::InvalidDhcpOptions(_)));
+
+        let settings = NetworkConnection {
+            id: "eth0".to_string(),
+            dhcp4: Some(DhcpSettings {
+                hostname: Some("host".to_string()),
+                fqdn: Some("host.example.net".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let error = Connection::try_from(settings).unwrap_err();
+        assert!(matches!(error, NetworkStateError::InvalidDhcpOptions(_)));
+
+        let settings = NetworkConnection {
+            id: "eth0".to_string(),
+            addr_gen_mode: Some("random".to_string()),
+            ..Default::default()
+        };
+        let error = Connection::try_from(settings).unwrap_err();
+        assert!(matches!(error, NetworkStateError::InvalidAddrGenMode(_)));
+
+        let settings = NetworkConnection {
+            id: "eth0".to_string(),
+            ip6_privacy: Some("always".to_string()),
+            ..Default::default()
+        };
+        let error = Connection::try_from(settings).unwrap_err();
+        assert!(matches!(error, NetworkStateError::InvalidIpv6Privacy(_)));
+    }
+
+    #[test]
+    fn test_global_config_from_settings() {
+        let settings = GlobalSettings {
+            hostname: Some("install.example.net".to_string()),
+            hostname_mode: Some("transient".to_string()),
+            nameservers: vec!["192.168.1.1".parse().unwrap()],
+            dns_searchlist: vec!["example.net".to_string()],
+            dns_options: vec!["rotate".to_string()],
+        };
+        let config = GlobalConfig::try_from(settings.clone()).unwrap();
+        assert_eq!(config.hostname, Some("install.example.net".to_string()));
+        assert_eq!(config.hostname_mode, HostnameMode::Transient);
+        assert_eq!(config.dns_options, vec!["rotate".to_string()]);
+
+        let config = GlobalConfig::try_from(GlobalSettings {
+            hostname: Some("agama".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(config.hostname_mode, HostnameMode::Static);
+
+        let roundtrip = GlobalSettings::from(GlobalConfig::try_from(settings.clone()).unwrap());
+        assert_eq!(roundtrip, settings);
+    }
+
+    #[test]
+    fn test_global_config_from_invalid_settings() {
+        for hostname in ["", "-agama", "agama-", "my_host", "a..b", &"a".repeat(65)] {
+            let settings = GlobalSettings {
+                hostname: Some(hostname.to_string()),
+                ..Default::default()
+            };
+            let error = GlobalConfig::try_from(settings).unwrap_err();
+            assert!(matches!(error, NetworkStateError::InvalidHostname(_)));
+        }
+
+        let settings = GlobalSettings {
+            hostname: Some("agama".to_string()),
+            hostname_mode: Some("pretty".to_string()),
+            ..Default::default()
+        };
+        let error = GlobalConfig::try_from(settings).unwrap_err();
+        assert!(matches!(error, NetworkStateError::InvalidHostnameMode(_)));
+    }
 }
 
 /// Network state
@@ -461,6 +984,130 @@ pub struct GeneralState {
     pub networking_enabled: bool, // pub network_state: NMSTATE
 }
 
+/// Maximum length of a hostname (see HOST_NAME_MAX).
+const HOSTNAME_MAX_LENGTH: usize = 64;
+
+/// Global network configuration
+///
+/// It contains the settings which are not bound to any connection.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GlobalConfig {
+    pub hostname: Option<String>,
+    pub hostname_mode: HostnameMode,
+    pub nameservers: Vec<IpAddr>,
+    pub dns_searchlist: Vec<String>,
+    pub dns_options: Vec<String>,
+}
+
+impl GlobalConfig {
+    /// Whether the global DNS configuration is the same in both configs.
+    ///
+    /// * `other`: configuration to compare with.
+    pub fn same_dns(&self, other: &GlobalConfig) -> bool {
+        self.nameservers == other.nameservers
+            && self.dns_searchlist == other.dns_searchlist
+            && self.dns_options == other.dns_options
+    }
+}
+
+impl TryFrom<GlobalSettings> for GlobalConfig {
+    type Error = NetworkStateError;
+
+    fn try_from(settings: GlobalSettings) -> Result<Self, Self::Error> {
+        if let Some(hostname) = &settings.hostname {
+            if !is_valid_hostname(hostname) {
+                return Err(NetworkStateError::InvalidHostname(hostname.to_string()));
+            }
+        }
+
+        let hostname_mode = match settings.hostname_mode {
+            Some(mode) => HostnameMode::from_str(&mode)
+                .map_err(|_| NetworkStateError::InvalidHostnameMode(mode))?,
+            None => HostnameMode::default(),
+        };
+
+        Ok(GlobalConfig {
+            hostname: settings.hostname,
+            hostname_mode,
+            nameservers: settings.nameservers,
+            dns_searchlist: settings.dns_searchlist,
+            dns_options: settings.dns_options,
+        })
+    }
+}
+
+impl From<GlobalConfig> for GlobalSettings {
+    fn from(config: GlobalConfig) -> Self {
+        GlobalSettings {
+            hostname_mode: config
+                .hostname
+                .as_ref()
+                .map(|_| config.hostname_mode.to_string()),
+            hostname: config.hostname,
+            nameservers: config.nameservers,
+            dns_searchlist: config.dns_searchlist,
+            dns_options: config.dns_options,
+        }
+    }
+}
+
+/// Determines whether the given string is a valid hostname.
+///
+/// It must be composed of dot-separated labels containing only ASCII letters, digits and
+/// hyphens, which cannot start or end with a hyphen.
+///
+/// * `hostname`: hostname to check.
+fn is_valid_hostname(hostname: &str) -> bool {
+    if hostname.is_empty() || hostname.len() > HOSTNAME_MAX_LENGTH {
+        return false;
+    }
+
+    hostname.split('.').all(|label| {
+        !label.is_empty()
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    })
+}
+
+/// How the hostname is set
+#[derive(Debug, Default, PartialEq, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HostnameMode {
+    /// Persistent hostname (written to /etc/hostname).
+    #[default]
+    Static,
+    /// Hostname for the running system only.
+    Transient,
+}
+
+#[derive(Debug, Error)]
+#[error("Invalid hostname mode: {0}")]
+pub struct InvalidHostnameMode(String);
+
+impl FromStr for HostnameMode {
+    type Err = InvalidHostnameMode;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "static" => Ok(Self::Static),
+            "transient" => Ok(Self::Transient),
+            _ => Err(InvalidHostnameMode(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for HostnameMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match &self {
+            HostnameMode::Static => "static",
+            HostnameMode::Transient => "transient",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 /// Access Point
 #[serde_as]
 #[derive(Default, Debug, Clone, Serialize, utoipa::ToSchema)]
@@ -525,6 +1172,11 @@ impl Connection {
             DeviceType::Bond => ConnectionConfig::Bond(Default::default()),
             DeviceType::Vlan => ConnectionConfig::Vlan(Default::default()),
             DeviceType::Bridge => ConnectionConfig::Bridge(Default::default()),
+            DeviceType::Wireguard => ConnectionConfig::Wireguard(Default::default()),
+            DeviceType::Vrf => ConnectionConfig::Vrf(Default::default()),
+            DeviceType::OvsBridge => ConnectionConfig::OvsBridge(Default::default()),
+            DeviceType::OvsPort => ConnectionConfig::OvsPort(Default::default()),
+            DeviceType::OvsInterface => ConnectionConfig::OvsInterface(Default::default()),
         };
         Self {
             id,
@@ -566,6 +1218,18 @@ impl Connection {
             || matches!(self.config, ConnectionConfig::Vlan(_))
             || matches!(self.config, ConnectionConfig::Bridge(_))
     }
+
+    /// Determines whether the connection can have ports (e.g., a bond or a VRF).
+    pub fn is_controller(&self) -> bool {
+        matches!(
+            self.config,
+            ConnectionConfig::Bond(_)
+                | ConnectionConfig::Bridge(_)
+                | ConnectionConfig::Vrf(_)
+                | ConnectionConfig::OvsBridge(_)
+                | ConnectionConfig::OvsPort(_)
+        )
+    }
 }
 
 impl Default for Connection {
@@ -623,16 +1287,98 @@ impl TryFrom<NetworkConnection> for Connection {
             connection.config = config.into();
         }
 
+        if let Some(bridge_config) = conn.bridge {
+            connection.config = ConnectionConfig::Bridge(bridge_config.into());
+        }
+
+        if let Some(vlan_config) = conn.vlan {
+            connection.config = ConnectionConfig::Vlan(VlanConfig::try_from(vlan_config)?);
+        }
+
+        if let Some(infiniband_config) = conn.infiniband {
+            let config = InfinibandConfig::try_from(infiniband_config)?;
+            connection.config = ConnectionConfig::Infiniband(config);
+        }
+
+        if let Some(tun_config) = conn.tun {
+            connection.config = ConnectionConfig::Tun(TunConfig::try_from(tun_config)?);
+        }
+
+        if let Some(wireguard_config) = conn.wireguard {
+            let config = WireguardConfig::try_from(wireguard_config)?;
+            connection.config = ConnectionConfig::Wireguard(config);
+        }
+
+        if let Some(vrf_config) = conn.vrf {
+            connection.config = ConnectionConfig::Vrf(vrf_config.into());
+        }
+
+        if let Some(ovs_bridge_config) = conn.ovs_bridge {
+            let config = OvsBridgeConfig::try_from(ovs_bridge_config)?;
+            connection.config = ConnectionConfig::OvsBridge(config);
+        }
+
+        if let Some(ovs_port_config) = conn.ovs_port {
+            connection.config = ConnectionConfig::OvsPort(ovs_port_config.into());
+        }
+
+        if let Some(ovs_interface_config) = conn.ovs_interface {
+            let config = OvsInterfaceConfig::try_from(ovs_interface_config)?;
+            connection.config = ConnectionConfig::OvsInterface(config);
+        }
+
+        if let Some(bridge_port_config) = conn.bridge_port {
+            connection.port_config = PortConfig::Bridge(bridge_port_config.into());
+        }
+
         if let Some(ieee_8021x_config) = conn.ieee_8021x {
             connection.ieee_8021x_config = Some(IEEE8021XConfig::try_from(ieee_8021x_config)?);
         }
 
+        if let Some(match_settings) = conn.match_settings {
+            connection.match_config = match_settings.into();
+        }
+
+        connection.mac_address = MacAddress::try_from(&conn.mac_address)
+            .map_err(|e| NetworkStateError::InvalidMacAddress(e.to_string()))?;
         connection.ip_config.addresses = conn.addresses;
         connection.ip_config.nameservers = conn.nameservers;
         connection.ip_config.dns_searchlist = conn.dns_searchlist;
         connection.ip_config.gateway4 = conn.gateway4;
         connection.ip_config.gateway6 = conn.gateway6;
+        connection.ip_config.routes4 = conn
+            .routes4
+            .map(|routes| routes.into_iter().map(IpRoute::from).collect());
+        connection.ip_config.routes6 = conn
+            .routes6
+            .map(|routes| routes.into_iter().map(IpRoute::from).collect());
+        connection.ip_config.route_metric4 = conn.route_metric4;
+        connection.ip_config.route_metric6 = conn.route_metric6;
+        if let Some(dhcp4) = conn.dhcp4 {
+            connection.ip_config.dhcp4 = DhcpConfig::try_from(dhcp4)?;
+        }
+        if let Some(dhcp6) = conn.dhcp6 {
+            if dhcp6.fqdn.is_some() {
+                return Err(NetworkStateError::InvalidDhcpOptions(
+                    "the FQDN can only be sent using DHCPv4".to_string(),
+                ));
+            }
+            connection.ip_config.dhcp6 = DhcpConfig::try_from(dhcp6)?;
+        }
+        connection.ip_config.may_fail4 = conn.may_fail4;
+        connection.ip_config.may_fail6 = conn.may_fail6;
+        if let Some(mode) = conn.addr_gen_mode {
+            let mode = Ipv6AddrGenMode::from_str(&mode)
+                .map_err(|_| NetworkStateError::InvalidAddrGenMode(mode))?;
+            connection.ip_config.addr_gen_mode = Some(mode);
+        }
+        if let Some(privacy) = conn.ip6_privacy {
+            let privacy = Ipv6Privacy::from_str(&privacy)
+                .map_err(|_| NetworkStateError::InvalidIpv6Privacy(privacy))?;
+            connection.ip_config.ip6_privacy = Some(privacy);
+        }
         connection.interface = conn.interface;
+        connection.firewall_zone = conn.firewall_zone;
         connection.mtu = conn.mtu;
 
         Ok(connection)
@@ -660,6 +1406,17 @@ impl TryFrom<Connection> for NetworkConnection {
         let ieee_8021x: Option<IEEE8021XSettings> = conn
             .ieee_8021x_config
             .and_then(|x| IEEE8021XSettings::try_from(x).ok());
+        let routes4 = conn
+            .ip_config
+            .routes4
+            .map(|routes| routes.into_iter().map(IpRouteSettings::from).collect());
+        let routes6 = conn
+            .ip_config
+            .routes6
+            .map(|routes| routes.into_iter().map(IpRouteSettings::from).collect());
+        let match_settings = MatchSettings::from(conn.match_config);
+        let dhcp4 = (!conn.ip_config.dhcp4.is_empty()).then(|| conn.ip_config.dhcp4.into());
+        let dhcp6 = (!conn.ip_config.dhcp6.is_empty()).then(|| conn.ip_config.dhcp6.into());
 
         let mut connection = NetworkConnection {
             id,
@@ -671,11 +1428,23 @@ impl TryFrom<Connection> for NetworkConnection {
             nameservers,
             dns_searchlist,
             ignore_auto_dns,
+            routes4,
+            routes6,
+            route_metric4: conn.ip_config.route_metric4,
+            route_metric6: conn.ip_config.route_metric6,
+            dhcp4,
+            dhcp6,
+            may_fail4: conn.ip_config.may_fail4,
+            may_fail6: conn.ip_config.may_fail6,
+            addr_gen_mode: conn.ip_config.addr_gen_mode.map(|m| m.to_string()),
+            ip6_privacy: conn.ip_config.ip6_privacy.map(|p| p.to_string()),
+            firewall_zone: conn.firewall_zone,
             mac_address,
             interface,
             addresses,
             mtu,
             ieee_8021x,
+            match_settings: (!match_settings.is_empty()).then_some(match_settings),
             ..Default::default()
         };
 
@@ -686,7 +1455,40 @@ impl TryFrom<Connection> for NetworkConnection {
             ConnectionConfig::Bond(config) => {
                 connection.bond = Some(BondSettings::try_from(config)?);
             }
-            _ => {}
+            ConnectionConfig::Bridge(config) => {
+                connection.bridge = Some(config.into());
+            }
+            ConnectionConfig::Vlan(config) => {
+                connection.vlan = Some(config.into());
+            }
+            ConnectionConfig::Infiniband(config) => {
+                connection.infiniband = Some(config.into());
+            }
+            ConnectionConfig::Tun(config) => {
+                connection.tun = Some(config.into());
+            }
+            ConnectionConfig::Wireguard(config) => {
+                connection.wireguard = Some(config.into());
+            }
+            ConnectionConfig::Vrf(config) => {
+                connection.vrf = Some(config.into());
+            }
+            ConnectionConfig::OvsBridge(config) => {
+                connection.ovs_bridge = Some(config.into());
+            }
+            ConnectionConfig::OvsPort(config) => {
+                connection.ovs_port = Some(config.into());
+            }
+            ConnectionConfig::OvsInterface(config) => {
+                connection.ovs_interface = Some(config.into());
+            }
+            ConnectionConfig::Dummy => connection.dummy = true,
+            ConnectionConfig::Loopback => connection.loopback = true,
+            ConnectionConfig::Ethernet => {}
+        }
+
+        if let PortConfig::Bridge(config) = conn.port_config {
+            connection.bridge_port = Some(config.into());
         }
 
         Ok(connection)
@@ -705,6 +1507,11 @@ pub enum ConnectionConfig {
     Bridge(BridgeConfig),
     Infiniband(InfinibandConfig),
     Tun(TunConfig),
+    Wireguard(WireguardConfig),
+    Vrf(VrfConfig),
+    OvsBridge(OvsBridgeConfig),
+    OvsPort(OvsPortConfig),
+    OvsInterface(OvsInterfaceConfig),
 }
 
 #[derive(Default, Debug, PartialEq, Clone, Serialize)]
@@ -807,6 +1614,145 @@ pub struct IpConfig {
     pub gateway6: Option<IpAddr>,
     pub routes4: Option<Vec<IpRoute>>,
     pub routes6: Option<Vec<IpRoute>>,
+    pub route_metric4: Option<u32>,
+    pub route_metric6: Option<u32>,
+    pub dhcp4: DhcpConfig,
+    pub dhcp6: DhcpConfig,
+    /// Whether the connection can be activated even if IPv4 configuration fails. `None` means
+    /// the backend default.
+    pub may_fail4: Option<bool>,
+    /// Whether the connection can be activated even if IPv6 configuration fails. `None` means
+    /// the backend default.
+    pub may_fail6: Option<bool>,
+    pub addr_gen_mode: Option<Ipv6AddrGenMode>,
+    pub ip6_privacy: Option<Ipv6Privacy>,
+}
+
+/// DHCP client options
+#[skip_serializing_none]
+#[derive(Default, Debug, PartialEq, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DhcpConfig {
+    /// Client identifier (DHCPv4) or DUID (DHCPv6).
+    pub client_id: Option<String>,
+    pub send_hostname: Option<bool>,
+    pub hostname: Option<String>,
+    /// Fully qualified domain name to send. It is only supported by DHCPv4.
+    pub fqdn: Option<String>,
+    /// Timeout in seconds.
+    pub timeout: Option<u32>,
+}
+
+impl DhcpConfig {
+    pub fn is_empty(&self) -> bool {
+        self == &DhcpConfig::default()
+    }
+}
+
+impl TryFrom<DhcpSettings> for DhcpConfig {
+    type Error = NetworkStateError;
+
+    fn try_from(settings: DhcpSettings) -> Result<Self, Self::Error> {
+        if settings.hostname.is_some() && settings.fqdn.is_some() {
+            return Err(NetworkStateError::InvalidDhcpOptions(
+                "the hostname and the FQDN cannot be set at the same time".to_string(),
+            ));
+        }
+
+        Ok(DhcpConfig {
+            client_id: settings.client_id,
+            send_hostname: settings.send_hostname,
+            hostname: settings.hostname,
+            fqdn: settings.fqdn,
+            timeout: settings.timeout,
+        })
+    }
+}
+
+impl From<DhcpConfig> for DhcpSettings {
+    fn from(config: DhcpConfig) -> Self {
+        DhcpSettings {
+            client_id: config.client_id,
+            send_hostname: config.send_hostname,
+            hostname: config.hostname,
+            fqdn: config.fqdn,
+            timeout: config.timeout,
+        }
+    }
+}
+
+/// How the IPv6 interface identifiers are generated
+#[derive(Debug, PartialEq, Clone, Copy, Serialize)]
+pub enum Ipv6AddrGenMode {
+    /// Based on the hardware address (EUI-64).
+    Eui64,
+    /// Stable privacy addresses (RFC 7217).
+    StablePrivacy,
+}
+
+#[derive(Debug, Error)]
+#[error("Invalid IPv6 address generation mode: {0}")]
+pub struct InvalidIpv6AddrGenMode(String);
+
+impl FromStr for Ipv6AddrGenMode {
+    type Err = InvalidIpv6AddrGenMode;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "eui64" => Ok(Self::Eui64),
+            "stable-privacy" => Ok(Self::StablePrivacy),
+            _ => Err(InvalidIpv6AddrGenMode(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Ipv6AddrGenMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match &self {
+            Ipv6AddrGenMode::Eui64 => "eui64",
+            Ipv6AddrGenMode::StablePrivacy => "stable-privacy",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// IPv6 privacy extensions (RFC 4941)
+#[derive(Debug, PartialEq, Clone, Copy, Serialize)]
+pub enum Ipv6Privacy {
+    /// Do not use temporary addresses.
+    Disabled,
+    /// Use temporary addresses, but prefer the public ones.
+    PreferPublic,
+    /// Use temporary addresses and prefer them over the public ones.
+    PreferTemporary,
+}
+
+#[derive(Debug, Error)]
+#[error("Invalid IPv6 privacy extensions mode: {0}")]
+pub struct InvalidIpv6Privacy(String);
+
+impl FromStr for Ipv6Privacy {
+    type Err = InvalidIpv6Privacy;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "disabled" => Ok(Self::Disabled),
+            "prefer-public" => Ok(Self::PreferPublic),
+            "prefer-temporary" => Ok(Self::PreferTemporary),
+            _ => Err(InvalidIpv6Privacy(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Ipv6Privacy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match &self {
+            Ipv6Privacy::Disabled => "disabled",
+            Ipv6Privacy::PreferPublic => "prefer-public",
+            Ipv6Privacy::PreferTemporary => "prefer-temporary",
+        };
+        write!(f, "{}", name)
+    }
 }
 
 #[skip_serializing_none]
@@ -822,6 +1768,28 @@ pub struct MatchConfig {
     pub kernel: Vec<String>,
 }
 
+impl From<MatchSettings> for MatchConfig {
+    fn from(settings: MatchSettings) -> Self {
+        MatchConfig {
+            driver: settings.driver,
+            interface: settings.interface,
+            path: settings.path,
+            kernel: settings.kernel,
+        }
+    }
+}
+
+impl From<MatchConfig> for MatchSettings {
+    fn from(config: MatchConfig) -> Self {
+        MatchSettings {
+            driver: config.driver,
+            interface: config.interface,
+            path: config.path,
+            kernel: config.kernel,
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 #[error("Unknown IP configuration method name: {0}")]
 pub struct UnknownIpMethod(String);
@@ -920,6 +1888,26 @@ pub struct IpRoute {
     pub metric: Option<u32>,
 }
 
+impl From<IpRouteSettings> for IpRoute {
+    fn from(settings: IpRouteSettings) -> Self {
+        IpRoute {
+            destination: settings.destination,
+            next_hop: settings.next_hop,
+            metric: settings.metric,
+        }
+    }
+}
+
+impl From<IpRoute> for IpRouteSettings {
+    fn from(route: IpRoute) -> Self {
+        IpRouteSettings {
+            destination: route.destination,
+            next_hop: route.next_hop,
+            metric: route.metric,
+        }
+    }
+}
+
 impl From<&IpRoute> for HashMap<&str, Value<'_>> {
     fn from(route: &IpRoute) -> Self {
         let mut map: HashMap<&str, Value> = HashMap::from([
@@ -979,6 +1967,34 @@ pub struct VlanConfig {
     pub protocol: VlanProtocol,
 }
 
+impl TryFrom<VlanSettings> for VlanConfig {
+    type Error = NetworkStateError;
+
+    fn try_from(settings: VlanSettings) -> Result<Self, Self::Error> {
+        let protocol = match settings.protocol {
+            Some(protocol) => VlanProtocol::from_str(&protocol)
+                .map_err(|_| NetworkStateError::InvalidVlanProtocol(protocol))?,
+            None => Default::default(),
+        };
+
+        Ok(VlanConfig {
+            parent: settings.parent,
+            id: settings.id,
+            protocol,
+        })
+    }
+}
+
+impl From<VlanConfig> for VlanSettings {
+    fn from(config: VlanConfig) -> Self {
+        VlanSettings {
+            id: config.id,
+            parent: config.parent,
+            protocol: Some(config.protocol.to_string()),
+        }
+    }
+}
+
 #[serde_as]
 #[derive(Debug, Default, PartialEq, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -1501,6 +2517,51 @@ pub struct BridgePortConfig {
     pub path_cost: Option<u32>,
 }
 
+impl From<BridgeSettings> for BridgeConfig {
+    fn from(settings: BridgeSettings) -> Self {
+        BridgeConfig {
+            stp: settings.stp.unwrap_or_default(),
+            priority: settings.priority,
+            forward_delay: settings.forward_delay,
+            hello_time: settings.hello_time,
+            max_age: settings.max_age,
+            ageing_time: settings.ageing_time,
+        }
+    }
+}
+
+impl From<BridgeConfig> for BridgeSettings {
+    fn from(config: BridgeConfig) -> Self {
+        BridgeSettings {
+            stp: Some(config.stp),
+            priority: config.priority,
+            forward_delay: config.forward_delay,
+            hello_time: config.hello_time,
+            max_age: config.max_age,
+            ageing_time: config.ageing_time,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<BridgePortSettings> for BridgePortConfig {
+    fn from(settings: BridgePortSettings) -> Self {
+        BridgePortConfig {
+            priority: settings.priority,
+            path_cost: settings.path_cost,
+        }
+    }
+}
+
+impl From<BridgePortConfig> for BridgePortSettings {
+    fn from(config: BridgePortConfig) -> Self {
+        BridgePortSettings {
+            priority: config.priority,
+            path_cost: config.path_cost,
+        }
+    }
+}
+
 #[derive(Default, Debug, PartialEq, Clone, Serialize)]
 pub struct InfinibandConfig {
     pub p_key: Option<i32>,
@@ -1541,6 +2602,34 @@ impl fmt::Display for InfinibandTransportMode {
     }
 }
 
+impl TryFrom<InfinibandSettings> for InfinibandConfig {
+    type Error = NetworkStateError;
+
+    fn try_from(settings: InfinibandSettings) -> Result<Self, Self::Error> {
+        let transport_mode = match settings.transport_mode {
+            Some(mode) => InfinibandTransportMode::from_str(&mode)
+                .map_err(|_| NetworkStateError::InvalidInfinibandTransportMode(mode))?,
+            None => Default::default(),
+        };
+
+        Ok(InfinibandConfig {
+            p_key: settings.p_key,
+            parent: settings.parent,
+            transport_mode,
+        })
+    }
+}
+
+impl From<InfinibandConfig> for InfinibandSettings {
+    fn from(config: InfinibandConfig) -> Self {
+        InfinibandSettings {
+            p_key: config.p_key,
+            parent: config.parent,
+            transport_mode: Some(config.transport_mode.to_string()),
+        }
+    }
+}
+
 #[derive(Default, Debug, PartialEq, Clone, Serialize)]
 pub enum TunMode {
     #[default]
@@ -1548,6 +2637,32 @@ pub enum TunMode {
     Tap = 2,
 }
 
+#[derive(Debug, Error)]
+#[error("Invalid TUN mode: {0}")]
+pub struct InvalidTunMode(String);
+
+impl FromStr for TunMode {
+    type Err = InvalidTunMode;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tun" => Ok(Self::Tun),
+            "tap" => Ok(Self::Tap),
+            _ => Err(InvalidTunMode(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for TunMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match &self {
+            TunMode::Tun => "tun",
+            TunMode::Tap => "tap",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 #[derive(Default, Debug, PartialEq, Clone, Serialize)]
 pub struct TunConfig {
     pub mode: TunMode,
@@ -1555,18 +2670,314 @@ pub struct TunConfig {
     pub owner: Option<String>,
 }
 
+impl TryFrom<TunSettings> for TunConfig {
+    type Error = NetworkStateError;
+
+    fn try_from(settings: TunSettings) -> Result<Self, Self::Error> {
+        let mode = TunMode::from_str(&settings.mode)
+            .map_err(|_| NetworkStateError::InvalidTunMode(settings.mode))?;
+
+        Ok(TunConfig {
+            mode,
+            group: settings.group,
+            owner: settings.owner,
+        })
+    }
+}
+
+impl From<TunConfig> for TunSettings {
+    fn from(config: TunConfig) -> Self {
+        TunSettings {
+            mode: config.mode.to_string(),
+            group: config.group,
+            owner: config.owner,
+        }
+    }
+}
+
+#[derive(Default, Debug, PartialEq, Clone, Serialize)]
+pub struct WireguardConfig {
+    pub private_key: Option<String>,
+    pub listen_port: Option<u32>,
+    pub fwmark: Option<u32>,
+    pub peers: Vec<WireguardPeer>,
+}
+
+#[derive(Default, Debug, PartialEq, Clone, Serialize)]
+pub struct WireguardPeer {
+    pub public_key: String,
+    pub preshared_key: Option<String>,
+    pub endpoint: Option<String>,
+    pub allowed_ips: Vec<IpInet>,
+    pub persistent_keepalive: Option<u32>,
+}
+
+/// Determines whether the given string is a valid WireGuard key.
+///
+/// WireGuard keys are 32 bytes long and they are encoded using base64.
+fn is_wireguard_key(key: &str) -> bool {
+    key.len() == 44
+        && key.ends_with('=')
+        && key[..43]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '/')
+}
+
+fn wireguard_key(key: String) -> Result<String, NetworkStateError> {
+    if is_wireguard_key(&key) {
+        Ok(key)
+    } else {
+        Err(NetworkStateError::InvalidWireguardKey)
+    }
+}
+
+impl TryFrom<WireguardSettings> for WireguardConfig {
+    type Error = NetworkStateError;
+
+    fn try_from(settings: WireguardSettings) -> Result<Self, Self::Error> {
+        let peers = settings
+            .peers
+            .into_iter()
+            .map(WireguardPeer::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(WireguardConfig {
+            private_key: settings.private_key.map(wireguard_key).transpose()?,
+            listen_port: settings.listen_port,
+            fwmark: settings.fwmark,
+            peers,
+        })
+    }
+}
+
+impl From<WireguardConfig> for WireguardSettings {
+    fn from(config: WireguardConfig) -> Self {
+        WireguardSettings {
+            private_key: config.private_key,
+            listen_port: config.listen_port,
+            fwmark: config.fwmark,
+            peers: config.peers.into_iter().map(|p| p.into()).collect(),
+        }
+    }
+}
+
+impl TryFrom<WireguardPeerSettings> for WireguardPeer {
+    type Error = NetworkStateError;
+
+    fn try_from(settings: WireguardPeerSettings) -> Result<Self, Self::Error> {
+        Ok(WireguardPeer {
+            public_key: wireguard_key(settings.public_key)?,
+            preshared_key: settings.preshared_key.map(wireguard_key).transpose()?,
+            endpoint: settings.endpoint,
+            allowed_ips: settings.allowed_ips,
+            persistent_keepalive: settings.persistent_keepalive,
+        })
+    }
+}
+
+impl From<WireguardPeer> for WireguardPeerSettings {
+    fn from(peer: WireguardPeer) -> Self {
+        WireguardPeerSettings {
+            public_key: peer.public_key,
+            preshared_key: peer.preshared_key,
+            endpoint: peer.endpoint,
+            allowed_ips: peer.allowed_ips,
+            persistent_keepalive: peer.persistent_keepalive,
+        }
+    }
+}
+
+#[derive(Default, Debug, PartialEq, Clone, Serialize)]
+pub struct VrfConfig {
+    pub table: u32,
+}
+
+impl From<VrfSettings> for VrfConfig {
+    fn from(settings: VrfSettings) -> Self {
+        VrfConfig {
+            table: settings.table,
+        }
+    }
+}
+
+impl From<VrfConfig> for VrfSettings {
+    fn from(config: VrfConfig) -> Self {
+        VrfSettings {
+            table: config.table,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Default, Debug, PartialEq, Clone, Serialize)]
+pub struct OvsBridgeConfig {
+    pub stp: bool,
+    pub rstp: bool,
+    pub mcast_snooping: bool,
+    pub fail_mode: Option<OvsFailMode>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub enum OvsFailMode {
+    Secure,
+    Standalone,
+}
+
+#[derive(Debug, Error)]
+#[error("Invalid OVS fail mode: {0}")]
+pub struct InvalidOvsFailMode(String);
+
+impl FromStr for OvsFailMode {
+    type Err = InvalidOvsFailMode;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "secure" => Ok(Self::Secure),
+            "standalone" => Ok(Self::Standalone),
+            _ => Err(InvalidOvsFailMode(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for OvsFailMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match &self {
+            OvsFailMode::Secure => "secure",
+            OvsFailMode::Standalone => "standalone",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl TryFrom<OvsBridgeSettings> for OvsBridgeConfig {
+    type Error = NetworkStateError;
+
+    fn try_from(settings: OvsBridgeSettings) -> Result<Self, Self::Error> {
+        let fail_mode = match settings.fail_mode {
+            Some(mode) => Some(
+                OvsFailMode::from_str(&mode)
+                    .map_err(|_| NetworkStateError::InvalidOvsFailMode(mode))?,
+            ),
+            None => None,
+        };
+
+        Ok(OvsBridgeConfig {
+            stp: settings.stp.unwrap_or_default(),
+            rstp: settings.rstp.unwrap_or_default(),
+            mcast_snooping: settings.mcast_snooping.unwrap_or_default(),
+            fail_mode,
+        })
+    }
+}
+
+impl From<OvsBridgeConfig> for OvsBridgeSettings {
+    fn from(config: OvsBridgeConfig) -> Self {
+        OvsBridgeSettings {
+            stp: Some(config.stp),
+            rstp: Some(config.rstp),
+            mcast_snooping: Some(config.mcast_snooping),
+            fail_mode: config.fail_mode.map(|m| m.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Default, Debug, PartialEq, Clone, Serialize)]
+pub struct OvsPortConfig {
+    pub tag: Option<u32>,
+}
+
+impl From<OvsPortSettings> for OvsPortConfig {
+    fn from(settings: OvsPortSettings) -> Self {
+        OvsPortConfig { tag: settings.tag }
+    }
+}
+
+impl From<OvsPortConfig> for OvsPortSettings {
+    fn from(config: OvsPortConfig) -> Self {
+        OvsPortSettings {
+            tag: config.tag,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Default, Debug, PartialEq, Clone, Serialize)]
+pub struct OvsInterfaceConfig {
+    pub interface_type: OvsInterfaceType,
+}
+
+#[derive(Default, Debug, PartialEq, Clone, Serialize)]
+pub enum OvsInterfaceType {
+    #[default]
+    Internal,
+    System,
+}
+
+#[derive(Debug, Error)]
+#[error("Invalid OVS interface type: {0}")]
+pub struct InvalidOvsInterfaceType(String);
+
+impl FromStr for OvsInterfaceType {
+    type Err = InvalidOvsInterfaceType;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "internal" => Ok(Self::Internal),
+            "system" => Ok(Self::System),
+            _ => Err(InvalidOvsInterfaceType(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for OvsInterfaceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match &self {
+            OvsInterfaceType::Internal => "internal",
+            OvsInterfaceType::System => "system",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl TryFrom<OvsInterfaceSettings> for OvsInterfaceConfig {
+    type Error = NetworkStateError;
+
+    fn try_from(settings: OvsInterfaceSettings) -> Result<Self, Self::Error> {
+        let interface_type = match settings.interface_type {
+            Some(type_) => OvsInterfaceType::from_str(&type_)
+                .map_err(|_| NetworkStateError::InvalidOvsInterfaceType(type_))?,
+            None => Default::default(),
+        };
+
+        Ok(OvsInterfaceConfig { interface_type })
+    }
+}
+
+impl From<OvsInterfaceConfig> for OvsInterfaceSettings {
+    fn from(config: OvsInterfaceConfig) -> Self {
+        OvsInterfaceSettings {
+            interface_type: Some(config.interface_type.to_string()),
+        }
+    }
+}
+
 /// Represents a network change.
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub enum NetworkChange {
     /// A new device has been added.
-    DeviceAdded(Device),
+    DeviceAdded(Box<Device>),
     /// A device has been removed.
     DeviceRemoved(String),
     /// The device has been updated. The String corresponds to the
     /// original device name, which is especially useful if the
     /// device gets renamed.
-    DeviceUpdated(String, Device),
+    DeviceUpdated(String, Box<Device>),
+    /// The applied changes were not confirmed in time, so they have been rolled back.
+    ChangesRolledBack,
+    /// The global configuration (hostname, DNS, etc.) has changed.
+    GlobalConfigChanged(GlobalConfig),
 }
 
 #[derive(Default, Debug, PartialEq, Clone, Serialize)]
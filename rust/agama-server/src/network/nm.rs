@@ -29,10 +29,12 @@ mod builder;
 mod client;
 mod dbus;
 mod error;
+mod keyfile;
 mod model;
 mod proxies;
 mod watcher;
 
 pub use adapter::NetworkManagerAdapter;
 pub use client::NetworkManagerClient;
+pub use keyfile::KeyfileAdapter;
 pub use watcher::NetworkManagerWatcher;
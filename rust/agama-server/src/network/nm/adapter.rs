@@ -20,7 +20,7 @@
 
 use crate::network::{
     adapter::Watcher,
-    model::{Connection, NetworkState, StateConfig},
+    model::{Connection, GlobalConfig, NetworkState, StateConfig},
     nm::{NetworkManagerClient, NetworkManagerWatcher},
     Adapter, NetworkAdapterError,
 };
@@ -28,12 +28,29 @@ use agama_lib::error::ServiceError;
 use async_trait::async_trait;
 use core::time;
 use log;
-use std::thread;
+use std::{sync::Mutex, thread, time::Duration};
+use zbus::zvariant::OwnedObjectPath;
+
+/// Extra seconds NetworkManager waits before rolling back a checkpoint by itself.
+const CHECKPOINT_GRACE_PERIOD: u32 = 10;
+
+/// Changes waiting for confirmation.
+struct PendingChanges {
+    /// NetworkManager checkpoint to roll back to.
+    checkpoint: OwnedObjectPath,
+    /// Global configuration before the changes. NetworkManager checkpoints do not cover the
+    /// global DNS nor the hostname, so it is restored apart.
+    old_global: GlobalConfig,
+    /// Global configuration written by the changes.
+    new_global: GlobalConfig,
+}
 
 /// An adapter for NetworkManager
 pub struct NetworkManagerAdapter<'a> {
     client: NetworkManagerClient<'a>,
     connection: zbus::Connection,
+    /// Changes waiting for confirmation.
+    pending: Mutex<Option<PendingChanges>>,
 }
 
 impl<'a> NetworkManagerAdapter<'a> {
@@ -41,7 +58,11 @@ impl<'a> NetworkManagerAdapter<'a> {
     pub async fn from_system() -> Result<NetworkManagerAdapter<'a>, ServiceError> {
         let connection = zbus::Connection::system().await?;
         let client = NetworkManagerClient::new(connection.clone()).await?;
-        Ok(Self { client, connection })
+        Ok(Self {
+            client,
+            connection,
+            pending: Mutex::new(None),
+        })
     }
 }
 
@@ -60,6 +81,14 @@ impl<'a> Adapter for NetworkManagerAdapter<'a> {
             state.general_state = general_state.clone();
         }
 
+        if config.global {
+            state.global = self
+                .client
+                .global_config()
+                .await
+                .map_err(NetworkAdapterError::Read)?;
+        }
+
         if config.devices {
             state.devices = self
                 .client
@@ -102,12 +131,94 @@ impl<'a> Adapter for NetworkManagerAdapter<'a> {
     ///
     /// * `network`: network model.
     async fn write(&self, network: &NetworkState) -> Result<(), NetworkAdapterError> {
-        let old_state = self.read(StateConfig::default()).await?;
+        if self.pending.lock().unwrap().is_some() {
+            return Err(NetworkAdapterError::PendingChanges);
+        }
+
         let checkpoint = self
             .client
-            .create_checkpoint()
+            .create_checkpoint(0)
             .await
             .map_err(NetworkAdapterError::Checkpoint)?;
+        self.write_changes(network, &checkpoint).await?;
+        self.client
+            .destroy_checkpoint(&checkpoint.as_ref())
+            .await
+            .map_err(NetworkAdapterError::Checkpoint)?;
+        Ok(())
+    }
+
+    /// Writes the connections to NetworkManager keeping the checkpoint until the changes are
+    /// confirmed or rolled back.
+    ///
+    /// The checkpoint is created with a rollback timeout slightly longer than `timeout`, so
+    /// NetworkManager undoes the changes by itself if Agama does not handle the checkpoint
+    /// (although, in that case, the global configuration is not restored).
+    ///
+    /// * `network`: network model.
+    /// * `timeout`: time to wait for a confirmation.
+    async fn write_with_checkpoint(
+        &self,
+        network: &NetworkState,
+        timeout: Duration,
+    ) -> Result<(), NetworkAdapterError> {
+        if self.pending.lock().unwrap().is_some() {
+            return Err(NetworkAdapterError::PendingChanges);
+        }
+
+        let rollback_timeout = u32::try_from(timeout.as_secs())
+            .unwrap_or(u32::MAX)
+            .saturating_add(CHECKPOINT_GRACE_PERIOD);
+        let checkpoint = self
+            .client
+            .create_checkpoint(rollback_timeout)
+            .await
+            .map_err(NetworkAdapterError::Checkpoint)?;
+        let old_global = self.write_changes(network, &checkpoint).await?;
+        *self.pending.lock().unwrap() = Some(PendingChanges {
+            checkpoint,
+            old_global,
+            new_global: network.global.clone(),
+        });
+        Ok(())
+    }
+
+    async fn confirm(&self) -> Result<(), NetworkAdapterError> {
+        let pending = self.take_pending()?;
+        self.client
+            .destroy_checkpoint(&pending.checkpoint.as_ref())
+            .await
+            .map_err(NetworkAdapterError::Checkpoint)
+    }
+
+    async fn rollback(&self) -> Result<(), NetworkAdapterError> {
+        let pending = self.take_pending()?;
+        self.rollback_changes(
+            &pending.checkpoint,
+            &pending.new_global,
+            &pending.old_global,
+        )
+        .await
+    }
+
+    fn watcher(&self) -> Option<Box<dyn Watcher + Send>> {
+        Some(Box::new(NetworkManagerWatcher::new(&self.connection)))
+    }
+}
+
+impl<'a> NetworkManagerAdapter<'a> {
+    /// Writes the changes to NetworkManager, rolling back to the given checkpoint on failure.
+    ///
+    /// It returns the global configuration before the changes, so it can be restored later.
+    ///
+    /// * `network`: network model.
+    /// * `checkpoint`: checkpoint to roll back to.
+    async fn write_changes(
+        &self,
+        network: &NetworkState,
+        checkpoint: &OwnedObjectPath,
+    ) -> Result<GlobalConfig, NetworkAdapterError> {
+        let old_state = self.read(StateConfig::default()).await?;
 
         log::info!("Updating the general state {:?}", &network.general_state);
 
@@ -130,6 +241,26 @@ impl<'a> Adapter for NetworkManagerAdapter<'a> {
             return Err(NetworkAdapterError::Write(e));
         }
 
+        log::info!("Updating the global configuration {:?}", &network.global);
+
+        let result = self
+            .client
+            .update_global_config(&old_state.global, &network.global)
+            .await;
+
+        if let Err(e) = result {
+            // part of the global configuration might be written already
+            self.rollback_changes(checkpoint, &network.global, &old_state.global)
+                .await?;
+
+            log::error!(
+                "Could not update the global configuration {:?}: {}",
+                &network.global,
+                &e
+            );
+            return Err(NetworkAdapterError::Write(e));
+        }
+
         for conn in ordered_connections(network) {
             if let Some(old_conn) = old_state.get_connection_by_uuid(conn.uuid) {
                 if old_conn == conn {
@@ -155,24 +286,45 @@ impl<'a> Adapter for NetworkManagerAdapter<'a> {
             };
 
             if let Err(e) = result {
-                self.client
-                    .rollback_checkpoint(&checkpoint.as_ref())
-                    .await
-                    .map_err(NetworkAdapterError::Checkpoint)?;
+                self.rollback_changes(checkpoint, &network.global, &old_state.global)
+                    .await?;
                 log::error!("Could not process the connection {}: {}", conn.id, &e);
                 return Err(NetworkAdapterError::Write(e));
             }
         }
 
+        Ok(old_state.global)
+    }
+
+    /// Rolls back to the given checkpoint and restores the global configuration, which is not
+    /// covered by the checkpoint.
+    ///
+    /// * `checkpoint`: checkpoint to roll back to.
+    /// * `current`: global configuration written by the changes.
+    /// * `previous`: global configuration to restore.
+    async fn rollback_changes(
+        &self,
+        checkpoint: &OwnedObjectPath,
+        current: &GlobalConfig,
+        previous: &GlobalConfig,
+    ) -> Result<(), NetworkAdapterError> {
         self.client
-            .destroy_checkpoint(&checkpoint.as_ref())
+            .rollback_checkpoint(&checkpoint.as_ref())
             .await
             .map_err(NetworkAdapterError::Checkpoint)?;
-        Ok(())
+        self.client
+            .update_global_config(current, previous)
+            .await
+            .map_err(NetworkAdapterError::Write)
     }
 
-    fn watcher(&self) -> Option<Box<dyn Watcher + Send>> {
-        Some(Box::new(NetworkManagerWatcher::new(&self.connection)))
+    /// Returns the pending changes, if any, forgetting about them.
+    fn take_pending(&self) -> Result<PendingChanges, NetworkAdapterError> {
+        self.pending
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or(NetworkAdapterError::NoPendingChanges)
     }
 }
 
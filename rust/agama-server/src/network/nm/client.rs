@@ -24,14 +24,16 @@ use std::collections::HashMap;
 use super::builder::DeviceFromProxyBuilder;
 use super::dbus::{
     cleanup_dbus_connection, connection_from_dbus, connection_to_dbus, controller_from_dbus,
-    merge_dbus_connections,
+    global_dns_from_dbus, global_dns_to_dbus, merge_dbus_connections,
 };
 use super::model::NmDeviceType;
 use super::proxies::{
-    AccessPointProxy, ActiveConnectionProxy, ConnectionProxy, DeviceProxy, NetworkManagerProxy,
-    SettingsProxy, WirelessProxy,
+    AccessPointProxy, ActiveConnectionProxy, ConnectionProxy, DeviceProxy, Hostname1Proxy,
+    NetworkManagerProxy, SettingsProxy, WirelessProxy,
+};
+use crate::network::model::{
+    AccessPoint, Connection, Device, GeneralState, GlobalConfig, HostnameMode,
 };
-use crate::network::model::{AccessPoint, Connection, Device, GeneralState};
 use agama_lib::error::ServiceError;
 use agama_lib::network::types::{DeviceType, SSID};
 use log;
@@ -66,8 +68,6 @@ impl<'a> NetworkManagerClient<'a> {
         let hostname = proxy.hostname().await?;
         let wireless_enabled = self.nm_proxy.wireless_enabled().await?;
         let networking_enabled = self.nm_proxy.networking_enabled().await?;
-        // TODO:: Allow to set global DNS configuration
-        // let global_dns_configuration = self.nm_proxy.global_dns_configuration().await?;
         // Fixme: save as NMConnectivityState enum
         let connectivity = self.nm_proxy.connectivity().await? == 4;
 
@@ -92,6 +92,78 @@ impl<'a> NetworkManagerClient<'a> {
         Ok(())
     }
 
+    /// Returns the global configuration (hostname and DNS settings).
+    ///
+    /// The static hostname takes precedence. The transient one is only reported when there is
+    /// no static hostname.
+    pub async fn global_config(&self) -> Result<GlobalConfig, ServiceError> {
+        let dns = self.nm_proxy.global_dns_configuration().await?;
+        let mut config = global_dns_from_dbus(&dns).unwrap_or_else(|| {
+            log::warn!("Could not read the global DNS configuration");
+            GlobalConfig::default()
+        });
+
+        let settings = SettingsProxy::new(&self.connection).await?;
+        let static_hostname = settings.hostname().await?;
+        if !static_hostname.is_empty() {
+            config.hostname = Some(static_hostname);
+            return Ok(config);
+        }
+
+        match self.transient_hostname().await {
+            Ok(hostname) if !hostname.is_empty() => {
+                config.hostname = Some(hostname);
+                config.hostname_mode = HostnameMode::Transient;
+            }
+            Ok(_) => {}
+            Err(e) => log::warn!("Could not read the transient hostname: {}", e),
+        }
+        Ok(config)
+    }
+
+    /// Updates the global configuration.
+    ///
+    /// Only the settings which changed are written. The static hostname is set through
+    /// NetworkManager, while the transient one is set using the hostname service.
+    ///
+    /// * `old`: current global configuration.
+    /// * `config`: global configuration to write.
+    pub async fn update_global_config(
+        &self,
+        old: &GlobalConfig,
+        config: &GlobalConfig,
+    ) -> Result<(), ServiceError> {
+        if !old.same_dns(config) {
+            self.nm_proxy
+                .set_global_dns_configuration(global_dns_to_dbus(config))
+                .await?;
+        }
+
+        let Some(hostname) = &config.hostname else {
+            return Ok(());
+        };
+        if old.hostname.as_ref() == Some(hostname) && old.hostname_mode == config.hostname_mode {
+            return Ok(());
+        }
+
+        match config.hostname_mode {
+            HostnameMode::Static => {
+                let proxy = SettingsProxy::new(&self.connection).await?;
+                proxy.save_hostname(hostname).await?;
+            }
+            HostnameMode::Transient => {
+                let proxy = Hostname1Proxy::new(&self.connection).await?;
+                proxy.set_hostname(hostname, false).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn transient_hostname(&self) -> Result<String, ServiceError> {
+        let proxy = Hostname1Proxy::new(&self.connection).await?;
+        Ok(proxy.hostname().await?)
+    }
+
     /// Returns the list of access points.
     pub async fn request_scan(&self) -> Result<(), ServiceError> {
         for path in &self.nm_proxy.get_devices().await? {
@@ -272,8 +344,17 @@ impl<'a> NetworkManagerClient<'a> {
     }
 
     /// Creates a checkpoint.
-    pub async fn create_checkpoint(&self) -> Result<OwnedObjectPath, ServiceError> {
-        let path = self.nm_proxy.checkpoint_create(&[], 0, 0).await?;
+    ///
+    /// * `rollback_timeout`: seconds after which NetworkManager rolls back automatically (0 means
+    ///   no automatic rollback).
+    pub async fn create_checkpoint(
+        &self,
+        rollback_timeout: u32,
+    ) -> Result<OwnedObjectPath, ServiceError> {
+        let path = self
+            .nm_proxy
+            .checkpoint_create(&[], rollback_timeout, 0)
+            .await?;
         Ok(path)
     }
 
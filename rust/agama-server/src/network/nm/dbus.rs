@@ -45,6 +45,11 @@ const BRIDGE_KEY: &str = "bridge";
 const BRIDGE_PORT_KEY: &str = "bridge-port";
 const INFINIBAND_KEY: &str = "infiniband";
 const TUN_KEY: &str = "tun";
+const WIREGUARD_KEY: &str = "wireguard";
+const VRF_KEY: &str = "vrf";
+const OVS_BRIDGE_KEY: &str = "ovs-bridge";
+const OVS_PORT_KEY: &str = "ovs-port";
+const OVS_INTERFACE_KEY: &str = "ovs-interface";
 const IEEE_8021X_KEY: &str = "802-1x";
 
 /// Converts a connection struct into a HashMap that can be sent over D-Bus.
@@ -68,6 +73,9 @@ pub fn connection_to_dbus<'a>(
         let slave_type = match controller.config {
             ConnectionConfig::Bond(_) => BOND_KEY,
             ConnectionConfig::Bridge(_) => BRIDGE_KEY,
+            ConnectionConfig::Vrf(_) => VRF_KEY,
+            ConnectionConfig::OvsBridge(_) => OVS_BRIDGE_KEY,
+            ConnectionConfig::OvsPort(_) => OVS_PORT_KEY,
             _ => {
                 log::error!("Controller {} has unhandled config type", controller.id);
                 ""
@@ -88,8 +96,14 @@ pub fn connection_to_dbus<'a>(
         connection_dbus.insert("zone", zone.into());
     }
 
-    result.insert("ipv4", ip_config_to_ipv4_dbus(&conn.ip_config));
-    result.insert("ipv6", ip_config_to_ipv6_dbus(&conn.ip_config));
+    // OVS bridges and ports do not have an IP configuration (it goes to the OVS interfaces)
+    if !matches!(
+        conn.config,
+        ConnectionConfig::OvsBridge(_) | ConnectionConfig::OvsPort(_)
+    ) {
+        result.insert("ipv4", ip_config_to_ipv4_dbus(&conn.ip_config));
+        result.insert("ipv6", ip_config_to_ipv6_dbus(&conn.ip_config));
+    }
     result.insert("match", match_config_to_dbus(&conn.match_config));
 
     if conn.is_ethernet() {
@@ -148,6 +162,41 @@ pub fn connection_to_dbus<'a>(
             connection_dbus.insert("type", TUN_KEY.into());
             result.insert(TUN_KEY, tun_config_to_dbus(tun));
         }
+        ConnectionConfig::Wireguard(wireguard) => {
+            connection_dbus.insert("type", WIREGUARD_KEY.into());
+            if !connection_dbus.contains_key("interface-name") {
+                connection_dbus.insert("interface-name", conn.id.as_str().into());
+            }
+            result.insert(WIREGUARD_KEY, wireguard_config_to_dbus(wireguard));
+        }
+        ConnectionConfig::Vrf(vrf) => {
+            connection_dbus.insert("type", VRF_KEY.into());
+            if !connection_dbus.contains_key("interface-name") {
+                connection_dbus.insert("interface-name", conn.id.as_str().into());
+            }
+            result.insert(VRF_KEY, HashMap::from([("table", vrf.table.into())]));
+        }
+        ConnectionConfig::OvsBridge(ovs_bridge) => {
+            connection_dbus.insert("type", OVS_BRIDGE_KEY.into());
+            if !connection_dbus.contains_key("interface-name") {
+                connection_dbus.insert("interface-name", conn.id.as_str().into());
+            }
+            result.insert(OVS_BRIDGE_KEY, ovs_bridge_config_to_dbus(ovs_bridge));
+        }
+        ConnectionConfig::OvsPort(ovs_port) => {
+            connection_dbus.insert("type", OVS_PORT_KEY.into());
+            if !connection_dbus.contains_key("interface-name") {
+                connection_dbus.insert("interface-name", conn.id.as_str().into());
+            }
+            result.insert(OVS_PORT_KEY, ovs_port_config_to_dbus(ovs_port));
+        }
+        ConnectionConfig::OvsInterface(ovs_interface) => {
+            connection_dbus.insert("type", OVS_INTERFACE_KEY.into());
+            result.insert(
+                OVS_INTERFACE_KEY,
+                HashMap::from([("type", ovs_interface.interface_type.to_string().into())]),
+            );
+        }
         _ => {}
     }
 
@@ -210,6 +259,31 @@ pub fn connection_from_dbus(conn: OwnedNestedHash) -> Option<Connection> {
         return Some(connection);
     }
 
+    if let Some(wireguard_config) = wireguard_config_from_dbus(&conn) {
+        connection.config = ConnectionConfig::Wireguard(wireguard_config);
+        return Some(connection);
+    }
+
+    if let Some(vrf_config) = vrf_config_from_dbus(&conn) {
+        connection.config = ConnectionConfig::Vrf(vrf_config);
+        return Some(connection);
+    }
+
+    if let Some(ovs_bridge_config) = ovs_bridge_config_from_dbus(&conn) {
+        connection.config = ConnectionConfig::OvsBridge(ovs_bridge_config);
+        return Some(connection);
+    }
+
+    if let Some(ovs_port_config) = ovs_port_config_from_dbus(&conn) {
+        connection.config = ConnectionConfig::OvsPort(ovs_port_config);
+        return Some(connection);
+    }
+
+    if let Some(ovs_interface_config) = ovs_interface_config_from_dbus(&conn) {
+        connection.config = ConnectionConfig::OvsInterface(ovs_interface_config);
+        return Some(connection);
+    }
+
     if conn.contains_key(DUMMY_KEY) {
         connection.config = ConnectionConfig::Dummy;
         return Some(connection);
@@ -301,6 +375,80 @@ pub fn controller_from_dbus(conn: &OwnedNestedHash) -> Option<String> {
     Some(master.to_string())
 }
 
+/// Converts the global DNS settings into NetworkManager's `GlobalDnsConfiguration` format.
+///
+/// The name servers are set for the default domain (`*`). An empty hash removes the global
+/// DNS configuration.
+///
+/// * `config`: global configuration.
+pub fn global_dns_to_dbus(config: &GlobalConfig) -> HashMap<&str, zvariant::Value<'_>> {
+    let mut dns: HashMap<&str, zvariant::Value> = HashMap::new();
+    if config.nameservers.is_empty()
+        && config.dns_searchlist.is_empty()
+        && config.dns_options.is_empty()
+    {
+        return dns;
+    }
+
+    let servers: Vec<String> = config.nameservers.iter().map(|s| s.to_string()).collect();
+    let default_domain: HashMap<&str, Value> = HashMap::from([("servers", Value::new(servers))]);
+    let domains: HashMap<&str, Value> = HashMap::from([("*", Value::new(default_domain))]);
+
+    dns.insert("searches", config.dns_searchlist.clone().into());
+    dns.insert("options", config.dns_options.clone().into());
+    dns.insert("domains", domains.into());
+    dns
+}
+
+/// Reads the global DNS settings from NetworkManager's `GlobalDnsConfiguration`.
+///
+/// It returns a [GlobalConfig] which only contains the DNS settings.
+///
+/// * `dns`: global DNS configuration.
+pub fn global_dns_from_dbus(dns: &HashMap<String, OwnedValue>) -> Option<GlobalConfig> {
+    let mut config = GlobalConfig::default();
+
+    if let Some(searches) = dns.get("searches") {
+        config.dns_searchlist = strings_from_dbus(searches)?;
+    }
+
+    if let Some(options) = dns.get("options") {
+        config.dns_options = strings_from_dbus(options)?;
+    }
+
+    if let Some(domains) = dns.get("domains") {
+        let domains = domains.downcast_ref::<zvariant::Dict>()?;
+        let domains = <HashMap<String, zvariant::Value<'_>>>::try_from(domains.clone()).ok()?;
+        if let Some(default_domain) = domains.get("*") {
+            let default_domain = default_domain.downcast_ref::<zvariant::Dict>()?;
+            let default_domain =
+                <HashMap<String, zvariant::Value<'_>>>::try_from(default_domain.clone()).ok()?;
+            if let Some(servers) = default_domain.get("servers") {
+                let servers: &zvariant::Array = servers.downcast_ref()?;
+                for server in servers.get() {
+                    let server: &str = server.downcast_ref()?;
+                    match server.parse() {
+                        Ok(server) => config.nameservers.push(server),
+                        Err(_) => log::warn!("Ignoring invalid global name server: {}", server),
+                    }
+                }
+            }
+        }
+    }
+
+    Some(config)
+}
+
+fn strings_from_dbus(value: &OwnedValue) -> Option<Vec<String>> {
+    let strings = value
+        .downcast_ref::<zbus::zvariant::Array>()?
+        .iter()
+        .flat_map(|x| x.downcast_ref::<str>())
+        .map(|x| x.to_string())
+        .collect();
+    Some(strings)
+}
+
 fn ip_config_to_ipv4_dbus(ip_config: &IpConfig) -> HashMap<&str, zvariant::Value> {
     let addresses: Vec<HashMap<&str, Value>> = ip_config
         .addresses
@@ -345,6 +493,15 @@ fn ip_config_to_ipv4_dbus(ip_config: &IpConfig) -> HashMap<&str, zvariant::Value
     if let Some(gateway) = &ip_config.gateway4 {
         ipv4_dbus.insert("gateway", gateway.to_string().into());
     }
+
+    dhcp_config_to_dbus(&ip_config.dhcp4, "dhcp-client-id", &mut ipv4_dbus);
+    let fqdn = ip_config.dhcp4.fqdn.as_deref().unwrap_or_default();
+    ipv4_dbus.insert("dhcp-fqdn", fqdn.into());
+    ipv4_dbus.insert(
+        "route-metric",
+        route_metric_to_dbus(ip_config.route_metric4),
+    );
+    ipv4_dbus.insert("may-fail", ip_config.may_fail4.unwrap_or(true).into());
     ipv4_dbus
 }
 
@@ -392,9 +549,145 @@ fn ip_config_to_ipv6_dbus(ip_config: &IpConfig) -> HashMap<&str, zvariant::Value
     if let Some(gateway) = &ip_config.gateway6 {
         ipv6_dbus.insert("gateway", gateway.to_string().into());
     }
+
+    dhcp_config_to_dbus(&ip_config.dhcp6, "dhcp-duid", &mut ipv6_dbus);
+    ipv6_dbus.insert(
+        "route-metric",
+        route_metric_to_dbus(ip_config.route_metric6),
+    );
+    ipv6_dbus.insert("may-fail", ip_config.may_fail6.unwrap_or(true).into());
+    ipv6_dbus.insert(
+        "addr-gen-mode",
+        addr_gen_mode_to_dbus(ip_config.addr_gen_mode).into(),
+    );
+    ipv6_dbus.insert(
+        "ip6-privacy",
+        ip6_privacy_to_dbus(ip_config.ip6_privacy).into(),
+    );
     ipv6_dbus
 }
 
+/// Adds the DHCP options which are common to both address families.
+///
+/// The unset options are written with NetworkManager's default values, so the ones from a
+/// previous configuration are not kept when the connection is updated.
+///
+/// * `dhcp`: DHCP configuration.
+/// * `client_id_key`: name of the setting for the client identifier.
+/// * `ip_dbus`: IPv4 or IPv6 settings.
+fn dhcp_config_to_dbus<'a>(
+    dhcp: &'a DhcpConfig,
+    client_id_key: &'a str,
+    ip_dbus: &mut HashMap<&'a str, zvariant::Value<'a>>,
+) {
+    let client_id = dhcp.client_id.as_deref().unwrap_or_default();
+    ip_dbus.insert(client_id_key, client_id.into());
+    let send_hostname = dhcp.send_hostname.unwrap_or(true);
+    ip_dbus.insert("dhcp-send-hostname", send_hostname.into());
+    let hostname = dhcp.hostname.as_deref().unwrap_or_default();
+    ip_dbus.insert("dhcp-hostname", hostname.into());
+    let timeout = dhcp
+        .timeout
+        .map_or(0, |t| i32::try_from(t).unwrap_or(i32::MAX));
+    ip_dbus.insert("dhcp-timeout", timeout.into());
+}
+
+/// Reads the DHCP options which are common to both address families.
+///
+/// NetworkManager reports the default values (e.g., sending the hostname or a timeout of 0),
+/// so they are ignored.
+///
+/// * `ip`: IPv4 or IPv6 settings.
+/// * `client_id_key`: name of the setting for the client identifier.
+fn dhcp_config_from_dbus(
+    ip: &HashMap<String, OwnedValue>,
+    client_id_key: &str,
+) -> Option<DhcpConfig> {
+    let mut dhcp = DhcpConfig::default();
+
+    if let Some(client_id) = ip.get(client_id_key) {
+        let client_id: &str = client_id.downcast_ref()?;
+        dhcp.client_id = (!client_id.is_empty()).then(|| client_id.to_string());
+    }
+
+    if let Some(send_hostname) = ip.get("dhcp-send-hostname") {
+        let send_hostname: bool = *send_hostname.downcast_ref()?;
+        dhcp.send_hostname = (!send_hostname).then_some(false);
+    }
+
+    if let Some(hostname) = ip.get("dhcp-hostname") {
+        let hostname: &str = hostname.downcast_ref()?;
+        dhcp.hostname = (!hostname.is_empty()).then(|| hostname.to_string());
+    }
+
+    if let Some(fqdn) = ip.get("dhcp-fqdn") {
+        let fqdn: &str = fqdn.downcast_ref()?;
+        dhcp.fqdn = (!fqdn.is_empty()).then(|| fqdn.to_string());
+    }
+
+    if let Some(timeout) = ip.get("dhcp-timeout") {
+        let timeout: i32 = *timeout.downcast_ref()?;
+        dhcp.timeout = u32::try_from(timeout).ok().filter(|t| *t > 0);
+    }
+
+    Some(dhcp)
+}
+
+/// Converts the route metric. If it is not set, the default value (-1) is used.
+fn route_metric_to_dbus<'a>(metric: Option<u32>) -> zvariant::Value<'a> {
+    metric.map_or(-1, i64::from).into()
+}
+
+/// Converts the route metric, ignoring the default value (-1).
+fn route_metric_from_dbus(metric: &OwnedValue) -> Option<u32> {
+    let metric: i64 = *metric.downcast_ref()?;
+    u32::try_from(metric).ok()
+}
+
+/// Converts the "may-fail" setting, ignoring the default value (true).
+fn may_fail_from_dbus(may_fail: &OwnedValue) -> Option<bool> {
+    let may_fail: bool = *may_fail.downcast_ref()?;
+    (!may_fail).then_some(false)
+}
+
+/// Converts the address generation mode. If it is not set, the "default" mode (3) is used.
+fn addr_gen_mode_to_dbus(mode: Option<Ipv6AddrGenMode>) -> i32 {
+    match mode {
+        Some(Ipv6AddrGenMode::Eui64) => 0,
+        Some(Ipv6AddrGenMode::StablePrivacy) => 1,
+        None => 3,
+    }
+}
+
+/// Converts NetworkManager's address generation mode. The "default" modes are ignored.
+fn addr_gen_mode_from_dbus(mode: i32) -> Option<Ipv6AddrGenMode> {
+    match mode {
+        0 => Some(Ipv6AddrGenMode::Eui64),
+        1 => Some(Ipv6AddrGenMode::StablePrivacy),
+        _ => None,
+    }
+}
+
+/// Converts the privacy extensions mode. If it is not set, the "unknown" mode (-1) is used.
+fn ip6_privacy_to_dbus(privacy: Option<Ipv6Privacy>) -> i32 {
+    match privacy {
+        Some(Ipv6Privacy::Disabled) => 0,
+        Some(Ipv6Privacy::PreferPublic) => 1,
+        Some(Ipv6Privacy::PreferTemporary) => 2,
+        None => -1,
+    }
+}
+
+/// Converts NetworkManager's privacy extensions mode. The "unknown" mode (-1) is ignored.
+fn ip6_privacy_from_dbus(privacy: i32) -> Option<Ipv6Privacy> {
+    match privacy {
+        0 => Some(Ipv6Privacy::Disabled),
+        1 => Some(Ipv6Privacy::PreferPublic),
+        2 => Some(Ipv6Privacy::PreferTemporary),
+        _ => None,
+    }
+}
+
 fn wireless_config_to_dbus(config: &'_ WirelessConfig) -> NestedHash<'_> {
     let mut wireless: HashMap<&str, zvariant::Value> = HashMap::from([
         ("mode", Value::new(config.mode.to_string())),
@@ -640,6 +933,191 @@ fn tun_config_from_dbus(conn: &OwnedNestedHash) -> Option<TunConfig> {
     Some(tun_config)
 }
 
+fn wireguard_config_to_dbus(config: &WireguardConfig) -> HashMap<&str, zvariant::Value<'_>> {
+    let peers: Vec<HashMap<&str, Value>> = config
+        .peers
+        .iter()
+        .map(|peer| {
+            let allowed_ips: Vec<String> =
+                peer.allowed_ips.iter().map(|ip| ip.to_string()).collect();
+            let mut peer_dbus: HashMap<&str, Value> = HashMap::from([
+                ("public-key", Value::new(peer.public_key.as_str())),
+                ("allowed-ips", Value::new(allowed_ips)),
+            ]);
+            if let Some(endpoint) = &peer.endpoint {
+                peer_dbus.insert("endpoint", endpoint.as_str().into());
+            }
+            if let Some(preshared_key) = &peer.preshared_key {
+                peer_dbus.insert("preshared-key", preshared_key.as_str().into());
+            }
+            if let Some(keepalive) = peer.persistent_keepalive {
+                peer_dbus.insert("persistent-keepalive", keepalive.into());
+            }
+            peer_dbus
+        })
+        .collect();
+
+    let mut wireguard: HashMap<&str, zvariant::Value> = HashMap::from([("peers", peers.into())]);
+
+    if let Some(private_key) = &config.private_key {
+        wireguard.insert("private-key", private_key.as_str().into());
+    }
+    if let Some(listen_port) = config.listen_port {
+        wireguard.insert("listen-port", listen_port.into());
+    }
+    if let Some(fwmark) = config.fwmark {
+        wireguard.insert("fwmark", fwmark.into());
+    }
+
+    wireguard
+}
+
+fn wireguard_config_from_dbus(conn: &OwnedNestedHash) -> Option<WireguardConfig> {
+    let wireguard = conn.get(WIREGUARD_KEY)?;
+
+    let mut wireguard_config = WireguardConfig::default();
+
+    // the private key is a secret, so NetworkManager does not include it in the settings
+    if let Some(private_key) = wireguard.get("private-key") {
+        wireguard_config.private_key = Some(private_key.downcast_ref::<str>()?.to_string());
+    }
+
+    if let Some(listen_port) = wireguard.get("listen-port") {
+        wireguard_config.listen_port = Some(*listen_port.downcast_ref::<u32>()?);
+    }
+
+    if let Some(fwmark) = wireguard.get("fwmark") {
+        wireguard_config.fwmark = Some(*fwmark.downcast_ref::<u32>()?);
+    }
+
+    if let Some(peers) = wireguard.get("peers") {
+        wireguard_config.peers = wireguard_peers_from_dbus(peers)?;
+    }
+
+    Some(wireguard_config)
+}
+
+fn wireguard_peers_from_dbus(peers: &OwnedValue) -> Option<Vec<WireguardPeer>> {
+    let peers = peers.downcast_ref::<zbus::zvariant::Array>()?;
+    let mut result: Vec<WireguardPeer> = vec![];
+    for peer in peers.get() {
+        let peer_dict = peer.downcast_ref::<zvariant::Dict>()?;
+        let peer_map = <HashMap<String, zvariant::Value<'_>>>::try_from(peer_dict.clone()).ok()?;
+        let public_key: &str = peer_map.get("public-key")?.downcast_ref()?;
+        let mut new_peer = WireguardPeer {
+            public_key: public_key.to_string(),
+            ..Default::default()
+        };
+        if let Some(endpoint) = peer_map.get("endpoint") {
+            new_peer.endpoint = Some(endpoint.downcast_ref::<str>()?.to_string());
+        }
+        if let Some(preshared_key) = peer_map.get("preshared-key") {
+            new_peer.preshared_key = Some(preshared_key.downcast_ref::<str>()?.to_string());
+        }
+        if let Some(keepalive) = peer_map.get("persistent-keepalive") {
+            new_peer.persistent_keepalive = Some(*keepalive.downcast_ref::<u32>()?);
+        }
+        if let Some(allowed_ips) = peer_map.get("allowed-ips") {
+            let allowed_ips: &zvariant::Array = allowed_ips.downcast_ref()?;
+            for ip in allowed_ips.get() {
+                let ip: &str = ip.downcast_ref()?;
+                match IpInet::from_str(ip) {
+                    Ok(ip) => new_peer.allowed_ips.push(ip),
+                    Err(_) => log::warn!("Ignoring invalid WireGuard allowed IP: {}", ip),
+                }
+            }
+        }
+        result.push(new_peer)
+    }
+    Some(result)
+}
+
+fn vrf_config_from_dbus(conn: &OwnedNestedHash) -> Option<VrfConfig> {
+    let vrf = conn.get(VRF_KEY)?;
+
+    let table = vrf.get("table")?;
+    Some(VrfConfig {
+        table: *table.downcast_ref::<u32>()?,
+    })
+}
+
+fn ovs_bridge_config_to_dbus(config: &OvsBridgeConfig) -> HashMap<&str, zvariant::Value<'_>> {
+    let mut ovs_bridge: HashMap<&str, zvariant::Value> = HashMap::from([
+        ("stp-enable", config.stp.into()),
+        ("rstp-enable", config.rstp.into()),
+        ("mcast-snooping-enable", config.mcast_snooping.into()),
+    ]);
+
+    if let Some(fail_mode) = &config.fail_mode {
+        ovs_bridge.insert("fail-mode", fail_mode.to_string().into());
+    }
+
+    ovs_bridge
+}
+
+fn ovs_bridge_config_from_dbus(conn: &OwnedNestedHash) -> Option<OvsBridgeConfig> {
+    let ovs_bridge = conn.get(OVS_BRIDGE_KEY)?;
+
+    let mut ovs_bridge_config = OvsBridgeConfig::default();
+
+    if let Some(stp) = ovs_bridge.get("stp-enable") {
+        ovs_bridge_config.stp = *stp.downcast_ref::<bool>()?;
+    }
+
+    if let Some(rstp) = ovs_bridge.get("rstp-enable") {
+        ovs_bridge_config.rstp = *rstp.downcast_ref::<bool>()?;
+    }
+
+    if let Some(mcast_snooping) = ovs_bridge.get("mcast-snooping-enable") {
+        ovs_bridge_config.mcast_snooping = *mcast_snooping.downcast_ref::<bool>()?;
+    }
+
+    if let Some(fail_mode) = ovs_bridge.get("fail-mode") {
+        ovs_bridge_config.fail_mode = OvsFailMode::from_str(fail_mode.downcast_ref::<str>()?).ok();
+    }
+
+    Some(ovs_bridge_config)
+}
+
+fn ovs_port_config_to_dbus(config: &OvsPortConfig) -> HashMap<&str, zvariant::Value<'_>> {
+    let mut ovs_port = HashMap::new();
+
+    if let Some(tag) = config.tag {
+        ovs_port.insert("tag", tag.into());
+    }
+
+    ovs_port
+}
+
+fn ovs_port_config_from_dbus(conn: &OwnedNestedHash) -> Option<OvsPortConfig> {
+    let ovs_port = conn.get(OVS_PORT_KEY)?;
+
+    let mut ovs_port_config = OvsPortConfig::default();
+
+    if let Some(tag) = ovs_port.get("tag") {
+        ovs_port_config.tag = Some(*tag.downcast_ref::<u32>()?);
+    }
+
+    Some(ovs_port_config)
+}
+
+fn ovs_interface_config_from_dbus(conn: &OwnedNestedHash) -> Option<OvsInterfaceConfig> {
+    // NetworkManager might add an "ovs-interface" section to the ethernet ports of an OVS port
+    let conn_type: &str = conn.get("connection")?.get("type")?.downcast_ref()?;
+    if conn_type != OVS_INTERFACE_KEY {
+        return None;
+    }
+
+    let mut ovs_interface_config = OvsInterfaceConfig::default();
+
+    if let Some(interface_type) = conn.get(OVS_INTERFACE_KEY)?.get("type") {
+        ovs_interface_config.interface_type =
+            OvsInterfaceType::from_str(interface_type.downcast_ref::<str>()?).ok()?;
+    }
+
+    Some(ovs_interface_config)
+}
+
 /// Converts a MatchConfig struct into a HashMap that can be sent over D-Bus.
 ///
 /// * `match_config`: MatchConfig to convert.
@@ -804,6 +1282,16 @@ fn ip_config_from_dbus(conn: &OwnedNestedHash) -> Option<IpConfig> {
             let gateway: &str = gateway.downcast_ref()?;
             ip_config.gateway4 = Some(gateway.parse().unwrap());
         }
+
+        ip_config.dhcp4 = dhcp_config_from_dbus(ipv4, "dhcp-client-id")?;
+
+        if let Some(metric) = ipv4.get("route-metric") {
+            ip_config.route_metric4 = route_metric_from_dbus(metric);
+        }
+
+        if let Some(may_fail) = ipv4.get("may-fail") {
+            ip_config.may_fail4 = may_fail_from_dbus(may_fail);
+        }
     }
 
     if let Some(ipv6) = conn.get("ipv6") {
@@ -846,6 +1334,24 @@ fn ip_config_from_dbus(conn: &OwnedNestedHash) -> Option<IpConfig> {
             let gateway: &str = gateway.downcast_ref()?;
             ip_config.gateway6 = Some(gateway.parse().unwrap());
         }
+
+        ip_config.dhcp6 = dhcp_config_from_dbus(ipv6, "dhcp-duid")?;
+
+        if let Some(metric) = ipv6.get("route-metric") {
+            ip_config.route_metric6 = route_metric_from_dbus(metric);
+        }
+
+        if let Some(may_fail) = ipv6.get("may-fail") {
+            ip_config.may_fail6 = may_fail_from_dbus(may_fail);
+        }
+
+        if let Some(mode) = ipv6.get("addr-gen-mode") {
+            ip_config.addr_gen_mode = addr_gen_mode_from_dbus(*mode.downcast_ref()?);
+        }
+
+        if let Some(privacy) = ipv6.get("ip6-privacy") {
+            ip_config.ip6_privacy = ip6_privacy_from_dbus(*privacy.downcast_ref()?);
+        }
     }
 
     Some(ip_config)
@@ -1259,14 +1765,17 @@ fn is_empty_value(value: &zvariant::Value) -> bool {
 #[cfg(test)]
 mod test {
     use super::{
-        connection_from_dbus, connection_to_dbus, merge_dbus_connections, NestedHash,
-        OwnedNestedHash,
+        connection_from_dbus, connection_to_dbus, global_dns_from_dbus, global_dns_to_dbus,
+        ip_config_from_dbus, merge_dbus_connections, NestedHash, OwnedNestedHash,
     };
     use crate::network::{
         model::*,
-        nm::dbus::{BOND_KEY, ETHERNET_KEY, INFINIBAND_KEY, WIRELESS_KEY, WIRELESS_SECURITY_KEY},
+        nm::dbus::{
+            BOND_KEY, ETHERNET_KEY, INFINIBAND_KEY, OVS_BRIDGE_KEY, OVS_INTERFACE_KEY,
+            OVS_PORT_KEY, VRF_KEY, WIREGUARD_KEY, WIRELESS_KEY, WIRELESS_SECURITY_KEY,
+        },
     };
-    use agama_lib::network::types::{BondMode, SSID};
+    use agama_lib::network::types::{BondMode, DeviceType, SSID};
     use cidr::IpInet;
     use std::{collections::HashMap, net::IpAddr, str::FromStr};
     use uuid::Uuid;
@@ -1561,6 +2070,161 @@ mod test {
         assert_eq!(infiniband.transport_mode, InfinibandTransportMode::Datagram);
     }
 
+    #[test]
+    fn test_connection_from_dbus_wireguard() {
+        let uuid = Uuid::new_v4().to_string();
+        let connection_section = HashMap::from([
+            ("id".to_string(), Value::new("wg0").to_owned()),
+            ("uuid".to_string(), Value::new(uuid).to_owned()),
+        ]);
+
+        let peer = HashMap::from([
+            (
+                "public-key",
+                Value::new("xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg="),
+            ),
+            ("endpoint", Value::new("vpn.example.net:51820")),
+            (
+                "allowed-ips",
+                Value::new(vec!["10.0.0.0/24".to_string(), "fd00::/64".to_string()]),
+            ),
+            ("persistent-keepalive", Value::new(25_u32)),
+        ]);
+        let wireguard_section = HashMap::from([
+            ("listen-port".to_string(), Value::new(51820_u32).to_owned()),
+            ("peers".to_string(), Value::new(vec![peer]).to_owned()),
+        ]);
+
+        let dbus_conn = HashMap::from([
+            ("connection".to_string(), connection_section),
+            (WIREGUARD_KEY.to_string(), wireguard_section),
+        ]);
+
+        let connection = connection_from_dbus(dbus_conn).unwrap();
+        let ConnectionConfig::Wireguard(wireguard) = &connection.config else {
+            panic!("Wrong connection type")
+        };
+        assert_eq!(wireguard.private_key, None);
+        assert_eq!(wireguard.listen_port, Some(51820));
+        let peer = &wireguard.peers[0];
+        assert_eq!(
+            peer.public_key,
+            "xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg="
+        );
+        assert_eq!(peer.endpoint, Some("vpn.example.net:51820".to_string()));
+        assert_eq!(peer.allowed_ips.len(), 2);
+        assert_eq!(peer.allowed_ips[0].to_string(), "10.0.0.0/24");
+        assert_eq!(peer.persistent_keepalive, Some(25));
+    }
+
+    #[test]
+    fn test_connection_from_dbus_vrf() {
+        let uuid = Uuid::new_v4().to_string();
+        let connection_section = HashMap::from([
+            ("id".to_string(), Value::new("vrf0").to_owned()),
+            ("uuid".to_string(), Value::new(uuid).to_owned()),
+        ]);
+        let vrf_section = HashMap::from([("table".to_string(), Value::new(100_u32).to_owned())]);
+
+        let dbus_conn = HashMap::from([
+            ("connection".to_string(), connection_section),
+            (VRF_KEY.to_string(), vrf_section),
+        ]);
+
+        let connection = connection_from_dbus(dbus_conn).unwrap();
+        assert_eq!(
+            connection.config,
+            ConnectionConfig::Vrf(VrfConfig { table: 100 })
+        );
+    }
+
+    #[test]
+    fn test_connection_from_dbus_ovs() {
+        let uuid = Uuid::new_v4().to_string();
+        let connection_section = HashMap::from([
+            ("id".to_string(), Value::new("ovs-br0").to_owned()),
+            ("uuid".to_string(), Value::new(uuid).to_owned()),
+        ]);
+        let ovs_bridge_section = HashMap::from([
+            ("rstp-enable".to_string(), Value::new(true).to_owned()),
+            ("fail-mode".to_string(), Value::new("secure").to_owned()),
+        ]);
+
+        let dbus_conn = HashMap::from([
+            ("connection".to_string(), connection_section),
+            (OVS_BRIDGE_KEY.to_string(), ovs_bridge_section),
+        ]);
+
+        let connection = connection_from_dbus(dbus_conn).unwrap();
+        let ConnectionConfig::OvsBridge(ovs_bridge) = &connection.config else {
+            panic!("Wrong connection type")
+        };
+        assert!(ovs_bridge.rstp);
+        assert!(!ovs_bridge.stp);
+        assert_eq!(ovs_bridge.fail_mode, Some(OvsFailMode::Secure));
+
+        let uuid = Uuid::new_v4().to_string();
+        let connection_section = HashMap::from([
+            ("id".to_string(), Value::new("ovs-port0").to_owned()),
+            ("uuid".to_string(), Value::new(uuid).to_owned()),
+        ]);
+        let ovs_port_section = HashMap::from([("tag".to_string(), Value::new(10_u32).to_owned())]);
+        let dbus_conn = HashMap::from([
+            ("connection".to_string(), connection_section),
+            (OVS_PORT_KEY.to_string(), ovs_port_section),
+        ]);
+
+        let connection = connection_from_dbus(dbus_conn).unwrap();
+        assert_eq!(
+            connection.config,
+            ConnectionConfig::OvsPort(OvsPortConfig { tag: Some(10) })
+        );
+    }
+
+    #[test]
+    fn test_connection_from_dbus_ovs_interface() {
+        let ovs_interface_section =
+            HashMap::from([("type".to_string(), Value::new("internal").to_owned())]);
+        let connection_section = HashMap::from([
+            ("id".to_string(), Value::new("ovs-iface0").to_owned()),
+            (
+                "uuid".to_string(),
+                Value::new(Uuid::new_v4().to_string()).to_owned(),
+            ),
+            ("type".to_string(), Value::new(OVS_INTERFACE_KEY).to_owned()),
+        ]);
+        let dbus_conn = HashMap::from([
+            ("connection".to_string(), connection_section),
+            (OVS_INTERFACE_KEY.to_string(), ovs_interface_section.clone()),
+        ]);
+
+        let connection = connection_from_dbus(dbus_conn).unwrap();
+        assert_eq!(
+            connection.config,
+            ConnectionConfig::OvsInterface(OvsInterfaceConfig {
+                interface_type: OvsInterfaceType::Internal
+            })
+        );
+
+        // an ethernet port of an OVS port
+        let connection_section = HashMap::from([
+            ("id".to_string(), Value::new("eth0").to_owned()),
+            (
+                "uuid".to_string(),
+                Value::new(Uuid::new_v4().to_string()).to_owned(),
+            ),
+            ("type".to_string(), Value::new(ETHERNET_KEY).to_owned()),
+        ]);
+        let dbus_conn = HashMap::from([
+            ("connection".to_string(), connection_section),
+            (ETHERNET_KEY.to_string(), build_ethernet_section_from_dbus()),
+            (OVS_INTERFACE_KEY.to_string(), ovs_interface_section),
+        ]);
+
+        let connection = connection_from_dbus(dbus_conn).unwrap();
+        assert_eq!(connection.config, ConnectionConfig::Ethernet);
+    }
+
     #[test]
     fn test_connection_from_dbus_ieee_8021x() {
         let connection_section = HashMap::from([
@@ -1645,6 +2309,117 @@ mod test {
         assert!(!config.peap_label);
     }
 
+    #[test]
+    fn test_dbus_from_ip_options() {
+        let mut conn = build_base_connection();
+        conn.ip_config.route_metric6 = Some(200);
+        conn.ip_config.dhcp4 = DhcpConfig {
+            client_id: Some("duid".to_string()),
+            fqdn: Some("host.example.net".to_string()),
+            timeout: Some(30),
+            ..Default::default()
+        };
+        conn.ip_config.dhcp6.client_id = Some("ll".to_string());
+        conn.ip_config.may_fail4 = Some(false);
+        conn.ip_config.addr_gen_mode = Some(Ipv6AddrGenMode::StablePrivacy);
+        conn.ip_config.ip6_privacy = Some(Ipv6Privacy::Disabled);
+        let conn_dbus = connection_to_dbus(&conn, None);
+
+        let ipv4 = conn_dbus.get("ipv4").unwrap();
+        let client_id: &str = ipv4.get("dhcp-client-id").unwrap().downcast_ref().unwrap();
+        assert_eq!(client_id, "duid");
+        let fqdn: &str = ipv4.get("dhcp-fqdn").unwrap().downcast_ref().unwrap();
+        assert_eq!(fqdn, "host.example.net");
+        let timeout: i32 = *ipv4.get("dhcp-timeout").unwrap().downcast_ref().unwrap();
+        assert_eq!(timeout, 30);
+        let may_fail: bool = *ipv4.get("may-fail").unwrap().downcast_ref().unwrap();
+        assert!(!may_fail);
+        let metric: i64 = *ipv4.get("route-metric").unwrap().downcast_ref().unwrap();
+        assert_eq!(metric, -1);
+
+        let ipv6 = conn_dbus.get("ipv6").unwrap();
+        let duid: &str = ipv6.get("dhcp-duid").unwrap().downcast_ref().unwrap();
+        assert_eq!(duid, "ll");
+        let metric: i64 = *ipv6.get("route-metric").unwrap().downcast_ref().unwrap();
+        assert_eq!(metric, 200);
+        let mode: i32 = *ipv6.get("addr-gen-mode").unwrap().downcast_ref().unwrap();
+        assert_eq!(mode, 1);
+        let privacy: i32 = *ipv6.get("ip6-privacy").unwrap().downcast_ref().unwrap();
+        assert_eq!(privacy, 0);
+        assert!(!ipv6.contains_key("dhcp-fqdn"));
+    }
+
+    #[test]
+    fn test_connection_from_dbus_ip_options() {
+        let uuid = Uuid::new_v4().to_string();
+        let connection_section = HashMap::from([
+            ("id".to_string(), Value::new("eth0").to_owned()),
+            ("uuid".to_string(), Value::new(uuid).to_owned()),
+        ]);
+        let address_data: Vec<HashMap<String, Value>> = vec![];
+        let ipv4_section = HashMap::from([
+            ("method".to_string(), Value::new("auto").to_owned()),
+            (
+                "address-data".to_string(),
+                Value::new(address_data.clone()).to_owned(),
+            ),
+            ("dhcp-hostname".to_string(), Value::new("laptop").to_owned()),
+            (
+                "dhcp-send-hostname".to_string(),
+                Value::new(true).to_owned(),
+            ),
+            ("dhcp-timeout".to_string(), Value::new(0_i32).to_owned()),
+            ("route-metric".to_string(), Value::new(100_i64).to_owned()),
+            ("may-fail".to_string(), Value::new(true).to_owned()),
+        ]);
+        let ipv6_section = HashMap::from([
+            ("method".to_string(), Value::new("auto").to_owned()),
+            (
+                "address-data".to_string(),
+                Value::new(address_data).to_owned(),
+            ),
+            (
+                "dhcp-send-hostname".to_string(),
+                Value::new(false).to_owned(),
+            ),
+            ("dhcp-timeout".to_string(), Value::new(20_i32).to_owned()),
+            ("route-metric".to_string(), Value::new(-1_i64).to_owned()),
+            ("may-fail".to_string(), Value::new(false).to_owned()),
+            ("addr-gen-mode".to_string(), Value::new(3_i32).to_owned()),
+            ("ip6-privacy".to_string(), Value::new(2_i32).to_owned()),
+        ]);
+        let dbus_conn = HashMap::from([
+            ("connection".to_string(), connection_section),
+            ("ipv4".to_string(), ipv4_section),
+            ("ipv6".to_string(), ipv6_section),
+            (ETHERNET_KEY.to_string(), HashMap::new()),
+        ]);
+
+        let connection = connection_from_dbus(dbus_conn).unwrap();
+        let ip_config = connection.ip_config;
+        assert_eq!(
+            ip_config.dhcp4,
+            DhcpConfig {
+                hostname: Some("laptop".to_string()),
+                ..Default::default()
+            }
+        );
+        assert_eq!(ip_config.route_metric4, Some(100));
+        assert_eq!(ip_config.may_fail4, None);
+        assert_eq!(
+            ip_config.dhcp6,
+            DhcpConfig {
+                send_hostname: Some(false),
+                timeout: Some(20),
+                ..Default::default()
+            }
+        );
+        assert_eq!(ip_config.route_metric6, None);
+        assert_eq!(ip_config.may_fail6, Some(false));
+        assert_eq!(ip_config.addr_gen_mode, None);
+        assert_eq!(ip_config.ip6_privacy, Some(Ipv6Privacy::PreferTemporary));
+    }
+
     #[test]
     fn test_dbus_from_infiniband_connection() {
         let config = InfinibandConfig {
@@ -1672,6 +2447,154 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_dbus_from_wireguard_connection() {
+        let config = WireguardConfig {
+            private_key: Some("yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=".to_string()),
+            listen_port: Some(51820),
+            fwmark: None,
+            peers: vec![WireguardPeer {
+                public_key: "xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=".to_string(),
+                endpoint: Some("192.168.1.1:51820".to_string()),
+                allowed_ips: vec!["10.0.0.0/24".parse().unwrap()],
+                ..Default::default()
+            }],
+        };
+        let mut wireguard = build_base_connection();
+        wireguard.config = ConnectionConfig::Wireguard(config);
+        let wireguard_dbus = connection_to_dbus(&wireguard, None);
+
+        let connection = wireguard_dbus.get("connection").unwrap();
+        let conn_type: &str = connection.get("type").unwrap().downcast_ref().unwrap();
+        assert_eq!(conn_type, WIREGUARD_KEY);
+        let interface: &str = connection
+            .get("interface-name")
+            .unwrap()
+            .downcast_ref()
+            .unwrap();
+        assert_eq!(interface, "agama");
+
+        let wireguard_section = wireguard_dbus.get(WIREGUARD_KEY).unwrap();
+        let private_key: &str = wireguard_section
+            .get("private-key")
+            .unwrap()
+            .downcast_ref()
+            .unwrap();
+        assert_eq!(private_key, "yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=");
+        assert!(!wireguard_section.contains_key("fwmark"));
+
+        let peers: &Array = wireguard_section
+            .get("peers")
+            .unwrap()
+            .downcast_ref()
+            .unwrap();
+        let peer: &Dict = peers.get()[0].downcast_ref().unwrap();
+        let peer = <HashMap<String, Value<'_>>>::try_from(peer.clone()).unwrap();
+        let allowed_ips: &Array = peer.get("allowed-ips").unwrap().downcast_ref().unwrap();
+        let allowed_ips: Vec<&str> = allowed_ips
+            .get()
+            .iter()
+            .map(|ip| ip.downcast_ref::<str>().unwrap())
+            .collect();
+        assert_eq!(allowed_ips, vec!["10.0.0.0/24"]);
+    }
+
+    #[test]
+    fn test_dbus_from_ovs_connections() {
+        let ovs_bridge = Connection {
+            id: "ovs-br0".to_string(),
+            config: ConnectionConfig::OvsBridge(OvsBridgeConfig {
+                stp: true,
+                fail_mode: Some(OvsFailMode::Standalone),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let ovs_bridge_dbus = connection_to_dbus(&ovs_bridge, None);
+        assert!(!ovs_bridge_dbus.contains_key("ipv4"));
+        let section = ovs_bridge_dbus.get(OVS_BRIDGE_KEY).unwrap();
+        let stp: bool = *section.get("stp-enable").unwrap().downcast_ref().unwrap();
+        assert!(stp);
+        let fail_mode: &str = section.get("fail-mode").unwrap().downcast_ref().unwrap();
+        assert_eq!(fail_mode, "standalone");
+
+        let ovs_port = Connection {
+            id: "ovs-port0".to_string(),
+            controller: Some(ovs_bridge.uuid),
+            config: ConnectionConfig::OvsPort(OvsPortConfig { tag: Some(10) }),
+            ..Default::default()
+        };
+        let ovs_port_dbus = connection_to_dbus(&ovs_port, Some(&ovs_bridge));
+        let connection = ovs_port_dbus.get("connection").unwrap();
+        let slave_type: &str = connection
+            .get("slave-type")
+            .unwrap()
+            .downcast_ref()
+            .unwrap();
+        assert_eq!(slave_type, OVS_BRIDGE_KEY);
+        let master: &str = connection.get("master").unwrap().downcast_ref().unwrap();
+        assert_eq!(master, "ovs-br0");
+        let tag: u32 = *ovs_port_dbus
+            .get(OVS_PORT_KEY)
+            .unwrap()
+            .get("tag")
+            .unwrap()
+            .downcast_ref()
+            .unwrap();
+        assert_eq!(tag, 10);
+
+        let mut ovs_interface = build_base_connection();
+        ovs_interface.config = ConnectionConfig::OvsInterface(Default::default());
+        let ovs_interface_dbus = connection_to_dbus(&ovs_interface, Some(&ovs_port));
+        let connection = ovs_interface_dbus.get("connection").unwrap();
+        let slave_type: &str = connection
+            .get("slave-type")
+            .unwrap()
+            .downcast_ref()
+            .unwrap();
+        assert_eq!(slave_type, OVS_PORT_KEY);
+        let interface_type: &str = ovs_interface_dbus
+            .get(OVS_INTERFACE_KEY)
+            .unwrap()
+            .get("type")
+            .unwrap()
+            .downcast_ref()
+            .unwrap();
+        assert_eq!(interface_type, "internal");
+        assert!(ovs_interface_dbus.contains_key("ipv4"));
+    }
+
+    #[test]
+    fn test_dbus_from_vrf_connection() {
+        let vrf = Connection {
+            id: "vrf0".to_string(),
+            interface: Some("vrf-blue".to_string()),
+            config: ConnectionConfig::Vrf(VrfConfig { table: 10 }),
+            ..Default::default()
+        };
+        let vrf_dbus = connection_to_dbus(&vrf, None);
+        let table: u32 = *vrf_dbus
+            .get(VRF_KEY)
+            .unwrap()
+            .get("table")
+            .unwrap()
+            .downcast_ref()
+            .unwrap();
+        assert_eq!(table, 10);
+
+        let port = build_base_connection();
+        let port_dbus = connection_to_dbus(&port, Some(&vrf));
+        let connection = port_dbus.get("connection").unwrap();
+        let slave_type: &str = connection
+            .get("slave-type")
+            .unwrap()
+            .downcast_ref()
+            .unwrap();
+        assert_eq!(slave_type, VRF_KEY);
+        let master: &str = connection.get("master").unwrap().downcast_ref().unwrap();
+        assert_eq!(master, "vrf-blue");
+    }
+
     #[test]
     fn test_dbus_from_wireless_connection() {
         let config = WirelessConfig {
@@ -2039,6 +2962,52 @@ mod test {
         assert_eq!(ethernet.get("mtu"), Some(&Value::from(0_u32)));
     }
 
+    #[test]
+    fn test_merge_dbus_connections_unset_ip_options() {
+        let to_owned = |conn: &NestedHash| -> OwnedNestedHash {
+            conn.iter()
+                .map(|(section, values)| {
+                    let values = values
+                        .iter()
+                        .map(|(key, value)| (key.to_string(), value.to_owned()))
+                        .collect();
+                    (section.to_string(), values)
+                })
+                .collect()
+        };
+
+        let mut conn = Connection::new("eth0".to_string(), DeviceType::Ethernet);
+        conn.ip_config.dhcp4 = DhcpConfig {
+            client_id: Some("duid".to_string()),
+            send_hostname: Some(false),
+            hostname: Some("host".to_string()),
+            fqdn: Some("host.example.net".to_string()),
+            timeout: Some(30),
+        };
+        conn.ip_config.dhcp6.client_id = Some("ll".to_string());
+        conn.ip_config.route_metric4 = Some(100);
+        conn.ip_config.route_metric6 = Some(200);
+        conn.ip_config.may_fail4 = Some(false);
+        conn.ip_config.may_fail6 = Some(false);
+        conn.ip_config.addr_gen_mode = Some(Ipv6AddrGenMode::Eui64);
+        conn.ip_config.ip6_privacy = Some(Ipv6Privacy::PreferTemporary);
+        let original = to_owned(&connection_to_dbus(&conn, None));
+
+        let updated = Connection::new("eth0".to_string(), DeviceType::Ethernet);
+        let updated = connection_to_dbus(&updated, None);
+        let merged = to_owned(&merge_dbus_connections(&original, &updated));
+
+        let ip_config = ip_config_from_dbus(&merged).unwrap();
+        assert_eq!(ip_config.dhcp4, DhcpConfig::default());
+        assert_eq!(ip_config.dhcp6, DhcpConfig::default());
+        assert_eq!(ip_config.route_metric4, None);
+        assert_eq!(ip_config.route_metric6, None);
+        assert_eq!(ip_config.may_fail4, None);
+        assert_eq!(ip_config.may_fail6, None);
+        assert_eq!(ip_config.addr_gen_mode, None);
+        assert_eq!(ip_config.ip6_privacy, None);
+    }
+
     fn build_ethernet_section_from_dbus() -> HashMap<String, OwnedValue> {
         HashMap::from([
             ("auto-negotiate".to_string(), true.into()),
@@ -2190,4 +3159,26 @@ mod test {
             .downcast_ref::<bool>()
             .unwrap());
     }
+
+    #[test]
+    fn test_global_dns_to_and_from_dbus() {
+        let config = GlobalConfig {
+            nameservers: vec!["192.168.1.1".parse().unwrap(), "::1".parse().unwrap()],
+            dns_searchlist: vec!["example.net".to_string()],
+            dns_options: vec!["rotate".to_string(), "timeout:2".to_string()],
+            ..Default::default()
+        };
+        let dns: HashMap<String, OwnedValue> = global_dns_to_dbus(&config)
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_owned()))
+            .collect();
+        let domains: &Dict = dns.get("domains").unwrap().downcast_ref().unwrap();
+        let domains = <HashMap<String, Value<'_>>>::try_from(domains.clone()).unwrap();
+        assert!(domains.contains_key("*"));
+
+        let dns_config = global_dns_from_dbus(&dns).unwrap();
+        assert_eq!(dns_config, config);
+
+        assert!(global_dns_to_dbus(&GlobalConfig::default()).is_empty());
+    }
 }
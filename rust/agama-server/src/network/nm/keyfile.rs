@@ -0,0 +1,1249 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! Adapter to read and write NetworkManager keyfiles.
+//!
+//! NetworkManager stores the connections as `.nmconnection` files (usually in
+//! `/etc/NetworkManager/system-connections`). Each file is an INI-like document whose sections
+//! and keys correspond to the NetworkManager settings. This adapter reuses the D-Bus mapping
+//! ([connection_to_dbus] and [connection_from_dbus]) and only takes care of converting those
+//! settings from/to the keyfile format. Hence, it does not need a running NetworkManager, which
+//! makes it useful to write the configuration of the target system (e.g.,
+//! `/mnt/etc/NetworkManager/system-connections`) and for testing purposes.
+
+use super::dbus::{
+    cleanup_dbus_connection, connection_from_dbus, connection_to_dbus, controller_from_dbus,
+};
+use crate::network::{
+    model::{Connection, NetworkState, StateConfig},
+    Adapter, NetworkAdapterError,
+};
+use agama_lib::{
+    dbus::{NestedHash, OwnedNestedHash},
+    error::ServiceError,
+};
+use async_trait::async_trait;
+use macaddr::MacAddr6;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, fs,
+    io::{self, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use uuid::Uuid;
+use zbus::zvariant::{self, OwnedValue, Value};
+
+/// Default directory for the NetworkManager keyfiles.
+pub const DEFAULT_KEYFILES_DIR: &str = "/etc/NetworkManager/system-connections";
+
+const KEYFILE_EXTENSION: &str = "nmconnection";
+
+/// Section names which are shortened in the keyfiles.
+const SECTION_ALIASES: [(&str, &str); 3] = [
+    ("802-3-ethernet", "ethernet"),
+    ("802-11-wireless", "wifi"),
+    ("802-11-wireless-security", "wifi-security"),
+];
+
+/// Prefix of the sections which contain the WireGuard peers.
+const WIREGUARD_PEER_SECTION: &str = "wireguard-peer";
+
+/// Keys containing a path to a certificate or a private key.
+const PATH_KEYS: [&str; 3] = ["ca-cert", "client-cert", "private-key"];
+
+/// An adapter for NetworkManager keyfiles.
+pub struct KeyfileAdapter {
+    root: PathBuf,
+}
+
+impl Default for KeyfileAdapter {
+    fn default() -> Self {
+        Self::new(DEFAULT_KEYFILES_DIR)
+    }
+}
+
+impl KeyfileAdapter {
+    /// Builds an adapter for the keyfiles in the given directory.
+    ///
+    /// * `root`: directory containing the keyfiles (e.g., `/mnt/etc/NetworkManager/system-connections`).
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Returns the connections defined in the keyfiles.
+    fn connections(&self) -> Result<Vec<Connection>, ServiceError> {
+        let mut controlled_by: HashMap<Uuid, String> = HashMap::new();
+        let mut uuids_map: HashMap<String, Uuid> = HashMap::new();
+        let mut connections = vec![];
+
+        for (path, keyfile) in self.keyfiles()? {
+            let settings = keyfile.to_dbus();
+            let Some(mut connection) = connection_from_dbus(settings.clone()) else {
+                log::warn!("Skipped unsupported keyfile {}", path.display());
+                continue;
+            };
+
+            if let Some(controller) = controller_from_dbus(&settings) {
+                controlled_by.insert(connection.uuid, controller);
+            }
+            let name = connection.interface.as_ref().unwrap_or(&connection.id);
+            uuids_map.insert(name.to_string(), connection.uuid);
+
+            if keyfile.get("connection", "autoconnect") == Some("false") {
+                connection.set_down();
+            }
+            connections.push(connection);
+        }
+
+        for conn in connections.iter_mut() {
+            let Some(name) = controlled_by.get(&conn.uuid) else {
+                continue;
+            };
+
+            if let Some(uuid) = uuids_map.get(name) {
+                conn.controller = Some(*uuid);
+            } else {
+                log::warn!(
+                    "Could not found a connection for the interface '{}' (required by connection '{}')",
+                    name,
+                    conn.id
+                );
+            }
+        }
+
+        Ok(connections)
+    }
+
+    /// Returns the parsed keyfiles, sorted by their file name.
+    fn keyfiles(&self) -> Result<Vec<(PathBuf, Keyfile)>, ServiceError> {
+        let entries = match fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(io_error(&self.root, e)),
+        };
+
+        let mut paths: Vec<PathBuf> = entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|e| e == KEYFILE_EXTENSION))
+            .collect();
+        paths.sort();
+
+        let mut keyfiles = Vec::with_capacity(paths.len());
+        for path in paths {
+            let content = fs::read_to_string(&path).map_err(|e| io_error(&path, e))?;
+            keyfiles.push((path, Keyfile::from_str(&content)?));
+        }
+        Ok(keyfiles)
+    }
+
+    /// Writes a connection to the given file.
+    ///
+    /// The file is only readable by the owner because it might contain secrets.
+    fn write_connection(
+        &self,
+        path: &Path,
+        conn: &Connection,
+        controller: Option<&Connection>,
+    ) -> Result<(), ServiceError> {
+        let content = Keyfile::from_connection(conn, controller).to_string();
+        fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)
+            .and_then(|mut file| file.write_all(content.as_bytes()))
+            .map_err(|e| io_error(path, e))
+    }
+
+    /// Returns the path of the file to write the connection to.
+    ///
+    /// The file is named after the connection ID. If that file belongs to another connection
+    /// (IDs are not unique), the UUID is appended to the name.
+    ///
+    /// * `conn`: connection to write.
+    /// * `owners`: connections (UUIDs) which own each file.
+    fn connection_path(&self, conn: &Connection, owners: &HashMap<PathBuf, Uuid>) -> PathBuf {
+        let name = conn.id.replace('/', "_");
+        let path = self.root.join(format!("{}.{}", name, KEYFILE_EXTENSION));
+        match owners.get(&path) {
+            Some(uuid) if *uuid != conn.uuid => self
+                .root
+                .join(format!("{}-{}.{}", name, conn.uuid, KEYFILE_EXTENSION)),
+            _ => path,
+        }
+    }
+}
+
+#[async_trait]
+impl Adapter for KeyfileAdapter {
+    async fn read(&self, config: StateConfig) -> Result<NetworkState, NetworkAdapterError> {
+        let mut state = NetworkState::default();
+
+        if config.connections {
+            state.connections = self.connections().map_err(NetworkAdapterError::Read)?;
+        }
+
+        Ok(state)
+    }
+
+    /// Writes the connections to the keyfiles.
+    ///
+    /// A connection is written to `<id>.nmconnection` or, if that file belongs to another
+    /// connection with the same ID, to `<id>-<uuid>.nmconnection`. If it was stored in a
+    /// different file (e.g., the connection was renamed), the old file is removed.
+    ///
+    /// * `network`: network model.
+    async fn write(&self, network: &NetworkState) -> Result<(), NetworkAdapterError> {
+        let current: HashMap<Uuid, PathBuf> = self
+            .keyfiles()
+            .map_err(NetworkAdapterError::Write)?
+            .into_iter()
+            .filter_map(|(path, keyfile)| {
+                let uuid = Uuid::parse_str(keyfile.get("connection", "uuid")?).ok()?;
+                Some((uuid, path))
+            })
+            .collect();
+
+        let mut owners: HashMap<PathBuf, Uuid> = current
+            .iter()
+            .map(|(uuid, path)| (path.clone(), *uuid))
+            .collect();
+
+        fs::create_dir_all(&self.root)
+            .map_err(|e| NetworkAdapterError::Write(io_error(&self.root, e)))?;
+
+        for conn in &network.connections {
+            let old_path = current.get(&conn.uuid);
+
+            if conn.is_removed() {
+                if let Some(old_path) = old_path {
+                    log::info!("Removing connection {} ({})", conn.id, conn.uuid);
+                    fs::remove_file(old_path)
+                        .map_err(|e| NetworkAdapterError::Write(io_error(old_path, e)))?;
+                }
+                continue;
+            }
+
+            log::info!("Writing connection {} ({})", conn.id, conn.uuid);
+            let controller = conn
+                .controller
+                .and_then(|uuid| network.get_connection_by_uuid(uuid));
+            let path = self.connection_path(conn, &owners);
+            owners.insert(path.clone(), conn.uuid);
+            self.write_connection(&path, conn, controller)
+                .map_err(NetworkAdapterError::Write)?;
+
+            if let Some(old_path) = old_path.filter(|p| **p != path) {
+                fs::remove_file(old_path)
+                    .map_err(|e| NetworkAdapterError::Write(io_error(old_path, e)))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn io_error(path: &Path, error: io::Error) -> ServiceError {
+    ServiceError::NetworkClientError(format!("{}: {}", path.display(), error))
+}
+
+/// Type of the value of a setting, needed to convert the keyfile strings to D-Bus values.
+enum SettingType {
+    Bool,
+    U32,
+    I32,
+    I64,
+    Str,
+    StrList,
+    Bytes,
+}
+
+fn setting_type(section: &str, key: &str) -> SettingType {
+    match (section, key) {
+        (_, "autoconnect" | "hidden" | "ignore-auto-dns" | "stp") => SettingType::Bool,
+        (_, "mtu") | ("802-11-wireless", "channel") => SettingType::U32,
+        ("802-11-wireless-security", "wep-key-type" | "wep-tx-keyidx") => SettingType::U32,
+        ("802-11-wireless-security", "pmf") | ("infiniband", "p-key") => SettingType::I32,
+        ("ipv4" | "ipv6", "may-fail" | "dhcp-send-hostname") => SettingType::Bool,
+        ("ipv4" | "ipv6", "dhcp-timeout") => SettingType::I32,
+        ("ipv4" | "ipv6", "route-metric") => SettingType::I64,
+        ("ipv6", "addr-gen-mode" | "ip6-privacy") => SettingType::I32,
+        ("802-11-wireless-security", "group" | "pairwise" | "proto") => SettingType::StrList,
+        ("bridge", "priority" | "forward-delay" | "hello-time" | "max-age" | "ageing-time") => {
+            SettingType::U32
+        }
+        ("bridge-port", "priority" | "path-cost") => SettingType::U32,
+        ("vlan", "id") | ("tun", "mode") => SettingType::U32,
+        ("wireguard", "listen-port" | "fwmark") | ("vrf", "table") | ("ovs-port", "tag") => {
+            SettingType::U32
+        }
+        ("wireguard-peer", "persistent-keepalive") => SettingType::U32,
+        ("wireguard-peer", "allowed-ips") => SettingType::StrList,
+        ("ovs-bridge", "stp-enable" | "rstp-enable" | "mcast-snooping-enable") => SettingType::Bool,
+        ("ipv4" | "ipv6", "dns-search") | ("802-1x", "eap") | ("match", _) => SettingType::StrList,
+        ("802-11-wireless", "ssid" | "bssid") => SettingType::Bytes,
+        ("802-1x", key) if PATH_KEYS.contains(&key) => SettingType::Bytes,
+        _ => SettingType::Str,
+    }
+}
+
+/// Represents the content of a keyfile.
+///
+/// The sections and the keys are kept sorted, except the "connection" section, which always
+/// comes first.
+#[derive(Debug, Default, PartialEq)]
+struct Keyfile {
+    sections: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Keyfile {
+    /// Converts a connection into a keyfile.
+    ///
+    /// * `conn`: connection to convert.
+    /// * `controller`: controller of the connection, if any.
+    pub fn from_connection(conn: &Connection, controller: Option<&Connection>) -> Self {
+        let mut settings = connection_to_dbus(conn, controller);
+        cleanup_dbus_connection(&mut settings);
+        if let Some(connection) = settings.get_mut("connection") {
+            connection.insert("uuid", conn.uuid.to_string().into());
+            if !conn.is_up() {
+                connection.insert("autoconnect", false.into());
+            }
+        }
+        Self::from_dbus(&settings)
+    }
+
+    /// Builds a keyfile from the NetworkManager settings.
+    ///
+    /// * `settings`: settings as they are sent over D-Bus.
+    pub fn from_dbus(settings: &NestedHash) -> Self {
+        let mut keyfile = Self::default();
+        for (name, settings) in settings {
+            if *name == "wireguard" {
+                if let Some(Value::Array(peers)) = settings.get("peers") {
+                    keyfile.add_wireguard_peers(peers);
+                }
+            }
+            let section = keyfile
+                .sections
+                .entry(section_to_keyfile(name).to_string())
+                .or_default();
+            for (key, value) in settings {
+                for (key, value) in setting_to_keyfile(name, key, value) {
+                    section.insert(key, value);
+                }
+            }
+        }
+        keyfile
+    }
+
+    /// Adds a section for each WireGuard peer.
+    ///
+    /// The peers are stored as `[wireguard-peer.<public-key>]` sections.
+    ///
+    /// * `peers`: peers as they are sent over D-Bus.
+    fn add_wireguard_peers(&mut self, peers: &zvariant::Array) {
+        for peer in dicts_from_array(peers) {
+            let Some(public_key) = peer.get("public-key").and_then(|k| k.downcast_ref::<str>())
+            else {
+                continue;
+            };
+            let section = self
+                .sections
+                .entry(format!("{}.{}", WIREGUARD_PEER_SECTION, public_key))
+                .or_default();
+            for (key, value) in &peer {
+                if key == "public-key" {
+                    continue;
+                }
+                if let Some(value) = value_to_keyfile(value) {
+                    section.insert(key.to_string(), value);
+                }
+            }
+        }
+    }
+
+    /// Converts the `[wireguard-peer.<public-key>]` sections into a list of peers.
+    fn wireguard_peers_to_dbus(&self) -> Vec<HashMap<String, OwnedValue>> {
+        self.sections
+            .iter()
+            .filter_map(|(name, section)| {
+                let public_key = name
+                    .strip_prefix(WIREGUARD_PEER_SECTION)?
+                    .strip_prefix('.')?;
+                let mut peer: HashMap<String, OwnedValue> = section
+                    .iter()
+                    .filter_map(|(key, value)| {
+                        let value = setting_from_keyfile(WIREGUARD_PEER_SECTION, key, value)?;
+                        Some((key.to_string(), value))
+                    })
+                    .collect();
+                peer.insert("public-key".to_string(), Value::new(public_key).into());
+                Some(peer)
+            })
+            .collect()
+    }
+
+    /// Converts the keyfile into NetworkManager settings.
+    pub fn to_dbus(&self) -> OwnedNestedHash {
+        let mut result = OwnedNestedHash::new();
+
+        for (name, section) in &self.sections {
+            if name.starts_with(WIREGUARD_PEER_SECTION) {
+                continue;
+            }
+            let name = section_from_keyfile(name);
+            let settings = match name {
+                "ipv4" | "ipv6" => ip_section_from_keyfile(name, section),
+                "bond" => {
+                    let options: HashMap<&str, &str> = section
+                        .iter()
+                        .map(|(k, v)| (k.as_str(), v.as_str()))
+                        .collect();
+                    HashMap::from([("options".to_string(), Value::new(options).into())])
+                }
+                _ => section
+                    .iter()
+                    .filter_map(|(key, value)| {
+                        Some((key.to_string(), setting_from_keyfile(name, key, value)?))
+                    })
+                    .collect(),
+            };
+            result.insert(name.to_string(), settings);
+        }
+
+        if let Some(wireguard) = result.get_mut("wireguard") {
+            let peers = self.wireguard_peers_to_dbus();
+            wireguard.insert("peers".to_string(), Value::new(peers).into());
+        }
+
+        // NetworkManager always includes the section of the connection type (even if it is empty)
+        let conn_type = self.get("connection", "type").map(section_from_keyfile);
+        if let Some(conn_type) = conn_type {
+            result.entry(conn_type.to_string()).or_default();
+            if let Some(connection) = result.get_mut("connection") {
+                connection.insert("type".to_string(), Value::new(conn_type).into());
+            }
+        }
+
+        result
+    }
+
+    /// Returns the raw value of a key.
+    ///
+    /// * `section`: section name as it appears in the keyfile.
+    /// * `key`: key name.
+    pub fn get(&self, section: &str, key: &str) -> Option<&str> {
+        self.sections.get(section)?.get(key).map(|v| v.as_str())
+    }
+}
+
+impl FromStr for Keyfile {
+    type Err = ServiceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut keyfile = Keyfile::default();
+        let mut current: Option<&mut BTreeMap<String, String>> = None;
+
+        for (number, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                current = Some(keyfile.sections.entry(name.to_string()).or_default());
+                continue;
+            }
+
+            let (Some(section), Some((key, value))) = (current.as_mut(), line.split_once('='))
+            else {
+                return Err(ServiceError::NetworkClientError(format!(
+                    "Invalid keyfile line {}: '{}'",
+                    number + 1,
+                    line
+                )));
+            };
+            section.insert(key.trim().to_string(), value.trim().to_string());
+        }
+
+        Ok(keyfile)
+    }
+}
+
+impl fmt::Display for Keyfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let connection = self.sections.get_key_value("connection");
+        let others = self.sections.iter().filter(|(n, _)| *n != "connection");
+        let mut first = true;
+
+        for (name, section) in connection.into_iter().chain(others) {
+            if !first {
+                writeln!(f)?;
+            }
+            first = false;
+            writeln!(f, "[{}]", name)?;
+            for (key, value) in section {
+                writeln!(f, "{}={}", key, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn section_to_keyfile(name: &str) -> &str {
+    SECTION_ALIASES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, alias)| *alias)
+        .unwrap_or(name)
+}
+
+fn section_from_keyfile(name: &str) -> &str {
+    SECTION_ALIASES
+        .iter()
+        .find(|(_, alias)| *alias == name)
+        .map(|(n, _)| *n)
+        .unwrap_or(name)
+}
+
+/// Converts a D-Bus setting into a list of keyfile entries.
+///
+/// Most settings are converted into a single entry, but some of them are split (e.g., the IP
+/// addresses or the bonding options). Empty values are omitted.
+///
+/// * `section`: section name (as used in D-Bus).
+/// * `key`: setting name.
+/// * `value`: setting value.
+fn setting_to_keyfile(section: &str, key: &str, value: &Value) -> Vec<(String, String)> {
+    match (section, key, value) {
+        ("ipv4" | "ipv6", "address-data", Value::Array(addresses)) => dicts_from_array(addresses)
+            .iter()
+            .enumerate()
+            .filter_map(|(i, address)| {
+                let ip: &str = address.get("address")?.downcast_ref()?;
+                let prefix: &u32 = address.get("prefix")?.downcast_ref()?;
+                Some((format!("address{}", i + 1), format!("{}/{}", ip, prefix)))
+            })
+            .collect(),
+        ("ipv4" | "ipv6", "route-data", Value::Array(routes)) => dicts_from_array(routes)
+            .iter()
+            .enumerate()
+            .filter_map(|(i, route)| {
+                let dest: &str = route.get("dest")?.downcast_ref()?;
+                let prefix: &u32 = route.get("prefix")?.downcast_ref()?;
+                let mut value = format!("{}/{}", dest, prefix);
+                let next_hop = route.get("next-hop").and_then(|n| n.downcast_ref::<str>());
+                let metric = route.get("metric").and_then(|m| m.downcast_ref::<u32>());
+                if next_hop.is_some() || metric.is_some() {
+                    let unspecified = if section == "ipv4" { "0.0.0.0" } else { "::" };
+                    value.push_str(&format!(",{}", next_hop.unwrap_or(unspecified)));
+                }
+                if let Some(metric) = metric {
+                    value.push_str(&format!(",{}", metric));
+                }
+                Some((format!("route{}", i + 1), value))
+            })
+            .collect(),
+        ("ipv4" | "ipv6", "dns-data", value) => value_to_keyfile(value)
+            .map(|v| vec![("dns".to_string(), v)])
+            .unwrap_or_default(),
+        // the peers are written to their own sections (see Keyfile::add_wireguard_peers)
+        ("wireguard", "peers", _) => vec![],
+        ("bond", "options", Value::Dict(options)) => {
+            <HashMap<String, String>>::try_from(options.clone())
+                .map(|o| o.into_iter().collect())
+                .unwrap_or_default()
+        }
+        ("connection", "type", Value::Str(conn_type)) => {
+            vec![(
+                key.to_string(),
+                section_to_keyfile(conn_type.as_str()).to_string(),
+            )]
+        }
+        (_, "bssid", Value::Array(bytes)) => {
+            let bytes = bytes_from_array(bytes);
+            match <[u8; 6]>::try_from(bytes.as_slice()) {
+                Ok(mac) => vec![(key.to_string(), MacAddr6::from(mac).to_string())],
+                Err(_) => vec![],
+            }
+        }
+        (_, _, Value::Array(bytes)) if bytes.element_signature() == "y" => {
+            let bytes = bytes_from_array(bytes);
+            let mut value = String::from_utf8_lossy(&bytes).to_string();
+            if PATH_KEYS.contains(&key) {
+                value = value
+                    .strip_prefix("file://")
+                    .unwrap_or(&value)
+                    .trim_end_matches('\0')
+                    .to_string();
+            }
+            vec![(key.to_string(), escape(&value))]
+        }
+        _ => value_to_keyfile(value)
+            .map(|v| vec![(key.to_string(), v)])
+            .unwrap_or_default(),
+    }
+}
+
+/// Converts a simple D-Bus value (strings, numbers, booleans and lists of strings) into a
+/// keyfile value.
+fn value_to_keyfile(value: &Value) -> Option<String> {
+    let value = match value {
+        Value::Str(s) if s.is_empty() => return None,
+        Value::Str(s) => escape(s.as_str()),
+        Value::Bool(b) => b.to_string(),
+        Value::U8(n) => n.to_string(),
+        Value::U16(n) => n.to_string(),
+        Value::I16(n) => n.to_string(),
+        Value::U32(n) => n.to_string(),
+        Value::I32(n) => n.to_string(),
+        Value::U64(n) => n.to_string(),
+        Value::I64(n) => n.to_string(),
+        Value::Array(items) if items.is_empty() => return None,
+        Value::Array(items) => items
+            .iter()
+            .map(|i| {
+                i.downcast_ref::<str>()
+                    .map(|s| escape(s).replace(';', "\\;"))
+            })
+            .map(|i| i.map(|s| format!("{};", s)))
+            .collect::<Option<String>>()?,
+        _ => {
+            log::warn!("Unsupported value for the keyfile: {:?}", value);
+            return None;
+        }
+    };
+    Some(value)
+}
+
+/// Converts a keyfile value into a D-Bus value.
+///
+/// * `section`: section name (as used in D-Bus).
+/// * `key`: setting name.
+/// * `value`: raw value from the keyfile.
+fn setting_from_keyfile(section: &str, key: &str, value: &str) -> Option<OwnedValue> {
+    let value: Value = match setting_type(section, key) {
+        SettingType::Bool => match value {
+            "true" | "yes" | "1" => true.into(),
+            "false" | "no" | "0" => false.into(),
+            _ => return invalid_value(section, key, value),
+        },
+        SettingType::U32 => match value.parse::<u32>() {
+            Ok(n) => n.into(),
+            Err(_) => return invalid_value(section, key, value),
+        },
+        SettingType::I32 if key == "addr-gen-mode" => match value {
+            "eui64" => 0.into(),
+            "stable-privacy" => 1.into(),
+            "default-or-eui64" => 2.into(),
+            "default" => 3.into(),
+            _ => match value.parse::<i32>() {
+                Ok(n) => n.into(),
+                Err(_) => return invalid_value(section, key, value),
+            },
+        },
+        SettingType::I32 => match value.parse::<i32>() {
+            Ok(n) => n.into(),
+            Err(_) => return invalid_value(section, key, value),
+        },
+        SettingType::I64 => match value.parse::<i64>() {
+            Ok(n) => n.into(),
+            Err(_) => return invalid_value(section, key, value),
+        },
+        SettingType::Str => Value::new(unescape(value)),
+        SettingType::StrList => Value::new(split_list(value)),
+        SettingType::Bytes if key == "bssid" => match MacAddr6::from_str(value) {
+            Ok(mac) => Value::new(mac.as_bytes().to_vec()),
+            Err(_) => return invalid_value(section, key, value),
+        },
+        SettingType::Bytes if PATH_KEYS.contains(&key) => {
+            Value::new(format!("file://{}\0", unescape(value)).into_bytes())
+        }
+        SettingType::Bytes => Value::new(unescape(value).into_bytes()),
+    };
+    Some(value.into())
+}
+
+fn invalid_value(section: &str, key: &str, value: &str) -> Option<OwnedValue> {
+    log::warn!(
+        "Ignoring invalid value for {}.{}: '{}'",
+        section,
+        key,
+        value
+    );
+    None
+}
+
+/// Converts the "ipv4" or "ipv6" sections, which contain numbered addresses and routes.
+///
+/// The addresses might include the gateway (e.g., `address1=192.168.1.1/24,192.168.1.254`).
+fn ip_section_from_keyfile(
+    section: &str,
+    values: &BTreeMap<String, String>,
+) -> HashMap<String, OwnedValue> {
+    let mut result: HashMap<String, OwnedValue> = HashMap::new();
+    let mut addresses: Vec<(u32, HashMap<&str, Value>)> = vec![];
+    let mut routes: Vec<(u32, HashMap<&str, Value>)> = vec![];
+    let mut gateway: Option<&str> = None;
+
+    for (key, value) in values {
+        if let Some(index) = numbered_key(key, "address") {
+            let mut parts = value.split(',');
+            let Some(address) = parts.next().and_then(ip_with_prefix) else {
+                invalid_value(section, key, value);
+                continue;
+            };
+            addresses.push((index, address));
+            gateway = gateway.or(parts.next());
+        } else if let Some(index) = numbered_key(key, "route") {
+            let mut parts = value.split(',');
+            let Some((dest, prefix)) = parts.next().and_then(ip_with_prefix).map(|mut r| {
+                let dest = r.remove("address").unwrap();
+                (dest, r.remove("prefix").unwrap())
+            }) else {
+                invalid_value(section, key, value);
+                continue;
+            };
+            let mut route = HashMap::from([("dest", dest), ("prefix", prefix)]);
+            if let Some(next_hop) = parts.next().filter(|n| !is_unspecified(n)) {
+                route.insert("next-hop", Value::new(next_hop.to_string()));
+            }
+            if let Some(metric) = parts.next().and_then(|m| m.parse::<u32>().ok()) {
+                route.insert("metric", Value::new(metric));
+            }
+            routes.push((index, route));
+        } else if key == "dns" {
+            result.insert("dns-data".to_string(), Value::new(split_list(value)).into());
+        } else if let Some(value) = setting_from_keyfile(section, key, value) {
+            result.insert(key.to_string(), value);
+        }
+    }
+
+    if let Some(gateway) = gateway {
+        result
+            .entry("gateway".to_string())
+            .or_insert_with(|| Value::new(gateway.to_string()).into());
+    }
+
+    addresses.sort_by_key(|(i, _)| *i);
+    let addresses: Vec<HashMap<&str, Value>> = addresses.into_iter().map(|(_, a)| a).collect();
+    result.insert("address-data".to_string(), Value::new(addresses).into());
+
+    if !routes.is_empty() {
+        routes.sort_by_key(|(i, _)| *i);
+        let routes: Vec<HashMap<&str, Value>> = routes.into_iter().map(|(_, r)| r).collect();
+        result.insert("route-data".to_string(), Value::new(routes).into());
+    }
+
+    result
+}
+
+/// Returns the index of a numbered key (e.g., 1 for "address1").
+fn numbered_key(key: &str, prefix: &str) -> Option<u32> {
+    key.strip_prefix(prefix)?.parse().ok()
+}
+
+/// Parses an "address/prefix" string into the D-Bus representation.
+fn ip_with_prefix(value: &str) -> Option<HashMap<&'static str, Value<'static>>> {
+    let (address, prefix) = value.split_once('/')?;
+    let prefix: u32 = prefix.parse().ok()?;
+    Some(HashMap::from([
+        ("address", Value::new(address.to_string())),
+        ("prefix", Value::new(prefix)),
+    ]))
+}
+
+fn is_unspecified(address: &str) -> bool {
+    address
+        .parse::<std::net::IpAddr>()
+        .is_ok_and(|a| a.is_unspecified())
+}
+
+fn dicts_from_array<'a>(array: &'a zvariant::Array) -> Vec<HashMap<String, Value<'a>>> {
+    array
+        .get()
+        .iter()
+        .filter_map(|item| item.downcast_ref::<zvariant::Dict>())
+        .filter_map(|dict| <HashMap<String, Value>>::try_from(dict.clone()).ok())
+        .collect()
+}
+
+fn bytes_from_array(array: &zvariant::Array) -> Vec<u8> {
+    array
+        .get()
+        .iter()
+        .filter_map(|b| b.downcast_ref::<u8>().copied())
+        .collect()
+}
+
+/// Escapes a value as NetworkManager does.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('s') => result.push(' '),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// Splits a list of values (e.g., "eth0;eth1;").
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = vec![];
+    let mut current = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => items.push(unescape(&std::mem::take(&mut current))),
+            '\\' => {
+                current.push(c);
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        items.push(unescape(&current));
+    }
+    items
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::network::model::*;
+    use agama_lib::network::types::{BondMode, Status, SSID};
+    use cidr::IpInet;
+    use std::{net::IpAddr, os::unix::fs::PermissionsExt};
+    use tempfile::TempDir;
+
+    fn ethernet() -> Connection {
+        Connection {
+            id: "eth0".to_string(),
+            uuid: Uuid::new_v4(),
+            interface: Some("eth0".to_string()),
+            mtu: 1500,
+            mac_address: MacAddress::from_str("00:11:22:33:44:55").unwrap(),
+            ip_config: IpConfig {
+                method4: Ipv4Method::Manual,
+                method6: Ipv6Method::Auto,
+                addresses: vec![
+                    IpInet::from_str("192.168.1.10/24").unwrap(),
+                    IpInet::from_str("2001:db8::10/64").unwrap(),
+                ],
+                nameservers: vec![
+                    IpAddr::from_str("192.168.1.1").unwrap(),
+                    IpAddr::from_str("2001:db8::1").unwrap(),
+                ],
+                dns_searchlist: vec!["example.net".to_string()],
+                gateway4: Some(IpAddr::from_str("192.168.1.254").unwrap()),
+                routes4: Some(vec![IpRoute {
+                    destination: IpInet::from_str("10.0.0.0/8").unwrap(),
+                    next_hop: Some(IpAddr::from_str("192.168.1.2").unwrap()),
+                    metric: Some(100),
+                }]),
+                ..Default::default()
+            },
+            match_config: MatchConfig {
+                driver: vec!["e1000e".to_string()],
+                ..Default::default()
+            },
+            firewall_zone: Some("public".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn wireless() -> Connection {
+        Connection {
+            id: "home wifi".to_string(),
+            uuid: Uuid::new_v4(),
+            status: Status::Down,
+            config: ConnectionConfig::Wireless(WirelessConfig {
+                ssid: SSID("Home Network".as_bytes().to_vec()),
+                security: SecurityProtocol::WPA2,
+                mode: WirelessMode::Infra,
+                hidden: true,
+                ..Default::default()
+            }),
+            ip_config: IpConfig {
+                method4: Ipv4Method::Auto,
+                method6: Ipv6Method::Auto,
+                route_metric4: Some(600),
+                dhcp4: DhcpConfig {
+                    client_id: Some("mac".to_string()),
+                    hostname: Some("laptop".to_string()),
+                    timeout: Some(45),
+                    ..Default::default()
+                },
+                dhcp6: DhcpConfig {
+                    send_hostname: Some(false),
+                    ..Default::default()
+                },
+                may_fail6: Some(false),
+                addr_gen_mode: Some(Ipv6AddrGenMode::Eui64),
+                ip6_privacy: Some(Ipv6Privacy::PreferPublic),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn bond_with_port() -> (Connection, Connection) {
+        let bond = Connection {
+            id: "bond0".to_string(),
+            uuid: Uuid::new_v4(),
+            interface: Some("bond0".to_string()),
+            config: ConnectionConfig::Bond(BondConfig {
+                mode: BondMode::ActiveBackup,
+                options: BondOptions(HashMap::from([("miimon".to_string(), "100".to_string())])),
+            }),
+            ..Default::default()
+        };
+        let port = Connection {
+            id: "eth1".to_string(),
+            uuid: Uuid::new_v4(),
+            interface: Some("eth1".to_string()),
+            controller: Some(bond.uuid),
+            ..Default::default()
+        };
+        (bond, port)
+    }
+
+    fn network(connections: Vec<Connection>) -> NetworkState {
+        NetworkState {
+            connections,
+            ..Default::default()
+        }
+    }
+
+    async fn read(adapter: &KeyfileAdapter) -> Vec<Connection> {
+        adapter
+            .read(StateConfig::default())
+            .await
+            .unwrap()
+            .connections
+    }
+
+    #[tokio::test]
+    async fn test_write_and_read_connections() {
+        let root = TempDir::new().unwrap();
+        let adapter = KeyfileAdapter::new(root.path().join("system-connections"));
+
+        let (bond, port) = bond_with_port();
+        let bridge = Connection {
+            id: "br0".to_string(),
+            uuid: Uuid::new_v4(),
+            config: ConnectionConfig::Bridge(BridgeConfig {
+                stp: true,
+                priority: Some(10),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let vlan = Connection {
+            id: "eth0.10".to_string(),
+            uuid: Uuid::new_v4(),
+            config: ConnectionConfig::Vlan(VlanConfig {
+                id: 10,
+                parent: "eth0".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let dummy = Connection {
+            id: "dummy0".to_string(),
+            uuid: Uuid::new_v4(),
+            config: ConnectionConfig::Dummy,
+            ..Default::default()
+        };
+        let state = network(vec![
+            ethernet(),
+            wireless(),
+            bond,
+            port,
+            bridge,
+            vlan,
+            dummy,
+        ]);
+        adapter.write(&state).await.unwrap();
+
+        let mut connections = read(&adapter).await;
+        connections.sort_by_key(|c| state.connections.iter().position(|o| o.uuid == c.uuid));
+        assert_eq!(connections, state.connections);
+    }
+
+    #[tokio::test]
+    async fn test_write_and_read_tunnels_and_ovs() {
+        let root = TempDir::new().unwrap();
+        let adapter = KeyfileAdapter::new(root.path());
+
+        let wireguard = Connection {
+            id: "wg0".to_string(),
+            interface: Some("wg0".to_string()),
+            config: ConnectionConfig::Wireguard(WireguardConfig {
+                private_key: Some("yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=".to_string()),
+                listen_port: Some(51820),
+                fwmark: Some(42),
+                peers: vec![
+                    // sorted by public key, as they are read from the keyfile
+                    WireguardPeer {
+                        public_key: "TrMvSoP4jYQlY6RIzBgbssQqY3vxI2Pi+y71lOWWXX0=".to_string(),
+                        preshared_key: Some(
+                            "FpCyhws9cxwWoV4xELtfJvjJN+zQVRPISllRWgeopVE=".to_string(),
+                        ),
+                        allowed_ips: vec!["10.0.1.0/24".parse().unwrap()],
+                        ..Default::default()
+                    },
+                    WireguardPeer {
+                        public_key: "xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=".to_string(),
+                        endpoint: Some("vpn.example.net:51820".to_string()),
+                        allowed_ips: vec![
+                            "10.0.0.0/24".parse().unwrap(),
+                            "fd00::/64".parse().unwrap(),
+                        ],
+                        persistent_keepalive: Some(25),
+                        ..Default::default()
+                    },
+                ],
+            }),
+            ..Default::default()
+        };
+        let vrf = Connection {
+            id: "vrf0".to_string(),
+            interface: Some("vrf0".to_string()),
+            config: ConnectionConfig::Vrf(VrfConfig { table: 100 }),
+            ..Default::default()
+        };
+        let vrf_port = Connection {
+            id: "eth2".to_string(),
+            interface: Some("eth2".to_string()),
+            controller: Some(vrf.uuid),
+            ..Default::default()
+        };
+        let ovs_bridge = Connection {
+            id: "ovs-br0".to_string(),
+            interface: Some("ovs-br0".to_string()),
+            config: ConnectionConfig::OvsBridge(OvsBridgeConfig {
+                rstp: true,
+                mcast_snooping: true,
+                fail_mode: Some(OvsFailMode::Secure),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let ovs_port = Connection {
+            id: "ovs-port0".to_string(),
+            interface: Some("ovs-port0".to_string()),
+            controller: Some(ovs_bridge.uuid),
+            config: ConnectionConfig::OvsPort(OvsPortConfig { tag: Some(10) }),
+            ..Default::default()
+        };
+        let ovs_interface = Connection {
+            id: "ovs-iface0".to_string(),
+            interface: Some("ovs-iface0".to_string()),
+            controller: Some(ovs_port.uuid),
+            config: ConnectionConfig::OvsInterface(OvsInterfaceConfig {
+                interface_type: OvsInterfaceType::Internal,
+            }),
+            ..Default::default()
+        };
+        let state = network(vec![
+            wireguard,
+            vrf,
+            vrf_port,
+            ovs_bridge,
+            ovs_port,
+            ovs_interface,
+        ]);
+        adapter.write(&state).await.unwrap();
+
+        let content = fs::read_to_string(root.path().join("wg0.nmconnection")).unwrap();
+        let keyfile = Keyfile::from_str(&content).unwrap();
+        assert_eq!(
+            keyfile.get(
+                "wireguard-peer.xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=",
+                "allowed-ips"
+            ),
+            Some("10.0.0.0/24;fd00::/64;")
+        );
+
+        let mut connections = read(&adapter).await;
+        connections.sort_by_key(|c| state.connections.iter().position(|o| o.uuid == c.uuid));
+        assert_eq!(connections, state.connections);
+    }
+
+    #[tokio::test]
+    async fn test_write_keyfile() {
+        let root = TempDir::new().unwrap();
+        let adapter = KeyfileAdapter::new(root.path());
+        let conn = ethernet();
+        adapter.write(&network(vec![conn.clone()])).await.unwrap();
+
+        let path = root.path().join("eth0.nmconnection");
+        let content = fs::read_to_string(&path).unwrap();
+        let keyfile = Keyfile::from_str(&content).unwrap();
+        assert!(content.starts_with("[connection]\n"));
+        assert_eq!(keyfile.get("connection", "type"), Some("ethernet"));
+        assert_eq!(
+            keyfile.get("connection", "uuid"),
+            Some(conn.uuid.to_string().as_str())
+        );
+        assert_eq!(keyfile.get("ipv4", "address1"), Some("192.168.1.10/24"));
+        assert_eq!(keyfile.get("ipv4", "dns"), Some("192.168.1.1;"));
+        assert_eq!(
+            keyfile.get("ipv4", "route1"),
+            Some("10.0.0.0/8,192.168.1.2,100")
+        );
+        assert_eq!(keyfile.get("ipv6", "address1"), Some("2001:db8::10/64"));
+        assert_eq!(keyfile.get("match", "driver"), Some("e1000e;"));
+        assert_eq!(keyfile.get("ethernet", "mtu"), Some("1500"));
+        assert_eq!(keyfile.get("connection", "master"), None);
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[tokio::test]
+    async fn test_read_keyfile() {
+        let root = TempDir::new().unwrap();
+        let content = r#"
+# written by hand
+[connection]
+id=Wired connection 1
+uuid=f6ec3dab-2f4f-4d1d-a1f0-2c71c4a7f8c0
+type=ethernet
+interface-name=enp1s0
+
+[ethernet]
+
+[ipv4]
+method=manual
+address1=192.168.122.100/24,192.168.122.1
+dns=192.168.122.1;8.8.8.8;
+dns-search=example.net;
+
+[ipv6]
+method=disabled
+"#;
+        fs::write(root.path().join("wired.nmconnection"), content).unwrap();
+        fs::write(root.path().join("ignored.txt"), "[connection]").unwrap();
+
+        let adapter = KeyfileAdapter::new(root.path());
+        let connections = read(&adapter).await;
+        assert_eq!(connections.len(), 1);
+
+        let conn = connections.first().unwrap();
+        assert_eq!(conn.id, "Wired connection 1");
+        assert_eq!(conn.interface, Some("enp1s0".to_string()));
+        assert!(matches!(conn.config, ConnectionConfig::Ethernet));
+        assert_eq!(conn.ip_config.method4, Ipv4Method::Manual);
+        assert_eq!(conn.ip_config.method6, Ipv6Method::Disabled);
+        assert_eq!(
+            conn.ip_config.addresses,
+            vec![IpInet::from_str("192.168.122.100/24").unwrap()]
+        );
+        assert_eq!(
+            conn.ip_config.gateway4,
+            Some(IpAddr::from_str("192.168.122.1").unwrap())
+        );
+        assert_eq!(conn.ip_config.nameservers.len(), 2);
+        assert_eq!(conn.ip_config.dns_searchlist, vec!["example.net"]);
+    }
+
+    #[tokio::test]
+    async fn test_rename_and_remove_connections() {
+        let root = TempDir::new().unwrap();
+        let adapter = KeyfileAdapter::new(root.path());
+        let (bond, port) = bond_with_port();
+        let mut state = network(vec![bond, port]);
+        adapter.write(&state).await.unwrap();
+        assert!(root.path().join("bond0.nmconnection").exists());
+
+        let bond = state.connections.get_mut(0).unwrap();
+        bond.id = "bonding".to_string();
+        state.connections.get_mut(1).unwrap().remove();
+        adapter.write(&state).await.unwrap();
+
+        assert!(root.path().join("bonding.nmconnection").exists());
+        assert!(!root.path().join("bond0.nmconnection").exists());
+        assert!(!root.path().join("eth1.nmconnection").exists());
+        assert_eq!(read(&adapter).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_write_connections_with_the_same_id() {
+        let root = TempDir::new().unwrap();
+        let adapter = KeyfileAdapter::new(root.path());
+        let conn = ethernet();
+        let other = Connection {
+            uuid: Uuid::new_v4(),
+            interface: Some("eth1".to_string()),
+            ..conn.clone()
+        };
+        adapter
+            .write(&network(vec![conn.clone(), other.clone()]))
+            .await
+            .unwrap();
+
+        assert!(root.path().join("eth0.nmconnection").exists());
+        let other_name = format!("eth0-{}.nmconnection", other.uuid);
+        assert!(root.path().join(&other_name).exists());
+        assert_eq!(read(&adapter).await.len(), 2);
+
+        // the files are kept when writing again, even in a different order
+        adapter.write(&network(vec![other, conn])).await.unwrap();
+        assert!(root.path().join("eth0.nmconnection").exists());
+        assert!(root.path().join(&other_name).exists());
+        assert_eq!(read(&adapter).await.len(), 2);
+    }
+
+    #[test]
+    fn test_split_list() {
+        assert_eq!(split_list("a;b;"), vec!["a", "b"]);
+        assert_eq!(split_list("a;b"), vec!["a", "b"]);
+        assert_eq!(split_list("a\\;b;c;"), vec!["a;b", "c"]);
+        assert!(split_list("").is_empty());
+    }
+}
@@ -105,6 +105,11 @@ impl TryFrom<NmDeviceType> for DeviceType {
             NmDeviceType(10) => Ok(DeviceType::Bond),
             NmDeviceType(13) => Ok(DeviceType::Bridge),
             NmDeviceType(22) => Ok(DeviceType::Dummy),
+            NmDeviceType(24) => Ok(DeviceType::OvsInterface),
+            NmDeviceType(25) => Ok(DeviceType::OvsPort),
+            NmDeviceType(26) => Ok(DeviceType::OvsBridge),
+            NmDeviceType(29) => Ok(DeviceType::Wireguard),
+            NmDeviceType(31) => Ok(DeviceType::Vrf),
             NmDeviceType(32) => Ok(DeviceType::Loopback),
             NmDeviceType(_) => Err(NmError::UnsupportedDeviceType(value.into())),
         }
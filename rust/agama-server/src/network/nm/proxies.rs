@@ -902,3 +902,25 @@ trait IP6Config {
     #[dbus_proxy(property)]
     fn searches(&self) -> zbus::Result<Vec<String>>;
 }
+
+/// # DBus interface proxy for: `org.freedesktop.hostname1`
+///
+/// It is not part of NetworkManager, but it is used to set the transient hostname, which
+/// NetworkManager does not allow to change.
+#[dbus_proxy(
+    interface = "org.freedesktop.hostname1",
+    default_service = "org.freedesktop.hostname1",
+    default_path = "/org/freedesktop/hostname1"
+)]
+trait Hostname1 {
+    /// SetHostname method
+    fn set_hostname(&self, hostname: &str, interactive: bool) -> zbus::Result<()>;
+
+    /// Hostname property
+    #[dbus_proxy(property)]
+    fn hostname(&self) -> zbus::Result<String>;
+
+    /// StaticHostname property
+    #[dbus_proxy(property)]
+    fn static_hostname(&self) -> zbus::Result<String>;
+}
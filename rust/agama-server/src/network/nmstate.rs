@@ -0,0 +1,1602 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! Conversion between the network state and the [nmstate](https://nmstate.io) YAML format.
+//!
+//! It supports the `interfaces` (ethernet, bond, vlan, linux-bridge, infiniband, dummy and
+//! loopback), `routes` and `dns-resolver` sections. As nmstate does not describe wireless
+//! connections, Agama adds a `wifi` interface type with a `wifi` section (ssid, mode,
+//! key-management, psk and hidden).
+//!
+//! The conversion fails when some setting cannot be represented in the other side (e.g., an
+//! unknown nmstate key or an Agama connection using 802.1X). Nothing is dropped silently.
+//!
+//! A document can be merged into the current connections (see [NmstateState::merge_into]),
+//! keeping the settings it does not include, as nmstate does.
+
+use crate::network::model::{
+    BondConfig, BondOptions, BridgeConfig, BridgePortConfig, Connection, ConnectionConfig,
+    DhcpConfig, GeneralState, InfinibandConfig, InfinibandTransportMode, IpRoute, Ipv4Method,
+    Ipv6AddrGenMode, Ipv6Method, MacAddress, MatchConfig, NetworkState, PortConfig,
+    SecurityProtocol, VlanConfig, VlanProtocol, WirelessConfig, WirelessMode,
+};
+use agama_lib::network::types::{BondMode, DeviceType, Status, SSID};
+use cidr::IpInet;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::IpAddr,
+    str::FromStr,
+};
+use thiserror::Error;
+use uuid::Uuid;
+
+#[derive(Debug, Error)]
+pub enum NmstateError {
+    #[error("Could not parse the nmstate document: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("Interface '{0}': {1} cannot be represented")]
+    Unsupported(String, String),
+    #[error("Interface '{0}' of type '{1}' requires a '{2}' section")]
+    MissingSection(String, String, String),
+    #[error("Interface '{0}' of type '{1}' cannot have a '{2}' section")]
+    UnexpectedSection(String, String, String),
+    #[error("Interface '{0}': invalid {1}")]
+    InvalidValue(String, String),
+    #[error("Unknown interface '{0}'")]
+    UnknownInterface(String),
+    #[error("Interface '{0}' is defined more than once")]
+    DuplicatedInterface(String),
+    #[error("Route to '{0}': {1}")]
+    UnsupportedRoute(String, String),
+    #[error("The DNS settings of '{0}' and '{1}' differ, but nmstate only supports global ones")]
+    DnsMismatch(String, String),
+    #[error("There is no interface to apply the DNS settings to")]
+    NoDnsInterface,
+}
+
+/// Network state using the nmstate schema.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct NmstateState {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dns_resolver: Option<DnsResolver>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routes: Option<Routes>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interfaces: Vec<Interface>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DnsResolver {
+    pub config: DnsConfig,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DnsConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub server: Vec<IpAddr>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub search: Vec<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Routes {
+    #[serde(default)]
+    pub config: Vec<Route>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Route {
+    pub destination: IpInet,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_hop_interface: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_hop_address: Option<IpAddr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metric: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<InterfaceState>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InterfaceType {
+    Ethernet,
+    Bond,
+    Vlan,
+    LinuxBridge,
+    Infiniband,
+    Wifi,
+    Dummy,
+    Loopback,
+}
+
+impl std::fmt::Display for InterfaceType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            InterfaceType::Ethernet => "ethernet",
+            InterfaceType::Bond => "bond",
+            InterfaceType::Vlan => "vlan",
+            InterfaceType::LinuxBridge => "linux-bridge",
+            InterfaceType::Infiniband => "infiniband",
+            InterfaceType::Wifi => "wifi",
+            InterfaceType::Dummy => "dummy",
+            InterfaceType::Loopback => "loopback",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InterfaceState {
+    Up,
+    Down,
+    Absent,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Interface {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_: InterfaceType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<InterfaceState>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtu: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mac_address: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipv4: Option<Ipv4>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipv6: Option<Ipv6>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_aggregation: Option<LinkAggregation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vlan: Option<Vlan>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bridge: Option<Bridge>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub infiniband: Option<Infiniband>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wifi: Option<Wifi>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Ipv4 {
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dhcp: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub address: Vec<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_dns: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_route_metric: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dhcp_client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dhcp_send_hostname: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dhcp_custom_hostname: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Ipv6 {
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dhcp: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub autoconf: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub address: Vec<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_dns: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_route_metric: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dhcp_duid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dhcp_send_hostname: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dhcp_custom_hostname: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub addr_gen_mode: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Address {
+    pub ip: IpAddr,
+    pub prefix_length: u8,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LinkAggregation {
+    pub mode: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub options: BTreeMap<String, serde_yaml::Value>,
+    #[serde(default)]
+    pub port: Vec<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Vlan {
+    pub base_iface: String,
+    pub id: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Bridge {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<BridgeOptions>,
+    #[serde(default)]
+    pub port: Vec<BridgePort>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct BridgeOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stp: Option<Stp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mac_ageing_time: Option<u32>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Stp {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forward_delay: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hello_time: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u32>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct BridgePort {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stp_priority: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stp_path_cost: Option<u32>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Infiniband {
+    pub mode: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pkey: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_iface: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Wifi {
+    pub ssid: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_management: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub psk: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hidden: Option<bool>,
+}
+
+impl NmstateState {
+    /// Parses an nmstate YAML document.
+    ///
+    /// * `content`: YAML document.
+    pub fn from_yaml(content: &str) -> Result<Self, NmstateError> {
+        Ok(serde_yaml::from_str(content)?)
+    }
+
+    /// Writes the state as an nmstate YAML document.
+    pub fn to_yaml(&self) -> Result<String, NmstateError> {
+        Ok(serde_yaml::to_string(self)?)
+    }
+
+    /// Converts the document into the connections to add, update or remove, merging them into
+    /// the current ones.
+    ///
+    /// The interfaces are matched with the current connections by ID or interface name. As
+    /// nmstate does, the settings which are not included in the document are kept: e.g.,
+    /// setting the MTU of an interface does not change its IP configuration, and the routes and
+    /// the DNS settings are only changed if the document contains the `routes` or the
+    /// `dns-resolver` sections. The ports which are removed from a bond or a bridge are
+    /// detached, and the absent interfaces which do not exist are ignored.
+    ///
+    /// * `current`: current connections.
+    pub fn merge_into(self, current: &[Connection]) -> Result<Vec<Connection>, NmstateError> {
+        let sections: HashMap<String, InterfaceSections> = self
+            .interfaces
+            .iter()
+            .map(|i| (i.name.clone(), InterfaceSections::from(i)))
+            .collect();
+        let routes = self.routes.is_some();
+        let dns = self.dns_resolver.is_some();
+        let desired = NetworkState::try_from(self)?.connections;
+
+        // keep the ID and the UUID of the connections which already exist
+        let mut uuids = HashMap::new();
+        let mut pending = vec![];
+        for conn in desired {
+            let orig = current.iter().find(|c| {
+                c.id == conn.id || (c.interface.is_some() && c.interface == conn.interface)
+            });
+            if let Some(orig) = orig {
+                uuids.insert(conn.uuid, orig.uuid);
+            }
+            pending.push((conn, orig));
+        }
+
+        let mut connections = vec![];
+        let mut ports = vec![];
+        for (conn, orig) in pending {
+            let is_port = conn.controller.is_some();
+            let mut conn = match orig {
+                Some(orig) => {
+                    let sections = conn.interface.as_ref().and_then(|i| sections.get(i));
+                    merge_connection(orig.clone(), conn, sections, routes, dns)
+                }
+                None if conn.is_removed() => continue,
+                None => conn,
+            };
+            if let Some(controller) = conn.controller.and_then(|c| uuids.get(&c)) {
+                conn.controller = Some(*controller);
+            }
+            if is_port {
+                ports.push(conn.uuid);
+            }
+            connections.push(conn);
+        }
+
+        // the document contains the full list of ports of a bond or a bridge, so the rest of
+        // them are detached
+        let controllers: Vec<Uuid> = connections
+            .iter()
+            .filter(|c| {
+                matches!(
+                    c.config,
+                    ConnectionConfig::Bond(_) | ConnectionConfig::Bridge(_)
+                )
+            })
+            .map(|c| c.uuid)
+            .collect();
+        let is_detached = |c: &Connection| {
+            c.controller.is_some_and(|c| controllers.contains(&c)) && !ports.contains(&c.uuid)
+        };
+        let others: Vec<Connection> = current
+            .iter()
+            .filter(|c| !connections.iter().any(|n| n.uuid == c.uuid))
+            .cloned()
+            .collect();
+        connections.extend(others.into_iter().filter(is_detached));
+        for conn in connections.iter_mut().filter(|c| is_detached(c)) {
+            conn.controller = None;
+            conn.port_config = PortConfig::None;
+        }
+
+        Ok(connections)
+    }
+}
+
+/// Sections of an interface which are included in an nmstate document.
+struct InterfaceSections {
+    mtu: bool,
+    mac_address: bool,
+    ipv4: bool,
+    ipv6: bool,
+}
+
+impl From<&Interface> for InterfaceSections {
+    fn from(interface: &Interface) -> Self {
+        Self {
+            mtu: interface.mtu.is_some(),
+            mac_address: interface.mac_address.is_some(),
+            ipv4: interface.ipv4.is_some(),
+            ipv6: interface.ipv6.is_some(),
+        }
+    }
+}
+
+impl TryFrom<&NetworkState> for NmstateState {
+    type Error = NmstateError;
+
+    fn try_from(state: &NetworkState) -> Result<Self, Self::Error> {
+        let connections: Vec<&Connection> = state
+            .connections
+            .iter()
+            .filter(|c| !c.is_removed())
+            .collect();
+
+        let mut interfaces = vec![];
+        let mut routes = vec![];
+        let mut dns: Option<(&Connection, DnsConfig)> = None;
+
+        for conn in &connections {
+            let interface = interface_from_connection(conn, &connections)?;
+            routes.extend(routes_from_connection(&interface.name, conn));
+
+            let ip_config = &conn.ip_config;
+            if !ip_config.nameservers.is_empty() || !ip_config.dns_searchlist.is_empty() {
+                let config = DnsConfig {
+                    server: ip_config.nameservers.clone(),
+                    search: ip_config.dns_searchlist.clone(),
+                };
+                match &dns {
+                    Some((other, other_config)) if other_config != &config => {
+                        return Err(NmstateError::DnsMismatch(other.id.clone(), conn.id.clone()));
+                    }
+                    Some(_) => {}
+                    None => dns = Some((conn, config)),
+                }
+            }
+            interfaces.push(interface);
+        }
+
+        Ok(NmstateState {
+            dns_resolver: dns.map(|(_, config)| DnsResolver { config }),
+            routes: (!routes.is_empty()).then_some(Routes { config: routes }),
+            interfaces,
+        })
+    }
+}
+
+impl TryFrom<NmstateState> for NetworkState {
+    type Error = NmstateError;
+
+    fn try_from(nmstate: NmstateState) -> Result<Self, Self::Error> {
+        let mut connections: Vec<Connection> = vec![];
+        let mut ports: Vec<(Uuid, Vec<(String, PortConfig)>)> = vec![];
+
+        for interface in nmstate.interfaces {
+            if connections
+                .iter()
+                .any(|c| c.interface.as_deref() == Some(&interface.name))
+            {
+                return Err(NmstateError::DuplicatedInterface(interface.name));
+            }
+            let (conn, conn_ports) = connection_from_interface(interface)?;
+            if !conn_ports.is_empty() {
+                ports.push((conn.uuid, conn_ports));
+            }
+            connections.push(conn);
+        }
+
+        for (controller, conn_ports) in ports {
+            for (name, port_config) in conn_ports {
+                let index = match connections
+                    .iter()
+                    .position(|c| c.interface.as_deref() == Some(&name))
+                {
+                    Some(index) => index,
+                    None => {
+                        let mut conn = Connection::new(name.clone(), DeviceType::Ethernet);
+                        conn.interface = Some(name);
+                        connections.push(conn);
+                        connections.len() - 1
+                    }
+                };
+                let conn = &mut connections[index];
+                conn.controller = Some(controller);
+                conn.port_config = port_config;
+            }
+        }
+
+        if let Some(routes) = nmstate.routes {
+            for route in routes.config {
+                add_route(&mut connections, route)?;
+            }
+        }
+
+        if let Some(dns) = nmstate.dns_resolver {
+            add_dns(&mut connections, dns.config)?;
+        }
+
+        Ok(NetworkState::new(
+            GeneralState::default(),
+            vec![],
+            vec![],
+            connections,
+        ))
+    }
+}
+
+fn unsupported(name: &str, feature: &str) -> NmstateError {
+    NmstateError::Unsupported(name.to_string(), feature.to_string())
+}
+
+fn invalid(name: &str, what: &str) -> NmstateError {
+    NmstateError::InvalidValue(name.to_string(), what.to_string())
+}
+
+fn interface_from_connection(
+    conn: &Connection,
+    connections: &[&Connection],
+) -> Result<Interface, NmstateError> {
+    let name = conn.interface.clone().unwrap_or_else(|| conn.id.clone());
+
+    if conn.ieee_8021x_config.is_some() {
+        return Err(unsupported(&name, "the 802.1X configuration"));
+    }
+    if conn.firewall_zone.is_some() {
+        return Err(unsupported(&name, "the firewall zone"));
+    }
+    if conn.match_config != MatchConfig::default() {
+        return Err(unsupported(&name, "the match configuration"));
+    }
+
+    let mac_address = match &conn.mac_address {
+        MacAddress::Unset => None,
+        MacAddress::MacAddress(mac) => Some(mac.to_string()),
+        other => return Err(unsupported(&name, &format!("the '{}' MAC address", other))),
+    };
+
+    let ports: Vec<&&Connection> = connections
+        .iter()
+        .filter(|c| c.controller == Some(conn.uuid))
+        .collect();
+    let port_names = ports
+        .iter()
+        .map(|c| c.interface.clone().unwrap_or_else(|| c.id.clone()));
+
+    let mut interface = Interface {
+        type_: InterfaceType::Ethernet,
+        state: Some(if conn.is_up() {
+            InterfaceState::Up
+        } else {
+            InterfaceState::Down
+        }),
+        mtu: (conn.mtu > 0).then_some(conn.mtu),
+        mac_address,
+        ipv4: Some(ipv4_from_connection(&name, conn)?),
+        ipv6: Some(ipv6_from_connection(&name, conn)?),
+        link_aggregation: None,
+        vlan: None,
+        bridge: None,
+        infiniband: None,
+        wifi: None,
+        name,
+    };
+
+    match &conn.config {
+        ConnectionConfig::Ethernet => {}
+        ConnectionConfig::Dummy => interface.type_ = InterfaceType::Dummy,
+        ConnectionConfig::Loopback => interface.type_ = InterfaceType::Loopback,
+        ConnectionConfig::Bond(config) => {
+            interface.type_ = InterfaceType::Bond;
+            interface.link_aggregation = Some(LinkAggregation {
+                mode: config.mode.to_string(),
+                options: config
+                    .options
+                    .0
+                    .iter()
+                    .map(|(k, v)| (k.clone(), serde_yaml::Value::String(v.clone())))
+                    .collect(),
+                port: port_names.collect(),
+            });
+        }
+        ConnectionConfig::Bridge(config) => {
+            interface.type_ = InterfaceType::LinuxBridge;
+            let port = ports
+                .iter()
+                .zip(port_names)
+                .map(|(port, name)| match &port.port_config {
+                    PortConfig::Bridge(config) => BridgePort {
+                        name,
+                        stp_priority: config.priority,
+                        stp_path_cost: config.path_cost,
+                    },
+                    PortConfig::None => BridgePort {
+                        name,
+                        ..Default::default()
+                    },
+                })
+                .collect();
+            interface.bridge = Some(Bridge {
+                options: Some(BridgeOptions {
+                    stp: Some(Stp {
+                        enabled: Some(config.stp),
+                        forward_delay: config.forward_delay,
+                        hello_time: config.hello_time,
+                        max_age: config.max_age,
+                        priority: config.priority,
+                    }),
+                    mac_ageing_time: config.ageing_time,
+                }),
+                port,
+            });
+        }
+        ConnectionConfig::Vlan(config) => {
+            interface.type_ = InterfaceType::Vlan;
+            let protocol = match config.protocol {
+                VlanProtocol::IEEE802_1Q => "802.1q",
+                VlanProtocol::IEEE802_1ad => "802.1ad",
+            };
+            interface.vlan = Some(Vlan {
+                base_iface: config.parent.clone(),
+                id: config.id,
+                protocol: Some(protocol.to_string()),
+            });
+        }
+        ConnectionConfig::Infiniband(config) => {
+            interface.type_ = InterfaceType::Infiniband;
+            interface.infiniband = Some(Infiniband {
+                mode: config.transport_mode.to_string(),
+                pkey: config.p_key,
+                base_iface: config.parent.clone(),
+            });
+        }
+        ConnectionConfig::Wireless(config) => {
+            interface.type_ = InterfaceType::Wifi;
+            interface.wifi = Some(wifi_from_config(&interface.name, config)?);
+        }
+        ConnectionConfig::Tun(_) => {
+            return Err(unsupported(&interface.name, "a TUN/TAP device"));
+        }
+        ConnectionConfig::Wireguard(_) => {
+            return Err(unsupported(&interface.name, "a WireGuard tunnel"));
+        }
+        ConnectionConfig::Vrf(_) => {
+            return Err(unsupported(&interface.name, "a VRF"));
+        }
+        ConnectionConfig::OvsBridge(_)
+        | ConnectionConfig::OvsPort(_)
+        | ConnectionConfig::OvsInterface(_) => {
+            return Err(unsupported(&interface.name, "an Open vSwitch device"));
+        }
+    }
+
+    Ok(interface)
+}
+
+fn wifi_from_config(name: &str, config: &WirelessConfig) -> Result<Wifi, NmstateError> {
+    if config.band.is_some() || config.channel != 0 || config.bssid.is_some() {
+        return Err(unsupported(name, "the wireless band, channel or BSSID"));
+    }
+    if config.wep_security.is_some() {
+        return Err(unsupported(name, "the WEP security"));
+    }
+    if !config.group_algorithms.is_empty()
+        || !config.pairwise_algorithms.is_empty()
+        || !config.wpa_protocol_versions.is_empty()
+        || config.pmf != 0
+    {
+        return Err(unsupported(name, "the WPA algorithms, versions or PMF"));
+    }
+
+    Ok(Wifi {
+        ssid: config.ssid.to_string(),
+        mode: Some(config.mode.to_string()),
+        key_management: Some(config.security.to_string()),
+        psk: config.password.clone(),
+        hidden: config.hidden.then_some(true),
+    })
+}
+
+fn addresses(conn: &Connection, ipv4: bool) -> Vec<Address> {
+    conn.ip_config
+        .addresses
+        .iter()
+        .filter(|a| a.is_ipv4() == ipv4)
+        .map(|a| Address {
+            ip: a.address(),
+            prefix_length: a.network_length(),
+        })
+        .collect()
+}
+
+fn ipv4_from_connection(name: &str, conn: &Connection) -> Result<Ipv4, NmstateError> {
+    let ip_config = &conn.ip_config;
+    check_ip_options(name, &ip_config.dhcp4, ip_config.may_fail4)?;
+    if ip_config.dhcp4.fqdn.is_some() {
+        return Err(unsupported(name, "the DHCP FQDN"));
+    }
+
+    let auto_dns = ip_config.ignore_auto_dns.then_some(false);
+    let ipv4 = match ip_config.method4 {
+        Ipv4Method::Disabled => return Ok(Ipv4::default()),
+        Ipv4Method::Auto => Ipv4 {
+            enabled: true,
+            dhcp: Some(true),
+            address: addresses(conn, true),
+            auto_dns,
+            ..Default::default()
+        },
+        Ipv4Method::Manual => Ipv4 {
+            enabled: true,
+            dhcp: Some(false),
+            address: addresses(conn, true),
+            auto_dns,
+            ..Default::default()
+        },
+        Ipv4Method::LinkLocal => return Err(unsupported(name, "the IPv4 link-local method")),
+    };
+    Ok(Ipv4 {
+        auto_route_metric: ip_config.route_metric4,
+        dhcp_client_id: ip_config.dhcp4.client_id.clone(),
+        dhcp_send_hostname: ip_config.dhcp4.send_hostname,
+        dhcp_custom_hostname: ip_config.dhcp4.hostname.clone(),
+        ..ipv4
+    })
+}
+
+/// Fails if the connection uses IP options which nmstate does not support.
+///
+/// * `name`: interface name.
+/// * `dhcp`: DHCP configuration of the address family.
+/// * `may_fail`: "may-fail" setting of the address family.
+fn check_ip_options(
+    name: &str,
+    dhcp: &DhcpConfig,
+    may_fail: Option<bool>,
+) -> Result<(), NmstateError> {
+    if dhcp.timeout.is_some() {
+        return Err(unsupported(name, "the DHCP timeout"));
+    }
+    if may_fail.is_some() {
+        return Err(unsupported(name, "the 'may-fail' setting"));
+    }
+    Ok(())
+}
+
+fn ipv6_from_connection(name: &str, conn: &Connection) -> Result<Ipv6, NmstateError> {
+    let ip_config = &conn.ip_config;
+    check_ip_options(name, &ip_config.dhcp6, ip_config.may_fail6)?;
+    if ip_config.ip6_privacy.is_some() {
+        return Err(unsupported(name, "the IPv6 privacy extensions"));
+    }
+
+    let (dhcp, autoconf) = match ip_config.method6 {
+        Ipv6Method::Disabled => return Ok(Ipv6::default()),
+        Ipv6Method::Auto => (true, true),
+        Ipv6Method::Dhcp => (true, false),
+        Ipv6Method::Manual | Ipv6Method::LinkLocal => (false, false),
+        Ipv6Method::Ignore => return Err(unsupported(name, "the 'ignore' IPv6 method")),
+    };
+
+    Ok(Ipv6 {
+        enabled: true,
+        dhcp: Some(dhcp),
+        autoconf: Some(autoconf),
+        address: addresses(conn, false),
+        auto_dns: ip_config.ignore_auto_dns.then_some(false),
+        auto_route_metric: ip_config.route_metric6,
+        dhcp_duid: ip_config.dhcp6.client_id.clone(),
+        dhcp_send_hostname: ip_config.dhcp6.send_hostname,
+        dhcp_custom_hostname: ip_config.dhcp6.hostname.clone(),
+        addr_gen_mode: ip_config.addr_gen_mode.map(|m| m.to_string()),
+    })
+}
+
+fn routes_from_connection(name: &str, conn: &Connection) -> Vec<Route> {
+    let ip_config = &conn.ip_config;
+    let gateways = [
+        (ip_config.gateway4, "0.0.0.0/0"),
+        (ip_config.gateway6, "::/0"),
+    ];
+    let default_routes = gateways.into_iter().filter_map(|(gateway, destination)| {
+        gateway.map(|gateway| Route {
+            destination: IpInet::from_str(destination).unwrap(),
+            next_hop_interface: Some(name.to_string()),
+            next_hop_address: Some(gateway),
+            metric: None,
+            state: None,
+        })
+    });
+
+    let routes = ip_config
+        .routes4
+        .iter()
+        .chain(ip_config.routes6.iter())
+        .flatten()
+        .map(|route| Route {
+            destination: route.destination,
+            next_hop_interface: Some(name.to_string()),
+            next_hop_address: route.next_hop,
+            metric: route.metric,
+            state: None,
+        });
+
+    default_routes.chain(routes).collect()
+}
+
+/// Converts an nmstate interface into a connection.
+///
+/// It returns the connection and, for bonds and bridges, the names of the ports and their
+/// configuration.
+fn connection_from_interface(
+    interface: Interface,
+) -> Result<(Connection, Vec<(String, PortConfig)>), NmstateError> {
+    let name = interface.name.clone();
+    let type_ = interface.type_;
+    let sections = [
+        (
+            interface.link_aggregation.is_some(),
+            "link-aggregation",
+            InterfaceType::Bond,
+        ),
+        (interface.vlan.is_some(), "vlan", InterfaceType::Vlan),
+        (
+            interface.bridge.is_some(),
+            "bridge",
+            InterfaceType::LinuxBridge,
+        ),
+        (
+            interface.infiniband.is_some(),
+            "infiniband",
+            InterfaceType::Infiniband,
+        ),
+        (interface.wifi.is_some(), "wifi", InterfaceType::Wifi),
+    ];
+    for (present, section, section_type) in sections {
+        if present && type_ != section_type {
+            return Err(NmstateError::UnexpectedSection(
+                name,
+                type_.to_string(),
+                section.to_string(),
+            ));
+        }
+        if !present && type_ == section_type && type_ != InterfaceType::Infiniband {
+            return Err(NmstateError::MissingSection(
+                name,
+                type_.to_string(),
+                section.to_string(),
+            ));
+        }
+    }
+
+    let mut conn = Connection::new(name.clone(), DeviceType::Ethernet);
+    conn.interface = Some(name.clone());
+    match interface.state {
+        Some(InterfaceState::Down) => conn.set_down(),
+        Some(InterfaceState::Absent) => conn.remove(),
+        Some(InterfaceState::Up) | None => conn.status = Status::Up,
+    }
+    conn.mtu = interface.mtu.unwrap_or_default();
+    if let Some(mac) = interface.mac_address {
+        conn.mac_address = match MacAddress::from_str(&mac) {
+            Ok(mac @ MacAddress::MacAddress(_)) => mac,
+            _ => return Err(invalid(&name, "MAC address")),
+        };
+    }
+
+    if let Some(ipv4) = interface.ipv4 {
+        apply_ipv4(&mut conn, ipv4)?;
+    }
+    if let Some(ipv6) = interface.ipv6 {
+        apply_ipv6(&mut conn, ipv6)?;
+    }
+
+    let mut ports = vec![];
+    match type_ {
+        InterfaceType::Ethernet => {}
+        InterfaceType::Dummy => conn.config = ConnectionConfig::Dummy,
+        InterfaceType::Loopback => conn.config = ConnectionConfig::Loopback,
+        InterfaceType::Bond => {
+            let bond = interface.link_aggregation.unwrap_or_default();
+            let mode =
+                BondMode::try_from(bond.mode.as_str()).map_err(|_| invalid(&name, "bond mode"))?;
+            let mut options = std::collections::HashMap::new();
+            for (key, value) in bond.options {
+                let value = match value {
+                    serde_yaml::Value::String(value) => value,
+                    serde_yaml::Value::Number(value) => value.to_string(),
+                    serde_yaml::Value::Bool(value) => value.to_string(),
+                    _ => return Err(invalid(&name, &format!("bond option '{}'", key))),
+                };
+                options.insert(key, value);
+            }
+            conn.config = ConnectionConfig::Bond(BondConfig {
+                mode,
+                options: BondOptions(options),
+            });
+            ports = bond
+                .port
+                .into_iter()
+                .map(|p| (p, PortConfig::None))
+                .collect();
+        }
+        InterfaceType::LinuxBridge => {
+            let bridge = interface.bridge.unwrap_or_default();
+            let options = bridge.options.unwrap_or_default();
+            let stp = options.stp.unwrap_or_default();
+            conn.config = ConnectionConfig::Bridge(BridgeConfig {
+                stp: stp.enabled.unwrap_or_default(),
+                priority: stp.priority,
+                forward_delay: stp.forward_delay,
+                hello_time: stp.hello_time,
+                max_age: stp.max_age,
+                ageing_time: options.mac_ageing_time,
+            });
+            ports = bridge
+                .port
+                .into_iter()
+                .map(|p| {
+                    let config = if p.stp_priority.is_some() || p.stp_path_cost.is_some() {
+                        PortConfig::Bridge(BridgePortConfig {
+                            priority: p.stp_priority,
+                            path_cost: p.stp_path_cost,
+                        })
+                    } else {
+                        PortConfig::None
+                    };
+                    (p.name, config)
+                })
+                .collect();
+        }
+        InterfaceType::Vlan => {
+            let vlan = interface.vlan.unwrap_or_default();
+            let protocol = match vlan.protocol.as_deref() {
+                None | Some("802.1q") => VlanProtocol::IEEE802_1Q,
+                Some("802.1ad") => VlanProtocol::IEEE802_1ad,
+                Some(_) => return Err(invalid(&name, "VLAN protocol")),
+            };
+            conn.config = ConnectionConfig::Vlan(VlanConfig {
+                parent: vlan.base_iface,
+                id: vlan.id,
+                protocol,
+            });
+        }
+        InterfaceType::Infiniband => {
+            let infiniband = interface.infiniband.unwrap_or_default();
+            let transport_mode = if infiniband.mode.is_empty() {
+                InfinibandTransportMode::default()
+            } else {
+                InfinibandTransportMode::from_str(&infiniband.mode)
+                    .map_err(|_| invalid(&name, "InfiniBand mode"))?
+            };
+            conn.config = ConnectionConfig::Infiniband(InfinibandConfig {
+                p_key: infiniband.pkey,
+                parent: infiniband.base_iface,
+                transport_mode,
+            });
+        }
+        InterfaceType::Wifi => {
+            let wifi = interface.wifi.unwrap_or_default();
+            let mut config = WirelessConfig {
+                ssid: SSID(wifi.ssid.into_bytes()),
+                password: wifi.psk,
+                hidden: wifi.hidden.unwrap_or_default(),
+                ..Default::default()
+            };
+            if let Some(mode) = wifi.mode {
+                config.mode = WirelessMode::try_from(mode.as_str())
+                    .map_err(|_| invalid(&name, "wireless mode"))?;
+            }
+            if let Some(key_management) = wifi.key_management {
+                config.security = SecurityProtocol::try_from(key_management.as_str())
+                    .map_err(|_| invalid(&name, "key management"))?;
+            }
+            conn.config = ConnectionConfig::Wireless(config);
+        }
+    }
+
+    Ok((conn, ports))
+}
+
+fn apply_ipv4(conn: &mut Connection, ipv4: Ipv4) -> Result<(), NmstateError> {
+    if !ipv4.enabled {
+        return Ok(());
+    }
+
+    let ip_config = &mut conn.ip_config;
+    ip_config.method4 = if ipv4.dhcp.unwrap_or_default() {
+        Ipv4Method::Auto
+    } else {
+        Ipv4Method::Manual
+    };
+    if ipv4.auto_dns == Some(false) {
+        ip_config.ignore_auto_dns = true;
+    }
+    ip_config.route_metric4 = ipv4.auto_route_metric;
+    ip_config.dhcp4.client_id = ipv4.dhcp_client_id;
+    ip_config.dhcp4.send_hostname = ipv4.dhcp_send_hostname;
+    ip_config.dhcp4.hostname = ipv4.dhcp_custom_hostname;
+    for address in ipv4.address {
+        if !address.ip.is_ipv4() {
+            return Err(invalid(&conn.id, "IPv4 address"));
+        }
+        ip_config.addresses.push(
+            IpInet::new(address.ip, address.prefix_length)
+                .map_err(|_| invalid(&conn.id, "IPv4 prefix length"))?,
+        );
+    }
+    Ok(())
+}
+
+fn apply_ipv6(conn: &mut Connection, ipv6: Ipv6) -> Result<(), NmstateError> {
+    if !ipv6.enabled {
+        return Ok(());
+    }
+
+    let ip_config = &mut conn.ip_config;
+    let dhcp = ipv6.dhcp.unwrap_or_default();
+    let autoconf = ipv6.autoconf.unwrap_or_default();
+    ip_config.method6 = match (dhcp, autoconf) {
+        (_, true) => Ipv6Method::Auto,
+        (true, false) => Ipv6Method::Dhcp,
+        (false, false) if ipv6.address.is_empty() => Ipv6Method::LinkLocal,
+        (false, false) => Ipv6Method::Manual,
+    };
+    if ipv6.auto_dns == Some(false) {
+        ip_config.ignore_auto_dns = true;
+    }
+    ip_config.route_metric6 = ipv6.auto_route_metric;
+    ip_config.dhcp6.client_id = ipv6.dhcp_duid;
+    ip_config.dhcp6.send_hostname = ipv6.dhcp_send_hostname;
+    ip_config.dhcp6.hostname = ipv6.dhcp_custom_hostname;
+    if let Some(mode) = ipv6.addr_gen_mode {
+        let mode = Ipv6AddrGenMode::from_str(&mode)
+            .map_err(|_| invalid(&conn.id, "IPv6 address generation mode"))?;
+        ip_config.addr_gen_mode = Some(mode);
+    }
+    for address in ipv6.address {
+        if !address.ip.is_ipv6() {
+            return Err(invalid(&conn.id, "IPv6 address"));
+        }
+        ip_config.addresses.push(
+            IpInet::new(address.ip, address.prefix_length)
+                .map_err(|_| invalid(&conn.id, "IPv6 prefix length"))?,
+        );
+    }
+    Ok(())
+}
+
+/// Merges a connection built from an nmstate document into the current one.
+///
+/// * `current`: current connection.
+/// * `desired`: connection built from the document.
+/// * `sections`: sections of the interface, or `None` if the document only references it as a
+///   port.
+/// * `routes`: whether the document contains the `routes` section.
+/// * `dns`: whether the document contains the `dns-resolver` section.
+fn merge_connection(
+    mut current: Connection,
+    desired: Connection,
+    sections: Option<&InterfaceSections>,
+    routes: bool,
+    dns: bool,
+) -> Connection {
+    if desired.controller.is_some() {
+        current.controller = desired.controller;
+        current.port_config = desired.port_config;
+    }
+    let Some(sections) = sections else {
+        return current;
+    };
+
+    current.status = desired.status;
+    current.config = desired.config;
+    if sections.mtu {
+        current.mtu = desired.mtu;
+    }
+    if sections.mac_address {
+        current.mac_address = desired.mac_address;
+    }
+
+    let ip_config = &mut current.ip_config;
+    let desired = desired.ip_config;
+    if sections.ipv4 {
+        ip_config.method4 = desired.method4;
+        ip_config.route_metric4 = desired.route_metric4;
+        merge_dhcp(&mut ip_config.dhcp4, desired.dhcp4);
+        ip_config.ignore_auto_dns = desired.ignore_auto_dns;
+        ip_config.addresses.retain(|a| !a.is_ipv4());
+    }
+    if sections.ipv6 {
+        ip_config.method6 = desired.method6;
+        ip_config.route_metric6 = desired.route_metric6;
+        merge_dhcp(&mut ip_config.dhcp6, desired.dhcp6);
+        ip_config.ignore_auto_dns = desired.ignore_auto_dns;
+        if desired.addr_gen_mode.is_some() {
+            ip_config.addr_gen_mode = desired.addr_gen_mode;
+        }
+        ip_config.addresses.retain(|a| a.is_ipv4());
+    }
+    ip_config.addresses.extend(desired.addresses);
+    if routes {
+        ip_config.gateway4 = desired.gateway4;
+        ip_config.gateway6 = desired.gateway6;
+        ip_config.routes4 = desired.routes4;
+        ip_config.routes6 = desired.routes6;
+    }
+    if dns {
+        ip_config.nameservers = desired.nameservers;
+        ip_config.dns_searchlist = desired.dns_searchlist;
+    }
+    current
+}
+
+/// Sets the DHCP options which nmstate supports.
+fn merge_dhcp(current: &mut DhcpConfig, desired: DhcpConfig) {
+    current.client_id = desired.client_id;
+    current.send_hostname = desired.send_hostname;
+    current.hostname = desired.hostname;
+}
+
+/// Adds a route to the connection of its next-hop interface.
+///
+/// Default routes without a metric are stored as the gateway of the connection.
+fn add_route(connections: &mut [Connection], route: Route) -> Result<(), NmstateError> {
+    let destination = route.destination.to_string();
+    if route.state.is_some() {
+        return Err(NmstateError::UnsupportedRoute(
+            destination,
+            "the route state is not supported".to_string(),
+        ));
+    }
+    let Some(name) = route.next_hop_interface else {
+        return Err(NmstateError::UnsupportedRoute(
+            destination,
+            "a next-hop interface is required".to_string(),
+        ));
+    };
+    let conn = connections
+        .iter_mut()
+        .find(|c| c.interface.as_deref() == Some(&name))
+        .ok_or(NmstateError::UnknownInterface(name))?;
+
+    let ip_config = &mut conn.ip_config;
+    let is_ipv4 = route.destination.is_ipv4();
+    if route.destination.network_length() == 0 && route.metric.is_none() {
+        if let Some(gateway) = route.next_hop_address {
+            if gateway.is_ipv4() != is_ipv4 {
+                return Err(NmstateError::UnsupportedRoute(
+                    destination,
+                    "the next-hop address family does not match".to_string(),
+                ));
+            }
+            if is_ipv4 {
+                ip_config.gateway4 = Some(gateway);
+            } else {
+                ip_config.gateway6 = Some(gateway);
+            }
+            return Ok(());
+        }
+    }
+
+    let routes = if is_ipv4 {
+        &mut ip_config.routes4
+    } else {
+        &mut ip_config.routes6
+    };
+    routes.get_or_insert_with(Vec::new).push(IpRoute {
+        destination: route.destination,
+        next_hop: route.next_hop_address,
+        metric: route.metric,
+    });
+    Ok(())
+}
+
+/// Applies the global DNS settings to the connections.
+///
+/// The settings are applied to the connections which have a gateway or, if there are none, to
+/// all the connections which have some IP configuration and are not ports.
+fn add_dns(connections: &mut [Connection], dns: DnsConfig) -> Result<(), NmstateError> {
+    if dns.server.is_empty() && dns.search.is_empty() {
+        return Ok(());
+    }
+
+    let has_gateway = |c: &Connection| {
+        !c.is_removed() && (c.ip_config.gateway4.is_some() || c.ip_config.gateway6.is_some())
+    };
+    let has_ip = |c: &Connection| {
+        !c.is_removed()
+            && c.controller.is_none()
+            && (c.ip_config.method4 != Ipv4Method::Disabled
+                || c.ip_config.method6 != Ipv6Method::Disabled)
+    };
+    let any_gateway = connections.iter().any(has_gateway);
+
+    let mut found = false;
+    for conn in connections.iter_mut() {
+        let selected = if any_gateway {
+            has_gateway(conn)
+        } else {
+            has_ip(conn)
+        };
+        if selected {
+            conn.ip_config.nameservers = dns.server.clone();
+            conn.ip_config.dns_searchlist = dns.search.clone();
+            found = true;
+        }
+    }
+
+    if !found {
+        return Err(NmstateError::NoDnsInterface);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::model::Ipv6Privacy;
+
+    const NMSTATE: &str = r#"
+dns-resolver:
+  config:
+    server:
+    - 192.168.1.1
+    search:
+    - example.net
+routes:
+  config:
+  - destination: 0.0.0.0/0
+    next-hop-interface: br0.10
+    next-hop-address: 192.168.1.1
+  - destination: 10.0.0.0/8
+    next-hop-interface: br0.10
+    next-hop-address: 192.168.1.254
+    metric: 100
+interfaces:
+- name: eth0
+  type: ethernet
+  state: up
+- name: eth1
+  type: ethernet
+  state: up
+  mtu: 9000
+- name: bond0
+  type: bond
+  state: up
+  link-aggregation:
+    mode: active-backup
+    options:
+      miimon: 100
+    port:
+    - eth0
+    - eth1
+- name: br0
+  type: linux-bridge
+  state: up
+  bridge:
+    options:
+      stp:
+        enabled: true
+        priority: 4096
+    port:
+    - name: bond0
+      stp-path-cost: 100
+- name: br0.10
+  type: vlan
+  state: up
+  vlan:
+    base-iface: br0
+    id: 10
+  ipv4:
+    enabled: true
+    dhcp: false
+    address:
+    - ip: 192.168.1.10
+      prefix-length: 24
+  ipv6:
+    enabled: true
+    dhcp: true
+    autoconf: true
+    auto-route-metric: 200
+    dhcp-duid: ll
+    addr-gen-mode: eui64
+- name: ib0
+  type: infiniband
+  state: down
+  infiniband:
+    mode: connected
+    pkey: 32769
+- name: wlan0
+  type: wifi
+  wifi:
+    ssid: AgamaNetwork
+    key-management: wpa-psk
+    psk: secret
+"#;
+
+    fn find<'a>(state: &'a NetworkState, id: &str) -> &'a Connection {
+        state
+            .get_connection(id)
+            .unwrap_or_else(|| panic!("Connection '{}' not found", id))
+    }
+
+    #[test]
+    fn test_nmstate_to_state() {
+        let nmstate = NmstateState::from_yaml(NMSTATE).unwrap();
+        let state = NetworkState::try_from(nmstate).unwrap();
+        assert_eq!(state.connections.len(), 7);
+
+        let bond0 = find(&state, "bond0");
+        let ConnectionConfig::Bond(config) = &bond0.config else {
+            panic!("Unexpected config: {:?}", bond0.config);
+        };
+        assert_eq!(config.mode, BondMode::ActiveBackup);
+        assert_eq!(config.options.0.get("miimon"), Some(&"100".to_string()));
+        assert_eq!(find(&state, "eth0").controller, Some(bond0.uuid));
+        assert_eq!(find(&state, "eth1").mtu, 9000);
+
+        let br0 = find(&state, "br0");
+        assert_eq!(bond0.controller, Some(br0.uuid));
+        assert_eq!(
+            bond0.port_config,
+            PortConfig::Bridge(BridgePortConfig {
+                priority: None,
+                path_cost: Some(100)
+            })
+        );
+
+        let vlan = find(&state, "br0.10");
+        assert!(matches!(vlan.config, ConnectionConfig::Vlan(_)));
+        let ip_config = &vlan.ip_config;
+        assert_eq!(ip_config.method4, Ipv4Method::Manual);
+        assert_eq!(ip_config.method6, Ipv6Method::Auto);
+        assert_eq!(
+            ip_config.addresses,
+            vec![IpInet::from_str("192.168.1.10/24").unwrap()]
+        );
+        assert_eq!(
+            ip_config.gateway4,
+            Some(IpAddr::from_str("192.168.1.1").unwrap())
+        );
+        assert_eq!(ip_config.routes4.as_ref().unwrap()[0].metric, Some(100));
+        assert_eq!(
+            ip_config.nameservers,
+            vec![IpAddr::from_str("192.168.1.1").unwrap()]
+        );
+        assert_eq!(ip_config.dns_searchlist, vec!["example.net".to_string()]);
+        assert_eq!(ip_config.route_metric6, Some(200));
+        assert_eq!(ip_config.dhcp6.client_id, Some("ll".to_string()));
+        assert_eq!(ip_config.addr_gen_mode, Some(Ipv6AddrGenMode::Eui64));
+        assert!(find(&state, "eth0").ip_config.nameservers.is_empty());
+
+        let ib0 = find(&state, "ib0");
+        assert!(!ib0.is_up());
+        let ConnectionConfig::Infiniband(config) = &ib0.config else {
+            panic!("Unexpected config: {:?}", ib0.config);
+        };
+        assert_eq!(config.p_key, Some(0x8001));
+        assert_eq!(config.transport_mode, InfinibandTransportMode::Connected);
+
+        let wlan0 = find(&state, "wlan0");
+        let ConnectionConfig::Wireless(config) = &wlan0.config else {
+            panic!("Unexpected config: {:?}", wlan0.config);
+        };
+        assert_eq!(config.ssid.to_string(), "AgamaNetwork");
+        assert_eq!(config.security, SecurityProtocol::WPA2);
+    }
+
+    #[test]
+    fn test_nmstate_round_trip() {
+        let nmstate = NmstateState::from_yaml(NMSTATE).unwrap();
+        let state = NetworkState::try_from(nmstate).unwrap();
+        let exported = NmstateState::try_from(&state).unwrap();
+        let yaml = exported.to_yaml().unwrap();
+
+        let reimported = NetworkState::try_from(NmstateState::from_yaml(&yaml).unwrap()).unwrap();
+        assert_eq!(reimported.connections.len(), state.connections.len());
+        for conn in &state.connections {
+            let other = find(&reimported, &conn.id);
+            assert_eq!(other.config, conn.config);
+            assert_eq!(other.ip_config, conn.ip_config);
+            assert_eq!(other.port_config, conn.port_config);
+            assert_eq!(other.mtu, conn.mtu);
+            assert_eq!(other.status, conn.status);
+            assert_eq!(other.controller.is_some(), conn.controller.is_some());
+        }
+
+        let vlan = exported
+            .interfaces
+            .iter()
+            .find(|i| i.name == "br0.10")
+            .unwrap();
+        assert_eq!(
+            vlan.vlan.as_ref().unwrap().protocol,
+            Some("802.1q".to_string())
+        );
+        let bond = exported
+            .interfaces
+            .iter()
+            .find(|i| i.name == "bond0")
+            .unwrap();
+        assert_eq!(
+            bond.link_aggregation.as_ref().unwrap().port,
+            vec!["eth0", "eth1"]
+        );
+    }
+
+    #[test]
+    fn test_nmstate_merge_into() {
+        let mut eth0 = Connection::new("Wired connection 1".to_string(), DeviceType::Ethernet);
+        eth0.interface = Some("eth0".to_string());
+        eth0.ip_config.method4 = Ipv4Method::Manual;
+        eth0.ip_config.addresses = vec![
+            IpInet::from_str("192.168.1.10/24").unwrap(),
+            IpInet::from_str("2001:db8::10/64").unwrap(),
+        ];
+        eth0.ip_config.gateway4 = Some(IpAddr::from_str("192.168.1.1").unwrap());
+        eth0.ip_config.nameservers = vec![IpAddr::from_str("192.168.1.1").unwrap()];
+        let mut bond0 = Connection::new("bond0".to_string(), DeviceType::Bond);
+        bond0.interface = Some("bond0".to_string());
+        let mut ports = vec![];
+        for name in ["eth1", "eth2"] {
+            let mut port = Connection::new(name.to_string(), DeviceType::Ethernet);
+            port.interface = Some(name.to_string());
+            port.controller = Some(bond0.uuid);
+            ports.push(port);
+        }
+        let current = [vec![eth0.clone(), bond0.clone()], ports].concat();
+
+        let nmstate = NmstateState::from_yaml(
+            r#"
+interfaces:
+- name: eth0
+  type: ethernet
+  mtu: 9000
+  ipv6:
+    enabled: false
+- name: bond0
+  type: bond
+  link-aggregation:
+    mode: active-backup
+    port:
+    - eth1
+- name: eth3
+  type: ethernet
+- name: eth9
+  type: ethernet
+  state: absent
+"#,
+        )
+        .unwrap();
+        let connections = nmstate.merge_into(&current).unwrap();
+        let state = NetworkState::new(GeneralState::default(), vec![], vec![], connections);
+
+        let merged = find(&state, "Wired connection 1");
+        assert_eq!(merged.uuid, eth0.uuid);
+        assert_eq!(merged.mtu, 9000);
+        assert_eq!(merged.ip_config.method4, Ipv4Method::Manual);
+        assert_eq!(merged.ip_config.method6, Ipv6Method::Disabled);
+        assert_eq!(
+            merged.ip_config.addresses,
+            vec![IpInet::from_str("192.168.1.10/24").unwrap()]
+        );
+        assert_eq!(merged.ip_config.gateway4, eth0.ip_config.gateway4);
+        assert_eq!(merged.ip_config.nameservers, eth0.ip_config.nameservers);
+
+        assert_eq!(find(&state, "bond0").uuid, bond0.uuid);
+        assert_eq!(find(&state, "eth1").controller, Some(bond0.uuid));
+        assert_eq!(find(&state, "eth2").controller, None);
+        assert!(state.get_connection("eth3").is_some());
+        assert!(state.get_connection("eth9").is_none());
+    }
+
+    #[test]
+    fn test_nmstate_import_errors() {
+        let cases = [
+            (
+                "interfaces:\n- name: ovs0\n  type: ovs-bridge\n",
+                "unknown variant `ovs-bridge`",
+            ),
+            (
+                "interfaces:\n- name: eth0\n  type: ethernet\n  ethtool: {}\n",
+                "unknown field `ethtool`",
+            ),
+            (
+                "interfaces:\n- name: bond0\n  type: bond\n",
+                "requires a 'link-aggregation' section",
+            ),
+            (
+                "interfaces:\n- name: eth0\n  type: ethernet\n  vlan:\n    base-iface: eth1\n    id: 10\n",
+                "cannot have a 'vlan' section",
+            ),
+            (
+                "interfaces:\n- name: eth0\n  type: ethernet\n- name: eth0\n  type: ethernet\n",
+                "defined more than once",
+            ),
+            (
+                "routes:\n  config:\n  - destination: 10.0.0.0/8\n    next-hop-interface: eth9\n",
+                "Unknown interface 'eth9'",
+            ),
+            (
+                "routes:\n  config:\n  - destination: 10.0.0.0/8\n    next-hop-address: 10.0.0.1\n",
+                "a next-hop interface is required",
+            ),
+            (
+                "dns-resolver:\n  config:\n    server:\n    - 10.0.0.1\n",
+                "no interface to apply the DNS settings",
+            ),
+            (
+                "interfaces:\n- name: bond0\n  type: bond\n  link-aggregation:\n    mode: foo\n    port: []\n",
+                "invalid bond mode",
+            ),
+        ];
+
+        for (yaml, message) in cases {
+            let error = NmstateState::from_yaml(yaml)
+                .and_then(NetworkState::try_from)
+                .unwrap_err();
+            assert!(
+                error.to_string().contains(message),
+                "'{}' does not contain '{}'",
+                error,
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn test_state_to_nmstate_errors() {
+        let mut tun = Connection::new("tun0".to_string(), DeviceType::Ethernet);
+        tun.config = ConnectionConfig::Tun(Default::default());
+        let mut zone = Connection::new("eth0".to_string(), DeviceType::Ethernet);
+        zone.firewall_zone = Some("public".to_string());
+        let mut eth1 = Connection::new("eth1".to_string(), DeviceType::Ethernet);
+        eth1.ip_config.nameservers = vec![IpAddr::from_str("10.0.0.1").unwrap()];
+        let mut eth2 = Connection::new("eth2".to_string(), DeviceType::Ethernet);
+        eth2.ip_config.nameservers = vec![IpAddr::from_str("10.0.0.2").unwrap()];
+        let mut timeout = Connection::new("eth3".to_string(), DeviceType::Ethernet);
+        timeout.ip_config.dhcp4.timeout = Some(30);
+        let mut privacy = Connection::new("eth4".to_string(), DeviceType::Ethernet);
+        privacy.ip_config.ip6_privacy = Some(Ipv6Privacy::Disabled);
+
+        let cases = [
+            (vec![tun], "a TUN/TAP device"),
+            (vec![zone], "the firewall zone"),
+            (vec![timeout], "the DHCP timeout"),
+            (vec![privacy], "the IPv6 privacy extensions"),
+            (
+                vec![eth1, eth2],
+                "The DNS settings of 'eth1' and 'eth2' differ",
+            ),
+        ];
+
+        for (connections, message) in cases {
+            let state = NetworkState::new(GeneralState::default(), vec![], vec![], connections);
+            let error = NmstateState::try_from(&state).unwrap_err();
+            assert!(
+                error.to_string().contains(message),
+                "'{}' does not contain '{}'",
+                error,
+                message
+            );
+        }
+    }
+}
@@ -0,0 +1,553 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! Implements a simulated network adapter.
+//!
+//! The [SimulatedAdapter] keeps the network state in memory, so it does not touch the system
+//! configuration at all. Additionally, it includes a [Watcher] that turns a set of
+//! [simulated events](SimulatedEvent) (link changes, DHCP leases, Wi-Fi scans, etc.) into
+//! [actions](crate::network::Action). The events can be scripted in advance (see
+//! [SimulatedAdapter::with_script]) or sent at any time through the [SimulatedAdapter::events]
+//! channel.
+//!
+//! It is useful for testing and for developing the user interface without a real network.
+
+use crate::network::{
+    adapter::Watcher,
+    model::{AccessPoint, Connection, Device, GeneralState, IpConfig, NetworkState, StateConfig},
+    Action, Adapter, NetworkAdapterError,
+};
+use agama_lib::network::types::{DeviceState, DeviceType, SSID};
+use async_trait::async_trait;
+use cidr::IpInet;
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    oneshot,
+};
+
+/// Simulated network event.
+#[derive(Clone, Debug)]
+pub enum SimulatedEvent {
+    /// The link of the given device is up.
+    LinkUp(String),
+    /// The link of the given device is down.
+    LinkDown(String),
+    /// The given device got its IP configuration from a DHCP server.
+    DhcpLease {
+        device: String,
+        ip_config: Box<IpConfig>,
+    },
+    /// A Wi-Fi scan finished with the given access points.
+    WifiScan(Vec<AccessPoint>),
+}
+
+/// List of events, each one preceded by the time to wait before emitting it.
+pub type SimulatedScript = Vec<(Duration, SimulatedEvent)>;
+
+/// Network adapter that works over an in-memory network state.
+pub struct SimulatedAdapter {
+    state: Arc<Mutex<NetworkState>>,
+    script: SimulatedScript,
+    events_tx: UnboundedSender<SimulatedEvent>,
+    events_rx: Mutex<Option<UnboundedReceiver<SimulatedEvent>>>,
+    /// State to restore if the pending changes are rolled back.
+    checkpoint: Mutex<Option<NetworkState>>,
+}
+
+impl SimulatedAdapter {
+    /// Builds an adapter with the given initial state.
+    ///
+    /// * `state`: initial network state.
+    pub fn new(state: NetworkState) -> Self {
+        let (events_tx, events_rx) = unbounded_channel();
+        Self {
+            state: Arc::new(Mutex::new(state)),
+            script: vec![],
+            events_tx,
+            events_rx: Mutex::new(Some(events_rx)),
+            checkpoint: Mutex::new(None),
+        }
+    }
+
+    /// Builds an adapter that simulates a laptop with a wired and a wireless device.
+    ///
+    /// The wired device gets connected and obtains a DHCP lease after a few seconds. Then, a
+    /// Wi-Fi scan finds a few access points.
+    pub fn demo() -> Self {
+        let general_state = GeneralState {
+            hostname: "agama".to_string(),
+            connectivity: true,
+            wireless_enabled: true,
+            networking_enabled: true,
+        };
+        let devices = vec![
+            demo_device("lo", DeviceType::Loopback, DeviceState::Activated),
+            demo_device("eth0", DeviceType::Ethernet, DeviceState::Disconnected),
+            demo_device("wlan0", DeviceType::Wireless, DeviceState::Disconnected),
+        ];
+        let mut loopback = Connection::new("lo".to_string(), DeviceType::Loopback);
+        loopback.interface = Some("lo".to_string());
+        let mut wired = Connection::new("Wired connection 1".to_string(), DeviceType::Ethernet);
+        wired.interface = Some("eth0".to_string());
+
+        let mut state = NetworkState::new(general_state, vec![], devices, vec![loopback, wired]);
+        state.global.hostname = Some("agama".to_string());
+        let lease = IpConfig {
+            addresses: vec![IpInet::from_str("192.168.122.100/24").unwrap()],
+            nameservers: vec!["192.168.122.1".parse().unwrap()],
+            gateway4: Some("192.168.122.1".parse().unwrap()),
+            ..Default::default()
+        };
+        let access_points = vec![
+            demo_access_point("Agama", "00:11:22:33:44:01", 90),
+            demo_access_point("Guests", "00:11:22:33:44:02", 60),
+            demo_access_point("Neighbors", "00:11:22:33:44:03", 25),
+        ];
+
+        Self::new(state).with_script(vec![
+            (
+                Duration::from_secs(2),
+                SimulatedEvent::LinkUp("eth0".to_string()),
+            ),
+            (
+                Duration::from_secs(1),
+                SimulatedEvent::DhcpLease {
+                    device: "eth0".to_string(),
+                    ip_config: Box::new(lease),
+                },
+            ),
+            (
+                Duration::from_secs(2),
+                SimulatedEvent::WifiScan(access_points),
+            ),
+        ])
+    }
+
+    /// Sets the events to emit once the watcher is running.
+    ///
+    /// * `script`: list of events and the time to wait before emitting each of them.
+    pub fn with_script(mut self, script: SimulatedScript) -> Self {
+        self.script = script;
+        self
+    }
+
+    /// Returns a channel to emit events once the watcher is running.
+    pub fn events(&self) -> UnboundedSender<SimulatedEvent> {
+        self.events_tx.clone()
+    }
+}
+
+fn demo_device(name: &str, type_: DeviceType, state: DeviceState) -> Device {
+    Device {
+        name: name.to_string(),
+        type_,
+        state,
+        ..Default::default()
+    }
+}
+
+fn demo_access_point(ssid: &str, hw_address: &str, strength: u8) -> AccessPoint {
+    AccessPoint {
+        ssid: SSID(ssid.as_bytes().to_vec()),
+        hw_address: hw_address.to_string(),
+        strength,
+        ..Default::default()
+    }
+}
+
+#[async_trait]
+impl Adapter for SimulatedAdapter {
+    async fn read(&self, config: StateConfig) -> Result<NetworkState, NetworkAdapterError> {
+        let current = self.state.lock().unwrap();
+        // the general state is always included because it is cheap to get
+        let mut state = NetworkState {
+            general_state: current.general_state.clone(),
+            ..Default::default()
+        };
+
+        if config.devices {
+            state.devices = current.devices.clone();
+        }
+
+        if config.connections {
+            state.connections = current.connections.clone();
+        }
+
+        if config.access_points {
+            state.access_points = current.access_points.clone();
+        }
+
+        if config.global {
+            state.global = current.global.clone();
+        }
+
+        Ok(state)
+    }
+
+    async fn write(&self, network: &NetworkState) -> Result<(), NetworkAdapterError> {
+        if self.checkpoint.lock().unwrap().is_some() {
+            return Err(NetworkAdapterError::PendingChanges);
+        }
+
+        let mut current = self.state.lock().unwrap();
+        current.general_state = network.general_state.clone();
+        current.global = network.global.clone();
+        current.connections = network
+            .connections
+            .iter()
+            .filter(|c| !c.is_removed())
+            .cloned()
+            .collect();
+        Ok(())
+    }
+
+    async fn write_with_checkpoint(
+        &self,
+        network: &NetworkState,
+        _timeout: Duration,
+    ) -> Result<(), NetworkAdapterError> {
+        let saved = self.state.lock().unwrap().clone();
+        self.write(network).await?;
+        *self.checkpoint.lock().unwrap() = Some(saved);
+        Ok(())
+    }
+
+    async fn confirm(&self) -> Result<(), NetworkAdapterError> {
+        self.checkpoint
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or(NetworkAdapterError::NoPendingChanges)?;
+        Ok(())
+    }
+
+    async fn rollback(&self) -> Result<(), NetworkAdapterError> {
+        let saved = self
+            .checkpoint
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or(NetworkAdapterError::NoPendingChanges)?;
+        // devices are not part of the configuration, so they are kept as they are
+        let mut current = self.state.lock().unwrap();
+        current.general_state = saved.general_state;
+        current.global = saved.global;
+        current.connections = saved.connections;
+        Ok(())
+    }
+
+    fn watcher(&self) -> Option<Box<dyn Watcher + Send>> {
+        let events = self.events_rx.lock().unwrap().take()?;
+        Some(Box::new(SimulatedWatcher {
+            state: Arc::clone(&self.state),
+            script: self.script.clone(),
+            events_tx: self.events_tx.clone(),
+            events_rx: events,
+        }))
+    }
+}
+
+/// Turns the simulated events into network actions.
+struct SimulatedWatcher {
+    state: Arc<Mutex<NetworkState>>,
+    script: SimulatedScript,
+    events_tx: UnboundedSender<SimulatedEvent>,
+    events_rx: UnboundedReceiver<SimulatedEvent>,
+}
+
+#[async_trait]
+impl Watcher for SimulatedWatcher {
+    async fn run(
+        mut self: Box<Self>,
+        actions: UnboundedSender<Action>,
+    ) -> Result<(), NetworkAdapterError> {
+        let script = std::mem::take(&mut self.script);
+        let events_tx = self.events_tx.clone();
+        tokio::spawn(async move {
+            for (delay, event) in script {
+                tokio::time::sleep(delay).await;
+                if events_tx.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+
+        while let Some(event) = self.events_rx.recv().await {
+            log::info!("Simulated network event: {:?}", &event);
+            if self.dispatch(event, &actions).await.is_err() {
+                log::warn!("The network system is not running anymore");
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SimulatedWatcher {
+    /// Updates the state according to the event and emits the corresponding action.
+    ///
+    /// It fails when the actions channel is closed.
+    async fn dispatch(
+        &self,
+        event: SimulatedEvent,
+        actions: &UnboundedSender<Action>,
+    ) -> Result<(), ()> {
+        let action = match event {
+            SimulatedEvent::LinkUp(name) => self.update_device(&name, |device| {
+                device.state = DeviceState::Activated;
+            }),
+            SimulatedEvent::LinkDown(name) => self.update_device(&name, |device| {
+                device.state = DeviceState::Disconnected;
+                device.ip_config = None;
+            }),
+            SimulatedEvent::DhcpLease { device, ip_config } => {
+                self.update_device(&device, |device| {
+                    device.state = DeviceState::Activated;
+                    device.ip_config = Some(*ip_config);
+                })
+            }
+            SimulatedEvent::WifiScan(access_points) => {
+                self.state.lock().unwrap().access_points = access_points;
+                let (tx, rx) = oneshot::channel();
+                actions.send(Action::RefreshScan(tx)).map_err(|_| ())?;
+                if let Ok(Err(error)) = rx.await {
+                    log::error!("Could not refresh the access points: {}", error);
+                }
+                return Ok(());
+            }
+        };
+
+        if let Some(action) = action {
+            actions.send(action).map_err(|_| ())?;
+        }
+        Ok(())
+    }
+
+    /// Updates a device and returns the action to notify the change.
+    ///
+    /// * `name`: device name.
+    /// * `func`: function to update the device.
+    fn update_device<F>(&self, name: &str, func: F) -> Option<Action>
+    where
+        F: FnOnce(&mut Device),
+    {
+        let mut state = self.state.lock().unwrap();
+        let Some(device) = state.devices.iter_mut().find(|d| d.name == name) else {
+            log::warn!("Unknown simulated device '{}'", name);
+            return None;
+        };
+        func(device);
+        Some(Action::UpdateDevice(
+            name.to_string(),
+            Box::new(device.clone()),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::network::{
+        model::{GlobalConfig, NetworkChange},
+        NetworkSystem,
+    };
+    use tokio::sync::broadcast::Receiver;
+
+    fn build_state() -> NetworkState {
+        let general_state = GeneralState {
+            wireless_enabled: true,
+            ..Default::default()
+        };
+        let device = demo_device("eth0", DeviceType::Ethernet, DeviceState::Disconnected);
+        let eth0 = Connection::new("eth0".to_string(), DeviceType::Ethernet);
+        NetworkState::new(general_state, vec![], vec![device], vec![eth0])
+    }
+
+    async fn next_device_update(changes: &mut Receiver<NetworkChange>) -> Device {
+        loop {
+            if let NetworkChange::DeviceUpdated(_, device) = changes.recv().await.unwrap() {
+                return *device;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_device_events() {
+        let adapter = SimulatedAdapter::new(build_state());
+        let events = adapter.events();
+        let client = NetworkSystem::new(adapter).start().await.unwrap();
+        let mut changes = client.subscribe();
+
+        events
+            .send(SimulatedEvent::LinkUp("eth0".to_string()))
+            .unwrap();
+        let device = next_device_update(&mut changes).await;
+        assert_eq!(device.state, DeviceState::Activated);
+
+        let ip_config = IpConfig {
+            addresses: vec![IpInet::from_str("10.0.0.2/24").unwrap()],
+            ..Default::default()
+        };
+        events
+            .send(SimulatedEvent::DhcpLease {
+                device: "eth0".to_string(),
+                ip_config: Box::new(ip_config.clone()),
+            })
+            .unwrap();
+        let device = next_device_update(&mut changes).await;
+        assert_eq!(device.ip_config, Some(ip_config));
+
+        events
+            .send(SimulatedEvent::LinkDown("eth0".to_string()))
+            .unwrap();
+        next_device_update(&mut changes).await;
+        let devices = client.get_devices().await.unwrap();
+        let device = devices.first().unwrap();
+        assert_eq!(device.state, DeviceState::Disconnected);
+        assert_eq!(device.ip_config, None);
+    }
+
+    #[tokio::test]
+    async fn test_scripted_wifi_scan() {
+        let access_point = demo_access_point("Agama", "00:11:22:33:44:55", 80);
+        let adapter = SimulatedAdapter::new(build_state()).with_script(vec![(
+            Duration::from_millis(10),
+            SimulatedEvent::WifiScan(vec![access_point]),
+        )]);
+        let client = NetworkSystem::new(adapter).start().await.unwrap();
+        assert!(client.get_access_points().await.unwrap().is_empty());
+
+        let mut access_points = vec![];
+        for _ in 0..100 {
+            access_points = client.get_access_points().await.unwrap();
+            if !access_points.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(access_points.first().unwrap().ssid.to_string(), "Agama");
+    }
+
+    #[tokio::test]
+    async fn test_write_connections() {
+        let adapter = SimulatedAdapter::new(build_state());
+        let client = NetworkSystem::new(adapter).start().await.unwrap();
+
+        let wlan0 = Connection::new("wlan0".to_string(), DeviceType::Wireless);
+        client.add_connection(wlan0).await.unwrap();
+        client.remove_connection("eth0").await.unwrap();
+        client.apply().await.unwrap();
+
+        let connections = client.get_connections().await.unwrap();
+        let ids: Vec<_> = connections.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["wlan0"]);
+    }
+
+    #[tokio::test]
+    async fn test_write_cmdline_connections() {
+        let adapter = SimulatedAdapter::new(build_state());
+        let state = Arc::clone(&adapter.state);
+        let client = NetworkSystem::new(adapter)
+            .with_cmdline("ip=eth0:dhcp ip=eth1:dhcp team=team0:eth2")
+            .start()
+            .await
+            .unwrap();
+
+        let written: Vec<_> = state
+            .lock()
+            .unwrap()
+            .connections
+            .iter()
+            .map(|c| c.id.clone())
+            .collect();
+        assert_eq!(written, vec!["eth0", "eth1"]);
+        assert!(client.get_connection("eth1").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_apply_with_confirmation() {
+        let adapter = SimulatedAdapter::new(build_state());
+        let client = NetworkSystem::new(adapter).start().await.unwrap();
+
+        let wlan0 = Connection::new("wlan0".to_string(), DeviceType::Wireless);
+        client.add_connection(wlan0).await.unwrap();
+        client
+            .apply_with_confirmation(Duration::from_millis(100))
+            .await
+            .unwrap();
+        client.confirm().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let connections = client.get_connections().await.unwrap();
+        assert_eq!(connections.len(), 2);
+        assert!(client.confirm().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_apply_rollback() {
+        let adapter = SimulatedAdapter::new(build_state());
+        let client = NetworkSystem::new(adapter).start().await.unwrap();
+        let mut changes = client.subscribe();
+
+        client.remove_connection("eth0").await.unwrap();
+        client
+            .apply_with_confirmation(Duration::from_millis(100))
+            .await
+            .unwrap();
+        assert!(client.get_connections().await.unwrap().is_empty());
+
+        let change = tokio::time::timeout(Duration::from_secs(5), changes.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(change, NetworkChange::ChangesRolledBack));
+
+        let connections = client.get_connections().await.unwrap();
+        let ids: Vec<_> = connections.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["eth0"]);
+    }
+
+    #[tokio::test]
+    async fn test_update_global_config() {
+        let adapter = SimulatedAdapter::new(build_state());
+        let client = NetworkSystem::new(adapter).start().await.unwrap();
+        let mut changes = client.subscribe();
+
+        let config = GlobalConfig {
+            hostname: Some("install".to_string()),
+            nameservers: vec!["192.168.1.1".parse().unwrap()],
+            ..Default::default()
+        };
+        client.update_global_config(config.clone()).unwrap();
+        let change = tokio::time::timeout(Duration::from_secs(5), changes.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(change, NetworkChange::GlobalConfigChanged(c) if c == config));
+
+        client.apply().await.unwrap();
+        assert_eq!(client.get_global_config().await.unwrap(), config);
+    }
+}
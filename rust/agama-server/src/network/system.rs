@@ -19,20 +19,24 @@
 // find current contact information at www.suse.com.
 
 use super::{
+    cmdline,
     error::NetworkStateError,
     model::{AccessPoint, Device, NetworkChange, StateConfig},
     NetworkAdapterError,
 };
 use crate::network::{
-    model::{Connection, GeneralState},
+    model::{Connection, GeneralState, GlobalConfig},
     Action, Adapter, NetworkState,
 };
 use agama_lib::{error::ServiceError, network::types::DeviceType};
-use std::error::Error;
-use tokio::sync::{
-    broadcast::{self, Receiver},
-    mpsc::{self, error::SendError, UnboundedReceiver, UnboundedSender},
-    oneshot::{self, error::RecvError},
+use std::{error::Error, time::Duration};
+use tokio::{
+    sync::{
+        broadcast::{self, Receiver},
+        mpsc::{self, error::SendError, UnboundedReceiver, UnboundedSender},
+        oneshot::{self, error::RecvError},
+    },
+    time::{self, Instant},
 };
 use uuid::Uuid;
 
@@ -78,6 +82,7 @@ pub enum NetworkSystemError {
 /// ```
 pub struct NetworkSystem<T: Adapter + Send> {
     adapter: T,
+    cmdline: Option<String>,
 }
 
 impl<T: Adapter + Send + Sync + 'static> NetworkSystem<T> {
@@ -88,7 +93,23 @@ impl<T: Adapter + Send + Sync + 'static> NetworkSystem<T> {
     ///
     /// * `adapter`: networking configuration adapter.
     pub fn new(adapter: T) -> Self {
-        Self { adapter }
+        Self {
+            adapter,
+            cmdline: None,
+        }
+    }
+
+    /// Sets the kernel command line whose network options are applied on startup.
+    ///
+    /// The new connections are written to the system when it starts. The ones which are
+    /// already known (e.g., because NetworkManager's initrd generator created them) are not
+    /// modified, and the invalid options are skipped. See [cmdline::parse] for the supported
+    /// options.
+    ///
+    /// * `cmdline`: kernel command line.
+    pub fn with_cmdline(mut self, cmdline: &str) -> Self {
+        self.cmdline = Some(cmdline.to_string());
+        self
     }
 
     /// Starts the network configuration service and returns a client for communication purposes.
@@ -96,7 +117,25 @@ impl<T: Adapter + Send + Sync + 'static> NetworkSystem<T> {
     /// This function starts the server (using [NetworkSystemServer]) on a separate
     /// task. All the communication is performed through the returned [NetworkSystemClient].
     pub async fn start(self) -> Result<NetworkSystemClient, NetworkSystemError> {
-        let state = self.adapter.read(StateConfig::default()).await?;
+        let mut state = self.adapter.read(StateConfig::default()).await?;
+        if let Some(cmdline) = &self.cmdline {
+            let (connections, errors) = cmdline::parse(cmdline);
+            for e in errors {
+                log::warn!(
+                    "Skipping a network option from the kernel command line: {}",
+                    e
+                );
+            }
+            if cmdline::merge(&mut state, connections) > 0 {
+                if let Err(e) = self.adapter.write(&state).await {
+                    log::error!(
+                        "Could not write the connections from the kernel command line: {}",
+                        e
+                    );
+                }
+            }
+        }
+
         let (actions_tx, actions_rx) = mpsc::unbounded_channel();
         let (updates_tx, _updates_rx) = broadcast::channel(1024);
 
@@ -114,6 +153,7 @@ impl<T: Adapter + Send + Sync + 'static> NetworkSystem<T> {
                 input: actions_rx,
                 output: updates_tx_clone,
                 adapter: self.adapter,
+                confirm_deadline: None,
             };
 
             server.listen().await;
@@ -154,6 +194,22 @@ impl NetworkSystemClient {
         Ok(())
     }
 
+    /// Returns the global configuration (hostname, DNS, etc.).
+    pub async fn get_global_config(&self) -> Result<GlobalConfig, NetworkSystemError> {
+        let (tx, rx) = oneshot::channel();
+        self.actions.send(Action::GetGlobalConfig(tx))?;
+        Ok(rx.await?)
+    }
+
+    /// Updates the global configuration.
+    ///
+    /// * `config`: new global configuration.
+    pub fn update_global_config(&self, config: GlobalConfig) -> Result<(), NetworkSystemError> {
+        self.actions
+            .send(Action::UpdateGlobalConfig(Box::new(config)))?;
+        Ok(())
+    }
+
     /// Returns the collection of network devices.
     pub async fn get_devices(&self) -> Result<Vec<Device>, NetworkSystemError> {
         let (tx, rx) = oneshot::channel();
@@ -202,6 +258,38 @@ impl NetworkSystemClient {
         Ok(result?)
     }
 
+    /// Adds or updates several connections at once.
+    ///
+    /// If any of them cannot be added or updated, none of them is.
+    ///
+    /// * `connections`: connections to add or update (matched by UUID).
+    pub async fn update_connections(
+        &self,
+        connections: Vec<Connection>,
+    ) -> Result<(), NetworkSystemError> {
+        let (tx, rx) = oneshot::channel();
+        self.actions
+            .send(Action::UpdateConnections(connections, tx))?;
+        let result = rx.await?;
+        Ok(result?)
+    }
+
+    /// Sets the ports of a controller connection.
+    ///
+    /// * `uuid`: controller connection UUID.
+    /// * `ports`: list of port names (using the connection ID or the interface name).
+    pub async fn set_ports(
+        &self,
+        uuid: Uuid,
+        ports: Vec<String>,
+    ) -> Result<(), NetworkSystemError> {
+        let (tx, rx) = oneshot::channel();
+        self.actions
+            .send(Action::SetPorts(uuid, Box::new(ports), tx))?;
+        let result = rx.await?;
+        Ok(result?)
+    }
+
     /// Removes the connection with the given ID.
     ///
     /// * `id`: Connection ID.
@@ -221,6 +309,29 @@ impl NetworkSystemClient {
         Ok(result?)
     }
 
+    /// Applies the network configuration, rolling it back unless it is confirmed (see
+    /// [Self::confirm]) before the given timeout.
+    ///
+    /// * `timeout`: time to wait for a confirmation.
+    pub async fn apply_with_confirmation(
+        &self,
+        timeout: Duration,
+    ) -> Result<(), NetworkSystemError> {
+        let (tx, rx) = oneshot::channel();
+        self.actions
+            .send(Action::ApplyWithConfirmation(timeout, tx))?;
+        let result = rx.await?;
+        Ok(result?)
+    }
+
+    /// Keeps the changes applied with [Self::apply_with_confirmation].
+    pub async fn confirm(&self) -> Result<(), NetworkSystemError> {
+        let (tx, rx) = oneshot::channel();
+        self.actions.send(Action::Confirm(tx))?;
+        let result = rx.await?;
+        Ok(result?)
+    }
+
     /// Returns the collection of access points.
     pub async fn get_access_points(&self) -> Result<Vec<AccessPoint>, NetworkSystemError> {
         let (tx, rx) = oneshot::channel();
@@ -242,15 +353,36 @@ struct NetworkSystemServer<T: Adapter> {
     input: UnboundedReceiver<Action>,
     output: broadcast::Sender<NetworkChange>,
     adapter: T,
+    /// Moment when the applied changes are rolled back unless they are confirmed.
+    confirm_deadline: Option<Instant>,
 }
 
-impl<T: Adapter> NetworkSystemServer<T> {
+impl<T: Adapter + Send + Sync> NetworkSystemServer<T> {
     /// Process incoming actions.
     ///
-    /// This function is expected to be executed on a separate thread.
+    /// This function is expected to be executed on a separate thread. Additionally, it rolls
+    /// back the applied changes which are not confirmed in time.
     pub async fn listen(&mut self) {
-        while let Some(action) = self.input.recv().await {
-            match self.dispatch_action(action).await {
+        loop {
+            let deadline = self.confirm_deadline;
+            let expired = async move {
+                match deadline {
+                    Some(deadline) => time::sleep_until(deadline).await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            let result = tokio::select! {
+                action = self.input.recv() => {
+                    let Some(action) = action else {
+                        break;
+                    };
+                    self.dispatch_action(action).await
+                }
+                _ = expired => self.rollback_action().await.map(Some).map_err(|e| e.into()),
+            };
+
+            match result {
                 Ok(Some(update)) => {
                     _ = self.output.send(update);
                 }
@@ -324,11 +456,11 @@ impl<T: Adapter> NetworkSystemServer<T> {
             }
             Action::AddDevice(device) => {
                 self.state.add_device(*device.clone())?;
-                return Ok(Some(NetworkChange::DeviceAdded(*device)));
+                return Ok(Some(NetworkChange::DeviceAdded(device)));
             }
             Action::UpdateDevice(name, device) => {
                 self.state.update_device(&name, *device.clone())?;
-                return Ok(Some(NetworkChange::DeviceUpdated(name, *device)));
+                return Ok(Some(NetworkChange::DeviceUpdated(name, device)));
             }
             Action::RemoveDevice(name) => {
                 self.state.remove_device(&name)?;
@@ -345,9 +477,22 @@ impl<T: Adapter> NetworkSystemServer<T> {
                 let result = self.state.update_connection(*conn);
                 tx.send(result).unwrap();
             }
+            Action::UpdateConnections(connections, tx) => {
+                let result = self.state.update_connections(connections);
+                tx.send(result).unwrap();
+            }
             Action::UpdateGeneralState(general_state) => {
                 self.state.general_state = general_state;
             }
+            Action::GetGlobalConfig(tx) => {
+                tx.send(self.state.global.clone()).unwrap();
+            }
+            Action::UpdateGlobalConfig(config) => {
+                if self.state.global != *config {
+                    self.state.global = *config.clone();
+                    return Ok(Some(NetworkChange::GlobalConfigChanged(*config)));
+                }
+            }
             Action::RemoveConnection(id, tx) => {
                 let result = self.state.remove_connection(id.as_str());
 
@@ -357,6 +502,17 @@ impl<T: Adapter> NetworkSystemServer<T> {
                 let result = self.write().await;
                 tx.send(result).unwrap();
             }
+            Action::ApplyWithConfirmation(timeout, tx) => {
+                let result = self.write_with_checkpoint(timeout).await;
+                tx.send(result).unwrap();
+            }
+            Action::Confirm(tx) => {
+                let result = self.adapter.confirm().await;
+                if result.is_ok() {
+                    self.confirm_deadline = None;
+                }
+                tx.send(result).unwrap();
+            }
         }
 
         Ok(None)
@@ -411,4 +567,32 @@ impl<T: Adapter> NetworkSystemServer<T> {
         self.state = self.adapter.read(StateConfig::default()).await?;
         Ok(())
     }
+
+    /// Writes the network configuration, which is rolled back unless it is confirmed before
+    /// the given timeout.
+    ///
+    /// * `timeout`: time to wait for a confirmation.
+    pub async fn write_with_checkpoint(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<(), NetworkAdapterError> {
+        let deadline = Instant::now()
+            .checked_add(timeout)
+            .ok_or(NetworkAdapterError::InvalidTimeout(timeout))?;
+        self.adapter
+            .write_with_checkpoint(&self.state, timeout)
+            .await?;
+        self.confirm_deadline = Some(deadline);
+        self.state = self.adapter.read(StateConfig::default()).await?;
+        Ok(())
+    }
+
+    /// Rolls back the changes which were not confirmed in time.
+    async fn rollback_action(&mut self) -> Result<NetworkChange, NetworkAdapterError> {
+        self.confirm_deadline = None;
+        log::warn!("The network changes were not confirmed in time, rolling them back");
+        self.adapter.rollback().await?;
+        self.state = self.adapter.read(StateConfig::default()).await?;
+        Ok(NetworkChange::ChangesRolledBack)
+    }
 }
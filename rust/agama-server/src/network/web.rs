@@ -26,25 +26,42 @@ use crate::{
 };
 use anyhow::Context;
 use axum::{
+    body::Bytes,
     extract::{Path, State},
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     routing::{delete, get, patch, post},
     Json, Router,
 };
 
 use super::{
+    diagnostics::{Diagnostics, SystemProbe},
     error::NetworkStateError,
-    model::{AccessPoint, GeneralState},
+    model::{AccessPoint, GeneralState, GlobalConfig, NetworkState},
+    nmstate::{NmstateError, NmstateState},
+    proxy::ProxyWriter,
     system::{NetworkSystemClient, NetworkSystemError},
     Adapter,
 };
 
 use crate::network::{model::Connection, model::Device, NetworkSystem};
-use agama_lib::{error::ServiceError, network::settings::NetworkConnection};
+use agama_lib::{
+    error::ServiceError,
+    network::{
+        diagnostics::{DiagnosticsOptions, DiagnosticsReport},
+        settings::{GlobalSettings, NetworkConnection},
+    },
+    proxy::{ProxyError, ProxySettings},
+};
 
+use serde::Deserialize;
 use serde_json::json;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 use thiserror::Error;
+use uuid::Uuid;
 
 #[derive(Error, Debug)]
 pub enum NetworkError {
@@ -58,11 +75,19 @@ pub enum NetworkError {
     CannotUpdate(String),
     #[error("Cannot apply configuration")]
     CannotApplyConfig,
+    #[error("Invalid parameters: {0}")]
+    InvalidParams(String),
     // TODO: to be removed after adapting to the NetworkSystemServer API
     #[error("Network state error: {0}")]
     Error(#[from] NetworkStateError),
     #[error("Network system error: {0}")]
     SystemError(#[from] NetworkSystemError),
+    #[error("Cannot convert the nmstate configuration: {0}")]
+    Nmstate(#[from] NmstateError),
+    #[error("Invalid proxy settings: {0}")]
+    InvalidProxy(#[from] ProxyError),
+    #[error("Could not write the proxy settings: {0}")]
+    CannotWriteProxy(#[from] std::io::Error),
 }
 
 impl IntoResponse for NetworkError {
@@ -79,14 +104,25 @@ struct NetworkServiceState {
     network: NetworkSystemClient,
 }
 
+#[derive(Clone)]
+struct ProxyServiceState {
+    settings: Arc<Mutex<ProxySettings>>,
+    writer: Option<Arc<ProxyWriter>>,
+}
+
 /// Sets up and returns the axum service for the network module.
 /// * `adapter`: networking configuration adapter.
+/// * `cmdline`: kernel command line whose network options are applied on startup.
 /// * `events`: sending-half of the broadcast channel.
 pub async fn network_service<T: Adapter + Send + Sync + 'static>(
     adapter: T,
+    cmdline: Option<String>,
     events: EventsSender,
 ) -> Result<Router, ServiceError> {
-    let network = NetworkSystem::new(adapter);
+    let mut network = NetworkSystem::new(adapter);
+    if let Some(cmdline) = cmdline {
+        network = network.with_cmdline(&cmdline);
+    }
     // FIXME: we are somehow abusing ServiceError. The HTTP/JSON API should have its own
     // error type.
     let client = network
@@ -124,11 +160,39 @@ pub async fn network_service<T: Adapter + Send + Sync + 'static>(
         .route("/connections/:id/connect", patch(connect))
         .route("/connections/:id/disconnect", patch(disconnect))
         .route("/devices", get(devices))
+        .route("/diagnostics", post(diagnostics))
+        .route("/global", get(global).put(update_global))
+        .route("/nmstate", get(nmstate).put(update_nmstate))
         .route("/system/apply", post(apply))
+        .route("/system/confirm", post(confirm))
         .route("/wifi", get(wifi_networks))
         .with_state(state))
 }
 
+/// Sets up and returns the axum service for the proxy settings.
+///
+/// It is meant to be merged into the network service.
+///
+/// * `writer`: writes the settings to the system. If it is `None`, the settings are only kept
+///   in memory (e.g., when using the simulated network backend).
+pub fn proxy_service(writer: Option<ProxyWriter>) -> Router {
+    let settings = match &writer {
+        Some(writer) => writer.read().unwrap_or_else(|e| {
+            log::warn!("Could not read the proxy settings: {}", e);
+            ProxySettings::default()
+        }),
+        None => ProxySettings::default(),
+    };
+    let state = ProxyServiceState {
+        settings: Arc::new(Mutex::new(settings)),
+        writer: writer.map(Arc::new),
+    };
+
+    Router::new()
+        .route("/proxy", get(proxy).put(update_proxy))
+        .with_state(state)
+}
+
 #[utoipa::path(
     get,
     path = "/state",
@@ -161,6 +225,41 @@ async fn update_general_state(
     Ok(Json(state))
 }
 
+#[utoipa::path(
+    get,
+    path = "/global",
+    context_path = "/api/network",
+    responses(
+      (status = 200, description = "Global network settings (hostname, DNS, etc.)", body = GlobalSettings)
+    )
+)]
+async fn global(
+    State(state): State<NetworkServiceState>,
+) -> Result<Json<GlobalSettings>, NetworkError> {
+    let config = state.network.get_global_config().await?;
+    Ok(Json(config.into()))
+}
+
+#[utoipa::path(
+    put,
+    path = "/global",
+    context_path = "/api/network",
+    request_body = GlobalSettings,
+    responses(
+      (status = 200, description = "Update the global network settings", body = GlobalSettings),
+      (status = 400, description = "Invalid global network settings")
+    )
+)]
+async fn update_global(
+    State(state): State<NetworkServiceState>,
+    Json(settings): Json<GlobalSettings>,
+) -> Result<Json<GlobalSettings>, NetworkError> {
+    let config = GlobalConfig::try_from(settings)?;
+    state.network.update_global_config(config)?;
+    let config = state.network.get_global_config().await?;
+    Ok(Json(config.into()))
+}
+
 #[utoipa::path(
     get,
     path = "/wifi",
@@ -211,12 +310,33 @@ async fn connections(
     State(state): State<NetworkServiceState>,
 ) -> Result<Json<Vec<NetworkConnection>>, NetworkError> {
     let connections = state.network.get_connections().await?;
-    let connections = connections
+    let network_connections = connections
+        .iter()
+        .map(|c| {
+            let mut conn = NetworkConnection::try_from(c.clone()).unwrap();
+            add_ports(&mut conn, c.uuid, &connections);
+            conn
+        })
+        .collect();
+
+    Ok(Json(network_connections))
+}
+
+/// Fills the ports of a controller connection using the connections it controls.
+///
+/// * `conn`: connection settings to update.
+/// * `uuid`: UUID of the connection.
+/// * `connections`: all the known connections.
+fn add_ports(conn: &mut NetworkConnection, uuid: Uuid, connections: &[Connection]) {
+    let ports: Vec<String> = connections
         .iter()
-        .map(|c| NetworkConnection::try_from(c.clone()).unwrap())
+        .filter(|c| c.controller == Some(uuid))
+        .map(|c| c.interface.clone().unwrap_or_else(|| c.id.clone()))
         .collect();
 
-    Ok(Json(connections))
+    if let Some(conn_ports) = conn.ports_mut() {
+        *conn_ports = ports;
+    }
 }
 
 #[utoipa::path(
@@ -231,14 +351,19 @@ async fn add_connection(
     State(state): State<NetworkServiceState>,
     Json(conn): Json<NetworkConnection>,
 ) -> Result<Json<Connection>, NetworkError> {
+    let ports = conn.ports().cloned();
     let conn = Connection::try_from(conn)?;
     let id = conn.id.clone();
 
     state.network.add_connection(conn).await?;
-    match state.network.get_connection(&id).await? {
-        None => Err(NetworkError::CannotAddConnection(id.clone())),
-        Some(conn) => Ok(Json(conn)),
+    let Some(conn) = state.network.get_connection(&id).await? else {
+        return Err(NetworkError::CannotAddConnection(id.clone()));
+    };
+
+    if let Some(ports) = ports {
+        state.network.set_ports(conn.uuid, ports).await?;
     }
+    Ok(Json(conn))
 }
 
 #[utoipa::path(
@@ -258,7 +383,10 @@ async fn connection(
         .await?
         .ok_or_else(|| NetworkError::UnknownConnection(id.clone()))?;
 
-    let conn = NetworkConnection::try_from(conn)?;
+    let uuid = conn.uuid;
+    let mut conn = NetworkConnection::try_from(conn)?;
+    let connections = state.network.get_connections().await?;
+    add_ports(&mut conn, uuid, &connections);
 
     Ok(Json(conn))
 }
@@ -300,15 +428,21 @@ async fn update_connection(
         .get_connection(&id)
         .await?
         .ok_or_else(|| NetworkError::UnknownConnection(id.clone()))?;
+    let ports = conn.ports().cloned();
     let mut conn = Connection::try_from(conn)?;
     if orig_conn.id != id {
         // FIXME: why?
         return Err(NetworkError::UnknownConnection(id));
     } else {
         conn.uuid = orig_conn.uuid;
+        conn.controller = orig_conn.controller;
     }
 
+    let uuid = conn.uuid;
     state.network.update_connection(conn).await?;
+    if let Some(ports) = ports {
+        state.network.set_ports(uuid, ports).await?;
+    }
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -376,22 +510,195 @@ async fn disconnect(
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// Runs the network connectivity diagnostics.
+///
+/// It is a POST request because the caller can make the server reach arbitrary hosts, so it
+/// requires the `configure` scope.
+#[utoipa::path(
+    post,
+    path = "/diagnostics",
+    context_path = "/api/network",
+    request_body(content = Option<DiagnosticsOptions>, description = "Diagnostics options"),
+    responses(
+      (status = 200, description = "Network connectivity diagnostics", body = DiagnosticsReport),
+      (status = 400, description = "Unknown device")
+    )
+)]
+async fn diagnostics(
+    State(state): State<NetworkServiceState>,
+    body: Bytes,
+) -> Result<Json<DiagnosticsReport>, NetworkError> {
+    // the body is optional, so all the devices are checked by default
+    let options: DiagnosticsOptions = if body.is_empty() {
+        DiagnosticsOptions::default()
+    } else {
+        serde_json::from_slice(&body).map_err(|e| NetworkError::InvalidParams(e.to_string()))?
+    };
+    let devices = state.network.get_devices().await?;
+    let report = Diagnostics::new(SystemProbe::default())
+        .run(&devices, &options)
+        .await?;
+    Ok(Json(report))
+}
+
+#[utoipa::path(
+    get,
+    path = "/proxy",
+    context_path = "/api/network",
+    responses(
+      (status = 200, description = "Installation proxy settings (without the password)", body = ProxySettings)
+    )
+)]
+async fn proxy(State(state): State<ProxyServiceState>) -> Json<ProxySettings> {
+    let settings = state.settings.lock().unwrap();
+    Json(ProxySettings {
+        password: None,
+        ..settings.clone()
+    })
+}
+
+/// Updates the proxy settings.
+///
+/// As the password is not exposed, it is kept if it is not given and the user does not change.
+#[utoipa::path(
+    put,
+    path = "/proxy",
+    context_path = "/api/network",
+    request_body = ProxySettings,
+    responses(
+      (status = 204, description = "The proxy settings were updated"),
+      (status = 400, description = "The proxy settings are not valid or could not be written")
+    )
+)]
+async fn update_proxy(
+    State(state): State<ProxyServiceState>,
+    Json(mut settings): Json<ProxySettings>,
+) -> Result<impl IntoResponse, NetworkError> {
+    if settings.password.is_none() {
+        let current = state.settings.lock().unwrap();
+        if settings.user.is_some() && settings.user == current.user {
+            settings.password.clone_from(&current.password);
+        }
+    }
+    settings.validate()?;
+    if let Some(writer) = &state.writer {
+        writer.write(&settings)?;
+    }
+    *state.settings.lock().unwrap() = settings;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/nmstate",
+    context_path = "/api/network",
+    responses(
+      (status = 200, description = "Network configuration using the nmstate YAML format", body = String, content_type = "application/yaml"),
+      (status = 400, description = "The configuration cannot be represented using nmstate")
+    )
+)]
+async fn nmstate(
+    State(state): State<NetworkServiceState>,
+) -> Result<impl IntoResponse, NetworkError> {
+    let connections = state.network.get_connections().await?;
+    let network = NetworkState::new(GeneralState::default(), vec![], vec![], connections);
+    let yaml = NmstateState::try_from(&network)?.to_yaml()?;
+
+    Ok(([(header::CONTENT_TYPE, "application/yaml")], yaml))
+}
+
+#[utoipa::path(
+    put,
+    path = "/nmstate",
+    context_path = "/api/network",
+    request_body(content = String, description = "nmstate YAML document, merged into the current configuration", content_type = "application/yaml"),
+    responses(
+      (status = 204, description = "The network configuration was updated"),
+      (status = 400, description = "The nmstate document cannot be converted")
+    )
+)]
+async fn update_nmstate(
+    State(state): State<NetworkServiceState>,
+    body: String,
+) -> Result<impl IntoResponse, NetworkError> {
+    let nmstate = NmstateState::from_yaml(&body)?;
+    let current = state.network.get_connections().await?;
+    let connections = nmstate.merge_into(&current)?;
+    state.network.update_connections(connections).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Minimum time to wait for a confirmation of the applied changes.
+const MIN_CONFIRM_TIMEOUT: Duration = Duration::from_secs(1);
+/// Maximum time to wait for a confirmation of the applied changes.
+const MAX_CONFIRM_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Options to apply the network configuration.
+#[derive(Debug, Default, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyParams {
+    /// Seconds to wait for a confirmation (see `/system/confirm`) before rolling the changes
+    /// back. If it is not set, the changes are kept right away. It must be between 1 second and
+    /// 1 hour.
+    pub confirm_timeout: Option<u64>,
+}
+
 #[utoipa::path(
     post,
     path = "/system/apply",
     context_path = "/api/network",
+    request_body(content = Option<ApplyParams>, description = "Apply options"),
     responses(
-      (status = 204, description = "Apply configuration")
+      (status = 204, description = "Apply configuration"),
+      (status = 400, description = "The configuration could not be applied")
     )
 )]
 async fn apply(
     State(state): State<NetworkServiceState>,
+    body: Bytes,
 ) -> Result<impl IntoResponse, NetworkError> {
-    state
-        .network
-        .apply()
-        .await
-        .map_err(|_| NetworkError::CannotApplyConfig)?;
+    // the body is optional, so the current clients keep working
+    let params: ApplyParams = if body.is_empty() {
+        ApplyParams::default()
+    } else {
+        serde_json::from_slice(&body).map_err(|e| NetworkError::InvalidParams(e.to_string()))?
+    };
 
+    match params.confirm_timeout {
+        Some(secs) => {
+            let timeout = Duration::from_secs(secs);
+            if !(MIN_CONFIRM_TIMEOUT..=MAX_CONFIRM_TIMEOUT).contains(&timeout) {
+                return Err(NetworkError::InvalidParams(format!(
+                    "the confirmation timeout must be between {} and {} seconds",
+                    MIN_CONFIRM_TIMEOUT.as_secs(),
+                    MAX_CONFIRM_TIMEOUT.as_secs()
+                )));
+            }
+            state.network.apply_with_confirmation(timeout).await?
+        }
+        None => state
+            .network
+            .apply()
+            .await
+            .map_err(|_| NetworkError::CannotApplyConfig)?,
+    };
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/system/confirm",
+    context_path = "/api/network",
+    responses(
+      (status = 204, description = "The applied changes are kept"),
+      (status = 400, description = "There are no changes waiting for confirmation")
+    )
+)]
+async fn confirm(
+    State(state): State<NetworkServiceState>,
+) -> Result<impl IntoResponse, NetworkError> {
+    state.network.confirm().await?;
     Ok(StatusCode::NO_CONTENT)
 }
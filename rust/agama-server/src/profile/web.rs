@@ -0,0 +1,250 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! This module implements the web API for the installation profile.
+//!
+//! The module offers one public function:
+//!
+//! * `profile_service` which returns the Axum service.
+//!
+//! The profile is validated against the JSON schema and each section is written to the
+//! corresponding service in the order defined by [agama_lib::SettingsSection::ORDERED].
+//!
+//! The sections are read and written through the same HTTP API on behalf of the caller, so the
+//! usual scopes apply (e.g., a read-only token cannot change the settings).
+//!
+//! When the `require_signed_profiles` option is enabled, the profile must come with a detached
+//! signature (in the `X-Agama-Signature` header) made by one of the keys in
+//! [agama_lib::profile::DEFAULT_KEYS_DIR].
+
+use crate::{error::Error, web::ServiceConfig};
+use agama_lib::{
+    auth::{AuthToken, TokenClaims},
+    error::{ProfileError, ServiceError},
+    install_settings::InstallSettings,
+    profile::{
+        ProfileFormat, ProfilePolicy, ProfileValidator, SignatureVerifier, ValidationResult,
+        DEFAULT_KEYS_DIR,
+    },
+    SectionStatus, Store,
+};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Extension, Json, Router,
+};
+use serde_json::json;
+use std::sync::Arc;
+
+/// Header containing the detached signature of the profile.
+const SIGNATURE_HEADER: &str = "X-Agama-Signature";
+
+#[derive(Clone)]
+struct ProfileState {
+    jwt_secret: Arc<String>,
+    validator: Arc<ProfileValidator>,
+    policy: ProfilePolicy,
+}
+
+impl ProfileState {
+    /// Returns a store which acts on behalf of the caller.
+    ///
+    /// The token is signed again from the caller's claims, so it works for the clients
+    /// authenticated with a certificate too. It keeps the same identifier and expiration time,
+    /// so revoking the caller's token revokes this one too.
+    ///
+    /// * `claims`: claims of the caller.
+    async fn store_for(&self, claims: &TokenClaims) -> Result<Store, Error> {
+        let token =
+            AuthToken::from_claims(claims, &self.jwt_secret).map_err(anyhow::Error::from)?;
+        Ok(Store::new_with_token(&token).await?)
+    }
+}
+
+/// Sets up and returns the axum service for the profile module.
+///
+/// The settings are read and written through the same HTTP API using the credentials of each
+/// caller.
+///
+/// * `config`: service configuration.
+pub fn profile_service(config: &ServiceConfig) -> Result<Router, ServiceError> {
+    let validator = ProfileValidator::default_schema().map_err(anyhow::Error::from)?;
+    let state = ProfileState {
+        jwt_secret: Arc::new(config.jwt_secret.clone()),
+        validator: Arc::new(validator),
+        policy: ProfilePolicy {
+            require_signature: config.require_signed_profiles,
+        },
+    };
+
+    Ok(profile_router(state))
+}
+
+fn profile_router(state: ProfileState) -> Router {
+    Router::new()
+        .route("/", get(get_profile).put(put_profile))
+        .route("/policy", get(get_policy))
+        .with_state(state)
+}
+
+/// Returns the policy for importing profiles.
+#[utoipa::path(
+    get,
+    path = "/policy",
+    context_path = "/api/profile",
+    responses(
+        (status = 200, description = "Profile import policy", body = ProfilePolicy)
+    )
+)]
+async fn get_policy(State(state): State<ProfileState>) -> Json<ProfilePolicy> {
+    Json(state.policy)
+}
+
+/// Returns the current installation settings as a profile.
+#[utoipa::path(
+    get,
+    path = "/",
+    context_path = "/api/profile",
+    responses(
+        (status = 200, description = "Current installation settings"),
+        (status = 400, description = "The settings could not be read")
+    )
+)]
+async fn get_profile(
+    State(state): State<ProfileState>,
+    Extension(claims): Extension<TokenClaims>,
+) -> Result<Json<InstallSettings>, Error> {
+    let store = state.store_for(&claims).await?;
+    Ok(Json(store.load().await?))
+}
+
+/// Validates and applies a profile.
+///
+/// The profile can be written in JSON, YAML or TOML (the format is detected from the content).
+/// The sections are applied in order, stopping at the first one that fails. The response
+/// contains the result of each section.
+#[utoipa::path(
+    put,
+    path = "/",
+    context_path = "/api/profile",
+    params(
+        ("X-Agama-Signature" = Option<String>, Header, description = "Base64-encoded detached signature of the profile")
+    ),
+    responses(
+        (status = 200, description = "The profile was applied", body = Vec<SectionResult>),
+        (status = 400, description = "Some section could not be applied", body = Vec<SectionResult>),
+        (status = 403, description = "The profile is not signed by a trusted key"),
+        (status = 422, description = "The profile is not valid. Details are in body", body = Vec<ValidationError>)
+    )
+)]
+async fn put_profile(
+    State(state): State<ProfileState>,
+    Extension(claims): Extension<TokenClaims>,
+    headers: HeaderMap,
+    profile: String,
+) -> Result<impl IntoResponse, Error> {
+    if state.policy.require_signature {
+        if let Err(error) = verify_signature(&headers, &profile) {
+            let body = json!({ "error": error.to_string() });
+            return Ok((StatusCode::FORBIDDEN, Json(body)).into_response());
+        }
+    }
+
+    let format = ProfileFormat::detect(&profile);
+    let result = state
+        .validator
+        .validate_content(&profile, format)
+        .map_err(anyhow::Error::from)?;
+    if let ValidationResult::NotValid(errors) = result {
+        return Ok((StatusCode::UNPROCESSABLE_ENTITY, Json(errors)).into_response());
+    }
+
+    let settings = InstallSettings::from_content(&profile, format).map_err(anyhow::Error::from)?;
+    let store = state.store_for(&claims).await?;
+    let results = store.store_sections(&settings).await;
+    let status = if results.iter().any(|r| r.status == SectionStatus::Failed) {
+        StatusCode::BAD_REQUEST
+    } else {
+        StatusCode::OK
+    };
+    Ok((status, Json(results)).into_response())
+}
+
+fn verify_signature(headers: &HeaderMap, profile: &str) -> Result<(), ProfileError> {
+    let signature = headers
+        .get(SIGNATURE_HEADER)
+        .ok_or(ProfileError::MissingSignature("in the request".to_string()))?;
+    let verifier = SignatureVerifier::from_dir(DEFAULT_KEYS_DIR)?;
+    if !verifier.verify(profile.as_bytes(), signature.as_bytes()) {
+        return Err(ProfileError::InvalidSignature("in the request".to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agama_lib::{SectionResult, SettingsSection};
+    use axum::{body::Body, http::Request};
+    use std::{path::Path, time::Duration};
+    use tower::ServiceExt;
+
+    fn build_router() -> Router {
+        let schema = Path::new("../agama-lib/share/profile.schema.json");
+        let state = ProfileState {
+            jwt_secret: Arc::new("secret".to_string()),
+            validator: Arc::new(ProfileValidator::new(schema).unwrap()),
+            policy: ProfilePolicy::default(),
+        };
+        let claims = TokenClaims::new("root", vec![], Duration::from_secs(60));
+        profile_router(state).layer(Extension(claims))
+    }
+
+    async fn put(router: Router, profile: &str) -> (StatusCode, String) {
+        let request = Request::builder()
+            .uri("/")
+            .method("PUT")
+            .body(Body::from(profile.to_string()))
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_put_yaml_profile() {
+        let (status, body) = put(build_router(), "localization:\n  language: 1\n").await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body.contains("/localization/language"));
+
+        // the settings cannot be written (there is no server), but the profile is applied
+        let (status, body) = put(build_router(), "localization:\n  language: en_US\n").await;
+        assert_ne!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        let results: Vec<SectionResult> = serde_json::from_str(&body).unwrap();
+        assert!(results
+            .iter()
+            .any(|r| r.section == SettingsSection::Localization));
+    }
+}
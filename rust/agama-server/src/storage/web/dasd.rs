@@ -26,17 +26,22 @@
 //! * `dasd_stream` which offers an stream that emits the DASD-related events coming from D-Bus.
 
 use agama_lib::{
+    auth::TokenScope,
     error::ServiceError,
     storage::{client::dasd::DASDClient, model::dasd::DASDDevice},
 };
 use axum::{
     extract::State,
+    middleware,
     routing::{get, post, put},
     Json, Router,
 };
 use serde::Deserialize;
 
-use crate::{error::Error, web::common::EventStreams};
+use crate::{
+    error::Error,
+    web::{common::EventStreams, require_scope},
+};
 
 use self::stream::{DASDDeviceStream, DASDFormatJobStream};
 
@@ -65,14 +70,21 @@ struct DASDState<'a> {
     client: DASDClient<'a>,
 }
 
+/// Returns the service to handle the DASD devices.
+///
+/// Formatting the devices destroys their content, so it requires the `install` scope. The rest
+/// of the actions only change the devices' state, so the `configure` scope is enough.
+///
+/// * `dbus`: D-Bus connection to use.
 pub async fn dasd_service<T>(dbus: &zbus::Connection) -> Result<Router<T>, ServiceError> {
     let client = DASDClient::new(dbus.clone()).await?;
     let state = DASDState { client };
+    let install_scope = middleware::from_fn_with_state(TokenScope::Install, require_scope);
     let router = Router::new()
         .route("/supported", get(supported))
         .route("/devices", get(devices))
         .route("/probe", post(probe))
-        .route("/format", post(format))
+        .route("/format", post(format).route_layer(install_scope))
         .route("/enable", post(enable))
         .route("/disable", post(disable))
         .route("/diag", put(set_diag))
@@ -26,9 +26,16 @@
 
 use crate::{
     error::Error,
+    hardware::web::hardware_service,
     l10n::web::l10n_service,
     manager::web::{manager_service, manager_stream},
-    network::{web::network_service, NetworkManagerAdapter},
+    network::{
+        cmdline,
+        proxy::ProxyWriter,
+        web::{network_service, proxy_service},
+        NetworkBackend, NetworkManagerAdapter, SimulatedAdapter,
+    },
+    profile::web::profile_service,
     questions::web::{questions_service, questions_stream},
     software::web::{software_service, software_streams},
     storage::web::{storage_service, storage_streams},
@@ -37,6 +44,7 @@ use crate::{
 };
 use axum::Router;
 
+mod audit;
 mod auth;
 pub mod common;
 mod config;
@@ -48,6 +56,7 @@ mod state;
 mod ws;
 
 use agama_lib::{connection, error::ServiceError};
+pub use auth::{require_scope, ClientCertificate};
 pub use config::ServiceConfig;
 pub use docs::ApiDoc;
 pub use event::{Event, EventsReceiver, EventsSender};
@@ -70,18 +79,34 @@ pub async fn service<P>(
 where
     P: AsRef<Path>,
 {
-    let network_adapter = NetworkManagerAdapter::from_system()
-        .await
-        .expect("Could not connect to NetworkManager to read the configuration");
+    let network = match config.network_backend {
+        NetworkBackend::NetworkManager => {
+            let network_adapter = NetworkManagerAdapter::from_system()
+                .await
+                .expect("Could not connect to NetworkManager to read the configuration");
+            let cmdline = cmdline::read_kernel_cmdline();
+            network_service(network_adapter, cmdline, events.clone())
+                .await?
+                .merge(proxy_service(Some(ProxyWriter::from_system())))
+        }
+        NetworkBackend::Simulated => {
+            log::warn!("Using a simulated network backend");
+            network_service(SimulatedAdapter::demo(), None, events.clone())
+                .await?
+                .merge(proxy_service(None))
+        }
+    };
 
     let router = MainServiceBuilder::new(events.clone(), web_ui_dir)
+        .add_service("/hardware", hardware_service().await?)
         .add_service("/l10n", l10n_service(dbus.clone(), events.clone()).await?)
         .add_service("/manager", manager_service(dbus.clone()).await?)
         .add_service("/software", software_service(dbus.clone()).await?)
         .add_service("/storage", storage_service(dbus.clone()).await?)
-        .add_service("/network", network_service(network_adapter, events).await?)
+        .add_service("/network", network)
         .add_service("/questions", questions_service(dbus.clone()).await?)
         .add_service("/users", users_service(dbus.clone()).await?)
+        .add_service("/profile", profile_service(&config)?)
         .with_config(config)
         .build();
     Ok(router)
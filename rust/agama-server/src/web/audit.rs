@@ -0,0 +1,269 @@
+// Copyright (c) [2024] SUSE LLC
+//
+// All Rights Reserved.
+//
+// This program is free software; you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation; either version 2 of the License, or (at your option)
+// any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License for
+// more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, contact SUSE LLC.
+//
+// To contact SUSE LLC about this file by physical or electronic mail, you may
+// find current contact information at www.suse.com.
+
+//! Implements the audit log.
+//!
+//! Every authentication attempt and every request which could change the system (i.e., any
+//! method but GET and HEAD) is recorded. The entries are written to the log (journald) and the
+//! latest ones are kept in memory so they can be queried through the `/api/audit` endpoint.
+
+use super::{
+    auth::{token_from_headers, ClientCertificate},
+    state::ServiceState,
+};
+use agama_lib::auth::{AuthToken, TokenScope};
+use axum::{
+    extract::{ConnectInfo, OriginalUri, Query, Request, State},
+    http::Method,
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+};
+
+/// Number of entries kept in memory.
+const MAX_AUDIT_ENTRIES: usize = 1000;
+
+/// Audit log entry.
+#[derive(Clone, Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    /// Time of the request (UNIX timestamp).
+    pub timestamp: i64,
+    /// User the token was issued to or client certificate's subject (if the request was
+    /// authenticated).
+    pub subject: Option<String>,
+    /// Session the token belongs to. The tokens created from a session (refreshed or scoped
+    /// ones) share it, so it tells which login they come from.
+    pub session: Option<String>,
+    /// Identifier of the token (`None` for client certificates).
+    pub token_id: Option<String>,
+    /// Scopes granted to the client.
+    pub scopes: Vec<TokenScope>,
+    /// Client address.
+    #[schema(value_type = Option<String>)]
+    pub address: Option<IpAddr>,
+    /// HTTP method.
+    pub method: String,
+    /// Requested path.
+    pub path: String,
+    /// HTTP status code of the response.
+    pub status: u16,
+    /// Whether the request succeeded.
+    pub success: bool,
+}
+
+/// Audit log.
+///
+/// It keeps the latest [MAX_AUDIT_ENTRIES] entries.
+#[derive(Clone, Default)]
+pub struct AuditLog(Arc<Mutex<VecDeque<AuditEntry>>>);
+
+impl AuditLog {
+    /// Records an entry, writing it to the log too.
+    ///
+    /// * `entry`: entry to record.
+    pub fn record(&self, entry: AuditEntry) {
+        tracing::info!(
+            target: "agama::audit",
+            subject = entry.subject.as_deref().unwrap_or("-"),
+            session = entry.session.as_deref().unwrap_or("-"),
+            token = entry.token_id.as_deref().unwrap_or("-"),
+            address = entry.address.map(|a| a.to_string()).unwrap_or("-".to_string()),
+            method = entry.method,
+            path = entry.path,
+            status = entry.status,
+            "{} {} {}",
+            entry.method,
+            entry.path,
+            if entry.success { "succeeded" } else { "failed" }
+        );
+
+        let mut entries = self.0.lock().unwrap();
+        if entries.len() == MAX_AUDIT_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Returns the entries matching the given filter, from the oldest to the newest one.
+    ///
+    /// * `filter`: criteria to filter the entries.
+    pub fn entries(&self, filter: &AuditFilter) -> Vec<AuditEntry> {
+        let entries = self.0.lock().unwrap();
+        let mut found: Vec<_> = entries
+            .iter()
+            .rev()
+            .filter(|e| filter.matches(e))
+            .take(filter.limit.unwrap_or(MAX_AUDIT_ENTRIES))
+            .cloned()
+            .collect();
+        found.reverse();
+        found
+    }
+}
+
+/// Criteria to filter the audit log entries.
+#[derive(Debug, Default, Deserialize, utoipa::IntoParams)]
+pub struct AuditFilter {
+    /// Only the entries for the given user.
+    pub subject: Option<String>,
+    /// Only the entries for the given session.
+    pub session: Option<String>,
+    /// Only the entries from the given client address.
+    #[param(value_type = Option<String>)]
+    pub address: Option<IpAddr>,
+    /// Only the failed (`true`) or successful (`false`) requests.
+    pub failed: Option<bool>,
+    /// Maximum number of entries (the newest ones).
+    pub limit: Option<usize>,
+}
+
+impl AuditFilter {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        (self.subject.is_none() || entry.subject == self.subject)
+            && (self.session.is_none() || entry.session == self.session)
+            && (self.address.is_none() || entry.address == self.address)
+            && (self.failed.is_none() || self.failed == Some(!entry.success))
+    }
+}
+
+/// Middleware that records the authentication attempts and the requests which could change
+/// the system.
+pub async fn audit_requests(
+    State(state): State<ServiceState>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    OriginalUri(uri): OriginalUri,
+    request: Request,
+    next: Next,
+) -> Response {
+    if matches!(*request.method(), Method::GET | Method::HEAD) {
+        return next.run(request).await;
+    }
+
+    let method = request.method().to_string();
+    let path = uri.path().to_string();
+    let (claims, token_id) = match token_from_headers(request.headers()) {
+        Some(token) => {
+            let claims = AuthToken::new(&token).claims(&state.config.jwt_secret).ok();
+            let token_id = claims.as_ref().map(|c| c.jti.clone());
+            (claims, token_id)
+        }
+        None => {
+            let claims = request
+                .extensions()
+                .get::<ClientCertificate>()
+                .map(|c| c.claims(&state.config));
+            (claims, None)
+        }
+    };
+
+    let response = next.run(request).await;
+    let status = response.status();
+    state.audit.record(AuditEntry {
+        timestamp: Utc::now().timestamp(),
+        subject: claims.as_ref().map(|c| c.sub.clone()),
+        session: claims.as_ref().map(|c| c.sid.clone()),
+        token_id,
+        scopes: claims.map(|c| c.scopes).unwrap_or_default(),
+        address: connect_info.map(|ConnectInfo(addr)| addr.ip().to_canonical()),
+        method,
+        path,
+        status: status.as_u16(),
+        success: status.is_success(),
+    });
+    response
+}
+
+#[utoipa::path(get, path = "/api/audit", params(AuditFilter), responses(
+    (status = 200, description = "Audit log entries, from the oldest to the newest one.", body = Vec<AuditEntry>),
+    (status = 403, description = "The token does not grant the admin scope.")
+))]
+pub async fn audit_log(
+    State(state): State<ServiceState>,
+    Query(filter): Query<AuditFilter>,
+) -> Json<Vec<AuditEntry>> {
+    Json(state.audit.entries(&filter))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuditEntry, AuditFilter, AuditLog, MAX_AUDIT_ENTRIES};
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn entry(path: &str, address: Option<IpAddr>, success: bool) -> AuditEntry {
+        AuditEntry {
+            timestamp: 0,
+            subject: None,
+            session: None,
+            token_id: None,
+            scopes: vec![],
+            address,
+            method: "POST".to_string(),
+            path: path.to_string(),
+            status: if success { 200 } else { 400 },
+            success,
+        }
+    }
+
+    #[test]
+    fn test_filter_entries() {
+        let log = AuditLog::default();
+        let addr = Some(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)));
+        log.record(entry("/api/auth", addr, false));
+        log.record(entry("/api/auth", addr, true));
+        log.record(entry("/api/manager/install", None, true));
+
+        let failed = log.entries(&AuditFilter {
+            failed: Some(true),
+            ..Default::default()
+        });
+        assert_eq!(failed.len(), 1);
+
+        let from_addr = log.entries(&AuditFilter {
+            address: addr,
+            ..Default::default()
+        });
+        assert_eq!(from_addr.len(), 2);
+
+        let latest = log.entries(&AuditFilter {
+            limit: Some(1),
+            ..Default::default()
+        });
+        assert_eq!(latest[0].path, "/api/manager/install");
+    }
+
+    #[test]
+    fn test_keep_latest_entries() {
+        let log = AuditLog::default();
+        for i in 0..=MAX_AUDIT_ENTRIES {
+            log.record(entry(&format!("/api/{}", i), None, true));
+        }
+        let entries = log.entries(&AuditFilter::default());
+        assert_eq!(entries.len(), MAX_AUDIT_ENTRIES);
+        assert_eq!(entries[0].path, "/api/1");
+    }
+}
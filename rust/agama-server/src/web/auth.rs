@@ -20,21 +20,27 @@
 
 //! Contains the code to handle access authorization.
 
-use super::state::ServiceState;
-use agama_lib::auth::{AuthToken, AuthTokenError, TokenClaims};
+use super::{config::ServiceConfig, state::ServiceState};
+use agama_lib::auth::{AuthToken, AuthTokenError, TokenClaims, TokenScope};
 use async_trait::async_trait;
 use axum::{
-    extract::FromRequestParts,
-    http::{request, StatusCode},
+    extract::{FromRequestParts, Request, State},
+    http::{header, request, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
     response::{IntoResponse, Response},
-    Json, RequestPartsExt,
-};
-use axum_extra::{
-    headers::{self, authorization::Bearer},
-    TypedHeader,
+    Json,
 };
+use axum_extra::headers::{self, authorization::Bearer, HeaderMapExt};
+use chrono::Utc;
+use openssl::{nid::Nid, x509::X509Ref};
 use pam::PamError;
 use serde_json::json;
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 use thiserror::Error;
 
 /// Represents an authentication error.
@@ -46,17 +52,43 @@ pub enum AuthError {
     /// The authentication error is invalid.
     #[error("Invalid authentication token: {0}")]
     InvalidToken(#[from] AuthTokenError),
+    /// The authentication token has been revoked (e.g., after logging out).
+    #[error("The authentication token has been revoked")]
+    RevokedToken,
+    /// The token does not grant the scope required by the request.
+    #[error("The authentication token does not grant the '{0}' scope")]
+    MissingScope(TokenScope),
+    /// A token cannot be created without any scope.
+    #[error("At least one scope is required")]
+    NoScopes,
+    /// Too many login attempts from the same address.
+    #[error("Too many login attempts, try again in {} seconds", .0.as_secs())]
+    TooManyAttempts(Duration),
     /// The authentication failed (most probably the password is wrong)
     #[error("Authentication via PAM failed: {0}")]
     Failed(#[from] PamError),
+    /// The authentication could not be completed.
+    #[error("Could not check the credentials: {0}")]
+    Unfinished(#[from] tokio::task::JoinError),
 }
 
 impl IntoResponse for AuthError {
     fn into_response(self) -> Response {
+        let status = match self {
+            AuthError::MissingScope(_) => StatusCode::FORBIDDEN,
+            AuthError::TooManyAttempts(_) => StatusCode::TOO_MANY_REQUESTS,
+            _ => StatusCode::BAD_REQUEST,
+        };
         let body = json!({
             "error": self.to_string()
         });
-        (StatusCode::BAD_REQUEST, Json(body)).into_response()
+        let mut response = (status, Json(body)).into_response();
+        if let AuthError::TooManyAttempts(wait) = self {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(wait.as_secs()));
+        }
+        response
     }
 }
 
@@ -68,24 +100,334 @@ impl FromRequestParts<ServiceState> for TokenClaims {
         parts: &mut request::Parts,
         state: &ServiceState,
     ) -> Result<Self, Self::Rejection> {
-        let token = match parts
-            .extract::<TypedHeader<headers::Authorization<Bearer>>>()
-            .await
-        {
-            Ok(TypedHeader(headers::Authorization(bearer))) => bearer.token().to_owned(),
-            Err(_) => {
-                let cookie = parts
-                    .extract::<TypedHeader<headers::Cookie>>()
-                    .await
-                    .map_err(|_| AuthError::MissingToken)?;
-                cookie
-                    .get("agamaToken")
-                    .ok_or(AuthError::MissingToken)?
-                    .to_owned()
-            }
+        let Some(token) = token_from_headers(&parts.headers) else {
+            return match parts.extensions.get::<ClientCertificate>() {
+                Some(certificate) => Ok(certificate.claims(&state.config)),
+                None => Err(AuthError::MissingToken),
+            };
         };
-
         let token = AuthToken::new(&token);
-        Ok(token.claims(&state.config.jwt_secret)?)
+        let claims = token.claims(&state.config.jwt_secret)?;
+        if state.revoked_tokens.contains(&claims.jti) {
+            return Err(AuthError::RevokedToken);
+        }
+        Ok(claims)
+    }
+}
+
+/// Returns the authentication token included in the request headers.
+///
+/// The token is taken from the `Authorization` header or, if it is missing, from
+/// the `agamaToken` cookie.
+///
+/// * `headers`: request headers.
+pub fn token_from_headers(headers: &HeaderMap) -> Option<String> {
+    if let Some(headers::Authorization(bearer)) =
+        headers.typed_get::<headers::Authorization<Bearer>>()
+    {
+        return Some(bearer.token().to_string());
+    }
+
+    let cookie = headers.typed_get::<headers::Cookie>()?;
+    cookie.get("agamaToken").map(|t| t.to_string())
+}
+
+/// Client certificate verified during the TLS handshake.
+///
+/// The web server adds it to the requests coming through a connection authenticated with
+/// a client certificate (see [ServiceConfig::client_ca]). When a request does not include
+/// a token, the certificate is used instead.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClientCertificate {
+    /// Certificate's common name (CN).
+    pub subject: String,
+}
+
+impl ClientCertificate {
+    /// Builds the client certificate information from an X509 certificate.
+    ///
+    /// * `cert`: verified certificate.
+    pub fn from_x509(cert: &X509Ref) -> Self {
+        let subject = cert
+            .subject_name()
+            .entries_by_nid(Nid::COMMONNAME)
+            .next()
+            .and_then(|e| e.data().as_utf8().ok())
+            .map(|cn| cn.to_string())
+            .unwrap_or_default();
+        Self { subject }
+    }
+
+    /// Returns the claims granted by the certificate.
+    ///
+    /// * `config`: service configuration, which defines the scopes.
+    pub fn claims(&self, config: &ServiceConfig) -> TokenClaims {
+        TokenClaims::new(
+            &self.subject,
+            config.client_cert_scopes.clone(),
+            config.token_lifetime(),
+        )
+    }
+}
+
+/// Extractor that checks whether the token grants access to the requested resource.
+///
+/// Requests using safe methods (GET and HEAD) require the `read` scope, while the rest require
+/// the `configure` one. Routes that need a different scope declare it using the [require_scope]
+/// middleware, which relies on the claims that this extractor adds to the request extensions.
+pub struct Authorized;
+
+#[async_trait]
+impl FromRequestParts<ServiceState> for Authorized {
+    type Rejection = AuthError;
+
+    async fn from_request_parts(
+        parts: &mut request::Parts,
+        state: &ServiceState,
+    ) -> Result<Self, Self::Rejection> {
+        let claims = TokenClaims::from_request_parts(parts, state).await?;
+        let scope = match parts.method {
+            Method::GET | Method::HEAD => TokenScope::Read,
+            _ => TokenScope::Configure,
+        };
+        if !claims.allows(scope) {
+            return Err(AuthError::MissingScope(scope));
+        }
+
+        parts.extensions.insert(claims);
+        Ok(Self)
+    }
+}
+
+/// Middleware to declare the scope required by a route.
+///
+/// Use it as `middleware::from_fn_with_state(TokenScope::Install, require_scope)` to protect
+/// a route with the `install` scope.
+pub async fn require_scope(
+    State(scope): State<TokenScope>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AuthError> {
+    let Some(claims) = request.extensions().get::<TokenClaims>() else {
+        return Err(AuthError::MissingToken);
+    };
+    if !claims.allows(scope) {
+        return Err(AuthError::MissingScope(scope));
+    }
+    Ok(next.run(request).await)
+}
+
+/// List of tokens that have been revoked before they expired.
+///
+/// The tokens are identified by their `jti` claim. Each entry is kept only
+/// until the token expires, as the token is rejected from then on anyway.
+#[derive(Clone, Default)]
+pub struct RevokedTokens(Arc<Mutex<HashMap<String, i64>>>);
+
+impl RevokedTokens {
+    /// Revokes the token with the given claims.
+    ///
+    /// * `claims`: claims of the token to revoke.
+    pub fn revoke(&self, claims: &TokenClaims) {
+        let now = Utc::now().timestamp();
+        let mut tokens = self.0.lock().unwrap();
+        tokens.retain(|_, exp| *exp > now);
+        tokens.insert(claims.jti.clone(), claims.exp);
+    }
+
+    /// Determines whether the token with the given ID has been revoked.
+    ///
+    /// * `jti`: token identifier.
+    pub fn contains(&self, jti: &str) -> bool {
+        self.0.lock().unwrap().contains_key(jti)
+    }
+}
+
+/// Number of failed login attempts after which the address is locked out.
+const MAX_LOGIN_FAILURES: u32 = 10;
+/// How long an address is locked out after too many failed login attempts.
+const LOGIN_LOCKOUT: Duration = Duration::from_secs(15 * 60);
+/// Time after which the failed login attempts from an address are forgotten.
+const LOGIN_FAILURES_EXPIRATION: Duration = Duration::from_secs(60 * 60);
+
+/// Failed login attempts from a given address.
+struct LoginFailures {
+    count: u32,
+    last: Instant,
+    blocked_until: Instant,
+}
+
+/// Limits the login attempts from each client address.
+///
+/// After each attempt, the address must wait before trying again, doubling the delay with each
+/// new failure (1, 2, 4... seconds). After [MAX_LOGIN_FAILURES] failures, the address is locked
+/// out for [LOGIN_LOCKOUT]. A successful login resets the counter.
+///
+/// The attempts are recorded as failures before checking the credentials (see
+/// [Self::start_attempt]), so concurrent requests from the same address cannot skip the delay
+/// while the authentication is in progress.
+///
+/// When the client address is unknown, all the attempts share the same counter.
+#[derive(Clone, Default)]
+pub struct LoginThrottle(Arc<Mutex<HashMap<Option<IpAddr>, LoginFailures>>>);
+
+impl LoginThrottle {
+    /// Checks whether the given address is allowed to try to log in and, if so, records the
+    /// attempt as a failure until [Self::reset] is called.
+    ///
+    /// * `addr`: client address.
+    pub fn start_attempt(&self, addr: Option<IpAddr>) -> Result<(), AuthError> {
+        self.start_attempt_at(addr, Instant::now())
+    }
+
+    /// Forgets the failed login attempts from the given address.
+    ///
+    /// * `addr`: client address.
+    pub fn reset(&self, addr: Option<IpAddr>) {
+        self.0.lock().unwrap().remove(&addr);
+    }
+
+    fn start_attempt_at(&self, addr: Option<IpAddr>, now: Instant) -> Result<(), AuthError> {
+        let mut failures = self.0.lock().unwrap();
+        failures.retain(|_, f| now.duration_since(f.last) < LOGIN_FAILURES_EXPIRATION);
+
+        let entry = failures.entry(addr).or_insert(LoginFailures {
+            count: 0,
+            last: now,
+            blocked_until: now,
+        });
+        if entry.blocked_until > now {
+            let wait = entry.blocked_until - now;
+            // round up so clients do not retry too early
+            let wait = Duration::from_secs(wait.as_secs() + u64::from(wait.subsec_nanos() > 0));
+            return Err(AuthError::TooManyAttempts(wait));
+        }
+
+        entry.count += 1;
+        entry.last = now;
+        let delay = if entry.count >= MAX_LOGIN_FAILURES {
+            LOGIN_LOCKOUT
+        } else {
+            Duration::from_secs(1 << (entry.count - 1))
+        };
+        entry.blocked_until = now + delay;
+        if entry.count == MAX_LOGIN_FAILURES {
+            tracing::warn!("Too many login attempts from {:?}", addr);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AuthError, ClientCertificate, LoginThrottle, RevokedTokens, LOGIN_LOCKOUT,
+        MAX_LOGIN_FAILURES,
+    };
+    use crate::{cert::Certificate, web::ServiceConfig};
+    use agama_lib::auth::{TokenClaims, TokenScope};
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        time::{Duration, Instant},
+    };
+
+    #[test]
+    fn test_revoke_token() {
+        let revoked = RevokedTokens::default();
+        let claims = TokenClaims::new("root", vec![TokenScope::Read], Duration::from_secs(60));
+        let other = claims.refresh(Duration::from_secs(60));
+
+        revoked.revoke(&claims);
+        assert!(revoked.contains(&claims.jti));
+        assert!(!revoked.contains(&other.jti));
+    }
+
+    #[test]
+    fn test_forget_expired_tokens() {
+        let revoked = RevokedTokens::default();
+        let mut expired = TokenClaims::default();
+        expired.exp = expired.iat - 1;
+        revoked.revoke(&expired);
+
+        let claims = TokenClaims::default();
+        revoked.revoke(&claims);
+        assert!(!revoked.contains(&expired.jti));
+        assert!(revoked.contains(&claims.jti));
+    }
+
+    fn assert_blocked_for(throttle: &LoginThrottle, addr: Option<IpAddr>, now: Instant, secs: u64) {
+        match throttle.start_attempt_at(addr, now) {
+            Err(AuthError::TooManyAttempts(wait)) => assert_eq!(wait, Duration::from_secs(secs)),
+            _ => panic!("The address should be blocked"),
+        }
+    }
+
+    #[test]
+    fn test_login_throttle_backoff() {
+        let throttle = LoginThrottle::default();
+        let addr = Some(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)));
+        let other = Some(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 11)));
+        let now = Instant::now();
+
+        assert!(throttle.start_attempt_at(addr, now).is_ok());
+        assert_blocked_for(&throttle, addr, now, 1);
+        assert!(throttle.start_attempt_at(other, now).is_ok());
+
+        let now = now + Duration::from_secs(1);
+        assert!(throttle.start_attempt_at(addr, now).is_ok());
+        assert_blocked_for(&throttle, addr, now, 2);
+
+        throttle.reset(addr);
+        assert!(throttle.start_attempt_at(addr, now).is_ok());
+    }
+
+    #[test]
+    fn test_login_throttle_concurrent_attempts() {
+        let throttle = LoginThrottle::default();
+        let addr = Some(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)));
+        let attempts: Vec<_> = (0..10)
+            .map(|_| {
+                let throttle = throttle.clone();
+                std::thread::spawn(move || throttle.start_attempt(addr))
+            })
+            .collect();
+
+        // only one attempt is allowed until the ongoing one succeeds or the delay expires
+        let allowed = attempts
+            .into_iter()
+            .map(|a| a.join().unwrap())
+            .filter(Result::is_ok)
+            .count();
+        assert_eq!(allowed, 1);
+    }
+
+    #[test]
+    fn test_login_throttle_lockout() {
+        let throttle = LoginThrottle::default();
+        let addr = Some(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)));
+        let mut now = Instant::now();
+        for _ in 0..MAX_LOGIN_FAILURES {
+            assert!(throttle.start_attempt_at(addr, now).is_ok());
+            now += Duration::from_secs(300);
+        }
+        now -= Duration::from_secs(300);
+        assert_blocked_for(&throttle, addr, now, LOGIN_LOCKOUT.as_secs());
+        assert!(throttle.start_attempt_at(addr, now + LOGIN_LOCKOUT).is_ok());
+    }
+
+    #[test]
+    fn test_client_certificate_claims() {
+        let certificate = Certificate::new().unwrap();
+        let client = ClientCertificate::from_x509(&certificate.cert);
+        assert!(!client.subject.is_empty());
+
+        let config = ServiceConfig {
+            client_cert_scopes: vec![TokenScope::Read],
+            ..Default::default()
+        };
+        let claims = client.claims(&config);
+        assert_eq!(claims.sub, client.subject);
+        assert!(claims.allows(TokenScope::Read));
+        assert!(!claims.allows(TokenScope::Install));
     }
 }
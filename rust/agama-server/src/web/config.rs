@@ -30,15 +30,46 @@
 //! All the settings are merged into a single configuration. The values in the latter locations
 //! take precedence.
 
+use crate::network::NetworkBackend;
+use agama_lib::auth::{TokenScope, DEFAULT_TOKEN_LIFETIME};
 use config::{Config, ConfigError, File};
 use rand::distributions::{Alphanumeric, DistString};
 use serde::Deserialize;
+use std::{path::PathBuf, time::Duration};
 
 /// Web service configuration.
 #[derive(Clone, Debug, Deserialize)]
 pub struct ServiceConfig {
     /// Key to sign the JSON Web Tokens.
     pub jwt_secret: String,
+    /// Whether the imported profiles must be signed with a key from `/etc/agama.d/keys`.
+    ///
+    /// It only applies to the profiles imported through `/api/profile` (and to `agama profile
+    /// import`). Clients allowed to change the settings can still use the endpoints of each
+    /// section (e.g., `/api/software/config`), so use scoped tokens to limit them.
+    #[serde(default)]
+    pub require_signed_profiles: bool,
+    /// Backend for the network service.
+    #[serde(default)]
+    pub network_backend: NetworkBackend,
+    /// Lifetime of the authentication tokens, in seconds.
+    #[serde(default = "default_token_lifetime")]
+    pub token_lifetime: u64,
+    /// CA certificates (PEM) to verify the client certificates. When it is set, a client
+    /// certificate signed by one of them is accepted instead of a token.
+    #[serde(default)]
+    pub client_ca: Option<PathBuf>,
+    /// Scopes granted to the clients authenticated with a certificate.
+    #[serde(default = "default_client_cert_scopes")]
+    pub client_cert_scopes: Vec<TokenScope>,
+}
+
+fn default_token_lifetime() -> u64 {
+    DEFAULT_TOKEN_LIFETIME.as_secs()
+}
+
+fn default_client_cert_scopes() -> Vec<TokenScope> {
+    vec![TokenScope::Configure, TokenScope::Install]
 }
 
 impl ServiceConfig {
@@ -55,12 +86,22 @@ impl ServiceConfig {
             .build()?;
         config.try_deserialize()
     }
+
+    /// Returns the lifetime of the authentication tokens.
+    pub fn token_lifetime(&self) -> Duration {
+        Duration::from_secs(self.token_lifetime)
+    }
 }
 
 impl Default for ServiceConfig {
     fn default() -> Self {
         Self {
             jwt_secret: "".to_string(),
+            require_signed_profiles: false,
+            network_backend: NetworkBackend::default(),
+            token_lifetime: default_token_lifetime(),
+            client_ca: None,
+            client_cert_scopes: default_client_cert_scopes(),
         }
     }
 }
@@ -23,6 +23,7 @@ use utoipa::OpenApi;
 #[openapi(
     info(description = "Agama web API description"),
     paths(
+        crate::hardware::web::hardware,
         crate::l10n::web::get_config,
         crate::l10n::web::keymaps,
         crate::l10n::web::locales,
@@ -34,12 +35,23 @@ use utoipa::OpenApi;
         crate::manager::web::probe_action,
         crate::network::web::add_connection,
         crate::network::web::apply,
+        crate::network::web::confirm,
         crate::network::web::connect,
         crate::network::web::connections,
         crate::network::web::delete_connection,
         crate::network::web::devices,
+        crate::network::web::diagnostics,
         crate::network::web::disconnect,
+        crate::network::web::global,
+        crate::network::web::nmstate,
+        crate::network::web::proxy,
+        crate::network::web::update_global,
+        crate::network::web::update_proxy,
+        crate::network::web::update_nmstate,
         crate::network::web::update_connection,
+        crate::profile::web::get_policy,
+        crate::profile::web::get_profile,
+        crate::profile::web::put_profile,
         crate::questions::web::answer_question,
         crate::questions::web::get_answer,
         crate::questions::web::delete_question,
@@ -89,13 +101,33 @@ use utoipa::OpenApi;
         crate::users::web::patch_root,
         crate::users::web::remove_first_user,
         crate::users::web::set_first_user,
+        super::audit::audit_log,
         super::http::ping
     ),
     components(
+        schemas(agama_lib::hardware::Cpu),
+        schemas(agama_lib::hardware::Disk),
+        schemas(agama_lib::hardware::Firmware),
+        schemas(agama_lib::hardware::HardwareInfo),
+        schemas(agama_lib::hardware::Memory),
+        schemas(agama_lib::hardware::NetworkInterface),
         schemas(agama_lib::manager::InstallationPhase),
+        schemas(agama_lib::network::settings::GlobalSettings),
         schemas(agama_lib::network::settings::NetworkConnection),
         schemas(agama_lib::network::types::DeviceType),
+        schemas(agama_lib::proxy::ProxySettings),
+        schemas(agama_lib::network::diagnostics::CheckKind),
+        schemas(agama_lib::network::diagnostics::CheckResult),
+        schemas(agama_lib::network::diagnostics::CheckStatus),
+        schemas(agama_lib::network::diagnostics::DeviceDiagnostics),
+        schemas(agama_lib::network::diagnostics::DiagnosticsOptions),
+        schemas(agama_lib::network::diagnostics::DiagnosticsReport),
         schemas(agama_lib::product::Product),
+        schemas(agama_lib::profile::ProfilePolicy),
+        schemas(agama_lib::profile::ValidationError),
+        schemas(agama_lib::SectionResult),
+        schemas(agama_lib::SectionStatus),
+        schemas(agama_lib::SettingsSection),
         schemas(agama_lib::software::Pattern),
         schemas(agama_lib::storage::model::Action),
         schemas(agama_lib::storage::model::BlockDevice),
@@ -138,6 +170,7 @@ use utoipa::OpenApi;
         schemas(agama_lib::localization::model::LocaleConfig),
         schemas(crate::manager::web::InstallerStatus),
         schemas(crate::network::model::Connection),
+        schemas(crate::network::web::ApplyParams),
         schemas(crate::network::model::Device),
         schemas(agama_lib::questions::model::Answer),
         schemas(agama_lib::questions::model::GenericAnswer),
@@ -154,6 +187,7 @@ use utoipa::OpenApi;
         schemas(crate::storage::web::iscsi::NodeParams),
         schemas(agama_lib::users::model::RootConfig),
         schemas(agama_lib::users::model::RootPatchSettings),
+        schemas(super::audit::AuditEntry),
         schemas(super::http::PingResponse)
     )
 )]
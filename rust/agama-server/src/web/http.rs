@@ -21,17 +21,18 @@
 //! Implements the basic handlers for the HTTP-based API (login, logout, ping, etc.).
 
 use super::{auth::AuthError, state::ServiceState};
-use agama_lib::auth::{AuthToken, TokenClaims};
+use agama_lib::auth::{AuthToken, TokenClaims, TokenRequest, TokenScope};
 use axum::{
     body::Body,
-    extract::{Query, State},
+    extract::{ConnectInfo, Query, State},
     http::{header, HeaderMap, HeaderValue, StatusCode},
     response::IntoResponse,
     Json,
 };
 use axum_extra::extract::cookie::CookieJar;
-use pam::Client;
+use pam::{Client, PamError};
 use serde::{Deserialize, Serialize};
+use std::{net::SocketAddr, time::Duration};
 use utoipa::ToSchema;
 
 #[derive(Serialize, ToSchema)]
@@ -61,20 +62,99 @@ pub struct LoginRequest {
     pub password: String,
 }
 
+/// Authenticates the user.
+///
+/// The failed attempts from each client address are limited (see
+/// [LoginThrottle](super::auth::LoginThrottle)).
 #[utoipa::path(post, path = "/api/auth", responses(
-    (status = 200, description = "The user has been successfully authenticated.", body = AuthResponse)
+    (status = 200, description = "The user has been successfully authenticated.", body = AuthResponse),
+    (status = 429, description = "Too many failed attempts from the client address.")
 ))]
 pub async fn login(
     State(state): State<ServiceState>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
     Json(login): Json<LoginRequest>,
 ) -> Result<impl IntoResponse, AuthError> {
+    let addr = connect_info.map(|ConnectInfo(addr)| addr.ip().to_canonical());
+    state.login_throttle.start_attempt(addr)?;
+
+    tokio::task::spawn_blocking(move || authenticate("root", login.password)).await??;
+    state.login_throttle.reset(addr);
+
+    let claims = TokenClaims::new(
+        "root",
+        vec![TokenScope::Admin],
+        state.config.token_lifetime(),
+    );
+    token_response(&claims, &state)
+}
+
+/// Checks the user credentials using PAM.
+///
+/// It blocks until PAM answers, so it should not run in the async runtime.
+fn authenticate(user: &str, password: String) -> Result<(), PamError> {
     let mut pam_client = Client::with_password("agama")?;
     pam_client
         .conversation_mut()
-        .set_credentials("root", login.password);
-    pam_client.authenticate()?;
+        .set_credentials(user, password);
+    pam_client.authenticate()
+}
+
+#[utoipa::path(post, path = "/api/auth/refresh", responses(
+    (status = 200, description = "The token has been replaced with a new one.", body = AuthResponse)
+))]
+pub async fn refresh(
+    State(state): State<ServiceState>,
+    claims: TokenClaims,
+) -> Result<impl IntoResponse, AuthError> {
+    let new_claims = claims.refresh(state.config.token_lifetime());
+    let response = token_response(&new_claims, &state)?;
+    state.revoked_tokens.revoke(&claims);
+    Ok(response)
+}
+
+/// Creates a token granting the given scopes.
+///
+/// It is meant to give limited access to automation tools (e.g., a read-only token for a
+/// monitoring dashboard). Only tokens with the `admin` scope can create new ones.
+#[utoipa::path(post, path = "/api/auth/tokens", responses(
+    (status = 200, description = "The token has been created.", body = AuthResponse),
+    (status = 403, description = "The token does not grant the admin scope.")
+))]
+pub async fn create_token(
+    State(state): State<ServiceState>,
+    claims: TokenClaims,
+    Json(request): Json<TokenRequest>,
+) -> Result<impl IntoResponse, AuthError> {
+    if !claims.allows(TokenScope::Admin) {
+        return Err(AuthError::MissingScope(TokenScope::Admin));
+    }
+    if request.scopes.is_empty() {
+        return Err(AuthError::NoScopes);
+    }
+
+    let lifetime = request
+        .ttl
+        .map(Duration::from_secs)
+        .unwrap_or(state.config.token_lifetime());
+    let new_claims = TokenClaims::new(&claims.sub, request.scopes, lifetime);
+    let token = AuthToken::from_claims(&new_claims, &state.config.jwt_secret)?;
+    Ok(Json(AuthResponse {
+        token: token.to_string(),
+    }))
+}
 
-    let token = AuthToken::generate(&state.config.jwt_secret)?;
+/// Builds the response containing a new token with the given claims.
+///
+/// The token is included in the body and in the authentication cookie.
+///
+/// * `claims`: claims of the new token.
+/// * `state`: service state.
+fn token_response(
+    claims: &TokenClaims,
+    state: &ServiceState,
+) -> Result<(HeaderMap, Json<AuthResponse>), AuthError> {
+    let token = AuthToken::from_claims(claims, &state.config.jwt_secret)?;
     let content = Json(AuthResponse {
         token: token.to_string(),
     });
@@ -105,7 +185,10 @@ pub async fn login_from_query(
     let mut headers = HeaderMap::new();
 
     let token = AuthToken::new(&params.token);
-    if token.claims(&state.config.jwt_secret).is_ok() {
+    let valid = token
+        .claims(&state.config.jwt_secret)
+        .is_ok_and(|claims| !state.revoked_tokens.contains(&claims.jti));
+    if valid {
         let cookie = auth_cookie_from_token(&token);
         headers.insert(
             header::SET_COOKIE,
@@ -118,9 +201,14 @@ pub async fn login_from_query(
 }
 
 #[utoipa::path(delete, path = "/api/auth", responses(
-    (status = 204, description = "The user has been logged out.")
+    (status = 204, description = "The user has been logged out and the token revoked.")
 ))]
-pub async fn logout(_claims: TokenClaims) -> Result<impl IntoResponse, AuthError> {
+pub async fn logout(
+    State(state): State<ServiceState>,
+    claims: TokenClaims,
+) -> Result<impl IntoResponse, AuthError> {
+    state.revoked_tokens.revoke(&claims);
+
     let mut headers = HeaderMap::new();
     let cookie = "agamaToken=deleted; HttpOnly; Expires=Thu, 01 Jan 1970 00:00:00 GMT".to_string();
     headers.insert(
@@ -18,9 +18,15 @@
 // To contact SUSE LLC about this file by physical or electronic mail, you may
 // find current contact information at www.suse.com.
 
-use super::http::{login, login_from_query, logout, session};
-use super::{config::ServiceConfig, state::ServiceState, EventsSender};
-use agama_lib::auth::TokenClaims;
+use super::http::{create_token, login, login_from_query, logout, refresh, session};
+use super::{
+    audit::{audit_log, audit_requests, AuditLog},
+    auth::{require_scope, Authorized, LoginThrottle, RevokedTokens},
+    config::ServiceConfig,
+    state::ServiceState,
+    EventsSender,
+};
+use agama_lib::auth::TokenScope;
 use axum::{
     body::Body,
     extract::Request,
@@ -44,7 +50,8 @@ use tracing::Span;
 ///
 /// * A static assets directory (`public_dir`).
 /// * A websocket at the `/ws` path.
-/// * An authentication endpoint at `/auth`.
+/// * An authentication endpoint at `/auth` (and `/auth/refresh` to refresh the token).
+/// * An audit log at `/audit`.
 /// * A 'ping' endpoint at '/ping'.
 /// * A number of authenticated services that are added using the `add_service` function.
 pub struct MainServiceBuilder {
@@ -99,15 +106,31 @@ impl MainServiceBuilder {
             config: self.config,
             events: self.events,
             public_dir: self.public_dir.clone(),
+            revoked_tokens: RevokedTokens::default(),
+            login_throttle: LoginThrottle::default(),
+            audit: AuditLog::default(),
         };
 
         let api_router = self
             .api_router
-            .route_layer(middleware::from_extractor_with_state::<TokenClaims, _>(
+            .route(
+                "/audit",
+                get(audit_log).route_layer(middleware::from_fn_with_state(
+                    TokenScope::Admin,
+                    require_scope,
+                )),
+            )
+            .route_layer(middleware::from_extractor_with_state::<Authorized, _>(
                 state.clone(),
             ))
             .route("/ping", get(super::http::ping))
-            .route("/auth", post(login).get(session).delete(logout));
+            .route("/auth", post(login).get(session).delete(logout))
+            .route("/auth/refresh", post(refresh))
+            .route("/auth/tokens", post(create_token))
+            .layer(middleware::from_fn_with_state(
+                state.clone(),
+                audit_requests,
+            ));
 
         tracing::info!("Serving static files from {}", self.public_dir.display());
         let serve = ServeDir::new(self.public_dir).precompressed_gzip();
@@ -20,15 +20,24 @@
 
 //! Implements the web service state.
 
-use super::{config::ServiceConfig, EventsSender};
+use super::{
+    audit::AuditLog,
+    auth::{LoginThrottle, RevokedTokens},
+    config::ServiceConfig,
+    EventsSender,
+};
 use std::path::PathBuf;
 
 /// Web service state.
 ///
-/// It holds the service configuration, the current D-Bus connection and a channel to send events.
+/// It holds the service configuration, the current D-Bus connection, a channel to send events,
+/// the list of revoked authentication tokens, the login attempts and the audit log.
 #[derive(Clone)]
 pub struct ServiceState {
     pub config: ServiceConfig,
     pub events: EventsSender,
     pub public_dir: PathBuf,
+    pub revoked_tokens: RevokedTokens,
+    pub login_throttle: LoginThrottle,
+    pub audit: AuditLog,
 }
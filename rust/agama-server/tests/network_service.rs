@@ -21,16 +21,18 @@
 pub mod common;
 
 use agama_lib::error::ServiceError;
-use agama_lib::network::settings::{BondSettings, NetworkConnection};
+use agama_lib::network::settings::{
+    BondSettings, DhcpSettings, GlobalSettings, NetworkConnection, OvsBridgeSettings,
+    OvsPortSettings, WireguardPeerSettings, WireguardSettings,
+};
 use agama_lib::network::types::{DeviceType, SSID};
-use agama_server::network::web::network_service;
+use agama_server::network::web::{network_service, proxy_service};
 use agama_server::network::{
-    self,
-    model::{self, AccessPoint, GeneralState, StateConfig},
-    Adapter, NetworkAdapterError, NetworkState,
+    model::{self, AccessPoint, GeneralState},
+    proxy::ProxyWriter,
+    NetworkState, SimulatedAdapter,
 };
 
-use async_trait::async_trait;
 use axum::http::header;
 use axum::{
     body::Body,
@@ -56,23 +58,9 @@ async fn build_state() -> NetworkState {
 }
 
 async fn build_service(state: NetworkState) -> Result<Router, ServiceError> {
-    let adapter = NetworkTestAdapter(state);
+    let adapter = SimulatedAdapter::new(state);
     let (tx, _rx) = broadcast::channel(16);
-    network_service(adapter, tx).await
-}
-
-#[derive(Default)]
-pub struct NetworkTestAdapter(network::NetworkState);
-
-#[async_trait]
-impl Adapter for NetworkTestAdapter {
-    async fn read(&self, _: StateConfig) -> Result<network::NetworkState, NetworkAdapterError> {
-        Ok(self.0.clone())
-    }
-
-    async fn write(&self, _network: &network::NetworkState) -> Result<(), NetworkAdapterError> {
-        unimplemented!("Not used in tests");
-    }
+    network_service(adapter, None, tx).await
 }
 
 #[test]
@@ -114,6 +102,57 @@ async fn test_change_network_state() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+#[test]
+async fn test_update_global_settings() -> Result<(), Box<dyn Error>> {
+    let state = build_state().await;
+    let adapter = SimulatedAdapter::new(state);
+    let (tx, mut rx) = broadcast::channel(16);
+    let network_service = network_service(adapter, None, tx).await?;
+
+    let global = GlobalSettings {
+        hostname: Some("install".to_string()),
+        hostname_mode: Some("transient".to_string()),
+        nameservers: vec!["192.168.1.1".parse().unwrap()],
+        dns_searchlist: vec!["example.net".to_string()],
+        dns_options: vec!["rotate".to_string()],
+    };
+    let request = Request::builder()
+        .uri("/global")
+        .method(Method::PUT)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(to_string(&global)?)
+        .unwrap();
+    let response = network_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let event = rx.recv().await?;
+    assert!(to_string(&event)?.contains(r#""globalConfigChanged""#));
+
+    let request = Request::builder()
+        .uri("/global")
+        .method(Method::GET)
+        .body(Body::empty())
+        .unwrap();
+    let response = network_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_to_string(response.into_body()).await;
+    assert_eq!(body, to_string(&global)?);
+
+    let invalid = GlobalSettings {
+        hostname: Some("my_host".to_string()),
+        ..Default::default()
+    };
+    let request = Request::builder()
+        .uri("/global")
+        .method(Method::PUT)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(to_string(&invalid)?)
+        .unwrap();
+    let response = network_service.oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    Ok(())
+}
+
 #[test]
 async fn test_network_connections() -> Result<(), Box<dyn Error>> {
     let state = build_state().await;
@@ -240,3 +279,456 @@ async fn test_add_bond_connection() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[test]
+async fn test_add_connection_with_ip_options() -> Result<(), Box<dyn Error>> {
+    let state = build_state().await;
+    let network_service = build_service(state.clone()).await?;
+
+    let eth1 = NetworkConnection {
+        id: "eth1".to_string(),
+        method4: Some("auto".to_string()),
+        method6: Some("auto".to_string()),
+        route_metric4: Some(100),
+        dhcp4: Some(DhcpSettings {
+            client_id: Some("01:52:54:00:12:34:56".to_string()),
+            fqdn: Some("install.example.net".to_string()),
+            ..Default::default()
+        }),
+        may_fail6: Some(false),
+        addr_gen_mode: Some("eui64".to_string()),
+        ip6_privacy: Some("disabled".to_string()),
+        ..Default::default()
+    };
+    let request = Request::builder()
+        .uri("/connections")
+        .header("Content-Type", "application/json")
+        .method(Method::POST)
+        .body(serde_json::to_string(&eth1)?)
+        .unwrap();
+    let response = network_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = Request::builder()
+        .uri("/connections/eth1")
+        .method(Method::GET)
+        .body(Body::empty())
+        .unwrap();
+    let response = network_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_to_string(response.into_body()).await;
+    let conn: NetworkConnection = serde_json::from_str(&body)?;
+    assert_eq!(conn.route_metric4, Some(100));
+    assert_eq!(conn.dhcp4, eth1.dhcp4);
+    assert_eq!(conn.may_fail6, Some(false));
+    assert_eq!(conn.addr_gen_mode, Some("eui64".to_string()));
+    assert_eq!(conn.ip6_privacy, Some("disabled".to_string()));
+
+    let eth2 = NetworkConnection {
+        id: "eth2".to_string(),
+        addr_gen_mode: Some("random".to_string()),
+        ..Default::default()
+    };
+    let request = Request::builder()
+        .uri("/connections")
+        .header("Content-Type", "application/json")
+        .method(Method::POST)
+        .body(serde_json::to_string(&eth2)?)
+        .unwrap();
+    let response = network_service.oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    Ok(())
+}
+
+#[test]
+async fn test_add_wireguard_and_ovs_connections() -> Result<(), Box<dyn Error>> {
+    let state = build_state().await;
+    let network_service = build_service(state.clone()).await?;
+
+    let wg0 = NetworkConnection {
+        id: "wg0".to_string(),
+        method4: Some("manual".to_string()),
+        addresses: vec!["10.0.0.2/24".parse().unwrap()],
+        wireguard: Some(WireguardSettings {
+            private_key: Some("yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=".to_string()),
+            listen_port: Some(51820),
+            peers: vec![WireguardPeerSettings {
+                public_key: "xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=".to_string(),
+                endpoint: Some("vpn.example.net:51820".to_string()),
+                allowed_ips: vec!["10.0.0.0/24".parse().unwrap()],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let ovs_port = NetworkConnection {
+        id: "ovs-port0".to_string(),
+        ovs_port: Some(OvsPortSettings {
+            tag: Some(10),
+            ports: vec!["eth0".to_string()],
+        }),
+        ..Default::default()
+    };
+    let ovs_bridge = NetworkConnection {
+        id: "ovs-br0".to_string(),
+        ovs_bridge: Some(OvsBridgeSettings {
+            stp: Some(true),
+            ports: vec!["ovs-port0".to_string()],
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    for conn in [&wg0, &ovs_port, &ovs_bridge] {
+        let request = Request::builder()
+            .uri("/connections")
+            .header("Content-Type", "application/json")
+            .method(Method::POST)
+            .body(serde_json::to_string(conn)?)
+            .unwrap();
+        let response = network_service.clone().oneshot(request).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let request = Request::builder()
+        .uri("/connections")
+        .method(Method::GET)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = network_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_to_string(response.into_body()).await;
+    let connections: Vec<NetworkConnection> = serde_json::from_str(&body)?;
+
+    let wg0 = connections.iter().find(|c| c.id == "wg0").unwrap();
+    let wireguard = wg0.wireguard.as_ref().unwrap();
+    assert_eq!(wireguard.listen_port, Some(51820));
+    assert_eq!(
+        wireguard.peers[0].endpoint,
+        Some("vpn.example.net:51820".to_string())
+    );
+
+    let ovs_port = connections.iter().find(|c| c.id == "ovs-port0").unwrap();
+    let ovs_port = ovs_port.ovs_port.as_ref().unwrap();
+    assert_eq!(ovs_port.tag, Some(10));
+    assert_eq!(ovs_port.ports, vec!["eth0".to_string()]);
+
+    let ovs_bridge = connections.iter().find(|c| c.id == "ovs-br0").unwrap();
+    let ovs_bridge = ovs_bridge.ovs_bridge.as_ref().unwrap();
+    assert_eq!(ovs_bridge.stp, Some(true));
+    assert_eq!(ovs_bridge.ports, vec!["ovs-port0".to_string()]);
+
+    let wg1 = NetworkConnection {
+        id: "wg1".to_string(),
+        wireguard: Some(WireguardSettings {
+            private_key: Some("invalid".to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let request = Request::builder()
+        .uri("/connections")
+        .header("Content-Type", "application/json")
+        .method(Method::POST)
+        .body(serde_json::to_string(&wg1)?)
+        .unwrap();
+    let response = network_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    Ok(())
+}
+
+#[test]
+async fn test_nmstate() -> Result<(), Box<dyn Error>> {
+    let state = build_state().await;
+    let network_service = build_service(state.clone()).await?;
+
+    let nmstate = r#"
+interfaces:
+- name: eth0
+  type: ethernet
+  ipv4:
+    enabled: true
+    dhcp: false
+    address:
+    - ip: 192.168.1.10
+      prefix-length: 24
+- name: eth1
+  type: ethernet
+- name: bond0
+  type: bond
+  link-aggregation:
+    mode: active-backup
+    port:
+    - eth1
+"#;
+    let request = Request::builder()
+        .uri("/nmstate")
+        .header("Content-Type", "application/yaml")
+        .method(Method::PUT)
+        .body(nmstate.to_string())
+        .unwrap();
+
+    let response = network_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let request = Request::builder()
+        .uri("/nmstate")
+        .method(Method::GET)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = network_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_to_string(response.into_body()).await;
+    assert!(body.contains("ip: 192.168.1.10"));
+    assert!(body.contains("mode: active-backup"));
+    assert!(body.contains("- eth1"));
+
+    // the settings which are not included are kept
+    let request = Request::builder()
+        .uri("/nmstate")
+        .header("Content-Type", "application/yaml")
+        .method(Method::PUT)
+        .body("interfaces:\n- name: eth0\n  type: ethernet\n  mtu: 9000\n".to_string())
+        .unwrap();
+
+    let response = network_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let request = Request::builder()
+        .uri("/nmstate")
+        .method(Method::GET)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = network_service.clone().oneshot(request).await?;
+    let body = body_to_string(response.into_body()).await;
+    assert!(body.contains("mtu: 9000"));
+    assert!(body.contains("ip: 192.168.1.10"));
+
+    let request = Request::builder()
+        .uri("/nmstate")
+        .header("Content-Type", "application/yaml")
+        .method(Method::PUT)
+        .body("interfaces:\n- name: ovs0\n  type: ovs-bridge\n".to_string())
+        .unwrap();
+
+    let response = network_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = body_to_string(response.into_body()).await;
+    assert!(body.contains("unknown variant `ovs-bridge`"));
+
+    Ok(())
+}
+
+#[test]
+async fn test_apply_with_confirmation() -> Result<(), Box<dyn Error>> {
+    let state = build_state().await;
+    let network_service = build_service(state.clone()).await?;
+
+    let request = Request::builder()
+        .uri("/system/apply")
+        .header("Content-Type", "application/json")
+        .method(Method::POST)
+        .body(r#"{ "confirmTimeout": 60 }"#.to_string())
+        .unwrap();
+
+    let response = network_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    // applying again is not allowed until the changes are confirmed
+    let request = Request::builder()
+        .uri("/system/apply")
+        .method(Method::POST)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = network_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let request = Request::builder()
+        .uri("/system/confirm")
+        .method(Method::POST)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = network_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let request = Request::builder()
+        .uri("/system/confirm")
+        .method(Method::POST)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = network_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = body_to_string(response.into_body()).await;
+    assert!(body.contains("no network changes waiting for confirmation"));
+
+    Ok(())
+}
+
+#[test]
+async fn test_apply_with_invalid_confirmation_timeout() -> Result<(), Box<dyn Error>> {
+    let state = build_state().await;
+    let network_service = build_service(state.clone()).await?;
+
+    for timeout in ["0", "3601", "18446744073709551615"] {
+        let request = Request::builder()
+            .uri("/system/apply")
+            .header("Content-Type", "application/json")
+            .method(Method::POST)
+            .body(format!(r#"{{ "confirmTimeout": {} }}"#, timeout))
+            .unwrap();
+
+        let response = network_service.clone().oneshot(request).await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = body_to_string(response.into_body()).await;
+        assert!(body.contains("must be between 1 and 3600 seconds"));
+    }
+
+    // nothing was applied, so there is nothing to confirm
+    let request = Request::builder()
+        .uri("/system/confirm")
+        .method(Method::POST)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = network_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    Ok(())
+}
+
+#[test]
+async fn test_diagnostics() -> Result<(), Box<dyn Error>> {
+    let state = build_state().await;
+    let network_service = build_service(state.clone()).await?;
+
+    let request = Request::builder()
+        .uri("/diagnostics")
+        .header("Content-Type", "application/json")
+        .method(Method::POST)
+        .body(r#"{ "device": "eth0" }"#.to_string())
+        .unwrap();
+
+    let response = network_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_to_string(response.into_body()).await;
+    assert!(body.contains(r#""device":"eth0""#));
+    assert!(body.contains(r#""kind":"link","status":"failed""#));
+    assert!(body.contains(r#""kind":"gateway","status":"skipped""#));
+
+    let request = Request::builder()
+        .uri("/diagnostics")
+        .header("Content-Type", "application/json")
+        .method(Method::POST)
+        .body(r#"{ "device": "wlan0" }"#.to_string())
+        .unwrap();
+
+    let response = network_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    Ok(())
+}
+
+#[test]
+async fn test_proxy() -> Result<(), Box<dyn Error>> {
+    let root = tempfile::TempDir::new()?;
+    let proxy_service = proxy_service(Some(ProxyWriter::new(root.path())));
+
+    let request = Request::builder()
+        .uri("/proxy")
+        .method(Method::GET)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = proxy_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_to_string(response.into_body()).await;
+    assert_eq!(body, "{}");
+
+    let request = Request::builder()
+        .uri("/proxy")
+        .header("Content-Type", "application/json")
+        .method(Method::PUT)
+        .body(r#"{ "http": "proxy.example.net" }"#.to_string())
+        .unwrap();
+
+    let response = proxy_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let request = Request::builder()
+        .uri("/proxy")
+        .header("Content-Type", "application/json")
+        .method(Method::PUT)
+        .body(
+            r#"{ "http": "http://proxy.example.net:3128", "noProxy": ["localhost"] }"#.to_string(),
+        )
+        .unwrap();
+
+    let response = proxy_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let sysconfig = std::fs::read_to_string(root.path().join("etc/sysconfig/proxy"))?;
+    assert!(sysconfig.contains("HTTP_PROXY=\"http://proxy.example.net:3128\""));
+
+    let request = Request::builder()
+        .uri("/proxy")
+        .method(Method::GET)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = proxy_service.clone().oneshot(request).await?;
+    let body = body_to_string(response.into_body()).await;
+    assert_eq!(
+        body,
+        r#"{"http":"http://proxy.example.net:3128","noProxy":["localhost"]}"#
+    );
+
+    let request = Request::builder()
+        .uri("/proxy")
+        .header("Content-Type", "application/json")
+        .method(Method::PUT)
+        .body(
+            r#"{ "http": "http://proxy.example.net:3128", "user": "john", "password": "secret" }"#
+                .to_string(),
+        )
+        .unwrap();
+
+    let response = proxy_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    // the password is not exposed
+    let request = Request::builder()
+        .uri("/proxy")
+        .method(Method::GET)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = proxy_service.clone().oneshot(request).await?;
+    let body = body_to_string(response.into_body()).await;
+    assert_eq!(
+        body,
+        r#"{"http":"http://proxy.example.net:3128","user":"john"}"#
+    );
+
+    // but it is kept when the settings are written back
+    let request = Request::builder()
+        .uri("/proxy")
+        .header("Content-Type", "application/json")
+        .method(Method::PUT)
+        .body(body)
+        .unwrap();
+
+    let response = proxy_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let curlrc = std::fs::read_to_string(root.path().join("root/.curlrc"))?;
+    assert_eq!(curlrc, "--proxy-user \"john:secret\"\n");
+
+    Ok(())
+}
@@ -20,8 +20,8 @@
 
 pub mod common;
 
-use agama_lib::auth::AuthToken;
-use agama_server::web::{MainServiceBuilder, ServiceConfig};
+use agama_lib::auth::{AuthToken, TokenClaims, TokenScope};
+use agama_server::web::{ClientCertificate, MainServiceBuilder, ServiceConfig};
 use axum::{
     body::Body,
     http::{Method, Request, StatusCode},
@@ -29,7 +29,7 @@ use axum::{
     routing::get,
 };
 use common::body_to_string;
-use std::{error::Error, path::PathBuf};
+use std::{error::Error, path::PathBuf, time::Duration};
 use tokio::{sync::broadcast::channel, test};
 use tower::ServiceExt;
 
@@ -66,6 +66,7 @@ async fn protected() -> String {
 async fn access_protected_route(token: &str, jwt_secret: &str) -> Response {
     let config = ServiceConfig {
         jwt_secret: jwt_secret.to_string(),
+        ..Default::default()
     };
     let (tx, _) = channel(16);
     let web_service = MainServiceBuilder::new(tx, public_dir())
@@ -103,3 +104,202 @@ async fn test_access_protected_route_failed() -> Result<(), Box<dyn Error>> {
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     Ok(())
 }
+
+fn build_auth_service(jwt_secret: &str) -> axum::Router {
+    let config = ServiceConfig {
+        jwt_secret: jwt_secret.to_string(),
+        ..Default::default()
+    };
+    let (tx, _) = channel(16);
+    MainServiceBuilder::new(tx, public_dir())
+        .add_service("/protected", get(protected).post(protected))
+        .with_config(config)
+        .build()
+}
+
+fn authenticated_request(uri: &str, method: Method, token: &str) -> Request<Body> {
+    Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("Authorization", format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[test]
+async fn test_logout_revokes_token() -> Result<(), Box<dyn Error>> {
+    let web_service = build_auth_service("nots3cr3t");
+    let token = AuthToken::generate("nots3cr3t")?;
+
+    let request = authenticated_request("/api/auth", Method::DELETE, token.as_str());
+    let response = web_service.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = authenticated_request("/api/protected", Method::GET, token.as_str());
+    let response = web_service.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    Ok(())
+}
+
+#[test]
+async fn test_refresh_token() -> Result<(), Box<dyn Error>> {
+    let web_service = build_auth_service("nots3cr3t");
+    let token = AuthToken::generate("nots3cr3t")?;
+    let claims = token.claims("nots3cr3t")?;
+
+    let request = authenticated_request("/api/auth/refresh", Method::POST, token.as_str());
+    let response = web_service.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = body_to_string(response.into_body()).await;
+    let body: serde_json::Value = serde_json::from_str(&body)?;
+    let new_token = AuthToken::new(body["token"].as_str().unwrap());
+    let new_claims = new_token.claims("nots3cr3t")?;
+    assert_eq!(new_claims.sid, claims.sid);
+    assert_ne!(new_claims.jti, claims.jti);
+
+    let request = authenticated_request("/api/protected", Method::GET, new_token.as_str());
+    let response = web_service.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // the old token is not valid anymore
+    let request = authenticated_request("/api/protected", Method::GET, token.as_str());
+    let response = web_service.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    Ok(())
+}
+
+#[test]
+async fn test_read_only_token() -> Result<(), Box<dyn Error>> {
+    let web_service = build_auth_service("nots3cr3t");
+    let claims = TokenClaims::new("root", vec![TokenScope::Read], Duration::from_secs(60));
+    let token = AuthToken::from_claims(&claims, "nots3cr3t")?;
+
+    let request = authenticated_request("/api/protected", Method::GET, token.as_str());
+    let response = web_service.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = authenticated_request("/api/protected", Method::POST, token.as_str());
+    let response = web_service.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    Ok(())
+}
+
+async fn create_token(
+    web_service: axum::Router,
+    token: &AuthToken,
+    body: &str,
+) -> Result<Response, Box<dyn Error>> {
+    let request = Request::builder()
+        .uri("/api/auth/tokens")
+        .method(Method::POST)
+        .header("Authorization", format!("Bearer {}", token))
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))?;
+    Ok(web_service.oneshot(request).await?)
+}
+
+#[test]
+async fn test_create_token() -> Result<(), Box<dyn Error>> {
+    let web_service = build_auth_service("nots3cr3t");
+    let token = AuthToken::generate("nots3cr3t")?;
+
+    let response = create_token(
+        web_service.clone(),
+        &token,
+        r#"{ "scopes": ["read", "install"], "ttl": 60 }"#,
+    )
+    .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = body_to_string(response.into_body()).await;
+    let body: serde_json::Value = serde_json::from_str(&body)?;
+    let new_token = AuthToken::new(body["token"].as_str().unwrap());
+    let claims = new_token.claims("nots3cr3t")?;
+    assert_eq!(claims.scopes, vec![TokenScope::Read, TokenScope::Install]);
+    assert_eq!(claims.exp - claims.iat, 60);
+
+    // only admin tokens can create new tokens
+    let response = create_token(
+        web_service.clone(),
+        &new_token,
+        r#"{ "scopes": ["admin"] }"#,
+    )
+    .await?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = create_token(web_service, &token, r#"{ "scopes": [] }"#).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    Ok(())
+}
+
+#[test]
+async fn test_audit_log() -> Result<(), Box<dyn Error>> {
+    let web_service = build_auth_service("nots3cr3t");
+    let token = AuthToken::generate("nots3cr3t")?;
+
+    let request = authenticated_request("/api/protected", Method::POST, token.as_str());
+    let response = web_service.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // read-only requests are not recorded
+    let request = authenticated_request("/api/protected", Method::GET, token.as_str());
+    web_service.clone().oneshot(request).await.unwrap();
+
+    let request = authenticated_request("/api/audit", Method::GET, token.as_str());
+    let response = web_service.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = body_to_string(response.into_body()).await;
+    let entries: serde_json::Value = serde_json::from_str(&body)?;
+    let entries = entries.as_array().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["path"], "/api/protected");
+    assert_eq!(entries[0]["method"], "POST");
+    assert_eq!(entries[0]["subject"], "root");
+    assert_eq!(entries[0]["success"], true);
+    let claims = token.claims("nots3cr3t")?;
+    assert_eq!(entries[0]["session"], claims.sid);
+    assert_eq!(entries[0]["tokenId"], claims.jti);
+    assert_eq!(entries[0]["scopes"], serde_json::json!(["admin"]));
+
+    // only admin tokens can read the audit log
+    let claims = TokenClaims::new("root", vec![TokenScope::Read], Duration::from_secs(60));
+    let token = AuthToken::from_claims(&claims, "nots3cr3t")?;
+    let request = authenticated_request("/api/audit", Method::GET, token.as_str());
+    let response = web_service.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    Ok(())
+}
+
+#[test]
+async fn test_client_certificate() -> Result<(), Box<dyn Error>> {
+    let web_service = build_auth_service("nots3cr3t");
+    let certificate = ClientCertificate {
+        subject: "orchestrator".to_string(),
+    };
+
+    let request = Request::builder()
+        .uri("/api/protected")
+        .method(Method::POST)
+        .extension(certificate.clone())
+        .body(Body::empty())?;
+    let response = web_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // the default scopes do not include "admin"
+    let request = Request::builder()
+        .uri("/api/audit")
+        .extension(certificate)
+        .body(Body::empty())?;
+    let response = web_service.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let token = AuthToken::generate("nots3cr3t")?;
+    let request = authenticated_request("/api/audit", Method::GET, token.as_str());
+    let response = web_service.oneshot(request).await?;
+    let body = body_to_string(response.into_body()).await;
+    let entries: serde_json::Value = serde_json::from_str(&body)?;
+    assert_eq!(entries[0]["subject"], "orchestrator");
+    Ok(())
+}
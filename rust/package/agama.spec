@@ -36,9 +36,6 @@ Requires:       dbus-1-common
 BuildRequires:  dbus-1-daemon
 BuildRequires:  clang-devel
 BuildRequires:  pkgconfig(pam)
-# required by autoinstallation
-Requires:       jsonnet
-Requires:       lshw
 # required by "agama logs store"
 Requires:       gzip
 Requires:       tar
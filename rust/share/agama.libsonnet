@@ -1,6 +1,82 @@
+// function goes through the disks of the hardware inventory and returns only the ones using the
+// given transport.
+// @param hardware: Object with the hardware inventory (see "hardware" in hw.libsonnet)
+// @param transport: String with the transport (e.g., "nvme", "sata", "usb" or "virtio")
+// @return Array of disks with given transport
+selectDisksByTransport(hardware, transport)::
+  std.filter(function(d) d.transport == transport, hardware.disks),
+
+// function returns the biggest disk or null if there are no disks.
+// @param hardware: Object with the hardware inventory (see "hardware" in hw.libsonnet)
+// @return Disk object or null
+findBiggestDisk(hardware)::
+  local sorted = std.sort(hardware.disks, function(d) -d.size);
+  if std.length(sorted) > 0 then sorted[0] else null,
+
+// function returns the network interface with the given MAC address or null if not found.
+// @param hardware: Object with the hardware inventory (see "hardware" in hw.libsonnet)
+// @param mac: String with the MAC address
+// @return Network interface object or null
+findInterfaceByMAC(hardware, mac)::
+  local result = std.filter(
+    function(i) i.mac != null && std.asciiLower(i.mac) == std.asciiLower(mac),
+    hardware.networkInterfaces
+  );
+  if std.length(result) > 0 then result[0] else null,
+
+// The functions below are kept for compatibility with the profiles written for the `lshw -json`
+// output. They are deprecated: use the ones above and the "hardware" object instead.
+
+// Hardware inventory using the layout of `lshw -json`. Only the processor, the memory, the disks
+// and the network interfaces are included.
+lshw:: {
+  local hardware = $.hardware,
+  id: 'computer',
+  class: 'system',
+  children: [{
+    id: 'core',
+    class: 'bus',
+    children: [
+      {
+        id: 'cpu',
+        class: 'processor',
+        product: hardware.cpu.model,
+        vendor: hardware.cpu.vendor,
+      },
+      {
+        id: 'memory',
+        class: 'memory',
+        units: 'bytes',
+        size: hardware.memory.total,
+      },
+    ] + [
+      {
+        id: 'disk:' + i,
+        class: 'disk',
+        logicalname: hardware.disks[i].device,
+        units: 'bytes',
+        size: hardware.disks[i].size,
+        product: hardware.disks[i].model,
+        vendor: hardware.disks[i].vendor,
+      }
+      for i in std.range(0, std.length(hardware.disks) - 1)
+    ] + [
+      {
+        id: 'network:' + i,
+        class: 'network',
+        logicalname: hardware.networkInterfaces[i].name,
+        serial: hardware.networkInterfaces[i].mac,
+        configuration: { driver: hardware.networkInterfaces[i].driver },
+      }
+      for i in std.range(0, std.length(hardware.networkInterfaces) - 1)
+    ],
+  }],
+},
+
 // function go throught lshw output and enlist only given class.
 // Basically it is same as calling `lshw -class <class>`.
-// @param lshw: Object with content of `lshw -json`
+// Deprecated: use the "hardware" object instead.
+// @param lshw: Object with content of `lshw -json` (see "lshw" above)
 // @param class: String with class identifier as can be found in "class" element of lshw
 // @return Array of objects with given class
 selectByClass(lshw, class)::
@@ -16,7 +92,8 @@ selectByClass(lshw, class)::
   result,
 
 // function go throught lshw output and returns object with given "id" or null if not found.
-// @param lshw: Object with content of `lshw -json`
+// Deprecated: use the "hardware" object instead.
+// @param lshw: Object with content of `lshw -json` (see "lshw" above)
 // @param id: String with identifier as can be found in "id" element of lshw
 // @return Object with given id or null
 findByID(lshw, id)::